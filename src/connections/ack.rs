@@ -0,0 +1,304 @@
+use std::time::Duration;
+
+use prost::Message;
+use tokio::sync::broadcast;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+
+use super::response::{decoded_response, wait_for_response};
+use super::wrappers::NodeId;
+
+/// An enum that represents the delivery outcome of a sent mesh packet, as reported by the
+/// `Routing` packets sent back to the client by the radio.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AckOutcome {
+    /// The destination node acknowledged the packet.
+    Ack,
+
+    /// The connected radio heard another node rebroadcast the packet. This is the only
+    /// acknowledgement that is sent for broadcast packets.
+    ImplicitAck,
+
+    /// The packet could not be delivered, for the specified reason (e.g., `NoRoute` or `MaxRetransmit`).
+    Nak(protobufs::routing::Error),
+}
+
+/// A handle to a mesh packet that has been sent to the radio.
+///
+/// This handle exposes the ID of the sent packet, and can be used to wait for the packet
+/// to be acknowledged. Acknowledgements are only sent by the mesh if the packet was sent
+/// with `want_ack` set, with the exception of implicit acknowledgements for broadcasts.
+///
+/// Waiting for an acknowledgement is optional, and the handle can safely be dropped.
+#[derive(Debug)]
+pub struct SentPacket {
+    packet_id: u32,
+    destination: NodeId,
+    source: NodeId,
+    packet_rx: broadcast::Receiver<protobufs::FromRadio>,
+}
+
+impl SentPacket {
+    /// Creates a handle for a packet from `source` to `destination`. The `packet_rx` receiver must
+    /// have been subscribed before the packet was sent to avoid missing early acknowledgements.
+    pub(crate) fn new(
+        packet_id: u32,
+        destination: NodeId,
+        source: NodeId,
+        packet_rx: broadcast::Receiver<protobufs::FromRadio>,
+    ) -> Self {
+        SentPacket {
+            packet_id,
+            destination,
+            source,
+            packet_rx,
+        }
+    }
+
+    /// Returns the ID of the sent packet.
+    pub fn packet_id(&self) -> u32 {
+        self.packet_id
+    }
+
+    /// Returns the ID of the node the packet was sent to. Broadcast packets are sent to `u32::MAX`.
+    pub fn destination(&self) -> NodeId {
+        self.destination
+    }
+
+    /// Waits for the radio to report the delivery outcome of the packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum amount of time to wait for an acknowledgement.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to an `AckOutcome` describing whether the packet was acknowledged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let sent_packet = stream_api
+    ///     .send_text(packet_router, "Hello!".to_string(), PacketDestination::Node(node_id), true, channel)
+    ///     .await?;
+    ///
+    /// match sent_packet.wait_for_ack(Duration::from_secs(60)).await? {
+    ///     AckOutcome::Ack | AckOutcome::ImplicitAck => println!("Delivered"),
+    ///     AckOutcome::Nak(reason) => println!("Failed to deliver: {:?}", reason),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::ResponseTimeout` if no acknowledgement is received before `timeout` elapses.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn wait_for_ack(mut self, timeout: Duration) -> Result<AckOutcome, Error> {
+        let packet_id = self.packet_id;
+        let destination = self.destination;
+        let source = self.source;
+
+        wait_for_response(&mut self.packet_rx, packet_id, timeout, |packet| {
+            ack_outcome(packet, packet_id, destination, source).map(Ok)
+        })
+        .await
+    }
+
+    /// Waits for the connected radio to report that it has restarted with a `Rebooted` packet.
+    ///
+    /// This method is intended to be used after sending an admin message that restarts the
+    /// connected radio, such as a reboot request or a configuration commit. Since the packet
+    /// listener of this handle was created before the packet was sent, a restart reported
+    /// immediately after the packet was sent will not be missed.
+    ///
+    /// **Note:** Only the connected radio reports restarts, so this method will time out for
+    /// admin messages sent to remote nodes.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum amount of time to wait for the radio to restart.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the radio restarted before `timeout` elapsed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api
+    ///     .reboot(packet_router, Duration::from_secs(5))
+    ///     .await?
+    ///     .wait_for_reboot(Duration::from_secs(60))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::ResponseTimeout` if the radio does not restart before `timeout` elapses.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn wait_for_reboot(mut self, timeout: Duration) -> Result<(), Error> {
+        wait_for_response(
+            &mut self.packet_rx,
+            self.packet_id,
+            timeout,
+            |packet| match packet.payload_variant {
+                Some(protobufs::from_radio::PayloadVariant::Rebooted(true)) => Some(Ok(())),
+                _ => None,
+            },
+        )
+        .await
+    }
+}
+
+/// Returns the delivery outcome reported by the given packet, if it is a `Routing` packet that
+/// refers to the packet with ID `packet_id`.
+pub(crate) fn ack_outcome(
+    packet: &protobufs::FromRadio,
+    packet_id: u32,
+    destination: NodeId,
+    source: NodeId,
+) -> Option<AckOutcome> {
+    let (mesh_packet, data) = decoded_response(packet, packet_id, protobufs::PortNum::RoutingApp)?;
+    let routing = protobufs::Routing::decode(data.payload.as_slice()).ok()?;
+
+    let error_reason = match routing.variant {
+        Some(protobufs::routing::Variant::ErrorReason(error_reason)) => {
+            protobufs::routing::Error::try_from(error_reason).ok()?
+        }
+        _ => return None,
+    };
+
+    if error_reason != protobufs::routing::Error::None {
+        return Some(AckOutcome::Nak(error_reason));
+    }
+
+    // Implicit acknowledgements are reported by the connected radio itself
+    if destination != mesh_packet.from && source == mesh_packet.from {
+        return Some(AckOutcome::ImplicitAck);
+    }
+
+    Some(AckOutcome::Ack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKET_ID: u32 = 1234;
+    const SOURCE: u32 = 1;
+    const DESTINATION: u32 = 2;
+
+    fn routing_packet(
+        from: u32,
+        request_id: u32,
+        error: protobufs::routing::Error,
+    ) -> protobufs::FromRadio {
+        let routing = protobufs::Routing {
+            variant: Some(protobufs::routing::Variant::ErrorReason(error as i32)),
+        };
+
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(
+                protobufs::MeshPacket {
+                    from,
+                    to: SOURCE,
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: protobufs::PortNum::RoutingApp as i32,
+                            payload: routing.encode_to_vec(),
+                            request_id,
+                            ..Default::default()
+                        },
+                    )),
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+
+    fn outcome(packet: protobufs::FromRadio) -> Option<AckOutcome> {
+        ack_outcome(&packet, PACKET_ID, DESTINATION.into(), SOURCE.into())
+    }
+
+    #[test]
+    fn recognizes_ack_from_destination() {
+        let packet = routing_packet(DESTINATION, PACKET_ID, protobufs::routing::Error::None);
+        assert_eq!(outcome(packet), Some(AckOutcome::Ack));
+    }
+
+    #[test]
+    fn recognizes_implicit_ack_from_connected_radio() {
+        let packet = routing_packet(SOURCE, PACKET_ID, protobufs::routing::Error::None);
+        assert_eq!(outcome(packet), Some(AckOutcome::ImplicitAck));
+    }
+
+    #[test]
+    fn recognizes_nak_reason() {
+        let packet = routing_packet(SOURCE, PACKET_ID, protobufs::routing::Error::MaxRetransmit);
+        assert_eq!(
+            outcome(packet),
+            Some(AckOutcome::Nak(protobufs::routing::Error::MaxRetransmit))
+        );
+    }
+
+    #[test]
+    fn ignores_other_packets() {
+        let packet = routing_packet(DESTINATION, PACKET_ID + 1, protobufs::routing::Error::None);
+        assert_eq!(outcome(packet), None);
+    }
+
+    #[tokio::test]
+    async fn wait_for_ack_resolves_on_routing_packet() {
+        let (packet_tx, packet_rx) = broadcast::channel(16);
+        let sent_packet = SentPacket::new(PACKET_ID, DESTINATION.into(), SOURCE.into(), packet_rx);
+
+        packet_tx
+            .send(routing_packet(
+                DESTINATION,
+                PACKET_ID,
+                protobufs::routing::Error::NoRoute,
+            ))
+            .unwrap();
+
+        let outcome = sent_packet
+            .wait_for_ack(Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        assert_eq!(outcome, AckOutcome::Nak(protobufs::routing::Error::NoRoute));
+    }
+
+    #[tokio::test]
+    async fn wait_for_reboot_resolves_on_rebooted_packet() {
+        let (packet_tx, packet_rx) = broadcast::channel(16);
+        let sent_packet = SentPacket::new(PACKET_ID, SOURCE.into(), SOURCE.into(), packet_rx);
+
+        packet_tx
+            .send(routing_packet(
+                SOURCE,
+                PACKET_ID,
+                protobufs::routing::Error::None,
+            ))
+            .unwrap();
+        packet_tx
+            .send(protobufs::FromRadio {
+                id: 0,
+                payload_variant: Some(protobufs::from_radio::PayloadVariant::Rebooted(true)),
+            })
+            .unwrap();
+
+        sent_packet
+            .wait_for_reboot(Duration::from_secs(1))
+            .await
+            .unwrap();
+    }
+}
@@ -1,19 +1,27 @@
+use std::time::Duration;
+
 use btleplug::api::{
     Central, CentralEvent, Characteristic, Manager as _, Peripheral as _, ScanFilter,
     ValueNotification, WriteType,
 };
 use btleplug::platform::{Adapter, Manager, Peripheral};
 use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
 use log::error;
 use uuid::Uuid;
 
 use crate::errors_internal::{BleConnectionError, Error, InternalStreamError};
 
+use super::ble_stream::{BlePeripheral, BlePeripheralEvent};
+
 const MSH_SERVICE: Uuid = Uuid::from_u128(0x6ba1b218_15a8_461f_9fa8_5dcae273eafd);
 const FROMRADIO: Uuid = Uuid::from_u128(0x2c55e69e_4993_11ed_b878_0242ac120002);
 const TORADIO: Uuid = Uuid::from_u128(0xf75c76d2_129e_4dad_a1dd_7866124401e7);
 const FROMNUM: Uuid = Uuid::from_u128(0xed9da18c_a800_4f66_a670_aa7547e34453);
 
+/// The interval at which discovered peripherals are polled while scanning for a radio.
+const SCAN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 pub struct BleHandler {
     radio: Peripheral,
     adapter: Adapter,
@@ -24,11 +32,13 @@ pub struct BleHandler {
 
 #[allow(dead_code)]
 impl BleHandler {
-    pub async fn new(name: String) -> Result<Self, Error> {
-        let (radio, adapter) = Self::find_ble_radio(&name).await?;
+    /// Scans for a radio whose advertised name or MAC address matches `name_or_mac`,
+    /// for at most `scan_duration`, and connects to it.
+    pub async fn new(name_or_mac: String, scan_duration: Duration) -> Result<Self, Error> {
+        let (radio, adapter) = Self::find_ble_radio(&name_or_mac, scan_duration).await?;
         radio.connect().await.map_err(|e| Error::StreamBuildError {
             source: Box::new(e),
-            description: format!("Failed to connect to the device {name}"),
+            description: format!("Failed to connect to the device {name_or_mac}"),
         })?;
         let [toradio_char, fromnum_char, fromradio_char] =
             Self::find_characteristics(&radio).await?;
@@ -50,43 +60,61 @@ impl BleHandler {
         adapter.peripherals().await
     }
 
-    /// Finds a BLE radio matching a given name and running meshtastic.
+    /// Finds a BLE radio matching a given name or MAC address and running meshtastic.
     /// It searches for the 'MSH_SERVICE' running on the device.
     ///
     /// It also returns the associated adapter that can reach this radio.
-    async fn find_ble_radio(name: &str) -> Result<(Peripheral, Adapter), Error> {
-        //TODO: support searching both by a name and by a MAC address
+    async fn find_ble_radio(
+        name_or_mac: &str,
+        scan_duration: Duration,
+    ) -> Result<(Peripheral, Adapter), Error> {
         let scan_error_fn = |e: btleplug::Error| Error::StreamBuildError {
             source: Box::new(e),
             description: "Failed to scan for BLE devices".to_owned(),
         };
         let manager = Manager::new().await.map_err(scan_error_fn)?;
         let adapters = manager.adapters().await.map_err(scan_error_fn)?;
-
-        for adapter in &adapters {
-            let peripherals = Self::scan_peripherals(&adapter).await;
-            match peripherals {
-                Err(e) => {
-                    error!("Error while scanning for meshtastic peripherals: {e:?}");
-                    // We continue, as there can be another adapter that can work
-                    continue;
-                }
-                Ok(peripherals) => {
-                    let needle = Some(name.to_owned());
-                    for peripheral in peripherals {
-                        if let Ok(Some(peripheral_properties)) = peripheral.properties().await {
-                            if peripheral_properties.local_name == needle {
-                                return Ok((peripheral, adapter.clone()));
+        let deadline = tokio::time::Instant::now() + scan_duration;
+
+        loop {
+            for adapter in &adapters {
+                let peripherals = Self::scan_peripherals(adapter).await;
+                match peripherals {
+                    Err(e) => {
+                        error!("Error while scanning for meshtastic peripherals: {e:?}");
+                        // We continue, as there can be another adapter that can work
+                        continue;
+                    }
+                    Ok(peripherals) => {
+                        for peripheral in peripherals {
+                            if let Ok(Some(peripheral_properties)) = peripheral.properties().await {
+                                let name_matches = peripheral_properties.local_name.as_deref()
+                                    == Some(name_or_mac);
+                                let mac_matches = peripheral_properties
+                                    .address
+                                    .to_string()
+                                    .eq_ignore_ascii_case(name_or_mac);
+
+                                if name_matches || mac_matches {
+                                    return Ok((peripheral, adapter.clone()));
+                                }
                             }
                         }
                     }
                 }
             }
+
+            if tokio::time::Instant::now() >= deadline {
+                break;
+            }
+
+            tokio::time::sleep(SCAN_POLL_INTERVAL).await;
         }
+
         Err(Error::StreamBuildError {
             source: Box::new(BleConnectionError()),
             description: format!(
-                "Failed to find {name}, or meshtastic is not running on the device"
+                "Failed to find {name_or_mac}, or meshtastic is not running on the device"
             ) + ", or it's already connected.",
         })
     }
@@ -119,31 +147,12 @@ impl BleHandler {
         ])
     }
 
-    pub async fn write_to_radio(&self, buffer: &[u8]) -> Result<(), Error> {
-        self.radio
-            // TODO: remove the skipping of the first 4 bytes
-            .write(&self.toradio_char, &buffer[4..], WriteType::WithResponse)
-            .await
-            .map_err(|e: btleplug::Error| {
-                Error::InternalStreamError(InternalStreamError::StreamWriteError {
-                    source: Box::new(e),
-                })
-            })
-    }
-
     fn ble_read_error_fn(e: btleplug::Error) -> Error {
         Error::InternalStreamError(InternalStreamError::StreamReadError {
             source: Box::new(e),
         })
     }
 
-    pub async fn read_from_radio(&self) -> Result<Vec<u8>, Error> {
-        self.radio
-            .read(&self.fromradio_char)
-            .await
-            .map_err(Self::ble_read_error_fn)
-    }
-
     fn parse_u32(data: Vec<u8>) -> Result<u32, Error> {
         let parsed_value = u32::from_le_bytes(data.as_slice().try_into().map_err(|e| {
             Error::InternalStreamError(InternalStreamError::StreamReadError {
@@ -162,34 +171,13 @@ impl BleHandler {
         Self::parse_u32(data)
     }
 
-    pub async fn notifications(&self) -> Result<BoxStream<ValueNotification>, Error> {
-        self.radio
-            .subscribe(&self.fromnum_char)
-            .await
-            .map_err(Self::ble_read_error_fn)?;
-        self.radio
-            .notifications()
-            .await
-            .map_err(Self::ble_read_error_fn)
-    }
-
-    pub async fn filter_map(notification: ValueNotification) -> Option<u32> {
-        match notification {
-            ValueNotification {
-                uuid: FROMNUM,
-                value,
-            } => Some(Self::parse_u32(value).unwrap()),
-            _ => None,
-        }
-    }
-
-    pub async fn adapter_events(&self) -> Result<BoxStream<CentralEvent>, Error> {
+    pub async fn adapter_events(&self) -> Result<BoxStream<'static, CentralEvent>, Error> {
         self.adapter
             .events()
             .await
             .map_err(|e| Error::StreamBuildError {
                 source: Box::new(e),
-                description: format!("Failed to listen to device events"),
+                description: "Failed to listen to device events".to_owned(),
             })
     }
 
@@ -197,6 +185,62 @@ impl BleHandler {
         if let Some(CentralEvent::DeviceDisconnected(peripheral_id)) = event {
             return self.radio.id() == peripheral_id;
         }
-        return false;
+        false
+    }
+}
+
+impl BlePeripheral for BleHandler {
+    async fn write_to_radio(&self, packet: &[u8]) -> Result<(), Error> {
+        self.radio
+            .write(&self.toradio_char, packet, WriteType::WithResponse)
+            .await
+            .map_err(|e: btleplug::Error| {
+                Error::InternalStreamError(InternalStreamError::StreamWriteError {
+                    source: Box::new(e),
+                })
+            })
+    }
+
+    async fn read_from_radio(&self) -> Result<Vec<u8>, Error> {
+        self.radio
+            .read(&self.fromradio_char)
+            .await
+            .map_err(Self::ble_read_error_fn)
+    }
+
+    async fn events(&self) -> Result<BoxStream<'static, BlePeripheralEvent>, Error> {
+        self.radio
+            .subscribe(&self.fromnum_char)
+            .await
+            .map_err(Self::ble_read_error_fn)?;
+
+        let notifications = self
+            .radio
+            .notifications()
+            .await
+            .map_err(Self::ble_read_error_fn)?
+            .filter_map(|notification: ValueNotification| async move {
+                match notification.uuid {
+                    FROMNUM => match Self::parse_u32(notification.value) {
+                        Ok(from_num) => Some(BlePeripheralEvent::FromNum(from_num)),
+                        Err(e) => {
+                            error!("Failed to parse FROMNUM notification: {e:?}");
+                            None
+                        }
+                    },
+                    _ => None,
+                }
+            });
+
+        let radio_id = self.radio.id();
+        let disconnections = self.adapter_events().await?.filter_map(move |event| {
+            let is_disconnected = matches!(
+                &event,
+                CentralEvent::DeviceDisconnected(peripheral_id) if *peripheral_id == radio_id
+            );
+            async move { is_disconnected.then_some(BlePeripheralEvent::Disconnected) }
+        });
+
+        Ok(futures_util::stream::select(notifications, disconnections).boxed())
     }
 }
@@ -0,0 +1,337 @@
+use std::future::Future;
+
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use log::{debug, error, trace, warn};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream, WriteHalf};
+
+use crate::errors_internal::{Error, InternalStreamError};
+use crate::utils_internal::{format_data_packet, take_next_frame};
+
+use super::stream_api::StreamHandle;
+
+/// The size of the in-memory pipe used to bridge a BLE peripheral to the `StreamApi` byte stream.
+const BLE_BRIDGE_BUFFER_SIZE: usize = 4096;
+
+/// An event emitted by a `BlePeripheral` that drives the BLE bridge task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlePeripheralEvent {
+    /// The radio notified the `FROMNUM` characteristic, meaning that new `FromRadio`
+    /// packets are waiting to be read from the `FROMRADIO` characteristic.
+    FromNum(u32),
+
+    /// The radio disconnected from the host adapter.
+    Disconnected,
+}
+
+/// This trait abstracts over the three Meshtastic BLE characteristics (`TORADIO`,
+/// `FROMRADIO` and `FROMNUM`) so that the BLE bridge can be driven by a real
+/// adapter or by a mock peripheral within tests.
+///
+/// Unlike serial and TCP connections, BLE radios exchange whole protobuf frames
+/// without the `0x94c3` packet header. Implementors should therefore read and write
+/// unframed `FromRadio` and `ToRadio` packets.
+pub trait BlePeripheral: Send + Sync + 'static {
+    /// Writes a single encoded `ToRadio` packet to the `TORADIO` characteristic.
+    fn write_to_radio(&self, packet: &[u8]) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Reads a single encoded `FromRadio` packet from the `FROMRADIO` characteristic.
+    /// An empty buffer indicates that the radio has no more packets queued.
+    fn read_from_radio(&self) -> impl Future<Output = Result<Vec<u8>, Error>> + Send;
+
+    /// Subscribes to `FROMNUM` notifications and disconnection events for the peripheral.
+    fn events(
+        &self,
+    ) -> impl Future<Output = Result<BoxStream<'static, BlePeripheralEvent>, Error>> + Send;
+}
+
+/// A helper method that bridges a `BlePeripheral` to a byte stream that is compatible
+/// with the `StreamApi::connect` method.
+///
+/// This method spawns a worker task that drains the `FROMRADIO` characteristic every
+/// time the radio notifies `FROMNUM`, and that writes outgoing `ToRadio` packets to the
+/// `TORADIO` characteristic after removing the packet header added by the `StreamApi`.
+///
+/// # Arguments
+///
+/// * `peripheral` - A connected peripheral that implements the `BlePeripheral` trait.
+///
+/// # Returns
+///
+/// Returns a `StreamHandle` wrapping one end of an in-memory pipe, along with the join
+/// handle of the bridge task.
+///
+/// # Examples
+///
+/// ```
+/// let stream_handle = utils::stream::build_ble_stream_from_peripheral(mock_peripheral);
+/// let (decoded_listener, stream_api) = stream_api.connect(stream_handle).await;
+/// ```
+///
+/// # Errors
+///
+/// None
+///
+/// # Panics
+///
+/// None
+///
+pub fn build_ble_stream_from_peripheral<P: BlePeripheral>(
+    peripheral: P,
+) -> StreamHandle<DuplexStream> {
+    let (client_stream, bridge_stream) = tokio::io::duplex(BLE_BRIDGE_BUFFER_SIZE);
+
+    let join_handle = tokio::spawn(async move {
+        let result = run_ble_bridge(peripheral, bridge_stream).await;
+
+        if let Err(e) = &result {
+            error!("BLE bridge unexpectedly terminated: {e:?}");
+        }
+
+        result
+    });
+
+    StreamHandle {
+        stream: client_stream,
+        join_handle: Some(join_handle),
+    }
+}
+
+async fn run_ble_bridge<P: BlePeripheral>(
+    peripheral: P,
+    bridge_stream: DuplexStream,
+) -> Result<(), Error> {
+    debug!("Started BLE bridge");
+
+    let (mut read_stream, mut write_stream) = tokio::io::split(bridge_stream);
+    let mut events = peripheral.events().await?;
+
+    // The radio may already have packets queued before the first notification
+    drain_from_radio(&peripheral, &mut write_stream).await?;
+
+    let mut pending = Vec::new();
+    let mut buffer = [0u8; 1024];
+
+    loop {
+        tokio::select! {
+            read_result = read_stream.read(&mut buffer) => {
+                let n = read_result.map_err(|e| {
+                    Error::InternalStreamError(InternalStreamError::StreamReadError {
+                        source: Box::new(e),
+                    })
+                })?;
+
+                if n == 0 {
+                    debug!("StreamApi side of the BLE bridge closed");
+                    return Ok(());
+                }
+
+                pending.extend_from_slice(&buffer[..n]);
+
+                while let Some(packet) = take_next_frame(&mut pending) {
+                    trace!("Writing {} bytes to TORADIO", packet.len());
+                    peripheral.write_to_radio(&packet).await?;
+                }
+
+                // Responses to written packets are not always announced through FROMNUM
+                drain_from_radio(&peripheral, &mut write_stream).await?;
+            }
+            event = events.next() => {
+                match event {
+                    Some(BlePeripheralEvent::FromNum(from_num)) => {
+                        trace!("Received FROMNUM notification {from_num}");
+                        drain_from_radio(&peripheral, &mut write_stream).await?;
+                    }
+                    Some(BlePeripheralEvent::Disconnected) | None => {
+                        warn!("BLE peripheral disconnected");
+                        return Err(Error::InternalStreamError(InternalStreamError::ConnectionLost));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reads `FROMRADIO` until the radio returns an empty packet, and forwards each packet
+/// to the `StreamApi` side of the bridge with the packet header the `StreamBuffer` expects.
+async fn drain_from_radio<P: BlePeripheral>(
+    peripheral: &P,
+    write_stream: &mut WriteHalf<DuplexStream>,
+) -> Result<(), Error> {
+    loop {
+        let packet = peripheral.read_from_radio().await?;
+
+        if packet.is_empty() {
+            return Ok(());
+        }
+
+        trace!("Read {} bytes from FROMRADIO", packet.len());
+
+        let packet_with_header = format_data_packet(packet.into())?;
+
+        write_stream
+            .write_all(packet_with_header.data())
+            .await
+            .map_err(|e| {
+                Error::InternalStreamError(InternalStreamError::StreamWriteError {
+                    source: Box::new(e),
+                })
+            })?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use futures_util::stream;
+    use prost::Message;
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+    use crate::protobufs;
+    use crate::utils_internal::{FRAME_HEADER_SIZE, FRAME_MAGIC};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct MockPeripheral {
+        from_radio: Arc<Mutex<VecDeque<Vec<u8>>>>,
+        to_radio: Arc<Mutex<Vec<Vec<u8>>>>,
+        events: Arc<Mutex<Option<tokio::sync::mpsc::UnboundedReceiver<BlePeripheralEvent>>>>,
+    }
+
+    impl MockPeripheral {
+        fn new() -> (Self, UnboundedSender<BlePeripheralEvent>) {
+            let (events_tx, events_rx) = unbounded_channel();
+            let peripheral = MockPeripheral {
+                events: Arc::new(Mutex::new(Some(events_rx))),
+                ..Default::default()
+            };
+
+            (peripheral, events_tx)
+        }
+
+        fn queue_from_radio(&self, packet: protobufs::FromRadio) {
+            self.from_radio
+                .lock()
+                .unwrap()
+                .push_back(packet.encode_to_vec());
+        }
+    }
+
+    impl BlePeripheral for MockPeripheral {
+        async fn write_to_radio(&self, packet: &[u8]) -> Result<(), Error> {
+            self.to_radio.lock().unwrap().push(packet.to_vec());
+            Ok(())
+        }
+
+        async fn read_from_radio(&self) -> Result<Vec<u8>, Error> {
+            Ok(self
+                .from_radio
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_default())
+        }
+
+        async fn events(&self) -> Result<BoxStream<'static, BlePeripheralEvent>, Error> {
+            match self.events.lock().unwrap().take() {
+                Some(rx) => Ok(stream::unfold(rx, |mut rx| async move {
+                    rx.recv().await.map(|event| (event, rx))
+                })
+                .boxed()),
+                None => Ok(stream::empty().boxed()),
+            }
+        }
+    }
+
+    fn mock_from_radio(id: u32) -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::ConfigCompleteId(id)),
+        }
+    }
+
+    async fn read_frame(stream: &mut DuplexStream) -> protobufs::FromRadio {
+        let mut header = [0u8; FRAME_HEADER_SIZE];
+        stream.read_exact(&mut header).await.unwrap();
+        assert_eq!(header[..2], FRAME_MAGIC);
+
+        let mut data = vec![0u8; u16::from_be_bytes([header[2], header[3]]) as usize];
+        stream.read_exact(&mut data).await.unwrap();
+
+        protobufs::FromRadio::decode(data.as_slice()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn drains_from_radio_on_fromnum_notification() {
+        let (peripheral, events_tx) = MockPeripheral::new();
+        let mut handle = build_ble_stream_from_peripheral(peripheral.clone());
+
+        peripheral.queue_from_radio(mock_from_radio(1));
+        peripheral.queue_from_radio(mock_from_radio(2));
+        events_tx.send(BlePeripheralEvent::FromNum(2)).unwrap();
+
+        let timeout = Duration::from_millis(500);
+        let first = tokio::time::timeout(timeout, read_frame(&mut handle.stream))
+            .await
+            .unwrap();
+        let second = tokio::time::timeout(timeout, read_frame(&mut handle.stream))
+            .await
+            .unwrap();
+
+        assert_eq!(first, mock_from_radio(1));
+        assert_eq!(second, mock_from_radio(2));
+    }
+
+    #[tokio::test]
+    async fn writes_unframed_to_radio_packets() {
+        let (peripheral, _events_tx) = MockPeripheral::new();
+        let mut handle = build_ble_stream_from_peripheral(peripheral.clone());
+
+        let to_radio = protobufs::ToRadio {
+            payload_variant: Some(protobufs::to_radio::PayloadVariant::WantConfigId(42)),
+        };
+        let packet = format_data_packet(to_radio.encode_to_vec().into()).unwrap();
+
+        // Split the write to make sure partial frames are reassembled
+        let (head, tail) = packet.data().split_at(3);
+        handle.stream.write_all(head).await.unwrap();
+        handle.stream.write_all(tail).await.unwrap();
+
+        tokio::time::timeout(Duration::from_millis(500), async {
+            while peripheral.to_radio.lock().unwrap().is_empty() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            *peripheral.to_radio.lock().unwrap(),
+            vec![to_radio.encode_to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn disconnection_terminates_bridge() {
+        let (peripheral, events_tx) = MockPeripheral::new();
+        let handle = build_ble_stream_from_peripheral(peripheral);
+
+        events_tx.send(BlePeripheralEvent::Disconnected).unwrap();
+
+        let result = tokio::time::timeout(Duration::from_millis(500), handle.join_handle.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::InternalStreamError(
+                InternalStreamError::ConnectionLost
+            ))
+        ));
+    }
+}
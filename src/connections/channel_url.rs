@@ -0,0 +1,468 @@
+use base64::{
+    alphabet,
+    engine::{
+        general_purpose::URL_SAFE_NO_PAD, DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig,
+    },
+    Engine,
+};
+use prost::Message;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+
+/// The prefix of URLs that replace the channels of a radio.
+pub const CHANNEL_URL_PREFIX: &str = "https://meshtastic.org/e/#";
+
+/// The prefix of URLs that add channels to the existing channels of a radio.
+pub const ADD_CHANNEL_URL_PREFIX: &str = "https://meshtastic.org/e/?add=true#";
+
+/// The maximum number of channels a radio supports.
+pub(crate) const MAX_CHANNEL_COUNT: usize = 8;
+
+/// A URL-safe base64 engine that accepts encoded data with or without padding.
+const URL_SAFE_INDIFFERENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// A struct that represents a parsed channel sharing URL.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ChannelUrl {
+    /// The channels and LoRa configuration encoded in the URL.
+    pub channel_set: protobufs::ChannelSet,
+
+    /// Whether the channels should be added to the existing channels of the radio,
+    /// as opposed to replacing them.
+    pub add_only: bool,
+}
+
+/// Parses a channel sharing URL in either the `/e/#` or the `/e/?add=true#` form.
+pub(crate) fn parse_channel_url(url: &str) -> Result<ChannelUrl, Error> {
+    let invalid = |description: &str| Error::InvalidChannelUrl {
+        url: url.to_string(),
+        description: description.to_string(),
+    };
+
+    let (location, fragment) = url
+        .trim()
+        .split_once('#')
+        .ok_or_else(|| invalid("URL does not contain a '#' fragment"))?;
+
+    let add_only = if location.ends_with("/e/") {
+        false
+    } else if location.ends_with("/e/?add=true") {
+        true
+    } else {
+        return Err(invalid("URL path is not of the form /e/# or /e/?add=true#"));
+    };
+
+    // Older clients encoded the channel set with the standard base64 alphabet
+    let fragment = fragment.replace('+', "-").replace('/', "_");
+
+    let bytes = URL_SAFE_INDIFFERENT
+        .decode(fragment)
+        .map_err(|e| invalid(&format!("fragment is not valid base64: {e}")))?;
+
+    let channel_set = protobufs::ChannelSet::decode(bytes.as_slice())?;
+
+    if channel_set.settings.is_empty() {
+        return Err(invalid("URL does not contain any channels"));
+    }
+
+    if channel_set.settings.len() > MAX_CHANNEL_COUNT {
+        return Err(invalid(&format!(
+            "URL contains {} channels, but radios support at most {MAX_CHANNEL_COUNT}",
+            channel_set.settings.len()
+        )));
+    }
+
+    Ok(ChannelUrl {
+        channel_set,
+        add_only,
+    })
+}
+
+/// Builds the full list of radio channels that replaces the existing channels of a radio. The
+/// first channel settings become the primary channel, the remaining settings become secondary
+/// channels, and all unused channel slots are disabled.
+pub(crate) fn replacement_channels(
+    settings: &[protobufs::ChannelSettings],
+) -> Vec<protobufs::Channel> {
+    (0..MAX_CHANNEL_COUNT)
+        .map(|index| {
+            let (role, settings) = match settings.get(index) {
+                Some(settings) if index == 0 => (protobufs::channel::Role::Primary, Some(settings)),
+                Some(settings) => (protobufs::channel::Role::Secondary, Some(settings)),
+                None => (protobufs::channel::Role::Disabled, None),
+            };
+
+            protobufs::Channel {
+                index: index as i32,
+                settings: settings.cloned(),
+                role: role as i32,
+            }
+        })
+        .collect()
+}
+
+/// Builds the radio channels needed to add the specified channel settings to the existing
+/// channels of a radio. Settings that match an enabled channel by name and key are skipped, and
+/// the remaining settings are assigned to disabled channel slots as secondary channels.
+pub(crate) fn added_channels(
+    existing: &[protobufs::Channel],
+    settings: &[protobufs::ChannelSettings],
+) -> Result<Vec<protobufs::Channel>, Error> {
+    let is_enabled =
+        |channel: &&protobufs::Channel| channel.role != protobufs::channel::Role::Disabled as i32;
+
+    let new_settings: Vec<&protobufs::ChannelSettings> = settings
+        .iter()
+        .filter(|settings| {
+            !existing.iter().filter(is_enabled).any(|channel| {
+                channel.settings.as_ref().is_some_and(|existing_settings| {
+                    existing_settings.name == settings.name && existing_settings.psk == settings.psk
+                })
+            })
+        })
+        .collect();
+
+    // The primary channel is never replaced, even if the radio reports it as disabled
+    let free_indices: Vec<i32> = (1..MAX_CHANNEL_COUNT as i32)
+        .filter(|index| {
+            !existing
+                .iter()
+                .filter(is_enabled)
+                .any(|channel| channel.index == *index)
+        })
+        .collect();
+
+    if new_settings.len() > free_indices.len() {
+        return Err(Error::ChannelSlotsExhausted {
+            required: new_settings.len(),
+            available: free_indices.len(),
+        });
+    }
+
+    Ok(new_settings
+        .into_iter()
+        .zip(free_indices)
+        .map(|(settings, index)| protobufs::Channel {
+            index,
+            settings: Some(settings.clone()),
+            role: protobufs::channel::Role::Secondary as i32,
+        })
+        .collect())
+}
+
+impl protobufs::ChannelSet {
+    /// Decodes a channel set from a channel sharing URL, as generated by the Meshtastic apps.
+    ///
+    /// Both the `https://meshtastic.org/e/#...` form, which replaces the channels of a radio,
+    /// and the `https://meshtastic.org/e/?add=true#...` form, which adds channels to a radio,
+    /// are accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The channel sharing URL to decode.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `ChannelSet` encoded in the URL.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let channel_set = ChannelSet::from_url("https://meshtastic.org/e/#CgMSAQE")?;
+    /// assert_eq!(channel_set.settings[0].psk, vec![1]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidChannelUrl` if the URL is not a channel sharing URL, and with
+    /// `Error::DecodeError` if the URL does not contain a valid `ChannelSet`.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        Ok(parse_channel_url(url)?.channel_set)
+    }
+
+    /// Encodes the channel set as a channel sharing URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `add_only` - Whether the URL should add the channels to the existing channels of a radio
+    ///     (the `/e/?add=true#` form), instead of replacing them (the `/e/#` form).
+    ///
+    /// # Returns
+    ///
+    /// The channel sharing URL.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let url = channel_set.to_url(false);
+    /// assert!(url.starts_with("https://meshtastic.org/e/#"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn to_url(&self, add_only: bool) -> String {
+        let prefix = if add_only {
+            ADD_CHANNEL_URL_PREFIX
+        } else {
+            CHANNEL_URL_PREFIX
+        };
+
+        format!("{prefix}{}", URL_SAFE_NO_PAD.encode(self.encode_to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::connections::test_support::{configured_stream_api, TestRouter};
+    use crate::connections::wrappers::mesh_channel::MeshChannel;
+
+    use super::*;
+
+    fn test_channel_set() -> protobufs::ChannelSet {
+        protobufs::ChannelSet {
+            settings: vec![
+                protobufs::ChannelSettings {
+                    psk: vec![1],
+                    name: "LongFast".to_string(),
+                    ..Default::default()
+                },
+                protobufs::ChannelSettings {
+                    psk: (0..16).collect(),
+                    name: "Private".to_string(),
+                    ..Default::default()
+                },
+            ],
+            lora_config: Some(protobufs::config::LoRaConfig {
+                use_preset: true,
+                region: protobufs::config::lo_ra_config::RegionCode::Us as i32,
+                hop_limit: 3,
+                tx_enabled: true,
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn decodes_known_url() {
+        let channel_set =
+            protobufs::ChannelSet::from_url("https://meshtastic.org/e/#CgMSAQE").unwrap();
+
+        assert_eq!(channel_set.settings.len(), 1);
+        assert_eq!(channel_set.settings[0].psk, vec![1]);
+        assert_eq!(channel_set.lora_config, None);
+    }
+
+    #[test]
+    fn accepts_padding_and_standard_alphabet() {
+        let padded = protobufs::ChannelSet::from_url("https://meshtastic.org/e/#CgMSAQE=").unwrap();
+        assert_eq!(padded.settings[0].psk, vec![1]);
+
+        let channel_set = protobufs::ChannelSettings {
+            psk: vec![0xfb, 0xff],
+            ..Default::default()
+        };
+        let encoded = base64::engine::general_purpose::STANDARD.encode(
+            protobufs::ChannelSet {
+                settings: vec![channel_set.clone()],
+                lora_config: None,
+            }
+            .encode_to_vec(),
+        );
+
+        let decoded =
+            protobufs::ChannelSet::from_url(&format!("https://meshtastic.org/e/#{encoded}"))
+                .unwrap();
+        assert_eq!(decoded.settings, vec![channel_set]);
+    }
+
+    #[test]
+    fn roundtrips_both_url_forms() {
+        let channel_set = test_channel_set();
+
+        let url = channel_set.to_url(false);
+        assert!(url.starts_with(CHANNEL_URL_PREFIX));
+        assert!(!url.contains('='));
+
+        let parsed = parse_channel_url(&url).unwrap();
+        assert_eq!(parsed.channel_set, channel_set);
+        assert!(!parsed.add_only);
+
+        let url = channel_set.to_url(true);
+        assert!(url.starts_with(ADD_CHANNEL_URL_PREFIX));
+
+        let parsed = parse_channel_url(&url).unwrap();
+        assert_eq!(parsed.channel_set, channel_set);
+        assert!(parsed.add_only);
+    }
+
+    #[test]
+    fn assigns_replacement_roles() {
+        let channel_set = test_channel_set();
+        let channels = replacement_channels(&channel_set.settings);
+
+        assert_eq!(channels.len(), MAX_CHANNEL_COUNT);
+        assert_eq!(channels[0].role, protobufs::channel::Role::Primary as i32);
+        assert_eq!(
+            channels[0].settings.as_ref(),
+            Some(&channel_set.settings[0])
+        );
+        assert_eq!(channels[1].role, protobufs::channel::Role::Secondary as i32);
+        assert_eq!(
+            channels[1].settings.as_ref(),
+            Some(&channel_set.settings[1])
+        );
+
+        for (index, channel) in channels.iter().enumerate().skip(2) {
+            assert_eq!(channel.index, index as i32);
+            assert_eq!(channel.role, protobufs::channel::Role::Disabled as i32);
+            assert_eq!(channel.settings, None);
+        }
+    }
+
+    #[test]
+    fn adds_channels_to_free_slots() {
+        let channel_set = test_channel_set();
+        let existing = vec![
+            protobufs::Channel {
+                index: 0,
+                settings: Some(channel_set.settings[0].clone()),
+                role: protobufs::channel::Role::Primary as i32,
+            },
+            protobufs::Channel {
+                index: 1,
+                settings: Some(protobufs::ChannelSettings {
+                    name: "Other".to_string(),
+                    ..Default::default()
+                }),
+                role: protobufs::channel::Role::Secondary as i32,
+            },
+        ];
+
+        let channels = added_channels(&existing, &channel_set.settings).unwrap();
+
+        assert_eq!(
+            channels,
+            vec![protobufs::Channel {
+                index: 2,
+                settings: Some(channel_set.settings[1].clone()),
+                role: protobufs::channel::Role::Secondary as i32,
+            }]
+        );
+
+        let full: Vec<protobufs::Channel> = (0..MAX_CHANNEL_COUNT as i32)
+            .map(|index| protobufs::Channel {
+                index,
+                settings: Some(protobufs::ChannelSettings {
+                    name: format!("Channel {index}"),
+                    ..Default::default()
+                }),
+                role: protobufs::channel::Role::Secondary as i32,
+            })
+            .collect();
+
+        assert!(matches!(
+            added_channels(&full, &channel_set.settings),
+            Err(Error::ChannelSlotsExhausted {
+                required: 2,
+                available: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_urls() {
+        assert!(matches!(
+            parse_channel_url("https://meshtastic.org/e/CgMSAQE"),
+            Err(Error::InvalidChannelUrl { .. })
+        ));
+        assert!(matches!(
+            parse_channel_url("https://meshtastic.org/d/#CgMSAQE"),
+            Err(Error::InvalidChannelUrl { .. })
+        ));
+        assert!(matches!(
+            parse_channel_url("https://meshtastic.org/e/#C!MSAQE"),
+            Err(Error::InvalidChannelUrl { .. })
+        ));
+        assert!(matches!(
+            parse_channel_url("https://meshtastic.org/e/#_w"),
+            Err(Error::DecodeError(_))
+        ));
+        assert!(matches!(
+            parse_channel_url("https://meshtastic.org/e/#"),
+            Err(Error::InvalidChannelUrl { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn applies_channel_urls_to_radio() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        let channel_set = test_channel_set();
+        stream_api
+            .set_channel_url(
+                &mut router,
+                &channel_set.to_url(false),
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap();
+
+        let added = protobufs::ChannelSet {
+            settings: vec![protobufs::ChannelSettings {
+                name: "Added".to_string(),
+                ..Default::default()
+            }],
+            lora_config: None,
+        };
+        stream_api
+            .set_channel_url(&mut router, &added.to_url(true), Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        // The mock radio handles packets in order, so the added channel has been applied once
+        // it is returned
+        let added_channel = stream_api
+            .get_channel(
+                &mut router,
+                MeshChannel::new(2).unwrap(),
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(added_channel.settings, Some(added.settings[0].clone()));
+
+        let state = mock_radio.state();
+        let roles: Vec<i32> = state.channels.iter().map(|channel| channel.role).collect();
+
+        assert_eq!(state.config.lora, channel_set.lora_config);
+        assert_eq!(state.channels.len(), MAX_CHANNEL_COUNT);
+        assert_eq!(
+            roles[..3],
+            [
+                protobufs::channel::Role::Primary as i32,
+                protobufs::channel::Role::Secondary as i32,
+                protobufs::channel::Role::Secondary as i32,
+            ]
+        );
+        assert_eq!(state.channels[2].settings, Some(added.settings[0].clone()));
+        assert_eq!(roles[3], protobufs::channel::Role::Disabled as i32);
+    }
+}
@@ -0,0 +1,551 @@
+use std::{fmt::Display, time::Duration};
+
+use log::warn;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+
+use super::{
+    ack::SentPacket,
+    remote_admin::AdminTarget,
+    snapshot::{split_local_config, split_local_module_config},
+    stream_api::{state, ConnectedStreamApi},
+    PacketRouter,
+};
+
+/// A guard that represents an open bulk configuration update on the connected radio, or on a
+/// remote node.
+///
+/// This struct cannot be created directly, and must be created by calling the
+/// `begin_edit_settings` method on a configured `ConnectedStreamApi` instance or on a
+/// `RemoteAdmin` handle. While the
+/// transaction is open, the radio buffers configuration, module configuration, channel and
+/// owner updates instead of restarting after each of them. The buffered updates are applied
+/// when the transaction is consumed by the `commit` method, which triggers a radio restart.
+///
+/// The transaction mutably borrows the `ConnectedStreamApi` instance, which guarantees that
+/// no other packets are sent to the radio while the transaction is open, and that a single
+/// transaction is open at a time.
+///
+/// **Note:** Dropping the transaction without calling `commit` leaves the updates buffered on
+/// the radio without applying them.
+#[derive(Debug)]
+pub struct ConfigTransaction<'a> {
+    stream_api: &'a mut ConnectedStreamApi<state::Configured>,
+    target: AdminTarget,
+    committed: bool,
+}
+
+impl<'a> ConfigTransaction<'a> {
+    /// Creates a guard for a transaction that has been started on the radio.
+    pub(crate) fn new(
+        stream_api: &'a mut ConnectedStreamApi<state::Configured>,
+        target: AdminTarget,
+    ) -> Self {
+        ConfigTransaction {
+            stream_api,
+            target,
+            committed: false,
+        }
+    }
+
+    /// Buffers a single admin setter on the target node of the transaction.
+    async fn send_setter<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        payload_variant: protobufs::admin_message::PayloadVariant,
+    ) -> Result<(), Error> {
+        self.stream_api
+            .send_admin_message_to(packet_router, self.target, true, payload_variant)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Buffers an update of the configuration of the radio. See `ConnectedStreamApi::update_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `config` - An instance of the `Config` struct to update the radio with.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the config was successfully sent to the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.update_config(packet_router, config_update).await?;
+    /// transaction.commit(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn update_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        config: protobufs::Config,
+    ) -> Result<(), Error> {
+        self.send_setter(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::SetConfig(config),
+        )
+        .await
+    }
+
+    /// Buffers an update of the module configuration of the radio. See
+    /// `ConnectedStreamApi::update_module_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `module_config` - An instance of the `ModuleConfig` struct to update the radio with.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the module config was successfully sent to the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.update_module_config(packet_router, module_config_update).await?;
+    /// transaction.commit(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn update_module_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        module_config: protobufs::ModuleConfig,
+    ) -> Result<(), Error> {
+        self.send_setter(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::SetModuleConfig(module_config),
+        )
+        .await
+    }
+
+    /// Buffers an update of a message channel of the radio. See
+    /// `ConnectedStreamApi::update_channel_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `channel_config` - An instance of the `Channel` struct to update the radio with.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the channel config was successfully sent to the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.update_channel_config(packet_router, channel_config_update).await?;
+    /// transaction.commit(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn update_channel_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        channel_config: protobufs::Channel,
+    ) -> Result<(), Error> {
+        self.send_setter(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::SetChannel(channel_config),
+        )
+        .await
+    }
+
+    /// Buffers an update of the user of the radio. See `ConnectedStreamApi::update_user`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `user` - An instance of the `User` struct to update the radio user with.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the new `User` information was successfully sent to the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.update_user(packet_router, new_user).await?;
+    /// transaction.commit(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn update_user<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        user: protobufs::User,
+    ) -> Result<(), Error> {
+        self.send_setter(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::SetOwner(user),
+        )
+        .await
+    }
+
+    /// Buffers an update of every configuration field set in the `LocalConfig` struct.
+    /// See `ConnectedStreamApi::set_local_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `local_config` - An instance of the `LocalConfig` struct to update the radio with.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the config was successfully sent to the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.set_local_config(packet_router, local_config).await?;
+    /// transaction.commit(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn set_local_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        local_config: protobufs::LocalConfig,
+    ) -> Result<(), Error> {
+        for config in split_local_config(local_config) {
+            self.update_config(packet_router, config).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Buffers an update of every module configuration field set in the `LocalModuleConfig` struct.
+    /// See `ConnectedStreamApi::set_local_module_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `local_module_config` - An instance of the `LocalModuleConfig` struct to update the radio with.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the module config was successfully sent to the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.set_local_module_config(packet_router, local_module_config).await?;
+    /// transaction.commit(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn set_local_module_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        local_module_config: protobufs::LocalModuleConfig,
+    ) -> Result<(), Error> {
+        for module_config in split_local_module_config(local_module_config) {
+            self.update_module_config(packet_router, module_config)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Buffers an update of every message channel in the list of `Channel` structs.
+    /// See `ConnectedStreamApi::set_message_channel_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `channel_config` - A list of `Channel` structs to update the radio with.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the channel configs were successfully sent to the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.set_message_channel_config(packet_router, vec![ ... ]).await?;
+    /// transaction.commit(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn set_message_channel_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        channel_config: Vec<protobufs::Channel>,
+    ) -> Result<(), Error> {
+        for channel in channel_config {
+            self.update_channel_config(packet_router, channel).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Tells the radio to apply the buffered updates, which triggers a radio restart.
+    /// This consumes the transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle for the transaction commit packet. For remote
+    /// nodes, the `wait_for_ack` method of the handle can be used to confirm that the remote
+    /// node has received the commit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.update_config(packet_router, config_update_1).await?;
+    /// transaction.update_config(packet_router, config_update_2).await?;
+    /// transaction.commit(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn commit<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_commit(packet_router).await
+    }
+
+    /// Tells the radio to apply the buffered updates in the same way as the `commit` method,
+    /// and then waits for the radio to report that it has restarted with a `Rebooted` packet.
+    ///
+    /// **Note:** Serial and TCP connections are usually closed when the radio restarts. To keep
+    /// receiving packets after the restart, connect to the radio with the
+    /// `StreamApi::connect_with_reconnect` method, which reconnects to the radio automatically.
+    ///
+    /// Only the connected radio reports restarts. For transactions on remote nodes, use the
+    /// `commit` method and wait for the acknowledgement of the remote node instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `timeout` - The maximum amount of time to wait for the radio to restart.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the radio restarted after the transaction was committed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.update_config(packet_router, config_update).await?;
+    /// transaction
+    ///     .commit_and_wait_for_reboot(packet_router, Duration::from_secs(60))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send, and with `Error::ResponseTimeout` if the radio does not
+    /// report a restart before `timeout` elapses.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn commit_and_wait_for_reboot<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        mut self,
+        packet_router: &mut R,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        self.send_commit(packet_router)
+            .await?
+            .wait_for_reboot(timeout)
+            .await
+    }
+
+    async fn send_commit<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        let sent_packet = self
+            .stream_api
+            .send_admin_message_to(
+                packet_router,
+                self.target,
+                false,
+                protobufs::admin_message::PayloadVariant::CommitEditSettings(true),
+            )
+            .await?;
+
+        self.committed = true;
+
+        Ok(sent_packet)
+    }
+}
+
+impl Drop for ConfigTransaction<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            warn!("Configuration transaction dropped without being committed, updates will not be applied");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connections::test_support::{
+        configured_stream_api, next_admin_packet, TestRouter, MY_NODE_NUM,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn sends_wrapped_admin_messages() {
+        use protobufs::admin_message::PayloadVariant;
+
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        let mut transaction = stream_api.begin_edit_settings(&mut router).await.unwrap();
+        transaction
+            .update_user(&mut router, protobufs::User::default())
+            .await
+            .unwrap();
+        transaction.commit(&mut router).await.unwrap();
+
+        let (packet, payload_variant) = next_admin_packet(&mock_radio, 0).await;
+        assert_eq!(packet.to, MY_NODE_NUM);
+        assert_eq!(payload_variant, PayloadVariant::BeginEditSettings(true));
+        assert!(matches!(
+            next_admin_packet(&mock_radio, 1).await.1,
+            PayloadVariant::SetOwner(_)
+        ));
+        assert_eq!(
+            next_admin_packet(&mock_radio, 2).await.1,
+            PayloadVariant::CommitEditSettings(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn waits_for_reboot_after_commit() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        let transaction = stream_api.begin_edit_settings(&mut router).await.unwrap();
+        // The mock radio reports a reboot once the transaction is committed
+        transaction
+            .commit_and_wait_for_reboot(&mut router, Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        assert_eq!(mock_radio.received_mesh_packets().len(), 2);
+    }
+}
@@ -0,0 +1,451 @@
+use aes::{Aes128, Aes256};
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use prost::Message;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+
+/// AES in CTR mode, with the whole 16 byte counter block incremented as a big-endian integer.
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+type Aes256Ctr = ctr::Ctr128BE<Aes256>;
+
+/// The default channel key of the firmware, which the 1-byte PSK shorthands are derived from.
+pub const DEFAULT_PSK: [u8; 16] = [
+    0xd4, 0xf1, 0xbb, 0x3a, 0x20, 0x29, 0x07, 0x59, 0xf0, 0xbc, 0xff, 0xab, 0xcf, 0x4e, 0x69, 0x01,
+];
+
+/// An enum that represents the AES key of a channel, as derived from the `psk` field of its
+/// `ChannelSettings`.
+///
+/// # Variants
+///
+/// * `None` - The channel is not encrypted, and payloads are sent in plain text.
+/// * `Aes128` - The channel is encrypted with AES-128 in CTR mode.
+/// * `Aes256` - The channel is encrypted with AES-256 in CTR mode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChannelKey {
+    None,
+    Aes128([u8; 16]),
+    Aes256([u8; 32]),
+}
+
+impl ChannelKey {
+    /// Derives the key of a channel from its PSK, in the same way as the firmware.
+    ///
+    /// An empty PSK disables encryption. A 1-byte PSK is a shorthand for one of the default
+    /// keys, where `0` disables encryption, `1` selects `DEFAULT_PSK`, and higher values select
+    /// `DEFAULT_PSK` with its last byte incremented by the value minus one. Other PSKs shorter
+    /// than 16 or 32 bytes are padded with zeros.
+    ///
+    /// # Arguments
+    ///
+    /// * `psk` - The `psk` field of the `ChannelSettings` of the channel.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `ChannelKey` of the channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let key = ChannelKey::from_psk(&[1])?;
+    /// assert_eq!(key, ChannelKey::Aes128(DEFAULT_PSK));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidChannelKey` if the PSK is longer than 32 bytes.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn from_psk(psk: &[u8]) -> Result<ChannelKey, Error> {
+        match psk.len() {
+            0 => Ok(ChannelKey::None),
+            1 => match psk[0] {
+                0 => Ok(ChannelKey::None),
+                index => {
+                    let mut key = DEFAULT_PSK;
+                    key[15] = key[15].wrapping_add(index - 1);
+                    Ok(ChannelKey::Aes128(key))
+                }
+            },
+            2..=16 => {
+                let mut key = [0u8; 16];
+                key[..psk.len()].copy_from_slice(psk);
+                Ok(ChannelKey::Aes128(key))
+            }
+            17..=32 => {
+                let mut key = [0u8; 32];
+                key[..psk.len()].copy_from_slice(psk);
+                Ok(ChannelKey::Aes256(key))
+            }
+            length => Err(Error::InvalidChannelKey { length }),
+        }
+    }
+
+    /// Returns the bytes of the key, which are empty if the channel is not encrypted.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            ChannelKey::None => &[],
+            ChannelKey::Aes128(key) => key,
+            ChannelKey::Aes256(key) => key,
+        }
+    }
+
+    /// Returns whether payloads sent with this key are encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        !matches!(self, ChannelKey::None)
+    }
+
+    /// Applies the AES-CTR keystream of a packet to the given bytes. Encryption and decryption
+    /// are the same operation in CTR mode.
+    fn apply_keystream(&self, packet_id: u32, from: u32, bytes: &mut [u8]) {
+        let nonce = packet_nonce(packet_id, from);
+
+        match self {
+            ChannelKey::None => {}
+            ChannelKey::Aes128(key) => {
+                Aes128Ctr::new(key.into(), &nonce.into()).apply_keystream(bytes)
+            }
+            ChannelKey::Aes256(key) => {
+                Aes256Ctr::new(key.into(), &nonce.into()).apply_keystream(bytes)
+            }
+        }
+    }
+}
+
+/// Computes the 1-byte hash of a channel, which is sent in the `channel` field of encrypted
+/// packets in place of the channel index.
+///
+/// **Note:** Channels with an empty name are named after the modem preset of the radio, such
+/// as `LongFast`, and that name must be passed to this function.
+///
+/// # Arguments
+///
+/// * `name` - The name of the channel.
+/// * `key` - The `ChannelKey` of the channel.
+///
+/// # Returns
+///
+/// The hash of the channel, which is the XOR of all bytes of the name and of the key.
+///
+/// # Examples
+///
+/// ```
+/// let hash = channel_hash("LongFast", &ChannelKey::from_psk(&[1])?);
+/// assert_eq!(hash, 8);
+/// ```
+///
+/// # Errors
+///
+/// None
+///
+/// # Panics
+///
+/// None
+///
+pub fn channel_hash(name: &str, key: &ChannelKey) -> u8 {
+    name.bytes()
+        .chain(key.as_bytes().iter().copied())
+        .fold(0, |hash, byte| hash ^ byte)
+}
+
+/// Builds the AES-CTR nonce of a packet, which is made of the packet ID as a little-endian
+/// 64-bit integer, followed by the sender node number as a little-endian 32-bit integer and four
+/// zero bytes.
+pub fn packet_nonce(packet_id: u32, from: u32) -> [u8; 16] {
+    let mut nonce = [0u8; 16];
+    nonce[..8].copy_from_slice(&u64::from(packet_id).to_le_bytes());
+    nonce[8..12].copy_from_slice(&from.to_le_bytes());
+    nonce
+}
+
+/// Decrypts the `Encrypted` payload of a mesh packet into its `Data` payload.
+///
+/// # Arguments
+///
+/// * `mesh_packet` - The `MeshPacket` to decrypt. Packets that are already decoded are returned
+///     unchanged.
+/// * `key` - The `ChannelKey` of the channel the packet was sent on.
+///
+/// # Returns
+///
+/// A result resolving to a copy of the packet with a `Decoded` payload.
+///
+/// # Examples
+///
+/// ```
+/// let key = ChannelKey::from_psk(&channel_settings.psk)?;
+/// let mesh_packet = decrypt_packet(&mesh_packet, &key)?;
+/// ```
+///
+/// # Errors
+///
+/// Fails with `Error::PacketDecryptionFailed` if the decrypted bytes are not a valid `Data`
+/// message, which usually means that the packet was encrypted with another key.
+///
+/// # Panics
+///
+/// None
+///
+pub fn decrypt_packet(
+    mesh_packet: &protobufs::MeshPacket,
+    key: &ChannelKey,
+) -> Result<protobufs::MeshPacket, Error> {
+    let Some(protobufs::mesh_packet::PayloadVariant::Encrypted(encrypted)) =
+        &mesh_packet.payload_variant
+    else {
+        return Ok(mesh_packet.clone());
+    };
+
+    let mut bytes = encrypted.clone();
+    key.apply_keystream(mesh_packet.id, mesh_packet.from, &mut bytes);
+
+    let data =
+        protobufs::Data::decode(bytes.as_slice()).map_err(|_| Error::PacketDecryptionFailed {
+            packet_id: mesh_packet.id,
+        })?;
+
+    Ok(protobufs::MeshPacket {
+        payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)),
+        ..mesh_packet.clone()
+    })
+}
+
+/// Encrypts the `Data` payload of a mesh packet into an `Encrypted` payload.
+///
+/// # Arguments
+///
+/// * `mesh_packet` - The `MeshPacket` to encrypt, which must have its `id` and `from` fields set.
+///     Packets that are already encrypted are returned unchanged.
+/// * `key` - The `ChannelKey` of the channel the packet is sent on.
+///
+/// # Returns
+///
+/// A result resolving to a copy of the packet with an `Encrypted` payload.
+///
+/// # Examples
+///
+/// ```
+/// let key = ChannelKey::from_psk(&channel_settings.psk)?;
+/// let mut mesh_packet = encrypt_packet(&mesh_packet, &key)?;
+/// mesh_packet.channel = channel_hash("LongFast", &key).into();
+/// ```
+///
+/// # Errors
+///
+/// None
+///
+/// # Panics
+///
+/// None
+///
+pub fn encrypt_packet(
+    mesh_packet: &protobufs::MeshPacket,
+    key: &ChannelKey,
+) -> Result<protobufs::MeshPacket, Error> {
+    let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) = &mesh_packet.payload_variant
+    else {
+        return Ok(mesh_packet.clone());
+    };
+
+    let mut bytes = data.encode_to_vec();
+    key.apply_keystream(mesh_packet.id, mesh_packet.from, &mut bytes);
+
+    Ok(protobufs::MeshPacket {
+        payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Encrypted(bytes)),
+        ..mesh_packet.clone()
+    })
+}
+
+/// A struct that holds the keys of known channels, and decrypts packets with the key of the
+/// channel whose hash matches the `channel` field of the packet.
+#[derive(Clone, Debug, Default)]
+pub struct ChannelKeyring {
+    keys: Vec<(u8, ChannelKey)>,
+}
+
+impl ChannelKeyring {
+    /// Creates an empty keyring.
+    pub fn new() -> ChannelKeyring {
+        ChannelKeyring::default()
+    }
+
+    /// Adds the key of a channel, returning the hash of the channel.
+    pub fn add(&mut self, name: &str, key: ChannelKey) -> u8 {
+        let hash = channel_hash(name, &key);
+        self.keys.push((hash, key));
+        hash
+    }
+
+    /// Decrypts a packet with the keys of the channels whose hash matches the `channel` field of
+    /// the packet, trying each matching key in the order the keys were added.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh_packet` - The `MeshPacket` to decrypt.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a copy of the packet with a `Decoded` payload.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut keyring = ChannelKeyring::new();
+    /// keyring.add("LongFast", ChannelKey::from_psk(&[1])?);
+    ///
+    /// let mesh_packet = keyring.decrypt(&mesh_packet)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::PacketDecryptionFailed` if no matching key decrypts the packet.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn decrypt(
+        &self,
+        mesh_packet: &protobufs::MeshPacket,
+    ) -> Result<protobufs::MeshPacket, Error> {
+        self.keys
+            .iter()
+            .filter(|(hash, _)| u32::from(*hash) == mesh_packet.channel)
+            .find_map(|(_, key)| decrypt_packet(mesh_packet, key).ok())
+            .ok_or(Error::PacketDecryptionFailed {
+                packet_id: mesh_packet.id,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn expands_psk_shorthands_and_hashes_channels() {
+        let default_key = ChannelKey::from_psk(&[1]).unwrap();
+        assert_eq!(default_key, ChannelKey::Aes128(DEFAULT_PSK));
+
+        let mut second_key = DEFAULT_PSK;
+        second_key[15] = 0x02;
+        assert_eq!(
+            ChannelKey::from_psk(&[2]).unwrap(),
+            ChannelKey::Aes128(second_key)
+        );
+
+        assert_eq!(ChannelKey::from_psk(&[]).unwrap(), ChannelKey::None);
+        assert_eq!(ChannelKey::from_psk(&[0]).unwrap(), ChannelKey::None);
+        assert_eq!(
+            ChannelKey::from_psk(&[7; 5]).unwrap().as_bytes()[4..6],
+            [7, 0]
+        );
+        assert!(matches!(
+            ChannelKey::from_psk(&[7; 20]).unwrap(),
+            ChannelKey::Aes256(_)
+        ));
+        assert!(matches!(
+            ChannelKey::from_psk(&[7; 33]),
+            Err(Error::InvalidChannelKey { length: 33 })
+        ));
+
+        // The default primary channel of the firmware is reported with a channel hash of 8
+        assert_eq!(channel_hash("LongFast", &default_key), 8);
+        assert_eq!(channel_hash("", &ChannelKey::None), 0);
+    }
+
+    #[test]
+    fn decrypts_longfast_packet() {
+        // The expected ciphertexts were computed with OpenSSL, independently of this module
+        let encrypted = protobufs::MeshPacket {
+            id: 0x6a3f2c91,
+            from: 0x433e1f88,
+            to: u32::MAX,
+            channel: 8,
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Encrypted(hex(
+                "c1b5da4458af3dc97c0b41f4bfe165346aac02911eed5577",
+            ))),
+            ..Default::default()
+        };
+        let data = protobufs::Data {
+            portnum: protobufs::PortNum::TextMessageApp as i32,
+            payload: b"Hello from the mesh!".to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(
+            data.encode_to_vec(),
+            hex("0801121448656c6c6f2066726f6d20746865206d65736821")
+        );
+
+        let key = ChannelKey::from_psk(&[1]).unwrap();
+        let decrypted = decrypt_packet(&encrypted, &key).unwrap();
+        assert_eq!(
+            decrypted.payload_variant,
+            Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                data.clone()
+            ))
+        );
+        assert_eq!(encrypt_packet(&decrypted, &key).unwrap(), encrypted);
+
+        let key = ChannelKey::from_psk(&(0..32).collect::<Vec<u8>>()).unwrap();
+        let encrypted = encrypt_packet(&decrypted, &key).unwrap();
+        assert_eq!(
+            encrypted.payload_variant,
+            Some(protobufs::mesh_packet::PayloadVariant::Encrypted(hex(
+                "c7490813a73f80b2bbf0b9d2d79f1162e309b673385f626c"
+            )))
+        );
+    }
+
+    #[test]
+    fn encrypts_and_decrypts_packets() {
+        let decoded = protobufs::MeshPacket {
+            id: 0x12345678,
+            from: 0xdeadbeef,
+            to: u32::MAX,
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                protobufs::Data {
+                    portnum: protobufs::PortNum::TextMessageApp as i32,
+                    payload: b"hello".to_vec(),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            packet_nonce(decoded.id, decoded.from).to_vec(),
+            hex("7856341200000000efbeadde00000000")
+        );
+
+        let key = ChannelKey::from_psk(&[1]).unwrap();
+        let mut encrypted = encrypt_packet(&decoded, &key).unwrap();
+        assert_eq!(decrypt_packet(&encrypted, &key).unwrap(), decoded);
+
+        let mut keyring = ChannelKeyring::new();
+        keyring.add("Private", ChannelKey::from_psk(&[9; 32]).unwrap());
+        encrypted.channel = keyring.add("LongFast", key).into();
+
+        let decrypted = keyring.decrypt(&encrypted).unwrap();
+        assert_eq!(decrypted.payload_variant, decoded.payload_variant);
+
+        encrypted.channel = 0;
+        assert!(matches!(
+            keyring.decrypt(&encrypted),
+            Err(Error::PacketDecryptionFailed {
+                packet_id: 0x12345678
+            })
+        ));
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_util::Stream;
+
+use crate::packet::PacketReceiver;
+use crate::protobufs;
+
+use super::payload::{decode_mesh_packet, DecodedPayload};
+use super::store_forward::StoredTextMessage;
+
+/// An enum that represents a single packet received from the radio, with the payloads of mesh
+/// packets already decoded. This allows packet handlers to match on a single enum instead of
+/// matching on nested `FromRadio`, `MeshPacket` and `Data` structs.
+///
+/// # Variants
+///
+/// * `Packet` - A mesh packet, along with its decoded payload.
+/// * `DecodeFailed` - A mesh packet whose payload is not a valid message for its port.
+/// * `StoredText` - A text message replayed by a Store & Forward server from its history.
+/// * `MyInfo` - Information about the connected radio.
+/// * `NodeInfo` - An entry of the node database of the radio.
+/// * `Channel` - A message channel of the radio.
+/// * `Config` - A section of the configuration of the radio.
+/// * `ModuleConfig` - A section of the module configuration of the radio.
+/// * `Metadata` - Metadata on the firmware and hardware of the radio.
+/// * `ConfigComplete` - The radio has finished sending its configuration for the given ID.
+/// * `Rebooted` - The radio has rebooted.
+/// * `QueueStatus` - The status of the outgoing packet queue of the radio.
+/// * `LogRecord` - A log record of the radio.
+/// * `MqttClientProxyMessage` - A message to be published to an MQTT broker on behalf of the radio.
+/// * `Other` - Any other packet, such as file transfer packets.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshEvent {
+    Packet {
+        packet: protobufs::MeshPacket,
+        payload: DecodedPayload,
+    },
+    DecodeFailed {
+        packet: protobufs::MeshPacket,
+        error: prost::DecodeError,
+    },
+    StoredText(StoredTextMessage),
+    MyInfo(protobufs::MyNodeInfo),
+    NodeInfo(protobufs::NodeInfo),
+    Channel(protobufs::Channel),
+    Config(protobufs::Config),
+    ModuleConfig(protobufs::ModuleConfig),
+    Metadata(protobufs::DeviceMetadata),
+    ConfigComplete(u32),
+    Rebooted,
+    QueueStatus(protobufs::QueueStatus),
+    LogRecord(protobufs::LogRecord),
+    MqttClientProxyMessage(protobufs::MqttClientProxyMessage),
+    Other(protobufs::FromRadio),
+}
+
+impl From<protobufs::MeshPacket> for MeshEvent {
+    fn from(packet: protobufs::MeshPacket) -> Self {
+        if let Some(message) = StoredTextMessage::from_mesh_packet(&packet) {
+            return MeshEvent::StoredText(message);
+        }
+
+        let decoded = decode_mesh_packet(&packet);
+
+        match decoded {
+            Ok(payload) => MeshEvent::Packet { packet, payload },
+            Err(error) => MeshEvent::DecodeFailed { packet, error },
+        }
+    }
+}
+
+impl From<protobufs::FromRadio> for MeshEvent {
+    fn from(from_radio: protobufs::FromRadio) -> Self {
+        use protobufs::from_radio::PayloadVariant;
+
+        match from_radio.payload_variant {
+            Some(PayloadVariant::Packet(packet)) => packet.into(),
+            Some(PayloadVariant::MyInfo(my_info)) => MeshEvent::MyInfo(my_info),
+            Some(PayloadVariant::NodeInfo(node_info)) => MeshEvent::NodeInfo(node_info),
+            Some(PayloadVariant::Channel(channel)) => MeshEvent::Channel(channel),
+            Some(PayloadVariant::Config(config)) => MeshEvent::Config(config),
+            Some(PayloadVariant::ModuleConfig(module_config)) => {
+                MeshEvent::ModuleConfig(module_config)
+            }
+            Some(PayloadVariant::Metadata(metadata)) => MeshEvent::Metadata(metadata),
+            Some(PayloadVariant::ConfigCompleteId(config_id)) => {
+                MeshEvent::ConfigComplete(config_id)
+            }
+            Some(PayloadVariant::Rebooted(true)) => MeshEvent::Rebooted,
+            Some(PayloadVariant::QueueStatus(queue_status)) => MeshEvent::QueueStatus(queue_status),
+            Some(PayloadVariant::LogRecord(log_record)) => MeshEvent::LogRecord(log_record),
+            Some(PayloadVariant::MqttClientProxyMessage(message)) => {
+                MeshEvent::MqttClientProxyMessage(message)
+            }
+            payload_variant => MeshEvent::Other(protobufs::FromRadio {
+                payload_variant,
+                ..from_radio
+            }),
+        }
+    }
+}
+
+/// A wrapper around a `PacketReceiver` that converts every packet received from the radio into
+/// a `MeshEvent`.
+///
+/// Events can be received with the `recv` method, or consumed as a `futures_util::Stream`.
+///
+/// # Examples
+///
+/// ```
+/// let (decoded_listener, stream_api) = stream_api.connect(tcp_stream).await;
+/// let mut events = MeshEventStream::new(decoded_listener);
+///
+/// while let Some(event) = events.recv().await {
+///     match event {
+///         MeshEvent::Packet { packet, payload: DecodedPayload::Text(text) } => {
+///             println!("{}: {}", packet.from, text)
+///         }
+///         MeshEvent::NodeInfo(node_info) => println!("Node: {:?}", node_info),
+///         _ => {}
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MeshEventStream {
+    packet_rx: PacketReceiver,
+}
+
+impl MeshEventStream {
+    /// Wraps the specified `PacketReceiver`.
+    pub fn new(packet_rx: PacketReceiver) -> Self {
+        MeshEventStream { packet_rx }
+    }
+
+    /// Receives the next event, or `None` once the connection to the radio has been closed.
+    pub async fn recv(&mut self) -> Option<MeshEvent> {
+        self.packet_rx.recv().await.map(MeshEvent::from)
+    }
+
+    /// Returns the wrapped `PacketReceiver`.
+    pub fn into_inner(self) -> PacketReceiver {
+        self.packet_rx
+    }
+}
+
+impl From<PacketReceiver> for MeshEventStream {
+    fn from(packet_rx: PacketReceiver) -> Self {
+        MeshEventStream::new(packet_rx)
+    }
+}
+
+impl Stream for MeshEventStream {
+    type Item = MeshEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.packet_rx
+            .poll_recv(cx)
+            .map(|packet| packet.map(MeshEvent::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use prost::Message;
+
+    use super::*;
+
+    fn from_radio(payload_variant: protobufs::from_radio::PayloadVariant) -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(payload_variant),
+        }
+    }
+
+    fn text_packet(text: &str) -> protobufs::MeshPacket {
+        protobufs::MeshPacket {
+            from: 7,
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                protobufs::Data {
+                    portnum: protobufs::PortNum::TextMessageApp as i32,
+                    payload: text.as_bytes().to_vec(),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn converts_received_packets_to_events() {
+        let (packet_tx, packet_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut events = MeshEventStream::new(packet_rx);
+
+        packet_tx
+            .send(from_radio(protobufs::from_radio::PayloadVariant::Packet(
+                text_packet("hello"),
+            )))
+            .unwrap();
+        packet_tx
+            .send(from_radio(
+                protobufs::from_radio::PayloadVariant::ConfigCompleteId(7),
+            ))
+            .unwrap();
+        packet_tx
+            .send(from_radio(protobufs::from_radio::PayloadVariant::Rebooted(
+                true,
+            )))
+            .unwrap();
+        drop(packet_tx);
+
+        assert_eq!(
+            events.recv().await,
+            Some(MeshEvent::Packet {
+                packet: text_packet("hello"),
+                payload: DecodedPayload::Text("hello".to_string()),
+            })
+        );
+        assert_eq!(events.next().await, Some(MeshEvent::ConfigComplete(7)));
+        assert_eq!(events.next().await, Some(MeshEvent::Rebooted));
+        assert_eq!(events.recv().await, None);
+    }
+
+    #[test]
+    fn reports_invalid_payloads() {
+        let packet = protobufs::MeshPacket {
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                protobufs::Data {
+                    portnum: protobufs::PortNum::TelemetryApp as i32,
+                    payload: vec![0xff],
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            MeshEvent::from(packet),
+            MeshEvent::DecodeFailed { .. }
+        ));
+
+        let position = protobufs::Position {
+            latitude_i: 1,
+            ..Default::default()
+        };
+        let packet = protobufs::MeshPacket {
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                protobufs::Data {
+                    portnum: protobufs::PortNum::PositionApp as i32,
+                    payload: position.encode_to_vec(),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            MeshEvent::from(packet),
+            MeshEvent::Packet {
+                payload: DecodedPayload::Position(decoded),
+                ..
+            } if decoded == position
+        ));
+    }
+}
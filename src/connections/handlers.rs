@@ -6,6 +6,7 @@ use log::{debug, error, trace, warn};
 use prost::Message;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::spawn;
+use tokio::sync::broadcast;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
@@ -147,8 +148,9 @@ pub fn spawn_processing_handler(
     cancellation_token: CancellationToken,
     read_output_rx: UnboundedReceiver<IncomingStreamData>,
     decoded_packet_tx: UnboundedSender<protobufs::FromRadio>,
+    packet_broadcast_tx: broadcast::Sender<protobufs::FromRadio>,
 ) -> JoinHandle<Result<(), Error>> {
-    let handle = start_processing_handler(read_output_rx, decoded_packet_tx);
+    let handle = start_processing_handler(read_output_rx, decoded_packet_tx, packet_broadcast_tx);
 
     spawn(async move {
         tokio::select! {
@@ -167,13 +169,25 @@ pub fn spawn_processing_handler(
 async fn start_processing_handler(
     mut read_output_rx: tokio::sync::mpsc::UnboundedReceiver<IncomingStreamData>,
     decoded_packet_tx: UnboundedSender<protobufs::FromRadio>,
+    packet_broadcast_tx: broadcast::Sender<protobufs::FromRadio>,
 ) {
     debug!("Started message processing handler");
 
-    let mut buffer = StreamBuffer::new(decoded_packet_tx);
+    let (buffer_output_tx, mut buffer_output_rx) =
+        tokio::sync::mpsc::unbounded_channel::<protobufs::FromRadio>();
+    let mut buffer = StreamBuffer::new(buffer_output_tx);
 
     while let Some(message) = read_output_rx.recv().await {
         buffer.process_incoming_bytes(message);
+
+        while let Ok(packet) = buffer_output_rx.try_recv() {
+            // Having no internal subscribers (e.g., no pending responses) is not an error
+            let _ = packet_broadcast_tx.send(packet.clone());
+
+            if let Err(e) = decoded_packet_tx.send(packet) {
+                error!("Failed to send decoded packet: {e}");
+            }
+        }
     }
 
     debug!("Processing read_output_rx channel closed");
@@ -0,0 +1,777 @@
+use std::future::Future;
+use std::time::Duration;
+
+use log::{debug, trace, warn};
+use reqwest::{header::CONTENT_TYPE, Client};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+use crate::errors_internal::{Error, InternalStreamError};
+use crate::utils_internal::{format_data_packet, take_next_frame};
+
+use super::stream_api::StreamHandle;
+
+/// The path that `ToRadio` packets are sent to with `PUT` requests.
+pub const HTTP_TO_RADIO_PATH: &str = "/api/v1/toradio";
+
+/// The path that `FromRadio` packets are polled from with `GET` requests.
+pub const HTTP_FROM_RADIO_PATH: &str = "/api/v1/fromradio";
+
+/// The content type of the protobuf bodies exchanged with the radio.
+const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
+/// The size of the in-memory pipe between the `StreamApi` and the HTTP polling task.
+const HTTP_STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A struct that configures the connection of an HTTP stream built by `build_http_stream`.
+///
+/// # Fields
+///
+/// * `tls_enabled` - Whether to connect over HTTPS. Ignored if the address includes a scheme.
+/// * `accept_invalid_certs` - Whether to accept invalid TLS certificates. The firmware serves
+///     HTTPS with a self-signed certificate, which is rejected unless this field is set.
+/// * `poll_interval` - The time to wait before polling the radio again once its queue is empty.
+///     The radio is polled immediately after every packet sent to it.
+/// * `request_timeout` - The maximum duration of a single HTTP request.
+/// * `max_retries` - The number of times a failed request is retried before the stream fails.
+/// * `retry_backoff` - The delay before the first retry of a failed request, which doubles after
+///     every further failure.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpStreamOptions {
+    pub tls_enabled: bool,
+    pub accept_invalid_certs: bool,
+    pub poll_interval: Duration,
+    pub request_timeout: Duration,
+    pub max_retries: u32,
+    pub retry_backoff: Duration,
+}
+
+impl Default for HttpStreamOptions {
+    fn default() -> Self {
+        HttpStreamOptions {
+            tls_enabled: false,
+            accept_invalid_certs: false,
+            poll_interval: Duration::from_secs(1),
+            request_timeout: Duration::from_secs(10),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// A helper method that connects to a radio over the HTTP API exposed by nodes on Wi-Fi or
+/// Ethernet, and returns a stream that can be passed to the `StreamApi::connect` method.
+///
+/// The radio sends and receives unframed protobufs over HTTP, with `PUT /api/v1/toradio` and
+/// `GET /api/v1/fromradio` requests. The returned stream is backed by a background task that
+/// sends the packets written to the stream to the radio, and polls the radio for packets at the
+/// configured interval. The task stops when the stream is dropped.
+///
+/// Failed requests, such as requests to a radio that is briefly unreachable on Wi-Fi, are retried
+/// with the `max_retries` and `retry_backoff` options. If a request still fails, the task fails
+/// and the stream is closed. To survive longer outages, the connection can be supervised with
+/// `StreamApi::connect_with_reconnect`, which builds a new stream once the old one has failed.
+///
+/// # Arguments
+///
+/// * `address` - The address of the radio, such as `meshtastic.local` or `192.168.0.1:80`. A
+///     `http://` or `https://` scheme may be included to override the `tls_enabled` option.
+/// * `options` - The `HttpStreamOptions` of the connection.
+///
+/// # Returns
+///
+/// Returns a result that resolves to a `StreamHandle` wrapping the in-memory stream.
+///
+/// # Examples
+///
+/// ```
+/// let http_stream = utils::build_http_stream("meshtastic.local".to_string(), HttpStreamOptions::default()).await?;
+/// let (decoded_listener, stream_api) = stream_api.connect(http_stream).await;
+/// ```
+///
+/// # Errors
+///
+/// Fails with `Error::StreamBuildError` if the HTTP client cannot be built, or if the radio does
+/// not answer the first poll.
+///
+/// # Panics
+///
+/// None
+///
+pub async fn build_http_stream(
+    address: String,
+    options: HttpStreamOptions,
+) -> Result<StreamHandle<DuplexStream>, Error> {
+    let base_url = match address.contains("://") {
+        true => address.trim_end_matches('/').to_string(),
+        false => match options.tls_enabled {
+            true => format!("https://{}", address.trim_end_matches('/')),
+            false => format!("http://{}", address.trim_end_matches('/')),
+        },
+    };
+
+    let client = Client::builder()
+        .timeout(options.request_timeout)
+        .danger_accept_invalid_certs(options.accept_invalid_certs)
+        .build()
+        .map_err(|e| Error::StreamBuildError {
+            source: Box::new(e),
+            description: "Failed to build HTTP client".to_string(),
+        })?;
+
+    let radio = HttpRadio { client, base_url };
+
+    // * Poll once to check that the radio is reachable before handing out the stream
+    let first_packet = radio
+        .fetch_from_radio()
+        .await
+        .map_err(|e| Error::StreamBuildError {
+            source: Box::new(e),
+            description: format!("Failed to connect to {}", radio.base_url),
+        })?;
+
+    let (client_stream, radio_stream) = tokio::io::duplex(HTTP_STREAM_BUFFER_SIZE);
+    let join_handle = tokio::spawn(run_http_stream(radio_stream, radio, first_packet, options));
+
+    Ok(StreamHandle {
+        stream: client_stream,
+        join_handle: Some(join_handle),
+    })
+}
+
+/// The HTTP endpoints of a radio.
+struct HttpRadio {
+    client: Client,
+    base_url: String,
+}
+
+impl HttpRadio {
+    /// Polls the next packet queued by the radio, or `None` if the queue is empty.
+    async fn fetch_from_radio(&self) -> Result<Option<Vec<u8>>, reqwest::Error> {
+        let response = self
+            .client
+            .get(format!("{}{HTTP_FROM_RADIO_PATH}?all=false", self.base_url))
+            .header("Accept", PROTOBUF_CONTENT_TYPE)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let body = response.bytes().await?;
+
+        Ok((!body.is_empty()).then(|| body.to_vec()))
+    }
+
+    /// Sends an encoded `ToRadio` packet to the radio.
+    async fn send_to_radio(&self, packet: Vec<u8>) -> Result<(), reqwest::Error> {
+        self.client
+            .put(format!("{}{HTTP_TO_RADIO_PATH}", self.base_url))
+            .header(CONTENT_TYPE, PROTOBUF_CONTENT_TYPE)
+            .body(packet)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Forwards packets between the in-memory stream and the HTTP endpoints of the radio.
+async fn run_http_stream(
+    radio_stream: DuplexStream,
+    radio: HttpRadio,
+    first_packet: Option<Vec<u8>>,
+    options: HttpStreamOptions,
+) -> Result<(), Error> {
+    let read_error = |e: reqwest::Error| {
+        Error::InternalStreamError(InternalStreamError::StreamReadError {
+            source: Box::new(e),
+        })
+    };
+    let write_error = |e: reqwest::Error| {
+        Error::InternalStreamError(InternalStreamError::StreamWriteError {
+            source: Box::new(e),
+        })
+    };
+
+    let (mut read_stream, mut write_stream) = tokio::io::split(radio_stream);
+    let mut pending = Vec::new();
+    let mut buffer = [0u8; 1024];
+
+    if let Some(packet) = first_packet {
+        write_frame(&mut write_stream, packet).await?;
+    }
+
+    loop {
+        // * The radio returns a single packet per request, so drain its queue before waiting
+        while let Some(packet) = with_retries(&options, || radio.fetch_from_radio())
+            .await
+            .map_err(read_error)?
+        {
+            trace!("Received {} bytes over HTTP", packet.len());
+            write_frame(&mut write_stream, packet).await?;
+        }
+
+        tokio::select! {
+            read_result = read_stream.read(&mut buffer) => {
+                let n = read_result.map_err(|e| {
+                    Error::InternalStreamError(InternalStreamError::StreamReadError {
+                        source: Box::new(e),
+                    })
+                })?;
+
+                if n == 0 {
+                    debug!("HTTP stream closed by client");
+                    return Ok(());
+                }
+
+                pending.extend_from_slice(&buffer[..n]);
+
+                while let Some(packet) = take_next_frame(&mut pending) {
+                    trace!("Sending {} bytes over HTTP", packet.len());
+                    with_retries(&options, || radio.send_to_radio(packet.clone()))
+                        .await
+                        .map_err(write_error)?;
+                }
+            }
+            _ = tokio::time::sleep(options.poll_interval) => (),
+        }
+    }
+}
+
+/// Runs an HTTP request, and retries it up to `max_retries` times if it fails, waiting
+/// `retry_backoff` before the first retry and twice as long before every further retry.
+async fn with_retries<T, F, Fut>(
+    options: &HttpStreamOptions,
+    mut request: F,
+) -> Result<T, reqwest::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, reqwest::Error>>,
+{
+    let mut delay = options.retry_backoff;
+    let mut retries = 0;
+
+    loop {
+        match request().await {
+            Ok(value) => return Ok(value),
+            Err(e) if retries < options.max_retries => {
+                retries += 1;
+                warn!(
+                    "HTTP request failed, retrying in {delay:?} ({retries}/{}): {e}",
+                    options.max_retries
+                );
+
+                tokio::time::sleep(delay).await;
+                delay = delay.saturating_mul(2);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Writes an encoded packet to the stream with the framing used by serial and TCP connections.
+async fn write_frame<S: AsyncWriteExt + Unpin>(
+    write_stream: &mut S,
+    packet: Vec<u8>,
+) -> Result<(), Error> {
+    let packet = format_data_packet(packet.into())?;
+
+    write_stream.write_all(packet.data()).await.map_err(|e| {
+        Error::InternalStreamError(InternalStreamError::StreamWriteError {
+            source: Box::new(e),
+        })
+    })
+}
+
+#[cfg(any(test, feature = "testing"))]
+pub use stub::HttpStubServer;
+
+#[cfg(any(test, feature = "testing"))]
+mod stub {
+    use std::collections::VecDeque;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+
+    use log::warn;
+    use tokio::io::{ReadHalf, WriteHalf};
+    use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+    use tokio::task::JoinHandle;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+
+    /// The maximum size of a request accepted by the stub server.
+    const MAX_REQUEST_LENGTH: usize = 64 * 1024;
+
+    /// The packets received from the stream that have not been polled yet.
+    type PacketQueue = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+    /// A local HTTP server that exposes a framed stream, such as the stream of a `MockRadio`,
+    /// through the HTTP API of the firmware. This allows code built on `build_http_stream` to
+    /// be tested without a radio.
+    ///
+    /// The server answers `PUT /api/v1/toradio` requests by writing the body to the stream, and
+    /// `GET /api/v1/fromradio` requests with the oldest packet read from the stream, or with an
+    /// empty body if no packet is queued. Requests are handled one at a time, like the firmware.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let (mock_radio, stream_handle) = MockRadio::new(MockRadioState::default());
+    /// let server = HttpStubServer::bind("127.0.0.1:0", stream_handle).await?;
+    ///
+    /// let http_stream =
+    ///     build_http_stream(server.local_addr().to_string(), HttpStreamOptions::default()).await?;
+    /// let (decoded_listener, stream_api) = StreamApi::new().connect(http_stream).await;
+    /// ```
+    #[derive(Debug)]
+    pub struct HttpStubServer {
+        local_addr: SocketAddr,
+        cancellation_token: CancellationToken,
+        handles: Vec<JoinHandle<()>>,
+    }
+
+    impl HttpStubServer {
+        /// Starts serving the given stream on the given address.
+        ///
+        /// # Arguments
+        ///
+        /// * `addr` - The address to listen on. Port `0` binds to a random free port.
+        /// * `stream_handle` - The `StreamHandle` of the radio to expose.
+        ///
+        /// # Returns
+        ///
+        /// A result resolving to the running `HttpStubServer`.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// let server = HttpStubServer::bind("127.0.0.1:0", stream_handle).await?;
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// Fails with `Error::ListenerBindError` if the address cannot be bound.
+        ///
+        /// # Panics
+        ///
+        /// None
+        ///
+        pub async fn bind(
+            addr: impl ToSocketAddrs,
+            stream_handle: StreamHandle<DuplexStream>,
+        ) -> Result<HttpStubServer, Error> {
+            let listener = TcpListener::bind(addr)
+                .await
+                .map_err(|e| Error::ListenerBindError {
+                    source: Box::new(e),
+                    description: "Failed to bind HTTP stub server".to_string(),
+                })?;
+
+            let local_addr = listener
+                .local_addr()
+                .map_err(|e| Error::ListenerBindError {
+                    source: Box::new(e),
+                    description: "Failed to read address of HTTP stub server".to_string(),
+                })?;
+
+            let (read_stream, write_stream) = tokio::io::split(stream_handle.stream);
+            let queue = PacketQueue::default();
+            let cancellation_token = CancellationToken::new();
+
+            let handles = vec![
+                tokio::spawn(queue_packets(
+                    read_stream,
+                    queue.clone(),
+                    cancellation_token.clone(),
+                )),
+                tokio::spawn(serve(
+                    listener,
+                    write_stream,
+                    queue,
+                    cancellation_token.clone(),
+                )),
+            ];
+
+            Ok(HttpStubServer {
+                local_addr,
+                cancellation_token,
+                handles,
+            })
+        }
+
+        /// Returns the address the server is listening on.
+        pub fn local_addr(&self) -> SocketAddr {
+            self.local_addr
+        }
+
+        /// Stops the server and waits for its background tasks to exit.
+        ///
+        /// # Errors
+        ///
+        /// Fails with `Error::JoinError` if a background task panicked.
+        pub async fn shutdown(mut self) -> Result<(), Error> {
+            self.cancellation_token.cancel();
+
+            for handle in self.handles.drain(..) {
+                handle.await?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl Drop for HttpStubServer {
+        fn drop(&mut self) {
+            self.cancellation_token.cancel();
+        }
+    }
+
+    /// Reads framed packets from the stream into the queue polled by clients.
+    async fn queue_packets(
+        mut read_stream: ReadHalf<DuplexStream>,
+        queue: PacketQueue,
+        cancellation_token: CancellationToken,
+    ) {
+        let mut pending = Vec::new();
+        let mut buffer = [0u8; 1024];
+
+        loop {
+            let n = tokio::select! {
+                _ = cancellation_token.cancelled() => break,
+                read_result = read_stream.read(&mut buffer) => match read_result {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => n,
+                },
+            };
+
+            pending.extend_from_slice(&buffer[..n]);
+
+            while let Some(packet) = take_next_frame(&mut pending) {
+                lock(&queue).push_back(packet);
+            }
+        }
+    }
+
+    /// Answers requests one at a time until the server is cancelled.
+    async fn serve(
+        listener: TcpListener,
+        mut write_stream: WriteHalf<DuplexStream>,
+        queue: PacketQueue,
+        cancellation_token: CancellationToken,
+    ) {
+        loop {
+            let stream = tokio::select! {
+                _ = cancellation_token.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Failed to accept HTTP stub request: {e}");
+                        continue;
+                    }
+                },
+            };
+
+            if let Err(e) = handle_connection(stream, &mut write_stream, &queue).await {
+                warn!("Failed to answer HTTP stub request: {e}");
+            }
+        }
+    }
+
+    /// Answers a single HTTP request, then closes the connection.
+    async fn handle_connection(
+        mut stream: TcpStream,
+        write_stream: &mut WriteHalf<DuplexStream>,
+        queue: &PacketQueue,
+    ) -> Result<(), Error> {
+        let io_error = |e: std::io::Error| {
+            Error::InternalStreamError(InternalStreamError::StreamReadError {
+                source: Box::new(e),
+            })
+        };
+
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 1024];
+
+        let header_end = loop {
+            if let Some(position) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+                break position + 4;
+            }
+
+            let read = stream.read(&mut buffer).await.map_err(io_error)?;
+
+            if read == 0 || request.len() + read > MAX_REQUEST_LENGTH {
+                return Ok(());
+            }
+
+            request.extend_from_slice(&buffer[..read]);
+        };
+
+        let head = String::from_utf8_lossy(&request[..header_end]).to_string();
+        let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+        let method = request_line.next().unwrap_or_default();
+        let path = request_line.next().unwrap_or_default();
+        let path = path.split('?').next().unwrap_or_default();
+
+        let content_length = head
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+            .unwrap_or(0)
+            .min(MAX_REQUEST_LENGTH);
+
+        let mut body = request[header_end..].to_vec();
+        while body.len() < content_length {
+            let read = stream.read(&mut buffer).await.map_err(io_error)?;
+
+            if read == 0 {
+                break;
+            }
+
+            body.extend_from_slice(&buffer[..read]);
+        }
+
+        let (status, response_body) = match (method, path) {
+            ("GET", HTTP_FROM_RADIO_PATH) => {
+                ("200 OK", lock(queue).pop_front().unwrap_or_default())
+            }
+            ("PUT", HTTP_TO_RADIO_PATH) => {
+                write_frame(write_stream, body).await?;
+                ("200 OK", vec![])
+            }
+            _ => ("404 Not Found", vec![]),
+        };
+
+        let head = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: {PROTOBUF_CONTENT_TYPE}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response_body.len()
+        );
+
+        stream
+            .write_all(&[head.as_bytes(), &response_body].concat())
+            .await
+            .map_err(io_error)?;
+        stream.shutdown().await.map_err(io_error)
+    }
+
+    /// Locks the packet queue. The queue is only accessed synchronously, so a poisoned lock can
+    /// only be caused by a panicking task and is safe to recover from.
+    fn lock(queue: &PacketQueue) -> std::sync::MutexGuard<'_, VecDeque<Vec<u8>>> {
+        queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connections::mock_radio::MockRadio;
+    use crate::connections::stream_api::StreamApi;
+    use crate::connections::test_support::{mock_radio_state, TestRouter, MY_NODE_NUM};
+    use crate::connections::wrappers::{mesh_channel::MeshChannel, NodeId};
+    use crate::connections::PacketDestination;
+    use crate::protobufs;
+
+    use super::*;
+
+    fn fast_options() -> HttpStreamOptions {
+        HttpStreamOptions {
+            poll_interval: Duration::from_millis(10),
+            ..Default::default()
+        }
+    }
+
+    /// Starts a server that answers the requests it accepts with the given statuses and bodies in
+    /// order, and with empty bodies once they run out.
+    async fn scripted_radio(responses: Vec<(&'static str, Vec<u8>)>) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut responses = responses.into_iter();
+
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buffer).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+
+                let (status, body) = responses.next().unwrap_or(("200 OK", vec![]));
+                let head = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(&[head.as_bytes(), &body].concat()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        address
+    }
+
+    async fn stub_radio() -> (MockRadio, HttpStubServer) {
+        let (mock_radio, stream_handle) = MockRadio::new(mock_radio_state());
+
+        let server = HttpStubServer::bind("127.0.0.1:0", stream_handle)
+            .await
+            .unwrap();
+
+        (mock_radio, server)
+    }
+
+    #[tokio::test]
+    async fn configures_and_sends_over_http() {
+        let (mock_radio, server) = stub_radio().await;
+
+        let http_stream = build_http_stream(server.local_addr().to_string(), fast_options())
+            .await
+            .unwrap();
+        let (_packet_rx, stream_api) = StreamApi::new().connect(http_stream).await;
+        let (mut stream_api, snapshot) = stream_api
+            .configure_and_wait(7, Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_eq!(snapshot.my_node_id(), Some(NodeId::new(MY_NODE_NUM)));
+
+        let mut router = TestRouter;
+        stream_api
+            .send_text(
+                &mut router,
+                "Hello over HTTP".to_string(),
+                PacketDestination::Broadcast,
+                false,
+                MeshChannel::new(0).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while mock_radio.received_mesh_packets().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn polls_injected_packets() {
+        let (mock_radio, server) = stub_radio().await;
+
+        let http_stream =
+            build_http_stream(format!("http://{}/", server.local_addr()), fast_options())
+                .await
+                .unwrap();
+        let (mut packet_rx, _stream_api) = StreamApi::new().connect(http_stream).await;
+
+        for id in [1, 2, 3] {
+            mock_radio
+                .inject_mesh_packet(protobufs::MeshPacket {
+                    id,
+                    from: 43,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+
+        for id in [1, 2, 3] {
+            let packet = tokio::time::timeout(Duration::from_secs(5), packet_rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+
+            match packet.payload_variant {
+                Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)) => {
+                    assert_eq!(mesh_packet.id, id)
+                }
+                payload => panic!("Expected a mesh packet, received {payload:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn fails_when_radio_is_unreachable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+
+        assert!(matches!(
+            build_http_stream(address.to_string(), fast_options()).await,
+            Err(Error::StreamBuildError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn retries_failed_polls() {
+        let packet = protobufs::FromRadio {
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(
+                protobufs::MeshPacket {
+                    id: 7,
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        };
+        let address = scripted_radio(vec![
+            ("200 OK", vec![]),
+            ("503 Service Unavailable", vec![]),
+            ("503 Service Unavailable", vec![]),
+            ("200 OK", prost::Message::encode_to_vec(&packet)),
+        ])
+        .await;
+
+        let options = HttpStreamOptions {
+            retry_backoff: Duration::from_millis(10),
+            ..fast_options()
+        };
+        let http_stream = build_http_stream(address.to_string(), options)
+            .await
+            .unwrap();
+        let (mut packet_rx, _stream_api) = StreamApi::new().connect(http_stream).await;
+
+        let received = tokio::time::timeout(Duration::from_secs(5), packet_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received, packet);
+    }
+
+    #[tokio::test]
+    async fn fails_after_the_last_retry() {
+        let address = scripted_radio(vec![
+            ("200 OK", vec![]),
+            ("503 Service Unavailable", vec![]),
+            ("503 Service Unavailable", vec![]),
+        ])
+        .await;
+
+        let options = HttpStreamOptions {
+            max_retries: 1,
+            retry_backoff: Duration::from_millis(10),
+            ..fast_options()
+        };
+        let http_stream = build_http_stream(address.to_string(), options)
+            .await
+            .unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), http_stream.join_handle.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            result,
+            Err(Error::InternalStreamError(
+                InternalStreamError::StreamReadError { .. }
+            ))
+        ));
+    }
+}
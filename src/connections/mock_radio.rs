@@ -0,0 +1,693 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use log::{debug, trace, warn};
+use prost::Message;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream, WriteHalf};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use crate::errors_internal::{Error, InternalChannelError, InternalStreamError};
+use crate::protobufs;
+use crate::utils_internal::{format_data_packet, take_next_frame};
+
+use super::snapshot::{apply_config, apply_module_config};
+use super::stream_api::StreamHandle;
+
+/// The size of the in-memory pipe between the `StreamApi` and the mock radio.
+const MOCK_RADIO_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The node ID used for broadcast packets.
+const BROADCAST_NODE_ID: u32 = u32::MAX;
+
+/// A struct that represents the state of a `MockRadio`. This state is reported to the client
+/// during the `WantConfigId` handshake and in response to admin requests, and is updated when
+/// the client sends admin setters.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MockRadioState {
+    /// Information about the mock radio, including its node number.
+    pub my_info: protobufs::MyNodeInfo,
+
+    /// The node database of the mock radio. The entry of the mock radio itself holds its owner.
+    pub nodes: Vec<protobufs::NodeInfo>,
+
+    /// The message channels of the mock radio.
+    pub channels: Vec<protobufs::Channel>,
+
+    /// The configuration of the mock radio. Unset fields are reported with default values.
+    pub config: protobufs::LocalConfig,
+
+    /// The module configuration of the mock radio. Unset fields are reported with default values.
+    pub module_config: protobufs::LocalModuleConfig,
+
+    /// Metadata on the firmware and hardware of the mock radio.
+    pub metadata: protobufs::DeviceMetadata,
+}
+
+impl MockRadioState {
+    /// Returns the node number of the mock radio.
+    pub fn my_node_num(&self) -> u32 {
+        self.my_info.my_node_num
+    }
+
+    /// Returns the owner of the mock radio, as stored in its own node database entry.
+    pub fn owner(&self) -> Option<&protobufs::User> {
+        let my_node_num = self.my_node_num();
+
+        self.nodes
+            .iter()
+            .find(|node| node.num == my_node_num)
+            .and_then(|node| node.user.as_ref())
+    }
+
+    fn set_owner(&mut self, user: protobufs::User) {
+        let my_node_num = self.my_node_num();
+
+        match self.nodes.iter_mut().find(|node| node.num == my_node_num) {
+            Some(node) => node.user = Some(user),
+            None => self.nodes.push(protobufs::NodeInfo {
+                num: my_node_num,
+                user: Some(user),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn set_channel(&mut self, channel: protobufs::Channel) {
+        self.channels.retain(|c| c.index != channel.index);
+        self.channels.push(channel);
+        self.channels.sort_by_key(|c| c.index);
+    }
+}
+
+/// An in-memory radio that speaks the Meshtastic client API, intended for testing code built on
+/// the `StreamApi` without a physical device.
+///
+/// The mock radio is connected to the `StreamApi` through the `StreamHandle` returned by the
+/// `new` method, and behaves as follows:
+///
+/// * It answers `WantConfigId` packets with its `MyInfo`, node database, metadata, channels,
+///     configuration and module configuration, followed by a matching `ConfigCompleteId` packet.
+/// * It acknowledges every mesh packet sent with `want_ack` set, as if it had been delivered.
+/// * It applies `SetConfig`, `SetModuleConfig`, `SetChannel` and `SetOwner` admin messages to its
+///     state, and answers the corresponding admin getters.
+/// * It sends a `Rebooted` packet when asked to commit a configuration transaction, reboot, or
+///     factory reset, without dropping the connection.
+///
+/// Arbitrary `FromRadio` traffic can be injected with the `inject` method.
+#[derive(Clone, Debug)]
+pub struct MockRadio {
+    state: Arc<Mutex<MockRadioState>>,
+    received_packets: Arc<Mutex<Vec<protobufs::ToRadio>>>,
+    inject_tx: UnboundedSender<protobufs::FromRadio>,
+}
+
+impl MockRadio {
+    /// Creates a mock radio with the specified initial state.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The initial `MockRadioState` of the radio.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `MockRadio` instance used to inspect and drive the radio, and a `StreamHandle`
+    /// to pass to the `StreamApi::connect` method.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let (mock_radio, stream_handle) = MockRadio::new(MockRadioState {
+    ///     my_info: protobufs::MyNodeInfo { my_node_num: 1, ..Default::default() },
+    ///     ..Default::default()
+    /// });
+    ///
+    /// let (decoded_listener, stream_api) = StreamApi::new().connect(stream_handle).await;
+    /// let (stream_api, snapshot) = stream_api
+    ///     .configure_and_wait(generate_rand_id(), Duration::from_secs(1))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn new(state: MockRadioState) -> (MockRadio, StreamHandle<DuplexStream>) {
+        let (client_stream, radio_stream) = tokio::io::duplex(MOCK_RADIO_BUFFER_SIZE);
+        let (inject_tx, inject_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let mock_radio = MockRadio {
+            state: Arc::new(Mutex::new(state)),
+            received_packets: Arc::new(Mutex::new(Vec::new())),
+            inject_tx,
+        };
+
+        let join_handle = tokio::spawn(run_mock_radio(
+            radio_stream,
+            mock_radio.state.clone(),
+            mock_radio.received_packets.clone(),
+            inject_rx,
+        ));
+
+        (
+            mock_radio,
+            StreamHandle {
+                stream: client_stream,
+                join_handle: Some(join_handle),
+            },
+        )
+    }
+
+    /// Sends the specified `FromRadio` packet to the client.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the mock radio is no longer running.
+    pub fn inject(&self, packet: protobufs::FromRadio) -> Result<(), Error> {
+        self.inject_tx
+            .send(packet)
+            .map_err(|_| Error::InternalChannelError(InternalChannelError::ChannelClosedEarly))
+    }
+
+    /// Sends the specified `MeshPacket` to the client, as if it had been received from the mesh.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the mock radio is no longer running.
+    pub fn inject_mesh_packet(&self, packet: protobufs::MeshPacket) -> Result<(), Error> {
+        self.inject(protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(packet)),
+        })
+    }
+
+    /// Returns a copy of the current state of the mock radio.
+    pub fn state(&self) -> MockRadioState {
+        lock(&self.state).clone()
+    }
+
+    /// Returns every `ToRadio` packet received from the client, in the order they were received.
+    pub fn received_packets(&self) -> Vec<protobufs::ToRadio> {
+        lock(&self.received_packets).clone()
+    }
+
+    /// Returns every `MeshPacket` received from the client, in the order they were received.
+    pub fn received_mesh_packets(&self) -> Vec<protobufs::MeshPacket> {
+        self.received_packets()
+            .into_iter()
+            .filter_map(|packet| match packet.payload_variant {
+                Some(protobufs::to_radio::PayloadVariant::Packet(mesh_packet)) => Some(mesh_packet),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Locks the shared state of the mock radio. The state is only accessed synchronously, so a
+/// poisoned lock can only be caused by a panicking test and is safe to recover from.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+async fn run_mock_radio(
+    radio_stream: DuplexStream,
+    state: Arc<Mutex<MockRadioState>>,
+    received_packets: Arc<Mutex<Vec<protobufs::ToRadio>>>,
+    mut inject_rx: UnboundedReceiver<protobufs::FromRadio>,
+) -> Result<(), Error> {
+    debug!("Started mock radio");
+
+    let (mut read_stream, mut write_stream) = tokio::io::split(radio_stream);
+    let mut pending = Vec::new();
+    let mut buffer = [0u8; 1024];
+
+    loop {
+        tokio::select! {
+            read_result = read_stream.read(&mut buffer) => {
+                let n = match read_result {
+                    Ok(0) => {
+                        debug!("Mock radio client disconnected");
+                        return Ok(());
+                    }
+                    Ok(n) => n,
+                    Err(e) => {
+                        return Err(Error::InternalStreamError(InternalStreamError::StreamReadError {
+                            source: Box::new(e),
+                        }))
+                    }
+                };
+
+                pending.extend_from_slice(&buffer[..n]);
+
+                while let Some(frame) = take_next_frame(&mut pending) {
+                    let to_radio = match protobufs::ToRadio::decode(frame.as_slice()) {
+                        Ok(to_radio) => to_radio,
+                        Err(e) => {
+                            warn!("Mock radio failed to decode ToRadio packet: {e}");
+                            continue;
+                        }
+                    };
+
+                    trace!("Mock radio received {to_radio:?}");
+                    lock(&received_packets).push(to_radio.clone());
+
+                    let responses = handle_to_radio(&mut lock(&state), to_radio);
+
+                    for response in responses {
+                        write_from_radio(&mut write_stream, response).await?;
+                    }
+                }
+            }
+            Some(packet) = inject_rx.recv() => {
+                write_from_radio(&mut write_stream, packet).await?;
+            }
+        }
+    }
+}
+
+async fn write_from_radio(
+    write_stream: &mut WriteHalf<DuplexStream>,
+    packet: protobufs::FromRadio,
+) -> Result<(), Error> {
+    let packet = format_data_packet(packet.encode_to_vec().into())?;
+
+    write_stream.write_all(packet.data()).await.map_err(|e| {
+        Error::InternalStreamError(InternalStreamError::StreamWriteError {
+            source: Box::new(e),
+        })
+    })
+}
+
+/// Returns the `FromRadio` packets the mock radio sends in response to a `ToRadio` packet.
+fn handle_to_radio(
+    state: &mut MockRadioState,
+    to_radio: protobufs::ToRadio,
+) -> Vec<protobufs::FromRadio> {
+    use protobufs::from_radio::PayloadVariant;
+
+    let payload_variants = match to_radio.payload_variant {
+        Some(protobufs::to_radio::PayloadVariant::WantConfigId(config_id)) => {
+            config_payloads(state, config_id)
+        }
+        Some(protobufs::to_radio::PayloadVariant::Packet(mesh_packet)) => {
+            handle_mesh_packet(state, mesh_packet)
+        }
+        _ => vec![],
+    };
+
+    payload_variants
+        .into_iter()
+        .map(|payload_variant: PayloadVariant| protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(payload_variant),
+        })
+        .collect()
+}
+
+/// Returns the packets sent by the mock radio during the `WantConfigId` handshake.
+fn config_payloads(
+    state: &MockRadioState,
+    config_id: u32,
+) -> Vec<protobufs::from_radio::PayloadVariant> {
+    use protobufs::from_radio::PayloadVariant;
+
+    let mut payloads = vec![PayloadVariant::MyInfo(state.my_info)];
+
+    payloads.extend(state.nodes.iter().cloned().map(PayloadVariant::NodeInfo));
+    payloads.push(PayloadVariant::Metadata(state.metadata.clone()));
+    payloads.extend(state.channels.iter().cloned().map(PayloadVariant::Channel));
+
+    payloads.extend(
+        (0..)
+            .map_while(|i| protobufs::admin_message::ConfigType::try_from(i).ok())
+            .map(|config_type| PayloadVariant::Config(config_of_type(&state.config, config_type))),
+    );
+
+    payloads.extend(
+        (0..)
+            .map_while(|i| protobufs::admin_message::ModuleConfigType::try_from(i).ok())
+            .map(|module_config_type| {
+                PayloadVariant::ModuleConfig(module_config_of_type(
+                    &state.module_config,
+                    module_config_type,
+                ))
+            }),
+    );
+
+    payloads.push(PayloadVariant::ConfigCompleteId(config_id));
+
+    payloads
+}
+
+/// Returns the packets sent by the mock radio in response to a mesh packet from the client.
+fn handle_mesh_packet(
+    state: &mut MockRadioState,
+    mesh_packet: protobufs::MeshPacket,
+) -> Vec<protobufs::from_radio::PayloadVariant> {
+    use protobufs::from_radio::PayloadVariant;
+
+    let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) = &mesh_packet.payload_variant
+    else {
+        return vec![];
+    };
+
+    let my_node_num = state.my_node_num();
+    let mut payloads = vec![];
+
+    if mesh_packet.want_ack {
+        // Broadcasts are only implicitly acknowledged by the connected radio
+        let ack_from = match mesh_packet.to {
+            BROADCAST_NODE_ID => my_node_num,
+            to => to,
+        };
+
+        let routing = protobufs::Routing {
+            variant: Some(protobufs::routing::Variant::ErrorReason(
+                protobufs::routing::Error::None as i32,
+            )),
+        };
+
+        payloads.push(PayloadVariant::Packet(response_packet(
+            ack_from,
+            &mesh_packet,
+            protobufs::PortNum::RoutingApp,
+            routing.encode_to_vec(),
+        )));
+    }
+
+    if data.portnum == protobufs::PortNum::AdminApp as i32 && mesh_packet.to == my_node_num {
+        let admin_message = match protobufs::AdminMessage::decode(data.payload.as_slice()) {
+            Ok(admin_message) => admin_message,
+            Err(e) => {
+                warn!("Mock radio failed to decode AdminMessage: {e}");
+                return payloads;
+            }
+        };
+
+        let (response, rebooted) = handle_admin_message(state, admin_message);
+
+        if let Some(response) = response {
+            let response = protobufs::AdminMessage {
+                payload_variant: Some(response),
+            };
+
+            payloads.push(PayloadVariant::Packet(response_packet(
+                my_node_num,
+                &mesh_packet,
+                protobufs::PortNum::AdminApp,
+                response.encode_to_vec(),
+            )));
+        }
+
+        if rebooted {
+            payloads.push(PayloadVariant::Rebooted(true));
+        }
+    }
+
+    payloads
+}
+
+/// Applies an admin message to the state of the mock radio. Returns the admin response to send
+/// to the client, if any, and whether the real radio would reboot.
+fn handle_admin_message(
+    state: &mut MockRadioState,
+    admin_message: protobufs::AdminMessage,
+) -> (Option<protobufs::admin_message::PayloadVariant>, bool) {
+    use protobufs::admin_message::{ConfigType, ModuleConfigType, PayloadVariant};
+
+    let Some(payload_variant) = admin_message.payload_variant else {
+        return (None, false);
+    };
+
+    match payload_variant {
+        PayloadVariant::SetConfig(config) => apply_config(&mut state.config, config),
+        PayloadVariant::SetModuleConfig(module_config) => {
+            apply_module_config(&mut state.module_config, module_config)
+        }
+        PayloadVariant::SetChannel(channel) => state.set_channel(channel),
+        PayloadVariant::SetOwner(user) => state.set_owner(user),
+        PayloadVariant::GetConfigRequest(config_type) => {
+            let config_type = ConfigType::try_from(config_type).unwrap_or_default();
+            let config = config_of_type(&state.config, config_type);
+            return (Some(PayloadVariant::GetConfigResponse(config)), false);
+        }
+        PayloadVariant::GetModuleConfigRequest(module_config_type) => {
+            let module_config_type =
+                ModuleConfigType::try_from(module_config_type).unwrap_or_default();
+            let module_config = module_config_of_type(&state.module_config, module_config_type);
+            return (
+                Some(PayloadVariant::GetModuleConfigResponse(module_config)),
+                false,
+            );
+        }
+        PayloadVariant::GetChannelRequest(index_plus_one) => {
+            let index = index_plus_one as i32 - 1;
+
+            let channel = state
+                .channels
+                .iter()
+                .find(|channel| channel.index == index)
+                .cloned()
+                .unwrap_or(protobufs::Channel {
+                    index,
+                    ..Default::default()
+                });
+
+            return (Some(PayloadVariant::GetChannelResponse(channel)), false);
+        }
+        PayloadVariant::GetOwnerRequest(_) => {
+            let owner = state.owner().cloned().unwrap_or_default();
+            return (Some(PayloadVariant::GetOwnerResponse(owner)), false);
+        }
+        PayloadVariant::GetDeviceMetadataRequest(_) => {
+            let metadata = state.metadata.clone();
+            return (
+                Some(PayloadVariant::GetDeviceMetadataResponse(metadata)),
+                false,
+            );
+        }
+        PayloadVariant::NodedbReset(_) => {
+            let my_node_num = state.my_node_num();
+            state.nodes.retain(|node| node.num == my_node_num);
+        }
+        PayloadVariant::CommitEditSettings(_)
+        | PayloadVariant::FactoryResetDevice(_)
+        | PayloadVariant::FactoryResetConfig(_) => return (None, true),
+        PayloadVariant::RebootSeconds(seconds) => return (None, seconds >= 0),
+        _ => trace!("Mock radio ignoring admin message"),
+    }
+
+    (None, false)
+}
+
+/// Builds a mesh packet sent from `from` to the sender of `request`, in response to `request`.
+fn response_packet(
+    from: u32,
+    request: &protobufs::MeshPacket,
+    port_num: protobufs::PortNum,
+    payload: Vec<u8>,
+) -> protobufs::MeshPacket {
+    protobufs::MeshPacket {
+        from,
+        to: request.from,
+        channel: request.channel,
+        payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+            protobufs::Data {
+                portnum: port_num as i32,
+                payload,
+                request_id: request.id,
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    }
+}
+
+/// Returns the configuration of the specified type, with default values if it is not set.
+fn config_of_type(
+    local_config: &protobufs::LocalConfig,
+    config_type: protobufs::admin_message::ConfigType,
+) -> protobufs::Config {
+    use protobufs::admin_message::ConfigType;
+    use protobufs::config::PayloadVariant;
+
+    let c = local_config.clone();
+
+    let payload_variant = match config_type {
+        ConfigType::DeviceConfig => PayloadVariant::Device(c.device.unwrap_or_default()),
+        ConfigType::PositionConfig => PayloadVariant::Position(c.position.unwrap_or_default()),
+        ConfigType::PowerConfig => PayloadVariant::Power(c.power.unwrap_or_default()),
+        ConfigType::NetworkConfig => PayloadVariant::Network(c.network.unwrap_or_default()),
+        ConfigType::DisplayConfig => PayloadVariant::Display(c.display.unwrap_or_default()),
+        ConfigType::LoraConfig => PayloadVariant::Lora(c.lora.unwrap_or_default()),
+        ConfigType::BluetoothConfig => PayloadVariant::Bluetooth(c.bluetooth.unwrap_or_default()),
+    };
+
+    protobufs::Config {
+        payload_variant: Some(payload_variant),
+    }
+}
+
+/// Returns the module configuration of the specified type, with default values if it is not set.
+fn module_config_of_type(
+    local_module_config: &protobufs::LocalModuleConfig,
+    module_config_type: protobufs::admin_message::ModuleConfigType,
+) -> protobufs::ModuleConfig {
+    use protobufs::admin_message::ModuleConfigType;
+    use protobufs::module_config::PayloadVariant;
+
+    let c = local_module_config.clone();
+
+    let payload_variant = match module_config_type {
+        ModuleConfigType::MqttConfig => PayloadVariant::Mqtt(c.mqtt.unwrap_or_default()),
+        ModuleConfigType::SerialConfig => PayloadVariant::Serial(c.serial.unwrap_or_default()),
+        ModuleConfigType::ExtnotifConfig => {
+            PayloadVariant::ExternalNotification(c.external_notification.unwrap_or_default())
+        }
+        ModuleConfigType::StoreforwardConfig => {
+            PayloadVariant::StoreForward(c.store_forward.unwrap_or_default())
+        }
+        ModuleConfigType::RangetestConfig => {
+            PayloadVariant::RangeTest(c.range_test.unwrap_or_default())
+        }
+        ModuleConfigType::TelemetryConfig => {
+            PayloadVariant::Telemetry(c.telemetry.unwrap_or_default())
+        }
+        ModuleConfigType::CannedmsgConfig => {
+            PayloadVariant::CannedMessage(c.canned_message.unwrap_or_default())
+        }
+        ModuleConfigType::AudioConfig => PayloadVariant::Audio(c.audio.unwrap_or_default()),
+        ModuleConfigType::RemotehardwareConfig => {
+            PayloadVariant::RemoteHardware(c.remote_hardware.unwrap_or_default())
+        }
+        ModuleConfigType::NeighborinfoConfig => {
+            PayloadVariant::NeighborInfo(c.neighbor_info.unwrap_or_default())
+        }
+        ModuleConfigType::AmbientlightingConfig => {
+            PayloadVariant::AmbientLighting(c.ambient_lighting.unwrap_or_default())
+        }
+        ModuleConfigType::DetectionsensorConfig => {
+            PayloadVariant::DetectionSensor(c.detection_sensor.unwrap_or_default())
+        }
+        ModuleConfigType::PaxcounterConfig => {
+            PayloadVariant::Paxcounter(c.paxcounter.unwrap_or_default())
+        }
+    };
+
+    protobufs::ModuleConfig {
+        payload_variant: Some(payload_variant),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::api::StreamApi;
+    use crate::connections::test_support::{TestRouter, MY_NODE_NUM};
+    use crate::connections::wrappers::{mesh_channel::MeshChannel, NodeId};
+    use crate::connections::PacketDestination;
+    use crate::packet::AckOutcome;
+
+    use super::*;
+
+    fn mock_state() -> MockRadioState {
+        MockRadioState {
+            my_info: protobufs::MyNodeInfo {
+                my_node_num: MY_NODE_NUM,
+                ..Default::default()
+            },
+            channels: vec![protobufs::Channel {
+                index: 0,
+                role: protobufs::channel::Role::Primary as i32,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn answers_config_handshake() {
+        let (_mock_radio, stream_handle) = MockRadio::new(mock_state());
+        let (_decoded_listener, stream_api) = StreamApi::new().connect(stream_handle).await;
+
+        let (_stream_api, snapshot) = stream_api
+            .configure_and_wait(7, Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        assert_eq!(snapshot.my_node_id(), Some(NodeId::new(MY_NODE_NUM)));
+        assert_eq!(snapshot.channels.len(), 1);
+        assert!(snapshot.config.lora.is_some());
+        assert!(snapshot.module_config.mqtt.is_some());
+    }
+
+    #[tokio::test]
+    async fn applies_admin_setters_and_acknowledges_packets() {
+        let (mock_radio, stream_handle) = MockRadio::new(mock_state());
+        let (_decoded_listener, stream_api) = StreamApi::new().connect(stream_handle).await;
+        let (mut stream_api, _) = stream_api
+            .configure_and_wait(7, Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        let mut router = TestRouter;
+        let owner = protobufs::User {
+            long_name: "Mock Radio".to_string(),
+            ..Default::default()
+        };
+
+        stream_api
+            .update_user(&mut router, owner.clone())
+            .await
+            .unwrap();
+
+        let received_owner = stream_api
+            .get_owner(&mut router, Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        assert_eq!(received_owner, owner);
+        assert_eq!(mock_radio.state().owner(), Some(&owner));
+
+        let outcome = stream_api
+            .send_text(
+                &mut router,
+                "Hello".to_string(),
+                PacketDestination::Node(NodeId::new(7)),
+                true,
+                MeshChannel::new(0).unwrap(),
+            )
+            .await
+            .unwrap()
+            .wait_for_ack(Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        assert_eq!(outcome, AckOutcome::Ack);
+        assert_eq!(mock_radio.received_mesh_packets().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn forwards_injected_packets() {
+        let (mock_radio, stream_handle) = MockRadio::new(mock_state());
+        let (mut decoded_listener, _stream_api) = StreamApi::new().connect(stream_handle).await;
+
+        let packet = protobufs::MeshPacket {
+            from: 7,
+            to: MY_NODE_NUM,
+            ..Default::default()
+        };
+
+        mock_radio.inject_mesh_packet(packet.clone()).unwrap();
+
+        let received = decoded_listener.recv().await.unwrap();
+
+        assert_eq!(
+            received.payload_variant,
+            Some(protobufs::from_radio::PayloadVariant::Packet(packet))
+        );
+    }
+}
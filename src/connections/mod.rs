@@ -4,12 +4,47 @@ use crate::protobufs;
 
 use self::wrappers::NodeId;
 
+pub mod ack;
 #[cfg(feature = "bluetooth-le")]
 pub mod ble_handler;
+#[cfg(feature = "bluetooth-le")]
+pub mod ble_stream;
+pub mod channel_url;
+pub mod config_transaction;
+pub mod crypto;
+pub mod events;
 pub mod handlers;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(any(test, feature = "testing"))]
+pub mod mock_radio;
+pub mod mqtt;
+#[cfg(feature = "mqtt")]
+pub mod mqtt_proxy;
+pub mod node_db;
+pub mod payload;
+pub mod position;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+pub mod reconnect;
+pub mod remote_admin;
+pub mod response;
+pub mod snapshot;
+pub mod store_forward;
+pub mod store_forward_host;
 pub mod stream_api;
 pub mod stream_buffer;
+pub mod subscription;
+pub mod telemetry;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod topology;
+pub mod traceroute;
+#[cfg(feature = "udp")]
+pub mod udp;
+pub mod waypoints;
 pub mod wrappers;
+pub(crate) mod xml_document;
 
 /// An enum that defines the possible destinations for a mesh packet.
 /// This enum is used to specify the destination of a packet when sending
@@ -33,6 +68,18 @@ pub enum PacketDestination {
     Node(NodeId),
 }
 
+impl PacketDestination {
+    /// Returns the node ID that a packet sent to this destination is addressed to, given
+    /// the node ID of the connected radio.
+    pub(crate) fn node_id(&self, own_node_id: NodeId) -> NodeId {
+        match self {
+            PacketDestination::Local => own_node_id,
+            PacketDestination::Broadcast => u32::MAX.into(),
+            PacketDestination::Node(id) => *id,
+        }
+    }
+}
+
 /// This trait defines the behavior of a struct that is able to route mesh packets.
 /// More generally, this trait defines the behavior of a struct that is able to send
 /// and receive mesh packets.
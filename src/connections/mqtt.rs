@@ -0,0 +1,495 @@
+use prost::Message;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+
+use super::crypto::{decrypt_packet, ChannelKey, ChannelKeyring};
+use super::wrappers::NodeId;
+
+/// The default root topic of the firmware, to which the region of the radio is appended.
+pub const DEFAULT_MQTT_ROOT_TOPIC: &str = "msh";
+
+/// The version segment of the topics used by current firmware versions.
+const TOPIC_VERSION: &str = "2";
+
+/// An enum that identifies the kind of messages published on a Meshtastic MQTT topic.
+///
+/// # Variants
+///
+/// * `Envelope` - Mesh packets wrapped in `ServiceEnvelope` protobufs, published on
+///     `<root>/2/e/<channel>/<gateway>`. Older firmware versions published encrypted packets on
+///     `<root>/2/c/<channel>/<gateway>`, which are parsed as envelopes as well.
+/// * `Json` - Decoded packets serialized as JSON, published on `<root>/2/json/<channel>/<gateway>`.
+/// * `Map` - `MapReport` packets wrapped in `ServiceEnvelope` protobufs, published on
+///     `<root>/2/map/`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MqttTopicKind {
+    Envelope,
+    Json,
+    Map,
+}
+
+impl MqttTopicKind {
+    fn segment(&self) -> &'static str {
+        match self {
+            MqttTopicKind::Envelope => "e",
+            MqttTopicKind::Json => "json",
+            MqttTopicKind::Map => "map",
+        }
+    }
+
+    fn from_segment(segment: &str) -> Option<MqttTopicKind> {
+        match segment {
+            "e" | "c" => Some(MqttTopicKind::Envelope),
+            "json" => Some(MqttTopicKind::Json),
+            "map" => Some(MqttTopicKind::Map),
+            _ => None,
+        }
+    }
+}
+
+/// A struct that represents a Meshtastic MQTT topic.
+///
+/// # Fields
+///
+/// * `root` - The root topic, such as `msh/US`. Root topics may contain multiple levels.
+/// * `kind` - The kind of messages published on the topic.
+/// * `channel_id` - The name of the channel the messages were sent on, if the topic has one.
+/// * `gateway_id` - The ID of the node that published the messages, if the topic has one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MqttTopic {
+    pub root: String,
+    pub kind: MqttTopicKind,
+    pub channel_id: Option<String>,
+    pub gateway_id: Option<NodeId>,
+}
+
+impl MqttTopic {
+    /// Returns the topic that a gateway publishes `ServiceEnvelope` messages of a channel on.
+    pub fn envelope(root: &str, channel_id: &str, gateway_id: NodeId) -> MqttTopic {
+        MqttTopic {
+            root: root.to_string(),
+            kind: MqttTopicKind::Envelope,
+            channel_id: Some(channel_id.to_string()),
+            gateway_id: Some(gateway_id),
+        }
+    }
+
+    /// Returns the topic that a gateway publishes JSON messages of a channel on.
+    pub fn json(root: &str, channel_id: &str, gateway_id: NodeId) -> MqttTopic {
+        MqttTopic {
+            kind: MqttTopicKind::Json,
+            ..MqttTopic::envelope(root, channel_id, gateway_id)
+        }
+    }
+
+    /// Returns the topic that map reports are published on.
+    pub fn map(root: &str) -> MqttTopic {
+        MqttTopic {
+            root: root.to_string(),
+            kind: MqttTopicKind::Map,
+            channel_id: None,
+            gateway_id: None,
+        }
+    }
+
+    /// Returns the topic filter that subscribes to every message of the given kind below the
+    /// given root topic, such as `msh/US/2/e/#`.
+    pub fn subscription_filter(root: &str, kind: MqttTopicKind) -> String {
+        format!("{root}/{TOPIC_VERSION}/{}/#", kind.segment())
+    }
+
+    /// Returns the topic filter that subscribes to the messages of a channel published by any
+    /// gateway, such as `msh/US/2/e/LongFast/+`.
+    pub fn channel_filter(root: &str, kind: MqttTopicKind, channel_id: &str) -> String {
+        format!("{root}/{TOPIC_VERSION}/{}/{channel_id}/+", kind.segment())
+    }
+
+    /// Parses a Meshtastic MQTT topic.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic a message was received on.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the parsed `MqttTopic`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let topic = MqttTopic::parse("msh/US/2/e/LongFast/!deadbeef")?;
+    /// assert_eq!(topic.root, "msh/US");
+    /// assert_eq!(topic.channel_id.as_deref(), Some("LongFast"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidMqttTopic` if the topic does not contain a version `2` message
+    /// topic, or if the channel or gateway of an envelope or JSON topic is missing.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn parse(topic: &str) -> Result<MqttTopic, Error> {
+        let invalid = |description: &str| Error::InvalidMqttTopic {
+            topic: topic.to_string(),
+            description: description.to_string(),
+        };
+
+        let segments: Vec<&str> = topic.split('/').collect();
+
+        // * The root topic is configurable and may contain any number of levels
+        let (version_index, kind) = segments
+            .windows(2)
+            .enumerate()
+            .skip(1)
+            .find_map(|(i, window)| match window {
+                [TOPIC_VERSION, kind] => MqttTopicKind::from_segment(kind).map(|kind| (i, kind)),
+                _ => None,
+            })
+            .ok_or_else(|| invalid("Topic is not a Meshtastic version 2 topic"))?;
+
+        let root = segments[..version_index].join("/");
+        let rest: Vec<&str> = segments[version_index + 2..]
+            .iter()
+            .copied()
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        if kind == MqttTopicKind::Map {
+            return Ok(MqttTopic::map(&root));
+        }
+
+        let channel_id = rest
+            .first()
+            .ok_or_else(|| invalid("Topic is missing a channel"))?;
+        let gateway_id = rest
+            .get(1)
+            .ok_or_else(|| invalid("Topic is missing a gateway"))?;
+        let gateway_id =
+            NodeId::from_user_id(gateway_id).ok_or_else(|| invalid("Invalid gateway ID"))?;
+
+        Ok(MqttTopic {
+            root,
+            kind,
+            channel_id: Some(channel_id.to_string()),
+            gateway_id: Some(gateway_id),
+        })
+    }
+}
+
+impl std::fmt::Display for MqttTopic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{TOPIC_VERSION}/{}/", self.root, self.kind.segment())?;
+
+        if let Some(channel_id) = &self.channel_id {
+            write!(f, "{channel_id}")?;
+        }
+
+        if let Some(gateway_id) = &self.gateway_id {
+            write!(f, "/{}", gateway_id.to_user_id())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A struct that represents a mesh packet published over MQTT, as carried by a `ServiceEnvelope`.
+///
+/// # Fields
+///
+/// * `packet` - The mesh packet, which is usually encrypted.
+/// * `channel_id` - The name of the channel the packet was sent on.
+/// * `gateway_id` - The ID of the node that published the packet, if it is a valid user ID.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MqttEnvelope {
+    pub packet: protobufs::MeshPacket,
+    pub channel_id: String,
+    pub gateway_id: Option<NodeId>,
+}
+
+impl MqttEnvelope {
+    /// Decodes the payload of a message received on an envelope or map topic.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The payload of the MQTT message.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the decoded `MqttEnvelope`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let envelope = MqttEnvelope::decode(&publish.payload)?;
+    /// println!("{} relayed packet {}", envelope.channel_id, envelope.packet.id);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::DecodeError` if the payload is not a `ServiceEnvelope`, and with
+    /// `Error::InvalidMqttMessage` if the envelope does not contain a packet.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn decode(payload: &[u8]) -> Result<MqttEnvelope, Error> {
+        let envelope = protobufs::ServiceEnvelope::decode(payload)?;
+
+        let packet = envelope.packet.ok_or_else(|| Error::InvalidMqttMessage {
+            description: "Service envelope does not contain a packet".to_string(),
+        })?;
+
+        Ok(MqttEnvelope {
+            packet,
+            gateway_id: NodeId::from_user_id(&envelope.gateway_id),
+            channel_id: envelope.channel_id,
+        })
+    }
+
+    /// Encodes the envelope into the payload of an MQTT message.
+    pub fn encode(&self) -> Vec<u8> {
+        protobufs::ServiceEnvelope {
+            packet: Some(self.packet.clone()),
+            channel_id: self.channel_id.clone(),
+            gateway_id: self
+                .gateway_id
+                .map(|gateway_id| gateway_id.to_user_id())
+                .unwrap_or_default(),
+        }
+        .encode_to_vec()
+    }
+
+    /// Returns the topic that the gateway of the envelope publishes it on below the given root
+    /// topic, or `None` if the envelope has no gateway.
+    pub fn topic(&self, root: &str) -> Option<MqttTopic> {
+        self.gateway_id
+            .map(|gateway_id| MqttTopic::envelope(root, &self.channel_id, gateway_id))
+    }
+
+    /// Returns the packet of the envelope with its payload decrypted with the given key.
+    /// Packets that were published decoded are returned unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The `ChannelKey` of the channel of the envelope.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the decoded `MeshPacket`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let packet = envelope.decrypt(&ChannelKey::from_psk(&[1])?)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::PacketDecryptionFailed` if the packet cannot be decrypted with the key.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn decrypt(&self, key: &ChannelKey) -> Result<protobufs::MeshPacket, Error> {
+        decrypt_packet(&self.packet, key)
+    }
+
+    /// Returns the packet of the envelope with its payload decrypted with the matching key of
+    /// the given keyring. Packets that were published decoded are returned unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `keyring` - A `ChannelKeyring` holding the keys of the channels to decrypt.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the decoded `MeshPacket`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let packet = envelope.decrypt_with(&keyring)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::PacketDecryptionFailed` if no key of the keyring decrypts the packet.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn decrypt_with(&self, keyring: &ChannelKeyring) -> Result<protobufs::MeshPacket, Error> {
+        match &self.packet.payload_variant {
+            Some(protobufs::mesh_packet::PayloadVariant::Encrypted(_)) => {
+                keyring.decrypt(&self.packet)
+            }
+            _ => Ok(self.packet.clone()),
+        }
+    }
+
+    /// Decodes the `MapReport` carried by the envelope, or returns `None` if the packet of the
+    /// envelope is not a decoded map report.
+    pub fn map_report(&self) -> Option<Result<protobufs::MapReport, Error>> {
+        let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) =
+            &self.packet.payload_variant
+        else {
+            return None;
+        };
+
+        if data.portnum != protobufs::PortNum::MapReportApp as i32 {
+            return None;
+        }
+
+        Some(protobufs::MapReport::decode(data.payload.as_slice()).map_err(Error::from))
+    }
+
+    /// Builds the envelope of a map report, as published by a gateway on the map topic.
+    pub fn from_map_report(
+        map_report: &protobufs::MapReport,
+        from: NodeId,
+        gateway_id: NodeId,
+    ) -> MqttEnvelope {
+        MqttEnvelope {
+            packet: protobufs::MeshPacket {
+                from: from.id(),
+                to: u32::MAX,
+                payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                    protobufs::Data {
+                        portnum: protobufs::PortNum::MapReportApp as i32,
+                        payload: map_report.encode_to_vec(),
+                        ..Default::default()
+                    },
+                )),
+                ..Default::default()
+            },
+            channel_id: String::new(),
+            gateway_id: Some(gateway_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connections::crypto::{channel_hash, encrypt_packet};
+
+    use super::*;
+
+    const GATEWAY_NODE_NUM: u32 = 0xdeadbeef;
+
+    #[test]
+    fn parses_and_builds_topics() {
+        let topic = MqttTopic::parse("msh/US/CA/2/e/LongFast/!deadbeef").unwrap();
+        assert_eq!(
+            topic,
+            MqttTopic::envelope("msh/US/CA", "LongFast", NodeId::new(GATEWAY_NODE_NUM))
+        );
+        assert_eq!(topic.to_string(), "msh/US/CA/2/e/LongFast/!deadbeef");
+
+        let legacy = MqttTopic::parse("msh/EU_868/2/c/LongFast/!deadbeef").unwrap();
+        assert_eq!(legacy.kind, MqttTopicKind::Envelope);
+
+        let json = MqttTopic::parse("msh/US/2/json/Private/!0000002a").unwrap();
+        assert_eq!(json.kind, MqttTopicKind::Json);
+        assert_eq!(json.gateway_id, Some(NodeId::new(42)));
+
+        let map = MqttTopic::parse("msh/US/2/map/").unwrap();
+        assert_eq!(map, MqttTopic::map("msh/US"));
+        assert_eq!(map.to_string(), "msh/US/2/map/");
+
+        assert_eq!(
+            MqttTopic::subscription_filter("msh/US", MqttTopicKind::Envelope),
+            "msh/US/2/e/#"
+        );
+        assert_eq!(
+            MqttTopic::channel_filter("msh/US", MqttTopicKind::Envelope, "LongFast"),
+            "msh/US/2/e/LongFast/+"
+        );
+
+        for invalid in [
+            "msh/US/1/e/LongFast/!deadbeef",
+            "2/e/LongFast/!deadbeef",
+            "msh/US/2/e/LongFast",
+            "msh/US/2/e/LongFast/deadbeef",
+        ] {
+            assert!(matches!(
+                MqttTopic::parse(invalid),
+                Err(Error::InvalidMqttTopic { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn decodes_and_decrypts_envelopes() {
+        let key = ChannelKey::from_psk(&[1]).unwrap();
+        let decoded = protobufs::MeshPacket {
+            id: 0x12345678,
+            from: 0x0badcafe,
+            to: u32::MAX,
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                protobufs::Data {
+                    portnum: protobufs::PortNum::TextMessageApp as i32,
+                    payload: b"hello".to_vec(),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        };
+
+        let mut encrypted = encrypt_packet(&decoded, &key).unwrap();
+        encrypted.channel = channel_hash("LongFast", &key).into();
+
+        let envelope = MqttEnvelope {
+            packet: encrypted.clone(),
+            channel_id: "LongFast".to_string(),
+            gateway_id: Some(NodeId::new(GATEWAY_NODE_NUM)),
+        };
+
+        let received = MqttEnvelope::decode(&envelope.encode()).unwrap();
+        assert_eq!(received, envelope);
+        assert_eq!(
+            received.topic("msh/US").unwrap().to_string(),
+            "msh/US/2/e/LongFast/!deadbeef"
+        );
+
+        let decrypted = received.decrypt(&key).unwrap();
+        assert_eq!(decrypted.payload_variant, decoded.payload_variant);
+
+        let mut keyring = ChannelKeyring::new();
+        keyring.add("LongFast", key);
+        assert_eq!(received.decrypt_with(&keyring).unwrap(), decrypted);
+
+        assert!(matches!(
+            MqttEnvelope::decode(&protobufs::ServiceEnvelope::default().encode_to_vec()),
+            Err(Error::InvalidMqttMessage { .. })
+        ));
+    }
+
+    #[test]
+    fn round_trips_map_reports() {
+        let map_report = protobufs::MapReport {
+            long_name: "Gateway".to_string(),
+            short_name: "GW".to_string(),
+            latitude_i: 473_977_420,
+            longitude_i: 85_455_940,
+            num_online_local_nodes: 12,
+            ..Default::default()
+        };
+
+        let envelope = MqttEnvelope::from_map_report(
+            &map_report,
+            NodeId::new(GATEWAY_NODE_NUM),
+            NodeId::new(GATEWAY_NODE_NUM),
+        );
+        let received = MqttEnvelope::decode(&envelope.encode()).unwrap();
+
+        assert_eq!(received.map_report().unwrap().unwrap(), map_report);
+        assert!(received.decrypt(&ChannelKey::None).is_ok());
+    }
+}
@@ -0,0 +1,708 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use log::{debug, trace, warn};
+use prost::Message;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS, Transport};
+use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+use crate::types::EncodedToRadioPacketWithHeader;
+use crate::utils_internal::{format_data_packet, generate_rand_id};
+
+use super::mqtt::{MqttTopic, MqttTopicKind, DEFAULT_MQTT_ROOT_TOPIC};
+use super::snapshot::RadioSnapshot;
+use super::stream_api::{state, ConnectedStreamApi};
+use super::subscription::{FromRadioKind, PacketFilter, PacketSubscription};
+
+/// The broker used by the firmware when no address is configured in the `MqttConfig`.
+pub const DEFAULT_MQTT_ADDRESS: &str = "mqtt.meshtastic.org";
+
+/// The username used by the firmware to connect to the default broker.
+const DEFAULT_MQTT_USERNAME: &str = "meshdev";
+
+/// The password used by the firmware to connect to the default broker.
+const DEFAULT_MQTT_PASSWORD: &str = "large4cats";
+
+/// The channel name used by the firmware for packets encrypted with public keys.
+const PKI_CHANNEL_ID: &str = "PKI";
+
+/// The maximum number of requests queued for the broker connection.
+const REQUEST_CHANNEL_CAPACITY: usize = 32;
+
+/// A struct that overrides the broker settings read from the `MqttConfig` of the radio when
+/// starting an `MqttProxy`. Fields left as `None` use the configuration of the radio.
+///
+/// # Fields
+///
+/// * `address` - The broker to connect to, as `host` or `host:port`.
+/// * `username` - The username to authenticate with.
+/// * `password` - The password to authenticate with.
+/// * `tls_enabled` - Whether to connect to the broker over TLS.
+/// * `client_id` - The MQTT client ID, which defaults to the user ID of the radio.
+/// * `keep_alive` - The interval of the keep-alive pings sent to the broker.
+/// * `reconnect_delay` - The time to wait before reconnecting after the broker connection fails.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MqttProxyOptions {
+    pub address: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub tls_enabled: Option<bool>,
+    pub client_id: Option<String>,
+    pub keep_alive: Duration,
+    pub reconnect_delay: Duration,
+}
+
+impl Default for MqttProxyOptions {
+    fn default() -> Self {
+        MqttProxyOptions {
+            address: None,
+            username: None,
+            password: None,
+            tls_enabled: None,
+            client_id: None,
+            keep_alive: Duration::from_secs(60),
+            reconnect_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// A struct that connects a radio to an MQTT broker through the connection of the host.
+///
+/// When `proxy_to_client_enabled` is set in its `MqttConfig`, the radio does not connect to the
+/// broker itself, and instead sends the messages it would publish as `MqttClientProxyMessage`
+/// packets. The proxy publishes these messages to the broker, subscribes to the channels of the
+/// radio that have downlink enabled, and forwards the messages received from the broker back to
+/// the radio.
+///
+/// The proxy runs in a background task that reconnects to the broker when the connection fails.
+/// The proxy stops when the `shutdown` method is called, when the struct is dropped, or when the
+/// connection to the radio is closed.
+///
+/// # Examples
+///
+/// ```
+/// let (stream_api, snapshot) = stream_api.configure_and_wait(config_id, timeout).await?;
+/// let proxy = stream_api.start_mqtt_proxy(&snapshot, MqttProxyOptions::default())?;
+///
+/// // ...
+///
+/// proxy.shutdown().await?;
+/// ```
+#[derive(Debug)]
+pub struct MqttProxy {
+    subscriptions: Vec<String>,
+    cancellation_token: CancellationToken,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MqttProxy {
+    /// Connects the radio described by the snapshot to its broker. This method is exposed
+    /// through `ConnectedStreamApi::start_mqtt_proxy`.
+    pub(crate) fn start(
+        stream_api: &ConnectedStreamApi<state::Configured>,
+        snapshot: &RadioSnapshot,
+        options: MqttProxyOptions,
+    ) -> Result<MqttProxy, Error> {
+        let mqtt_config = snapshot.module_config.mqtt.clone().unwrap_or_default();
+
+        if !mqtt_config.enabled || !mqtt_config.proxy_to_client_enabled {
+            warn!("MQTT client proxy is not enabled on the radio, no messages will be proxied");
+        }
+
+        let mqtt_options = broker_options(snapshot, &options)?;
+        let subscriptions = subscription_topics(snapshot);
+
+        let (client, event_loop) = AsyncClient::new(mqtt_options, REQUEST_CHANNEL_CAPACITY);
+
+        // * Subscribe before spawning the task to avoid missing early uplink messages
+        let uplink =
+            stream_api.subscribe(PacketFilter::new().kind(FromRadioKind::MqttClientProxyMessage));
+
+        let cancellation_token = CancellationToken::new();
+        let handle = tokio::spawn(run_proxy(
+            client,
+            event_loop,
+            uplink,
+            stream_api.write_input_sender(),
+            subscriptions.clone(),
+            options.reconnect_delay,
+            cancellation_token.clone(),
+        ));
+
+        Ok(MqttProxy {
+            subscriptions,
+            cancellation_token,
+            handle: Some(handle),
+        })
+    }
+
+    /// Returns the topic filters that the proxy subscribes to on behalf of the radio.
+    pub fn subscriptions(&self) -> &[String] {
+        &self.subscriptions
+    }
+
+    /// Stops the proxy and waits for its background task to exit.
+    ///
+    /// # Arguments
+    ///
+    /// None
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the background task exited cleanly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// proxy.shutdown().await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::JoinError` if the background task panicked.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn shutdown(mut self) -> Result<(), Error> {
+        self.cancellation_token.cancel();
+
+        match self.handle.take() {
+            Some(handle) => Ok(handle.await?),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for MqttProxy {
+    fn drop(&mut self) {
+        self.cancellation_token.cancel();
+    }
+}
+
+/// Builds the broker connection options from the `MqttConfig` of the radio and the overrides.
+pub(crate) fn broker_options(
+    snapshot: &RadioSnapshot,
+    options: &MqttProxyOptions,
+) -> Result<MqttOptions, Error> {
+    let mqtt_config = snapshot.module_config.mqtt.clone().unwrap_or_default();
+
+    let address = options
+        .address
+        .clone()
+        .unwrap_or(mqtt_config.address)
+        .trim()
+        .to_string();
+    let address = match address.is_empty() {
+        true => DEFAULT_MQTT_ADDRESS.to_string(),
+        false => address,
+    };
+
+    let tls_enabled = options.tls_enabled.unwrap_or(mqtt_config.tls_enabled);
+    let default_port = match tls_enabled {
+        true => 8883,
+        false => 1883,
+    };
+
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port.parse().map_err(|_| Error::InvalidMqttBrokerAddress {
+                address: address.clone(),
+            })?;
+
+            (host.to_string(), port)
+        }
+        None => (address.clone(), default_port),
+    };
+
+    if host.is_empty() {
+        return Err(Error::InvalidMqttBrokerAddress { address });
+    }
+
+    // * Like the firmware, fall back to the public credentials when connecting to the default broker
+    let (default_username, default_password) = match host == DEFAULT_MQTT_ADDRESS {
+        true => (DEFAULT_MQTT_USERNAME, DEFAULT_MQTT_PASSWORD),
+        false => ("", ""),
+    };
+
+    let username = options.username.clone().unwrap_or(mqtt_config.username);
+    let password = options.password.clone().unwrap_or(mqtt_config.password);
+    let (username, password) = match username.is_empty() {
+        true => (default_username.to_string(), default_password.to_string()),
+        false => (username, password),
+    };
+
+    let client_id = options.client_id.clone().unwrap_or_else(|| {
+        snapshot
+            .my_node_id()
+            .map(|node_id| node_id.to_user_id())
+            .unwrap_or_else(|| format!("meshtastic-{:08x}", generate_rand_id::<u32>()))
+    });
+
+    let mut mqtt_options = MqttOptions::new(client_id, host, port);
+    mqtt_options.set_keep_alive(options.keep_alive);
+
+    if !username.is_empty() {
+        mqtt_options.set_credentials(username, password);
+    }
+
+    if tls_enabled {
+        mqtt_options.set_transport(Transport::tls_with_default_config());
+    }
+
+    Ok(mqtt_options)
+}
+
+/// Returns the topic filters of the channels of the radio that have downlink enabled.
+pub(crate) fn subscription_topics(snapshot: &RadioSnapshot) -> Vec<String> {
+    let mqtt_config = snapshot.module_config.mqtt.clone().unwrap_or_default();
+    let root = match mqtt_config.root.is_empty() {
+        true => DEFAULT_MQTT_ROOT_TOPIC.to_string(),
+        false => mqtt_config.root,
+    };
+
+    let mut topics: Vec<String> = snapshot
+        .channels
+        .iter()
+        .filter(|channel| channel.role != protobufs::channel::Role::Disabled as i32)
+        .filter_map(|channel| channel.settings.as_ref())
+        .filter(|settings| settings.downlink_enabled)
+        .map(|settings| {
+            let channel_id = match settings.name.is_empty() {
+                true => preset_channel_id(snapshot.config.lora.as_ref()),
+                false => settings.name.clone(),
+            };
+
+            MqttTopic::channel_filter(&root, MqttTopicKind::Envelope, &channel_id)
+        })
+        .collect();
+
+    // * Direct messages encrypted with public keys are published on their own channel
+    if !topics.is_empty() {
+        topics.push(MqttTopic::channel_filter(
+            &root,
+            MqttTopicKind::Envelope,
+            PKI_CHANNEL_ID,
+        ));
+    }
+
+    // * Channels can resolve to the same name, e.g. an unnamed `LongFast` primary channel and a
+    // * secondary channel named `LongFast`, which must only be subscribed to once
+    let mut seen = HashSet::new();
+    topics.retain(|topic| seen.insert(topic.clone()));
+    topics
+}
+
+/// The value of the `SHORT_TURBO` modem preset, which is missing from the generated protobufs.
+const SHORT_TURBO_PRESET: i32 = 8;
+
+/// Returns the name used by the firmware for a channel without a name, which is the name of the
+/// modem preset of the radio, such as `LongFast`.
+fn preset_channel_id(lora_config: Option<&protobufs::config::LoRaConfig>) -> String {
+    let lora_config = lora_config.cloned().unwrap_or_default();
+
+    if !lora_config.use_preset {
+        return "Custom".to_string();
+    }
+
+    use protobufs::config::lo_ra_config::ModemPreset;
+
+    // * These names do not follow the protobuf names, e.g. `VERY_LONG_SLOW` is `VLongSlow`
+    let name = match ModemPreset::try_from(lora_config.modem_preset) {
+        Ok(ModemPreset::LongFast) => "LongFast",
+        Ok(ModemPreset::LongSlow) => "LongSlow",
+        Ok(ModemPreset::VeryLongSlow) => "VLongSlow",
+        Ok(ModemPreset::MediumSlow) => "MediumSlow",
+        Ok(ModemPreset::MediumFast) => "MediumFast",
+        Ok(ModemPreset::ShortSlow) => "ShortSlow",
+        Ok(ModemPreset::ShortFast) => "ShortFast",
+        Ok(ModemPreset::LongModerate) => "LongMod",
+        // * `SHORT_TURBO` is newer than the generated protobufs
+        Err(_) if lora_config.modem_preset == SHORT_TURBO_PRESET => "ShortTurbo",
+        Err(_) => "Invalid",
+    };
+
+    name.to_string()
+}
+
+/// Returns the topic, retained flag and payload of a message that the radio wants to publish.
+pub(crate) fn uplink_message(packet: &protobufs::FromRadio) -> Option<(String, bool, Vec<u8>)> {
+    let Some(protobufs::from_radio::PayloadVariant::MqttClientProxyMessage(message)) =
+        &packet.payload_variant
+    else {
+        return None;
+    };
+
+    let payload = match &message.payload_variant {
+        Some(protobufs::mqtt_client_proxy_message::PayloadVariant::Data(data)) => data.clone(),
+        Some(protobufs::mqtt_client_proxy_message::PayloadVariant::Text(text)) => {
+            text.as_bytes().to_vec()
+        }
+        None => vec![],
+    };
+
+    Some((message.topic.clone(), message.retained, payload))
+}
+
+/// Wraps a message received from the broker into the `ToRadio` packet forwarded to the radio.
+pub(crate) fn downlink_message(topic: &str, retained: bool, payload: &[u8]) -> protobufs::ToRadio {
+    protobufs::ToRadio {
+        payload_variant: Some(protobufs::to_radio::PayloadVariant::MqttClientProxyMessage(
+            protobufs::MqttClientProxyMessage {
+                topic: topic.to_string(),
+                retained,
+                payload_variant: Some(protobufs::mqtt_client_proxy_message::PayloadVariant::Data(
+                    payload.to_vec(),
+                )),
+            },
+        )),
+    }
+}
+
+/// Runs the uplink and downlink halves of the proxy until the proxy is cancelled.
+async fn run_proxy(
+    client: AsyncClient,
+    event_loop: EventLoop,
+    uplink: PacketSubscription,
+    write_input_tx: UnboundedSender<EncodedToRadioPacketWithHeader>,
+    subscriptions: Vec<String>,
+    reconnect_delay: Duration,
+    cancellation_token: CancellationToken,
+) {
+    tokio::join!(
+        forward_uplink(client.clone(), uplink, cancellation_token.clone()),
+        forward_downlink(
+            client.clone(),
+            event_loop,
+            write_input_tx,
+            subscriptions,
+            reconnect_delay,
+            cancellation_token,
+        ),
+    );
+
+    let _ = client.try_disconnect();
+}
+
+/// Publishes the messages emitted by the radio to the broker.
+async fn forward_uplink(
+    client: AsyncClient,
+    mut uplink: PacketSubscription,
+    cancellation_token: CancellationToken,
+) {
+    loop {
+        let packet = tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            packet = uplink.recv() => packet,
+        };
+
+        let packet = match packet {
+            Ok(packet) => packet,
+            Err(Error::SubscriptionLagged { skipped }) => {
+                warn!("MQTT proxy skipped {skipped} messages from the radio");
+                continue;
+            }
+            Err(e) => {
+                debug!("Stopping MQTT proxy after radio connection closed: {e}");
+                cancellation_token.cancel();
+                break;
+            }
+        };
+
+        let Some((topic, retained, payload)) = uplink_message(&packet) else {
+            continue;
+        };
+
+        trace!("Publishing {} bytes to {topic}", payload.len());
+
+        if let Err(e) = client
+            .publish(topic, QoS::AtMostOnce, retained, payload)
+            .await
+        {
+            warn!("Failed to publish MQTT proxy message: {e}");
+        }
+    }
+}
+
+/// Drives the broker connection, forwarding the messages received from the broker to the radio.
+async fn forward_downlink(
+    client: AsyncClient,
+    mut event_loop: EventLoop,
+    write_input_tx: UnboundedSender<EncodedToRadioPacketWithHeader>,
+    subscriptions: Vec<String>,
+    reconnect_delay: Duration,
+    cancellation_token: CancellationToken,
+) {
+    loop {
+        let event = tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            event = event_loop.poll() => event,
+        };
+
+        match event {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                debug!("MQTT proxy connected to broker");
+
+                // * Subscriptions are not kept by the broker across clean sessions
+                for topic in &subscriptions {
+                    if let Err(e) = client.try_subscribe(topic.clone(), QoS::AtMostOnce) {
+                        warn!("Failed to subscribe to {topic}: {e}");
+                    }
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                trace!(
+                    "Forwarding {} bytes from {} to radio",
+                    publish.payload.len(),
+                    publish.topic
+                );
+
+                let packet = downlink_message(&publish.topic, publish.retain, &publish.payload);
+                let sent = format_data_packet(packet.encode_to_vec().into()).and_then(|data| {
+                    write_input_tx
+                        .send(data)
+                        .map_err(|e| Error::InternalChannelError(e.into()))
+                });
+
+                if let Err(e) = sent {
+                    debug!("Stopping MQTT proxy after radio connection closed: {e}");
+                    cancellation_token.cancel();
+                    break;
+                }
+            }
+            Ok(_) => (),
+            Err(e) => {
+                warn!("MQTT proxy connection to broker failed: {e}");
+
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => break,
+                    _ = tokio::time::sleep(reconnect_delay) => (),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connections::wrappers::NodeId;
+
+    use super::*;
+
+    const MY_NODE_NUM: u32 = 42;
+
+    fn snapshot(mqtt_config: protobufs::module_config::MqttConfig) -> RadioSnapshot {
+        RadioSnapshot {
+            my_info: Some(protobufs::MyNodeInfo {
+                my_node_num: MY_NODE_NUM,
+                ..Default::default()
+            }),
+            module_config: protobufs::LocalModuleConfig {
+                mqtt: Some(mqtt_config),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn channel(
+        index: i32,
+        role: protobufs::channel::Role,
+        name: &str,
+        downlink: bool,
+    ) -> protobufs::Channel {
+        protobufs::Channel {
+            index,
+            role: role as i32,
+            settings: Some(protobufs::ChannelSettings {
+                name: name.to_string(),
+                downlink_enabled: downlink,
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn builds_broker_options_from_config() {
+        let radio = snapshot(protobufs::module_config::MqttConfig {
+            enabled: true,
+            proxy_to_client_enabled: true,
+            ..Default::default()
+        });
+
+        let mqtt_options = broker_options(&radio, &MqttProxyOptions::default()).unwrap();
+        assert_eq!(
+            mqtt_options.broker_address(),
+            (DEFAULT_MQTT_ADDRESS.to_string(), 1883)
+        );
+        assert_eq!(
+            mqtt_options.credentials(),
+            Some(("meshdev".to_string(), "large4cats".to_string()))
+        );
+        assert_eq!(
+            mqtt_options.client_id(),
+            NodeId::new(MY_NODE_NUM).to_user_id()
+        );
+
+        let radio = snapshot(protobufs::module_config::MqttConfig {
+            address: "broker.local:1884".to_string(),
+            username: "user".to_string(),
+            password: "secret".to_string(),
+            ..Default::default()
+        });
+
+        let mqtt_options = broker_options(&radio, &MqttProxyOptions::default()).unwrap();
+        assert_eq!(
+            mqtt_options.broker_address(),
+            ("broker.local".to_string(), 1884)
+        );
+        assert_eq!(
+            mqtt_options.credentials(),
+            Some(("user".to_string(), "secret".to_string()))
+        );
+
+        let overridden = MqttProxyOptions {
+            address: Some("other.local".to_string()),
+            tls_enabled: Some(false),
+            client_id: Some("proxy".to_string()),
+            ..Default::default()
+        };
+
+        let mqtt_options = broker_options(&radio, &overridden).unwrap();
+        assert_eq!(
+            mqtt_options.broker_address(),
+            ("other.local".to_string(), 1883)
+        );
+        assert_eq!(mqtt_options.client_id(), "proxy");
+
+        let invalid = MqttProxyOptions {
+            address: Some("broker.local:mqtt".to_string()),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            broker_options(&radio, &invalid),
+            Err(Error::InvalidMqttBrokerAddress { .. })
+        ));
+    }
+
+    #[test]
+    fn subscribes_to_downlink_channels() {
+        use protobufs::config::lo_ra_config::ModemPreset;
+
+        let mut radio = snapshot(protobufs::module_config::MqttConfig {
+            root: "msh/EU_868".to_string(),
+            ..Default::default()
+        });
+        radio.config.lora = Some(protobufs::config::LoRaConfig {
+            use_preset: true,
+            modem_preset: ModemPreset::MediumFast as i32,
+            ..Default::default()
+        });
+        radio.channels = vec![
+            channel(0, protobufs::channel::Role::Primary, "", true),
+            channel(1, protobufs::channel::Role::Secondary, "Private", true),
+            channel(2, protobufs::channel::Role::Secondary, "Uplink", false),
+            channel(3, protobufs::channel::Role::Disabled, "Old", true),
+        ];
+
+        assert_eq!(
+            subscription_topics(&radio),
+            vec![
+                "msh/EU_868/2/e/MediumFast/+".to_string(),
+                "msh/EU_868/2/e/Private/+".to_string(),
+                "msh/EU_868/2/e/PKI/+".to_string(),
+            ]
+        );
+
+        let topics = |modem_preset: ModemPreset, use_preset: bool| {
+            let mut radio = radio.clone();
+            radio.config.lora = Some(protobufs::config::LoRaConfig {
+                use_preset,
+                modem_preset: modem_preset as i32,
+                ..Default::default()
+            });
+            subscription_topics(&radio)[0].clone()
+        };
+        assert_eq!(
+            topics(ModemPreset::VeryLongSlow, true),
+            "msh/EU_868/2/e/VLongSlow/+"
+        );
+        assert_eq!(
+            topics(ModemPreset::LongModerate, true),
+            "msh/EU_868/2/e/LongMod/+"
+        );
+        assert_eq!(
+            topics(ModemPreset::LongModerate, false),
+            "msh/EU_868/2/e/Custom/+"
+        );
+
+        radio.channels.push(channel(
+            4,
+            protobufs::channel::Role::Secondary,
+            "MediumFast",
+            true,
+        ));
+        assert_eq!(
+            subscription_topics(&radio),
+            vec![
+                "msh/EU_868/2/e/MediumFast/+".to_string(),
+                "msh/EU_868/2/e/Private/+".to_string(),
+                "msh/EU_868/2/e/PKI/+".to_string(),
+            ]
+        );
+
+        radio.channels.truncate(0);
+        assert!(subscription_topics(&radio).is_empty());
+    }
+
+    #[test]
+    fn converts_proxy_messages() {
+        let packet = protobufs::FromRadio {
+            payload_variant: Some(
+                protobufs::from_radio::PayloadVariant::MqttClientProxyMessage(
+                    protobufs::MqttClientProxyMessage {
+                        topic: "msh/US/2/e/LongFast/!0000002a".to_string(),
+                        retained: false,
+                        payload_variant: Some(
+                            protobufs::mqtt_client_proxy_message::PayloadVariant::Data(vec![
+                                1, 2, 3,
+                            ]),
+                        ),
+                    },
+                ),
+            ),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            uplink_message(&packet),
+            Some((
+                "msh/US/2/e/LongFast/!0000002a".to_string(),
+                false,
+                vec![1, 2, 3]
+            ))
+        );
+        assert_eq!(uplink_message(&protobufs::FromRadio::default()), None);
+
+        let downlink = downlink_message("msh/US/2/e/LongFast/!deadbeef", true, &[4, 5]);
+        let Some(protobufs::to_radio::PayloadVariant::MqttClientProxyMessage(message)) =
+            downlink.payload_variant
+        else {
+            panic!("Expected an MQTT client proxy message");
+        };
+
+        assert_eq!(message.topic, "msh/US/2/e/LongFast/!deadbeef");
+        assert!(message.retained);
+        assert_eq!(
+            message.payload_variant,
+            Some(protobufs::mqtt_client_proxy_message::PayloadVariant::Data(
+                vec![4, 5]
+            ))
+        );
+    }
+}
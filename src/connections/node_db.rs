@@ -0,0 +1,687 @@
+use std::collections::HashMap;
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+use log::{trace, warn};
+use prost::Message;
+use tokio::sync::broadcast;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+use crate::utils_internal::current_epoch_secs_u32;
+
+use super::position::GeoPosition;
+use super::snapshot::RadioSnapshot;
+use super::wrappers::NodeId;
+
+/// The number of node database events buffered for each subscriber before the slowest
+/// subscriber starts missing events.
+const NODE_DB_EVENT_CAPACITY: usize = 256;
+
+/// A struct that represents everything that is known about a single node in the mesh.
+///
+/// This struct mirrors the `NodeInfo` struct reported by the radio, but stores fields that
+/// have not been reported yet as `None` rather than as zero values.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct NodeRecord {
+    /// The node number of the node.
+    pub num: u32,
+
+    /// The user information of the node, as last broadcast by the node.
+    pub user: Option<protobufs::User>,
+
+    /// The last known position of the node.
+    pub position: Option<protobufs::Position>,
+
+    /// The last device metrics (e.g., battery level) reported by the node.
+    pub device_metrics: Option<protobufs::DeviceMetrics>,
+
+    /// The time the node was last heard from, in seconds since the Unix epoch.
+    pub last_heard: Option<u32>,
+
+    /// The signal-to-noise ratio of the last packet received directly from the node.
+    pub snr: Option<f32>,
+
+    /// The received signal strength of the last packet received directly from the node.
+    pub rssi: Option<i32>,
+
+    /// The number of hops the last packet from the node took to reach the connected radio.
+    pub hops_away: Option<u32>,
+
+    /// The index of the channel the node was last heard on.
+    pub channel: u32,
+
+    /// Whether the node was last heard through an MQTT gateway.
+    pub via_mqtt: bool,
+
+    /// Whether the node is marked as a favorite on the connected radio.
+    pub is_favorite: bool,
+}
+
+impl NodeRecord {
+    /// Creates an empty record for the given node.
+    pub fn new(node_id: NodeId) -> NodeRecord {
+        NodeRecord {
+            num: node_id.id(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the ID of the node.
+    pub fn node_id(&self) -> NodeId {
+        self.num.into()
+    }
+
+    /// Returns the last known position of the node, if it has reported coordinates.
+    pub fn geo_position(&self) -> Option<GeoPosition> {
+        self.position
+            .as_ref()
+            .map(GeoPosition::from)
+            .filter(GeoPosition::has_coordinates)
+    }
+
+    /// Converts the record into the `NodeInfo` struct used by the radio.
+    pub fn to_node_info(&self) -> protobufs::NodeInfo {
+        protobufs::NodeInfo {
+            num: self.num,
+            user: self.user.clone(),
+            position: self.position,
+            snr: self.snr.unwrap_or_default(),
+            last_heard: self.last_heard.unwrap_or_default(),
+            device_metrics: self.device_metrics,
+            channel: self.channel,
+            via_mqtt: self.via_mqtt,
+            hops_away: self.hops_away.unwrap_or_default(),
+            is_favorite: self.is_favorite,
+        }
+    }
+
+    /// Merges a `NodeInfo` reported by the radio into the record. Fields that the radio has not
+    /// set are left unchanged, and older reports do not override more recent information.
+    fn merge_node_info(&mut self, node_info: protobufs::NodeInfo) {
+        if node_info.last_heard != 0 && Some(node_info.last_heard) < self.last_heard {
+            trace!("Ignoring outdated node info for node {}", node_info.num);
+            return;
+        }
+
+        if node_info.user.is_some() {
+            self.user = node_info.user;
+        }
+        if node_info.position.is_some() {
+            self.position = node_info.position;
+        }
+        if node_info.device_metrics.is_some() {
+            self.device_metrics = node_info.device_metrics;
+        }
+        if node_info.last_heard != 0 {
+            self.last_heard = Some(node_info.last_heard);
+            self.snr = Some(node_info.snr);
+            self.hops_away = Some(node_info.hops_away);
+        }
+
+        self.channel = node_info.channel;
+        self.via_mqtt = node_info.via_mqtt;
+        self.is_favorite = node_info.is_favorite;
+    }
+
+    /// Merges the metadata of a mesh packet sent by the node into the record.
+    fn merge_packet_metadata(&mut self, packet: &protobufs::MeshPacket) {
+        self.last_heard = Some(match packet.rx_time {
+            0 => current_epoch_secs_u32(),
+            rx_time => rx_time,
+        });
+
+        // Packets originating from the connected radio itself carry no reception metadata
+        if packet.rx_rssi != 0 || packet.rx_snr != 0.0 {
+            self.snr = Some(packet.rx_snr);
+            self.rssi = Some(packet.rx_rssi);
+        }
+
+        // Firmware older than 2.3 does not set `hop_start`
+        if packet.hop_start != 0 {
+            self.hops_away = Some(packet.hop_start.saturating_sub(packet.hop_limit));
+        }
+
+        self.channel = packet.channel;
+        self.via_mqtt = packet.via_mqtt;
+    }
+}
+
+impl From<protobufs::NodeInfo> for NodeRecord {
+    fn from(node_info: protobufs::NodeInfo) -> Self {
+        let mut record = NodeRecord::new(node_info.num.into());
+        record.merge_node_info(node_info);
+        record
+    }
+}
+
+/// An enum that represents a change to the node database.
+///
+/// # Variants
+///
+/// * `Added` - A node that was not yet known has been added to the database.
+/// * `Updated` - The record of a known node has been updated.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeDbEvent {
+    Added(NodeRecord),
+    Updated(NodeRecord),
+}
+
+impl NodeDbEvent {
+    /// Returns the record of the node that has changed.
+    pub fn record(&self) -> &NodeRecord {
+        match self {
+            NodeDbEvent::Added(record) | NodeDbEvent::Updated(record) => record,
+        }
+    }
+}
+
+/// A type alias for the tokio channel that is used to receive changes to a `NodeDb`.
+pub type NodeDbEventReceiver = broadcast::Receiver<NodeDbEvent>;
+
+/// The on-disk representation of a `NodeDb`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedNodeDb {
+    my_node_num: Option<u32>,
+    nodes: Vec<NodeRecord>,
+}
+
+/// A struct that aggregates everything that is known about the nodes in the mesh.
+///
+/// The database is updated by passing every `FromRadio` packet received from the radio to the
+/// `handle_packet` method. Nodes are added or updated from the `NodeInfo` packets sent during
+/// the configuration handshake, from the metadata of every received mesh packet, and from the
+/// payloads of `NodeinfoApp`, `PositionApp` and `TelemetryApp` packets.
+///
+/// Changes to the database are broadcast to the receivers returned by the `subscribe` method.
+/// When the `serde` feature is enabled, the database can be persisted to and loaded from a
+/// JSON file with the `save` and `load` methods.
+#[derive(Debug)]
+pub struct NodeDb {
+    my_node_num: Option<u32>,
+    nodes: HashMap<u32, NodeRecord>,
+    event_tx: broadcast::Sender<NodeDbEvent>,
+}
+
+impl Default for NodeDb {
+    fn default() -> Self {
+        NodeDb::new()
+    }
+}
+
+impl NodeDb {
+    /// Creates an empty node database.
+    pub fn new() -> NodeDb {
+        let (event_tx, _) = broadcast::channel(NODE_DB_EVENT_CAPACITY);
+
+        NodeDb {
+            my_node_num: None,
+            nodes: HashMap::new(),
+            event_tx,
+        }
+    }
+
+    /// Creates a node database containing the nodes reported by the radio during the
+    /// configuration handshake.
+    pub fn from_snapshot(snapshot: &RadioSnapshot) -> NodeDb {
+        let mut node_db = NodeDb::new();
+        node_db.my_node_num = snapshot.my_info.map(|info| info.my_node_num);
+
+        for node_info in &snapshot.nodes {
+            node_db.update_node(node_info.num, |record| {
+                record.merge_node_info(node_info.clone())
+            });
+        }
+
+        node_db
+    }
+
+    /// Returns a receiver that is notified of every subsequent change to the database.
+    pub fn subscribe(&self) -> NodeDbEventReceiver {
+        self.event_tx.subscribe()
+    }
+
+    /// Returns the ID of the connected radio, if the radio has reported it.
+    pub fn my_node_id(&self) -> Option<NodeId> {
+        self.my_node_num.map(NodeId::from)
+    }
+
+    /// Returns the record of the given node, if the node is known.
+    pub fn node(&self, node_id: NodeId) -> Option<&NodeRecord> {
+        self.nodes.get(&node_id.id())
+    }
+
+    /// Returns an iterator over the records of all known nodes, in no particular order.
+    pub fn nodes(&self) -> impl Iterator<Item = &NodeRecord> {
+        self.nodes.values()
+    }
+
+    /// Returns the records of all nodes heard from at or after the given time, in seconds since
+    /// the Unix epoch, sorted from the most recently heard node.
+    pub fn nodes_heard_since(&self, epoch_secs: u32) -> Vec<&NodeRecord> {
+        let mut nodes: Vec<&NodeRecord> = self
+            .nodes
+            .values()
+            .filter(|record| record.last_heard.is_some_and(|t| t >= epoch_secs))
+            .collect();
+
+        nodes.sort_by_key(|record| std::cmp::Reverse(record.last_heard));
+        nodes
+    }
+
+    /// Returns the record of the first node whose short or long name matches the given name.
+    pub fn find_by_name(&self, name: &str) -> Option<&NodeRecord> {
+        self.nodes.values().find(|record| {
+            record
+                .user
+                .as_ref()
+                .is_some_and(|user| user.short_name == name || user.long_name == name)
+        })
+    }
+
+    /// Returns the great-circle distance in meters between the last known positions of two nodes,
+    /// if both nodes have reported a position.
+    pub fn distance_between(&self, from: NodeId, to: NodeId) -> Option<f64> {
+        let from = self.node(from)?.geo_position()?;
+        let to = self.node(to)?.geo_position()?;
+
+        Some(from.distance_to(&to))
+    }
+
+    /// Returns the initial bearing in degrees from the last known position of a node towards the
+    /// last known position of another node, if both nodes have reported a position.
+    pub fn bearing_between(&self, from: NodeId, to: NodeId) -> Option<f64> {
+        let from = self.node(from)?.geo_position()?;
+        let to = self.node(to)?.geo_position()?;
+
+        Some(from.bearing_to(&to))
+    }
+
+    /// Returns the number of known nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns whether no nodes are known.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Updates the database from a packet received from the radio. Packets that do not carry
+    /// node information are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - A `FromRadio` packet received from the radio.
+    ///
+    /// # Returns
+    ///
+    /// The `NodeDbEvent` describing the change to the database, if the packet changed it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut node_db = NodeDb::new();
+    ///
+    /// while let Some(packet) = decoded_listener.recv().await {
+    ///     node_db.handle_packet(&packet);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn handle_packet(&mut self, packet: &protobufs::FromRadio) -> Option<NodeDbEvent> {
+        use protobufs::from_radio::PayloadVariant;
+
+        match packet.payload_variant.as_ref()? {
+            PayloadVariant::MyInfo(my_info) => {
+                self.my_node_num = Some(my_info.my_node_num);
+                None
+            }
+            PayloadVariant::NodeInfo(node_info) => {
+                Some(self.update_node(node_info.num, |record| {
+                    record.merge_node_info(node_info.clone())
+                }))
+            }
+            PayloadVariant::Packet(mesh_packet) => Some(self.handle_mesh_packet(mesh_packet)),
+            _ => None,
+        }
+    }
+
+    /// Updates the record of the sender of a mesh packet from the metadata and payload of the
+    /// packet.
+    fn handle_mesh_packet(&mut self, packet: &protobufs::MeshPacket) -> NodeDbEvent {
+        self.update_node(packet.from, |record| {
+            record.merge_packet_metadata(packet);
+
+            let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) =
+                &packet.payload_variant
+            else {
+                return;
+            };
+
+            match protobufs::PortNum::try_from(data.portnum) {
+                Ok(protobufs::PortNum::NodeinfoApp) => {
+                    match protobufs::User::decode(data.payload.as_slice()) {
+                        Ok(user) => record.user = Some(user),
+                        Err(e) => warn!("Failed to decode user of node {}: {e}", packet.from),
+                    }
+                }
+                Ok(protobufs::PortNum::PositionApp) => {
+                    match protobufs::Position::decode(data.payload.as_slice()) {
+                        Ok(position) => record.position = Some(position),
+                        Err(e) => warn!("Failed to decode position of node {}: {e}", packet.from),
+                    }
+                }
+                Ok(protobufs::PortNum::TelemetryApp) => {
+                    match protobufs::Telemetry::decode(data.payload.as_slice()) {
+                        Ok(protobufs::Telemetry {
+                            variant: Some(protobufs::telemetry::Variant::DeviceMetrics(metrics)),
+                            ..
+                        }) => record.device_metrics = Some(metrics),
+                        Ok(_) => trace!("Ignoring non-device telemetry of node {}", packet.from),
+                        Err(e) => warn!("Failed to decode telemetry of node {}: {e}", packet.from),
+                    }
+                }
+                _ => (),
+            }
+        })
+    }
+
+    /// Applies an update to the record of a node, creating the record if needed, and notifies
+    /// subscribers of the change.
+    fn update_node(&mut self, num: u32, update: impl FnOnce(&mut NodeRecord)) -> NodeDbEvent {
+        let is_new = !self.nodes.contains_key(&num);
+        let record = self
+            .nodes
+            .entry(num)
+            .or_insert_with(|| NodeRecord::new(num.into()));
+
+        update(record);
+
+        let event = if is_new {
+            NodeDbEvent::Added(record.clone())
+        } else {
+            NodeDbEvent::Updated(record.clone())
+        };
+
+        // Sending only fails if there are no subscribers
+        let _ = self.event_tx.send(event.clone());
+
+        event
+    }
+
+    /// Saves the database to a JSON file, replacing the file if it exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to save the database to.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the database was successfully saved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// node_db.save("nodes.json")?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::PersistenceError` if the file cannot be written.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+
+        let mut nodes: Vec<NodeRecord> = self.nodes.values().cloned().collect();
+        nodes.sort_by_key(|record| record.num);
+
+        let persisted = PersistedNodeDb {
+            my_node_num: self.my_node_num,
+            nodes,
+        };
+
+        let json = serde_json::to_vec_pretty(&persisted).map_err(|e| Error::PersistenceError {
+            source: Box::new(e),
+            description: "Failed to serialize node database".to_string(),
+        })?;
+
+        // Write to a temporary file first, so an interrupted save does not corrupt the database
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, json)
+            .and_then(|_| std::fs::rename(&temp_path, path))
+            .map_err(|e| Error::PersistenceError {
+                source: Box::new(e),
+                description: format!("Failed to write node database to {}", path.display()),
+            })
+    }
+
+    /// Loads a database from a JSON file previously written by the `save` method.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to load the database from.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the loaded `NodeDb`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let node_db = NodeDb::load("nodes.json").unwrap_or_default();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::PersistenceError` if the file cannot be read or does not contain a
+    /// valid node database.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    #[cfg(feature = "serde")]
+    pub fn load(path: impl AsRef<Path>) -> Result<NodeDb, Error> {
+        let path = path.as_ref();
+
+        let json = std::fs::read(path).map_err(|e| Error::PersistenceError {
+            source: Box::new(e),
+            description: format!("Failed to read node database from {}", path.display()),
+        })?;
+
+        let persisted: PersistedNodeDb =
+            serde_json::from_slice(&json).map_err(|e| Error::PersistenceError {
+                source: Box::new(e),
+                description: format!("Failed to parse node database from {}", path.display()),
+            })?;
+
+        let mut node_db = NodeDb::new();
+        node_db.my_node_num = persisted.my_node_num;
+        node_db.nodes = persisted
+            .nodes
+            .into_iter()
+            .map(|record| (record.num, record))
+            .collect();
+
+        Ok(node_db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh_packet(
+        from: u32,
+        portnum: protobufs::PortNum,
+        payload: Vec<u8>,
+    ) -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(
+                protobufs::MeshPacket {
+                    from,
+                    to: u32::MAX,
+                    rx_time: 1_700_000_000,
+                    rx_snr: 6.25,
+                    rx_rssi: -80,
+                    hop_start: 3,
+                    hop_limit: 1,
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: portnum as i32,
+                            payload,
+                            ..Default::default()
+                        },
+                    )),
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+
+    #[test]
+    fn aggregates_node_information() {
+        let mut node_db = NodeDb::new();
+        let mut events = node_db.subscribe();
+
+        let node_info = protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::NodeInfo(
+                protobufs::NodeInfo {
+                    num: 7,
+                    user: Some(protobufs::User {
+                        long_name: "Solar Router".to_string(),
+                        short_name: "SR".to_string(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )),
+        };
+        assert!(matches!(
+            node_db.handle_packet(&node_info),
+            Some(NodeDbEvent::Added(_))
+        ));
+
+        let position = protobufs::Position {
+            latitude_i: 475_000_000,
+            longitude_i: -1_220_000_000,
+            ..Default::default()
+        };
+        node_db.handle_packet(&mesh_packet(
+            7,
+            protobufs::PortNum::PositionApp,
+            position.encode_to_vec(),
+        ));
+
+        let telemetry = protobufs::Telemetry {
+            variant: Some(protobufs::telemetry::Variant::DeviceMetrics(
+                protobufs::DeviceMetrics {
+                    battery_level: 87,
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        };
+        let event = node_db.handle_packet(&mesh_packet(
+            7,
+            protobufs::PortNum::TelemetryApp,
+            telemetry.encode_to_vec(),
+        ));
+        assert!(matches!(event, Some(NodeDbEvent::Updated(_))));
+
+        let record = node_db.node(NodeId::new(7)).unwrap();
+        assert_eq!(record.position, Some(position));
+        assert_eq!(record.device_metrics.unwrap().battery_level, 87);
+        assert_eq!(record.last_heard, Some(1_700_000_000));
+        assert_eq!(record.snr, Some(6.25));
+        assert_eq!(record.rssi, Some(-80));
+        assert_eq!(record.hops_away, Some(2));
+        assert_eq!(node_db.find_by_name("SR").map(|r| r.num), Some(7));
+        assert_eq!(node_db.nodes_heard_since(1_700_000_000).len(), 1);
+        assert!(node_db.nodes_heard_since(1_700_000_001).is_empty());
+
+        assert!(matches!(events.try_recv(), Ok(NodeDbEvent::Added(_))));
+        assert!(matches!(events.try_recv(), Ok(NodeDbEvent::Updated(_))));
+        assert_eq!(events.try_recv().unwrap().record(), record);
+    }
+
+    #[test]
+    fn updates_user_from_nodeinfo_packets() {
+        let mut node_db = NodeDb::new();
+        let user = protobufs::User {
+            long_name: "Gateway".to_string(),
+            ..Default::default()
+        };
+
+        node_db.handle_packet(&mesh_packet(
+            9,
+            protobufs::PortNum::NodeinfoApp,
+            user.encode_to_vec(),
+        ));
+
+        // An older report from the radio does not override newer information
+        node_db.handle_packet(&protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::NodeInfo(
+                protobufs::NodeInfo {
+                    num: 9,
+                    last_heard: 1_600_000_000,
+                    ..Default::default()
+                },
+            )),
+        });
+
+        let record = node_db.node(NodeId::new(9)).unwrap();
+        assert_eq!(record.user, Some(user));
+        assert_eq!(record.last_heard, Some(1_700_000_000));
+        assert_eq!(node_db.len(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn persists_to_json() {
+        let mut node_db = NodeDb::new();
+        node_db.handle_packet(&protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::MyInfo(
+                protobufs::MyNodeInfo {
+                    my_node_num: 9,
+                    ..Default::default()
+                },
+            )),
+        });
+        node_db.handle_packet(&mesh_packet(9, protobufs::PortNum::TextMessageApp, vec![]));
+
+        let path = std::env::temp_dir().join(format!("node_db_{}.json", std::process::id()));
+        node_db.save(&path).unwrap();
+        let loaded = NodeDb::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.my_node_id(), Some(NodeId::new(9)));
+        assert_eq!(loaded.node(NodeId::new(9)), node_db.node(NodeId::new(9)));
+
+        assert!(matches!(
+            NodeDb::load(&path),
+            Err(Error::PersistenceError { .. })
+        ));
+    }
+}
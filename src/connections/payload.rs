@@ -0,0 +1,278 @@
+use prost::Message;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+
+/// An enum that represents the decoded payload of a `MeshPacket`, keyed on the `PortNum` the
+/// packet was sent on.
+///
+/// # Variants
+///
+/// * `Text` - A text message sent on the `TextMessageApp` port.
+/// * `Position` - A position sent on the `PositionApp` port.
+/// * `User` - The user information of a node, sent on the `NodeinfoApp` port.
+/// * `Routing` - A routing message, such as an acknowledgement, sent on the `RoutingApp` port.
+/// * `Admin` - An admin message sent on the `AdminApp` port.
+/// * `Waypoint` - A waypoint sent on the `WaypointApp` port.
+/// * `Telemetry` - Telemetry sent on the `TelemetryApp` port.
+/// * `Traceroute` - A route discovery request or response sent on the `TracerouteApp` port.
+/// * `NeighborInfo` - The neighbors of a node, sent on the `NeighborinfoApp` port.
+/// * `StoreAndForward` - A store and forward message sent on the `StoreForwardApp` port.
+/// * `Paxcount` - A count of nearby devices sent on the `PaxcounterApp` port.
+/// * `MapReport` - A map report sent on the `MapReportApp` port.
+/// * `RangeTest` - A range test message sent on the `RangeTestApp` port.
+/// * `Tak` - An ATAK packet sent on the `AtakPlugin` port.
+/// * `Encrypted` - A payload that the radio could not decrypt.
+/// * `Unknown` - A payload sent on any other port, which is not decoded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodedPayload {
+    Text(String),
+    Position(protobufs::Position),
+    User(protobufs::User),
+    Routing(protobufs::Routing),
+    Admin(protobufs::AdminMessage),
+    Waypoint(protobufs::Waypoint),
+    Telemetry(protobufs::Telemetry),
+    Traceroute(protobufs::RouteDiscovery),
+    NeighborInfo(protobufs::NeighborInfo),
+    StoreAndForward(protobufs::StoreAndForward),
+    Paxcount(protobufs::Paxcount),
+    MapReport(protobufs::MapReport),
+    RangeTest(String),
+    Tak(protobufs::TakPacket),
+    Encrypted(Vec<u8>),
+    Unknown { portnum: i32, payload: Vec<u8> },
+}
+
+impl DecodedPayload {
+    /// Decodes the payload of a mesh packet based on the port the packet was sent on.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh_packet` - The `MeshPacket` to decode the payload of.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `DecodedPayload` of the packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// match DecodedPayload::from_mesh_packet(&mesh_packet)? {
+    ///     DecodedPayload::Text(text) => println!("{}: {}", mesh_packet.from, text),
+    ///     DecodedPayload::Position(position) => println!("Position: {:?}", position),
+    ///     _ => {}
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::DecodeError` if the payload is not a valid message for its port.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn from_mesh_packet(mesh_packet: &protobufs::MeshPacket) -> Result<Self, Error> {
+        Ok(decode_mesh_packet(mesh_packet)?)
+    }
+
+    /// Decodes the payload of a `Data` message based on its port. See `from_mesh_packet`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The `Data` message to decode the payload of.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `DecodedPayload` of the message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let payload = DecodedPayload::from_data(&data)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::DecodeError` if the payload is not a valid message for its port.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn from_data(data: &protobufs::Data) -> Result<Self, Error> {
+        Ok(decode_data(data)?)
+    }
+
+    /// Returns the port that the payload is sent on, or `None` for encrypted payloads.
+    pub fn port_num(&self) -> Option<protobufs::PortNum> {
+        use protobufs::PortNum;
+
+        let port_num = match self {
+            DecodedPayload::Text(_) => PortNum::TextMessageApp,
+            DecodedPayload::Position(_) => PortNum::PositionApp,
+            DecodedPayload::User(_) => PortNum::NodeinfoApp,
+            DecodedPayload::Routing(_) => PortNum::RoutingApp,
+            DecodedPayload::Admin(_) => PortNum::AdminApp,
+            DecodedPayload::Waypoint(_) => PortNum::WaypointApp,
+            DecodedPayload::Telemetry(_) => PortNum::TelemetryApp,
+            DecodedPayload::Traceroute(_) => PortNum::TracerouteApp,
+            DecodedPayload::NeighborInfo(_) => PortNum::NeighborinfoApp,
+            DecodedPayload::StoreAndForward(_) => PortNum::StoreForwardApp,
+            DecodedPayload::Paxcount(_) => PortNum::PaxcounterApp,
+            DecodedPayload::MapReport(_) => PortNum::MapReportApp,
+            DecodedPayload::RangeTest(_) => PortNum::RangeTestApp,
+            DecodedPayload::Tak(_) => PortNum::AtakPlugin,
+            DecodedPayload::Encrypted(_) => return None,
+            DecodedPayload::Unknown { portnum, .. } => {
+                return PortNum::try_from(*portnum).ok();
+            }
+        };
+
+        Some(port_num)
+    }
+}
+
+/// Decodes the payload of a mesh packet, keeping the underlying `prost` error on failure.
+pub(crate) fn decode_mesh_packet(
+    mesh_packet: &protobufs::MeshPacket,
+) -> Result<DecodedPayload, prost::DecodeError> {
+    match &mesh_packet.payload_variant {
+        Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) => decode_data(data),
+        Some(protobufs::mesh_packet::PayloadVariant::Encrypted(encrypted)) => {
+            Ok(DecodedPayload::Encrypted(encrypted.clone()))
+        }
+        None => Ok(DecodedPayload::Unknown {
+            portnum: protobufs::PortNum::UnknownApp as i32,
+            payload: vec![],
+        }),
+    }
+}
+
+/// Decodes the payload of a `Data` message, keeping the underlying `prost` error on failure.
+pub(crate) fn decode_data(data: &protobufs::Data) -> Result<DecodedPayload, prost::DecodeError> {
+    use protobufs::PortNum;
+
+    let payload = data.payload.as_slice();
+
+    let decoded = match PortNum::try_from(data.portnum) {
+        Ok(PortNum::TextMessageApp) => {
+            DecodedPayload::Text(String::from_utf8_lossy(payload).into_owned())
+        }
+        Ok(PortNum::PositionApp) => DecodedPayload::Position(protobufs::Position::decode(payload)?),
+        Ok(PortNum::NodeinfoApp) => DecodedPayload::User(protobufs::User::decode(payload)?),
+        Ok(PortNum::RoutingApp) => DecodedPayload::Routing(protobufs::Routing::decode(payload)?),
+        Ok(PortNum::AdminApp) => DecodedPayload::Admin(protobufs::AdminMessage::decode(payload)?),
+        Ok(PortNum::WaypointApp) => DecodedPayload::Waypoint(protobufs::Waypoint::decode(payload)?),
+        Ok(PortNum::TelemetryApp) => {
+            DecodedPayload::Telemetry(protobufs::Telemetry::decode(payload)?)
+        }
+        Ok(PortNum::TracerouteApp) => {
+            DecodedPayload::Traceroute(protobufs::RouteDiscovery::decode(payload)?)
+        }
+        Ok(PortNum::NeighborinfoApp) => {
+            DecodedPayload::NeighborInfo(protobufs::NeighborInfo::decode(payload)?)
+        }
+        Ok(PortNum::StoreForwardApp) => {
+            DecodedPayload::StoreAndForward(protobufs::StoreAndForward::decode(payload)?)
+        }
+        Ok(PortNum::PaxcounterApp) => {
+            DecodedPayload::Paxcount(protobufs::Paxcount::decode(payload)?)
+        }
+        Ok(PortNum::MapReportApp) => {
+            DecodedPayload::MapReport(protobufs::MapReport::decode(payload)?)
+        }
+        Ok(PortNum::RangeTestApp) => {
+            DecodedPayload::RangeTest(String::from_utf8_lossy(payload).into_owned())
+        }
+        Ok(PortNum::AtakPlugin) => DecodedPayload::Tak(protobufs::TakPacket::decode(payload)?),
+        _ => DecodedPayload::Unknown {
+            portnum: data.portnum,
+            payload: data.payload.clone(),
+        },
+    };
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh_packet(portnum: protobufs::PortNum, payload: Vec<u8>) -> protobufs::MeshPacket {
+        protobufs::MeshPacket {
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                protobufs::Data {
+                    portnum: portnum as i32,
+                    payload,
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decodes_payloads_by_port() {
+        let text = mesh_packet(protobufs::PortNum::TextMessageApp, b"hello mesh".to_vec());
+        assert_eq!(
+            DecodedPayload::from_mesh_packet(&text).unwrap(),
+            DecodedPayload::Text("hello mesh".to_string())
+        );
+
+        let route = protobufs::RouteDiscovery {
+            route: vec![1, 2, 3],
+        };
+        let traceroute = mesh_packet(protobufs::PortNum::TracerouteApp, route.encode_to_vec());
+        let decoded = DecodedPayload::from_mesh_packet(&traceroute).unwrap();
+        assert_eq!(decoded, DecodedPayload::Traceroute(route));
+        assert_eq!(decoded.port_num(), Some(protobufs::PortNum::TracerouteApp));
+
+        let user = protobufs::User {
+            long_name: "Gateway".to_string(),
+            ..Default::default()
+        };
+        let node_info = mesh_packet(protobufs::PortNum::NodeinfoApp, user.encode_to_vec());
+        assert_eq!(
+            DecodedPayload::from_mesh_packet(&node_info).unwrap(),
+            DecodedPayload::User(user)
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_and_encrypted_payloads() {
+        let serial = mesh_packet(protobufs::PortNum::SerialApp, vec![1, 2, 3]);
+        let decoded = DecodedPayload::from_mesh_packet(&serial).unwrap();
+        assert_eq!(
+            decoded,
+            DecodedPayload::Unknown {
+                portnum: protobufs::PortNum::SerialApp as i32,
+                payload: vec![1, 2, 3],
+            }
+        );
+        assert_eq!(decoded.port_num(), Some(protobufs::PortNum::SerialApp));
+
+        let encrypted = protobufs::MeshPacket {
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Encrypted(vec![
+                9;
+                4
+            ])),
+            ..Default::default()
+        };
+        assert_eq!(
+            DecodedPayload::from_mesh_packet(&encrypted).unwrap(),
+            DecodedPayload::Encrypted(vec![9; 4])
+        );
+    }
+
+    #[test]
+    fn fails_on_invalid_payloads() {
+        let position = mesh_packet(protobufs::PortNum::PositionApp, vec![0xff]);
+
+        assert!(matches!(
+            DecodedPayload::from_mesh_packet(&position),
+            Err(Error::DecodeError(_))
+        ));
+    }
+}
@@ -0,0 +1,502 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use log::warn;
+use prost::Message;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+use crate::utils_internal::format_rfc3339;
+
+use super::wrappers::NodeId;
+
+/// The mean radius of the Earth in meters, as used by the haversine formula.
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// The number of precision bits of a position that has not been truncated.
+pub const FULL_PRECISION_BITS: u32 = 32;
+
+/// The default maximum number of positions recorded per node by a `PositionTracks` struct.
+pub const DEFAULT_MAX_TRACK_POINTS: usize = 1000;
+
+/// A struct that represents a geographic position, as carried by the `Position` struct.
+///
+/// Coordinates are stored in the same units of 1e-7 degrees as the `Position` struct, so that
+/// converting between the two types never loses precision.
+///
+/// # Fields
+///
+/// * `latitude_i` - The latitude in units of 1e-7 degrees.
+/// * `longitude_i` - The longitude in units of 1e-7 degrees.
+/// * `altitude` - The altitude above mean sea level in meters, if known.
+/// * `time` - The time the position was recorded, in seconds since the Unix epoch, if known.
+/// * `precision_bits` - The number of significant bits of the coordinates, where `32` means that
+///     the coordinates have not been truncated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct GeoPosition {
+    pub latitude_i: i32,
+    pub longitude_i: i32,
+    pub altitude: Option<i32>,
+    pub time: Option<u32>,
+    pub precision_bits: u32,
+}
+
+impl GeoPosition {
+    /// Creates a position from coordinates in degrees.
+    ///
+    /// # Arguments
+    ///
+    /// * `latitude` - The latitude in degrees, in the range [-90..90].
+    /// * `longitude` - The longitude in degrees, in the range [-180..180].
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `GeoPosition`, rounded to the nearest 1e-7 degrees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let position = GeoPosition::from_degrees(47.3977, 8.5456)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidCoordinates` if a coordinate is out of range.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn from_degrees(latitude: f64, longitude: f64) -> Result<GeoPosition, Error> {
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return Err(Error::InvalidCoordinates {
+                latitude,
+                longitude,
+            });
+        }
+
+        Ok(GeoPosition {
+            latitude_i: (latitude * 1e7).round() as i32,
+            longitude_i: (longitude * 1e7).round() as i32,
+            altitude: None,
+            time: None,
+            precision_bits: FULL_PRECISION_BITS,
+        })
+    }
+
+    /// Returns the latitude in degrees.
+    pub fn latitude(&self) -> f64 {
+        f64::from(self.latitude_i) * 1e-7
+    }
+
+    /// Returns the longitude in degrees.
+    pub fn longitude(&self) -> f64 {
+        f64::from(self.longitude_i) * 1e-7
+    }
+
+    /// Returns whether the position carries coordinates. Positions at exactly `(0, 0)` are
+    /// reported by radios that do not have a position, and are treated as missing.
+    pub fn has_coordinates(&self) -> bool {
+        self.latitude_i != 0 || self.longitude_i != 0
+    }
+
+    /// Truncates the coordinates to the given number of significant bits, to share an approximate
+    /// position without revealing the exact position.
+    ///
+    /// The truncation matches the truncation performed by the firmware for channels with a
+    /// reduced position precision, and moves the position to the center of the area that all
+    /// positions with the same truncated coordinates fall into.
+    ///
+    /// # Arguments
+    ///
+    /// * `precision_bits` - The number of significant bits to keep, in the range [1..32]. For
+    ///     example, `13` bits leave an area of about 3 km, and `16` bits an area of about 350 m.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the truncated `GeoPosition`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let approximate = position.with_precision(13)?;
+    /// stream_api
+    ///     .send_position(packet_router, approximate, PacketDestination::Broadcast, false, channel)
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidPrecisionBits` if `precision_bits` is not in the range [1..32].
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn with_precision(&self, precision_bits: u32) -> Result<GeoPosition, Error> {
+        if !(1..=FULL_PRECISION_BITS).contains(&precision_bits) {
+            return Err(Error::InvalidPrecisionBits { precision_bits });
+        }
+
+        // Truncated coordinates cannot be made more precise
+        if precision_bits >= self.precision_bits {
+            return Ok(*self);
+        }
+
+        let mask = u32::MAX << (FULL_PRECISION_BITS - precision_bits);
+        let center = 1u32 << (FULL_PRECISION_BITS - 1 - precision_bits);
+        let truncate = |coordinate: i32| ((coordinate as u32 & mask).wrapping_add(center)) as i32;
+
+        Ok(GeoPosition {
+            latitude_i: truncate(self.latitude_i),
+            longitude_i: truncate(self.longitude_i),
+            precision_bits,
+            ..*self
+        })
+    }
+
+    /// Returns the great-circle distance to another position in meters, computed with the
+    /// haversine formula. Altitudes are ignored.
+    pub fn distance_to(&self, other: &GeoPosition) -> f64 {
+        let (lat_1, lat_2) = (self.latitude().to_radians(), other.latitude().to_radians());
+        let delta_lat = lat_2 - lat_1;
+        let delta_lon = (other.longitude() - self.longitude()).to_radians();
+
+        let haversine = (delta_lat / 2.0).sin().powi(2)
+            + lat_1.cos() * lat_2.cos() * (delta_lon / 2.0).sin().powi(2);
+
+        2.0 * EARTH_RADIUS_METERS * haversine.sqrt().asin()
+    }
+
+    /// Returns the initial bearing towards another position in degrees, in the range [0..360),
+    /// where `0` is north and `90` is east.
+    pub fn bearing_to(&self, other: &GeoPosition) -> f64 {
+        let (lat_1, lat_2) = (self.latitude().to_radians(), other.latitude().to_radians());
+        let delta_lon = (other.longitude() - self.longitude()).to_radians();
+
+        let y = delta_lon.sin() * lat_2.cos();
+        let x = lat_1.cos() * lat_2.sin() - lat_1.sin() * lat_2.cos() * delta_lon.cos();
+
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+}
+
+impl From<&protobufs::Position> for GeoPosition {
+    fn from(position: &protobufs::Position) -> Self {
+        GeoPosition {
+            latitude_i: position.latitude_i,
+            longitude_i: position.longitude_i,
+            altitude: Some(position.altitude).filter(|altitude| *altitude != 0),
+            time: Some(position.time).filter(|time| *time != 0),
+            // * Radios that do not report a precision send the full position
+            precision_bits: match position.precision_bits {
+                0 => FULL_PRECISION_BITS,
+                precision_bits => precision_bits,
+            },
+        }
+    }
+}
+
+impl From<protobufs::Position> for GeoPosition {
+    fn from(position: protobufs::Position) -> Self {
+        GeoPosition::from(&position)
+    }
+}
+
+impl From<GeoPosition> for protobufs::Position {
+    fn from(position: GeoPosition) -> Self {
+        protobufs::Position {
+            latitude_i: position.latitude_i,
+            longitude_i: position.longitude_i,
+            altitude: position.altitude.unwrap_or(0),
+            time: position.time.unwrap_or(0),
+            precision_bits: position.precision_bits,
+            ..Default::default()
+        }
+    }
+}
+
+/// A struct that records the positions reported by every node, and exports them as GPX tracks.
+///
+/// The tracks are updated by passing every `FromRadio` packet received from the radio to the
+/// `handle_packet` method. Positions without coordinates and positions identical to the previous
+/// position of the node are not recorded, and the oldest positions of a node are dropped once its
+/// track holds the maximum number of positions.
+#[derive(Clone, Debug)]
+pub struct PositionTracks {
+    tracks: BTreeMap<u32, Vec<GeoPosition>>,
+    max_points: usize,
+}
+
+impl Default for PositionTracks {
+    fn default() -> Self {
+        PositionTracks::new(DEFAULT_MAX_TRACK_POINTS)
+    }
+}
+
+impl PositionTracks {
+    /// Creates an empty set of tracks that keeps at most `max_points` positions per node.
+    pub fn new(max_points: usize) -> PositionTracks {
+        PositionTracks {
+            tracks: BTreeMap::new(),
+            max_points,
+        }
+    }
+
+    /// Returns the recorded positions of the given node, from the oldest to the newest.
+    pub fn track(&self, node_id: NodeId) -> &[GeoPosition] {
+        self.tracks
+            .get(&node_id.id())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the IDs of the nodes with a recorded track.
+    pub fn nodes(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.tracks.keys().copied().map(NodeId::from)
+    }
+
+    /// Removes the track of the given node.
+    pub fn clear(&mut self, node_id: NodeId) {
+        self.tracks.remove(&node_id.id());
+    }
+
+    /// Records a position of the given node, returning whether the position was recorded.
+    pub fn record(&mut self, node_id: NodeId, position: GeoPosition) -> bool {
+        if !position.has_coordinates() {
+            return false;
+        }
+
+        let track = self.tracks.entry(node_id.id()).or_default();
+
+        let is_repeated = track.last().is_some_and(|last| {
+            last.latitude_i == position.latitude_i && last.longitude_i == position.longitude_i
+        });
+
+        if is_repeated {
+            return false;
+        }
+
+        track.push(position);
+
+        if track.len() > self.max_points {
+            track.drain(..track.len() - self.max_points);
+        }
+
+        true
+    }
+
+    /// Updates the tracks from a packet received from the radio. Packets that do not carry a
+    /// position are ignored. Positions without a time are recorded with the time the packet was
+    /// received.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - A `FromRadio` packet received from the radio.
+    ///
+    /// # Returns
+    ///
+    /// The ID of the node and the recorded position, if a position was recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut tracks = PositionTracks::default();
+    ///
+    /// while let Some(packet) = decoded_listener.recv().await {
+    ///     tracks.handle_packet(&packet);
+    /// }
+    ///
+    /// std::fs::write("tracks.gpx", tracks.to_gpx())?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn handle_packet(
+        &mut self,
+        packet: &protobufs::FromRadio,
+    ) -> Option<(NodeId, GeoPosition)> {
+        let Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)) =
+            &packet.payload_variant
+        else {
+            return None;
+        };
+
+        let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) =
+            &mesh_packet.payload_variant
+        else {
+            return None;
+        };
+
+        if data.portnum != protobufs::PortNum::PositionApp as i32 {
+            return None;
+        }
+
+        let position = match protobufs::Position::decode(data.payload.as_slice()) {
+            Ok(position) => position,
+            Err(e) => {
+                warn!("Failed to decode Position: {e}");
+                return None;
+            }
+        };
+
+        let mut position = GeoPosition::from(position);
+        position.time = position
+            .time
+            .or(Some(mesh_packet.rx_time).filter(|rx_time| *rx_time != 0));
+
+        let node_id = NodeId::from(mesh_packet.from);
+        self.record(node_id, position)
+            .then_some((node_id, position))
+    }
+
+    /// Renders the recorded tracks as a GPX 1.1 document, with one track per node named after
+    /// the user ID of the node.
+    pub fn to_gpx(&self) -> String {
+        let mut gpx = String::new();
+
+        let _ = writeln!(gpx, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(
+            gpx,
+            r#"<gpx version="1.1" creator="{}" xmlns="http://www.topografix.com/GPX/1/1">"#,
+            env!("CARGO_PKG_NAME")
+        );
+
+        for (node_num, track) in &self.tracks {
+            let _ = writeln!(gpx, "  <trk>");
+            let _ = writeln!(
+                gpx,
+                "    <name>{}</name>",
+                NodeId::from(*node_num).to_user_id()
+            );
+            let _ = writeln!(gpx, "    <trkseg>");
+
+            for position in track {
+                let _ = write!(
+                    gpx,
+                    r#"      <trkpt lat="{:.7}" lon="{:.7}">"#,
+                    position.latitude(),
+                    position.longitude()
+                );
+
+                if let Some(altitude) = position.altitude {
+                    let _ = write!(gpx, "<ele>{altitude}</ele>");
+                }
+
+                if let Some(time) = position.time {
+                    let _ = write!(gpx, "<time>{}</time>", format_rfc3339(time));
+                }
+
+                let _ = writeln!(gpx, "</trkpt>");
+            }
+
+            let _ = writeln!(gpx, "    </trkseg>");
+            let _ = writeln!(gpx, "  </trk>");
+        }
+
+        let _ = writeln!(gpx, "</gpx>");
+        gpx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position_packet(from: u32, rx_time: u32, position: GeoPosition) -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(
+                protobufs::MeshPacket {
+                    from,
+                    rx_time,
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: protobufs::PortNum::PositionApp as i32,
+                            payload: protobufs::Position::from(position).encode_to_vec(),
+                            ..Default::default()
+                        },
+                    )),
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+
+    #[test]
+    fn converts_and_truncates_positions() {
+        let westminster = GeoPosition::from_degrees(51.5007, -0.1246).unwrap();
+        assert_eq!(westminster.latitude_i, 515_007_000);
+        assert_eq!(
+            GeoPosition::from(protobufs::Position::from(westminster)),
+            westminster
+        );
+
+        let approximate = westminster.with_precision(13).unwrap();
+        assert_eq!(approximate.latitude_i, 515_112_960);
+        assert_eq!(approximate.longitude_i, -1_310_720);
+        assert_eq!(approximate.precision_bits, 13);
+        assert_eq!(approximate.with_precision(13).unwrap(), approximate);
+
+        assert_eq!(westminster.with_precision(32).unwrap(), westminster);
+        assert!(matches!(
+            westminster.with_precision(0),
+            Err(Error::InvalidPrecisionBits { precision_bits: 0 })
+        ));
+        assert!(matches!(
+            GeoPosition::from_degrees(91.0, 0.0),
+            Err(Error::InvalidCoordinates { .. })
+        ));
+    }
+
+    #[test]
+    fn computes_distance_and_bearing() {
+        let westminster = GeoPosition::from_degrees(51.5007, -0.1246).unwrap();
+        let eiffel_tower = GeoPosition::from_degrees(48.8584, 2.2945).unwrap();
+
+        assert!((westminster.distance_to(&eiffel_tower) - 340_539.0).abs() < 1.0);
+        assert!((westminster.bearing_to(&eiffel_tower) - 148.68).abs() < 0.01);
+        assert!(eiffel_tower.bearing_to(&westminster) > 270.0);
+        assert_eq!(westminster.distance_to(&westminster), 0.0);
+    }
+
+    #[test]
+    fn exports_recorded_tracks_as_gpx() {
+        let mut tracks = PositionTracks::new(2);
+        let start = GeoPosition {
+            altitude: Some(410),
+            ..GeoPosition::from_degrees(47.3977, 8.5456).unwrap()
+        };
+        let moved = GeoPosition::from_degrees(47.3987, 8.5466).unwrap();
+        let latest = GeoPosition::from_degrees(47.3997, 8.5476).unwrap();
+
+        assert!(tracks
+            .handle_packet(&position_packet(42, 1_700_000_000, start))
+            .is_some());
+        assert!(tracks
+            .handle_packet(&position_packet(42, 1_700_000_060, start))
+            .is_none());
+        assert!(tracks
+            .handle_packet(&position_packet(42, 1_700_000_120, moved))
+            .is_some());
+
+        let gpx = tracks.to_gpx();
+        assert!(gpx.contains("<name>!0000002a</name>"));
+        assert!(gpx.contains(
+            r#"<trkpt lat="47.3977000" lon="8.5456000"><ele>410</ele><time>2023-11-14T22:13:20Z</time></trkpt>"#
+        ));
+
+        tracks.record(NodeId::new(42), latest);
+        let track = tracks.track(NodeId::new(42));
+        assert_eq!(track.len(), 2);
+        assert_eq!(track[0].latitude_i, moved.latitude_i);
+    }
+}
@@ -0,0 +1,366 @@
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use log::{debug, warn};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::errors_internal::Error;
+
+use super::telemetry::TelemetryStore;
+
+/// The path that the metrics are served on.
+pub const PROMETHEUS_METRICS_PATH: &str = "/metrics";
+
+/// The maximum size of a request header accepted by the exporter.
+const MAX_REQUEST_LENGTH: usize = 8 * 1024;
+
+/// The maximum amount of time a client may take to send its request header.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A type alias for a `TelemetryStore` that is shared between the task updating it and the
+/// Prometheus exporter.
+pub type SharedTelemetryStore = Arc<RwLock<TelemetryStore>>;
+
+/// A struct that serves the metrics of a `TelemetryStore` over HTTP, in the Prometheus text
+/// exposition format.
+///
+/// The exporter runs in a background task and answers `GET /metrics` requests with the latest
+/// metrics of the store. The exporter stops when the `shutdown` method is called or when the
+/// struct is dropped.
+///
+/// # Examples
+///
+/// ```
+/// let telemetry = SharedTelemetryStore::default();
+/// let exporter = PrometheusExporter::bind("0.0.0.0:9464", telemetry.clone()).await?;
+///
+/// while let Some(packet) = decoded_listener.recv().await {
+///     telemetry.write().unwrap().handle_packet(&packet);
+/// }
+///
+/// exporter.shutdown().await?;
+/// ```
+#[derive(Debug)]
+pub struct PrometheusExporter {
+    local_addr: SocketAddr,
+    cancellation_token: CancellationToken,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PrometheusExporter {
+    /// Starts serving the metrics of the given store on the given address.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to listen on. Port `0` binds to a random free port.
+    /// * `telemetry` - The `TelemetryStore` whose metrics are served.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the running `PrometheusExporter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let exporter = PrometheusExporter::bind("0.0.0.0:9464", telemetry.clone()).await?;
+    /// println!("Serving metrics on {}", exporter.local_addr());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::ListenerBindError` if the address cannot be bound.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn bind(
+        addr: impl ToSocketAddrs,
+        telemetry: SharedTelemetryStore,
+    ) -> Result<PrometheusExporter, Error> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| Error::ListenerBindError {
+                source: Box::new(e),
+                description: "Failed to bind Prometheus exporter".to_string(),
+            })?;
+
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| Error::ListenerBindError {
+                source: Box::new(e),
+                description: "Failed to read address of Prometheus exporter".to_string(),
+            })?;
+
+        let cancellation_token = CancellationToken::new();
+        let handle = tokio::spawn(serve(listener, telemetry, cancellation_token.clone()));
+
+        Ok(PrometheusExporter {
+            local_addr,
+            cancellation_token,
+            handle: Some(handle),
+        })
+    }
+
+    /// Returns the address the exporter is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops the exporter and waits for its background task to exit.
+    ///
+    /// # Arguments
+    ///
+    /// None
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the background task exited cleanly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// exporter.shutdown().await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::JoinError` if the background task panicked.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn shutdown(mut self) -> Result<(), Error> {
+        self.cancellation_token.cancel();
+
+        match self.handle.take() {
+            Some(handle) => Ok(handle.await?),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for PrometheusExporter {
+    fn drop(&mut self) {
+        self.cancellation_token.cancel();
+    }
+}
+
+/// Accepts connections until the exporter is cancelled.
+async fn serve(
+    listener: TcpListener,
+    telemetry: SharedTelemetryStore,
+    cancellation_token: CancellationToken,
+) {
+    loop {
+        let stream = tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, peer)) => {
+                    debug!("Accepted Prometheus scrape from {peer}");
+                    stream
+                }
+                Err(e) => {
+                    warn!("Failed to accept Prometheus scrape: {e}");
+                    continue;
+                }
+            },
+        };
+
+        let telemetry = telemetry.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &telemetry, REQUEST_TIMEOUT).await {
+                warn!("Failed to answer Prometheus scrape: {e}");
+            }
+        });
+    }
+}
+
+/// Answers a single HTTP request, then closes the connection. Connections whose request header
+/// is not received within `request_timeout`, or exceeds `MAX_REQUEST_LENGTH`, are closed without
+/// an answer.
+async fn handle_connection(
+    mut stream: TcpStream,
+    telemetry: &SharedTelemetryStore,
+    request_timeout: Duration,
+) -> std::io::Result<()> {
+    let request = tokio::time::timeout(request_timeout, read_request_header(&mut stream))
+        .await
+        .map_err(|_| {
+            std::io::Error::new(ErrorKind::TimedOut, "Timed out reading the request header")
+        })??;
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+
+    let (status, body) = match (method, path) {
+        ("GET", PROMETHEUS_METRICS_PATH) => {
+            let body = match telemetry.read() {
+                Ok(telemetry) => telemetry.to_prometheus(),
+                Err(poisoned) => poisoned.into_inner().to_prometheus(),
+            };
+            ("200 OK", body)
+        }
+        ("GET", _) => ("404 Not Found", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "Method not allowed\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Reads a request header up to the blank line that ends it, or until the client stops sending.
+async fn read_request_header(stream: &mut TcpStream) -> std::io::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+
+        if read == 0 {
+            break;
+        }
+
+        if request.len() + read > MAX_REQUEST_LENGTH {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "Request header exceeds the maximum length",
+            ));
+        }
+
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use crate::protobufs;
+
+    use super::*;
+
+    async fn scrape(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn serves_latest_metrics() {
+        let telemetry = SharedTelemetryStore::default();
+        let exporter = PrometheusExporter::bind("127.0.0.1:0", telemetry.clone())
+            .await
+            .unwrap();
+
+        let telemetry_packet = protobufs::Telemetry {
+            time: 1_700_000_000,
+            variant: Some(protobufs::telemetry::Variant::DeviceMetrics(
+                protobufs::DeviceMetrics {
+                    battery_level: 64,
+                    ..Default::default()
+                },
+            )),
+        };
+
+        telemetry
+            .write()
+            .unwrap()
+            .handle_packet(&protobufs::FromRadio {
+                id: 0,
+                payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(
+                    protobufs::MeshPacket {
+                        from: 7,
+                        payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                            protobufs::Data {
+                                portnum: protobufs::PortNum::TelemetryApp as i32,
+                                payload: telemetry_packet.encode_to_vec(),
+                                ..Default::default()
+                            },
+                        )),
+                        ..Default::default()
+                    },
+                )),
+            });
+
+        let response = scrape(exporter.local_addr(), PROMETHEUS_METRICS_PATH).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("meshtastic_device_battery_level_percent{node=\"7\"} 64\n"));
+
+        let response = scrape(exporter.local_addr(), "/").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        exporter.shutdown().await.unwrap();
+    }
+
+    /// Accepts a single connection from a new client, and returns the client and server ends.
+    async fn connection_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        (client, server)
+    }
+
+    #[tokio::test]
+    async fn closes_idle_connections() {
+        let (_client, server) = connection_pair().await;
+
+        let result = handle_connection(
+            server,
+            &SharedTelemetryStore::default(),
+            Duration::from_millis(50),
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_request_headers() {
+        let (mut client, server) = connection_pair().await;
+
+        let header = format!(
+            "GET /metrics HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(9000)
+        );
+        client.write_all(header.as_bytes()).await.unwrap();
+
+        let result = handle_connection(
+            server,
+            &SharedTelemetryStore::default(),
+            Duration::from_secs(1),
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,439 @@
+use std::{future::Future, time::Duration};
+
+use log::{debug, trace, warn};
+use prost::Message;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    spawn,
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc::{UnboundedReceiver, UnboundedSender},
+    },
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+use crate::types::EncodedToRadioPacketWithHeader;
+use crate::utils_internal::{format_data_packet, generate_rand_id};
+
+use super::{handlers, stream_api::StreamHandle, wrappers::encoded_data::IncomingStreamData};
+
+/// An enum that represents the state of a connection managed by the reconnect supervisor.
+/// These states are emitted on the channel returned by `StreamApi::connect_with_reconnect`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The supervisor is building a new stream to the radio.
+    Connecting,
+
+    /// The radio completed the `WantConfigId` handshake with the specified configuration ID.
+    Configured { config_id: u32 },
+
+    /// The stream to the radio was closed, failed, or did not complete the handshake in time.
+    Lost,
+
+    /// The supervisor will attempt to reconnect to the radio after waiting for `delay`.
+    /// The `attempt` counter is reset once the radio completes a handshake.
+    Reconnecting { attempt: u32, delay: Duration },
+}
+
+/// A type alias for the tokio channel that is used to receive `ConnectionState` events
+/// from the reconnect supervisor.
+pub type ConnectionStateReceiver = UnboundedReceiver<ConnectionState>;
+
+/// A struct that configures how the reconnect supervisor retries lost connections.
+///
+/// The delay between reconnect attempts starts at `initial_backoff` and doubles after each
+/// failed attempt, up to `max_backoff`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReconnectOptions {
+    /// The delay before the first reconnect attempt.
+    pub initial_backoff: Duration,
+
+    /// The upper bound of the delay between reconnect attempts.
+    pub max_backoff: Duration,
+
+    /// The number of consecutive reconnect attempts after which the supervisor gives up,
+    /// or `None` to retry forever.
+    pub max_attempts: Option<u32>,
+
+    /// The maximum amount of time to wait for the radio to complete the `WantConfigId`
+    /// handshake before treating the connection as lost.
+    pub config_timeout: Duration,
+}
+
+impl Default for ReconnectOptions {
+    fn default() -> Self {
+        ReconnectOptions {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+            config_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectOptions {
+    /// Returns the delay to wait before the given reconnect attempt, starting at 1.
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// The reason a supervised connection ended.
+enum SessionOutcome {
+    Cancelled,
+    Lost { configured: bool },
+}
+
+/// Spawns a task that connects to the radio through streams built by `stream_factory`,
+/// and rebuilds the stream with exponential backoff whenever it is lost.
+///
+/// Packets sent through `write_input_rx` while the radio is disconnected are queued and
+/// written once the next stream has been built and the `WantConfigId` packet has been sent.
+/// Decoded packets from every stream are forwarded to the same `decoded_packet_tx` and
+/// `packet_broadcast_tx` channels.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn spawn_reconnect_supervisor<S, F, Fut>(
+    cancellation_token: CancellationToken,
+    mut stream_factory: F,
+    config_id: u32,
+    options: ReconnectOptions,
+    mut write_input_rx: UnboundedReceiver<EncodedToRadioPacketWithHeader>,
+    decoded_packet_tx: UnboundedSender<protobufs::FromRadio>,
+    packet_broadcast_tx: broadcast::Sender<protobufs::FromRadio>,
+    connection_state_tx: UnboundedSender<ConnectionState>,
+) -> JoinHandle<Result<(), Error>>
+where
+    S: AsyncReadExt + AsyncWriteExt + Send + 'static,
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<StreamHandle<S>, Error>> + Send,
+{
+    spawn(async move {
+        debug!("Started reconnect supervisor");
+
+        // Receivers may be dropped by users that do not care about connection state
+        let emit = |state: ConnectionState| {
+            trace!("Connection state changed to {state:?}");
+            let _ = connection_state_tx.send(state);
+        };
+
+        let mut config_id = config_id;
+        let mut attempt = 0;
+
+        loop {
+            emit(ConnectionState::Connecting);
+
+            let stream_result = tokio::select! {
+                _ = cancellation_token.cancelled() => return Ok(()),
+                stream_result = stream_factory() => stream_result,
+            };
+
+            match stream_result {
+                Ok(stream_handle) => {
+                    let outcome = run_session(
+                        &cancellation_token,
+                        stream_handle,
+                        config_id,
+                        options.config_timeout,
+                        &mut write_input_rx,
+                        &decoded_packet_tx,
+                        &packet_broadcast_tx,
+                        &emit,
+                    )
+                    .await;
+
+                    match outcome {
+                        SessionOutcome::Cancelled => {
+                            debug!("Reconnect supervisor cancelled");
+                            return Ok(());
+                        }
+                        SessionOutcome::Lost { configured } => {
+                            emit(ConnectionState::Lost);
+
+                            if configured {
+                                attempt = 0;
+                            }
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to build stream to radio: {e}"),
+            }
+
+            attempt += 1;
+
+            if options.max_attempts.is_some_and(|max| attempt > max) {
+                warn!(
+                    "Giving up on reconnecting to the radio after {} attempts",
+                    attempt - 1
+                );
+                return Err(Error::ReconnectAttemptsExhausted {
+                    attempts: attempt - 1,
+                });
+            }
+
+            let delay = options.backoff_delay(attempt);
+            emit(ConnectionState::Reconnecting { attempt, delay });
+
+            tokio::select! {
+                _ = cancellation_token.cancelled() => return Ok(()),
+                _ = tokio::time::sleep(delay) => (),
+            }
+
+            // Use a fresh configuration ID to avoid matching a stale handshake
+            config_id = generate_rand_id();
+        }
+    })
+}
+
+/// Runs a single connection to the radio until it is lost or the supervisor is cancelled.
+/// The read and processing handlers are spawned for this connection only, while writes are
+/// performed by this function so that the write channel survives the connection.
+#[allow(clippy::too_many_arguments)]
+async fn run_session<S>(
+    cancellation_token: &CancellationToken,
+    stream_handle: StreamHandle<S>,
+    config_id: u32,
+    config_timeout: Duration,
+    write_input_rx: &mut UnboundedReceiver<EncodedToRadioPacketWithHeader>,
+    decoded_packet_tx: &UnboundedSender<protobufs::FromRadio>,
+    packet_broadcast_tx: &broadcast::Sender<protobufs::FromRadio>,
+    emit: &impl Fn(ConnectionState),
+) -> SessionOutcome
+where
+    S: AsyncReadExt + AsyncWriteExt + Send + 'static,
+{
+    let session_token = cancellation_token.child_token();
+
+    let (read_stream, mut write_stream) = tokio::io::split(stream_handle.stream);
+    let (read_output_tx, read_output_rx) =
+        tokio::sync::mpsc::unbounded_channel::<IncomingStreamData>();
+
+    let mut read_handle =
+        handlers::spawn_read_handler(session_token.clone(), read_stream, read_output_tx);
+
+    let _processing_handle = handlers::spawn_processing_handler(
+        session_token.clone(),
+        read_output_rx,
+        decoded_packet_tx.clone(),
+        packet_broadcast_tx.clone(),
+    );
+
+    // Subscribe before sending the handshake to avoid missing its completion
+    let mut packet_rx = packet_broadcast_tx.subscribe();
+    let mut configured = false;
+
+    let outcome = match write_want_config_id(&mut write_stream, config_id).await {
+        Ok(()) => {
+            let config_deadline = tokio::time::sleep(config_timeout);
+            tokio::pin!(config_deadline);
+
+            loop {
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => break SessionOutcome::Cancelled,
+                    read_result = &mut read_handle => {
+                        warn!("Lost connection to radio: {read_result:?}");
+                        break SessionOutcome::Lost { configured };
+                    }
+                    message = write_input_rx.recv() => {
+                        let Some(message) = message else {
+                            debug!("Write channel closed, stopping reconnect supervisor");
+                            break SessionOutcome::Cancelled;
+                        };
+
+                        trace!("Writing packet data: {:?}", message);
+
+                        if let Err(e) = write_stream.write_all(message.data()).await {
+                            warn!("Lost connection to radio while writing: {e:?}");
+                            break SessionOutcome::Lost { configured };
+                        }
+                    }
+                    packet = packet_rx.recv(), if !configured => match packet {
+                        Ok(protobufs::FromRadio {
+                            payload_variant:
+                                Some(protobufs::from_radio::PayloadVariant::ConfigCompleteId(received)),
+                            ..
+                        }) if received == config_id => {
+                            configured = true;
+                            emit(ConnectionState::Configured { config_id });
+                        }
+                        Ok(_) | Err(RecvError::Lagged(_)) => (),
+                        Err(RecvError::Closed) => break SessionOutcome::Cancelled,
+                    },
+                    _ = &mut config_deadline, if !configured => {
+                        warn!("Radio did not complete configuration {config_id} in time");
+                        break SessionOutcome::Lost { configured };
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            warn!("Failed to send configuration request to radio: {e}");
+            SessionOutcome::Lost { configured }
+        }
+    };
+
+    // Stop the handlers of this connection, which drops the stream
+    session_token.cancel();
+
+    if let Some(join_handle) = stream_handle.join_handle {
+        join_handle.abort();
+    }
+
+    outcome
+}
+
+async fn write_want_config_id<W>(write_stream: &mut W, config_id: u32) -> Result<(), Error>
+where
+    W: AsyncWriteExt + Unpin,
+{
+    let to_radio = protobufs::ToRadio {
+        payload_variant: Some(protobufs::to_radio::PayloadVariant::WantConfigId(config_id)),
+    };
+
+    let packet = format_data_packet(to_radio.encode_to_vec().into())?;
+
+    write_stream.write_all(packet.data()).await.map_err(|e| {
+        Error::InternalStreamError(
+            crate::errors_internal::InternalStreamError::StreamWriteError {
+                source: Box::new(e),
+            },
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::DuplexStream;
+    use tokio::sync::mpsc;
+
+    use crate::api::StreamApi;
+    use crate::utils_internal::strip_data_packet_header;
+
+    use super::*;
+
+    /// Reads the next `WantConfigId` request sent to the radio, and answers it.
+    async fn complete_handshake(radio_stream: &mut DuplexStream) {
+        let mut buffer = [0u8; 64];
+        let n = radio_stream.read(&mut buffer).await.unwrap();
+
+        let packet = strip_data_packet_header(buffer[..n].to_vec().into()).unwrap();
+        let to_radio = protobufs::ToRadio::decode(packet.data()).unwrap();
+
+        let Some(protobufs::to_radio::PayloadVariant::WantConfigId(config_id)) =
+            to_radio.payload_variant
+        else {
+            panic!("Expected a WantConfigId packet, received {to_radio:?}");
+        };
+
+        let from_radio = protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::ConfigCompleteId(
+                config_id,
+            )),
+        };
+
+        let packet = format_data_packet(from_radio.encode_to_vec().into()).unwrap();
+        radio_stream.write_all(packet.data()).await.unwrap();
+    }
+
+    fn test_options() -> ReconnectOptions {
+        ReconnectOptions {
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(40),
+            max_attempts: Some(3),
+            config_timeout: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let options = test_options();
+
+        assert_eq!(options.backoff_delay(1), Duration::from_millis(10));
+        assert_eq!(options.backoff_delay(2), Duration::from_millis(20));
+        assert_eq!(options.backoff_delay(3), Duration::from_millis(40));
+        assert_eq!(options.backoff_delay(10), Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn reconnects_after_stream_is_lost() {
+        let (radio_tx, mut radio_rx) = mpsc::unbounded_channel::<DuplexStream>();
+
+        let stream_factory = move || {
+            let radio_tx = radio_tx.clone();
+
+            async move {
+                let (client_stream, radio_stream) = tokio::io::duplex(1024);
+                radio_tx.send(radio_stream).unwrap();
+                Ok(StreamHandle::from_stream(client_stream))
+            }
+        };
+
+        let (_decoded_listener, mut state_listener, stream_api) = StreamApi::new()
+            .connect_with_reconnect(stream_factory, 7, test_options())
+            .await;
+
+        // Complete the first handshake, then drop the stream to simulate a radio reboot
+        let mut radio_stream = radio_rx.recv().await.unwrap();
+        complete_handshake(&mut radio_stream).await;
+
+        assert_eq!(
+            state_listener.recv().await,
+            Some(ConnectionState::Connecting)
+        );
+        assert_eq!(
+            state_listener.recv().await,
+            Some(ConnectionState::Configured { config_id: 7 })
+        );
+
+        drop(radio_stream);
+
+        assert_eq!(state_listener.recv().await, Some(ConnectionState::Lost));
+        assert!(matches!(
+            state_listener.recv().await,
+            Some(ConnectionState::Reconnecting { attempt: 1, .. })
+        ));
+        assert_eq!(
+            state_listener.recv().await,
+            Some(ConnectionState::Connecting)
+        );
+
+        let mut radio_stream = radio_rx.recv().await.unwrap();
+        complete_handshake(&mut radio_stream).await;
+
+        assert!(matches!(
+            state_listener.recv().await,
+            Some(ConnectionState::Configured { .. })
+        ));
+
+        stream_api.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let stream_factory = || async {
+            Err::<StreamHandle<DuplexStream>, _>(Error::StreamBuildError {
+                source: "radio unavailable".into(),
+                description: "Failed to build test stream".to_string(),
+            })
+        };
+
+        let (mut decoded_listener, _state_listener, stream_api) = StreamApi::new()
+            .connect_with_reconnect(stream_factory, 7, test_options())
+            .await;
+
+        // The packet channel closes once the supervisor gives up
+        assert!(decoded_listener.recv().await.is_none());
+
+        assert!(matches!(
+            stream_api.disconnect().await,
+            Err(Error::ReconnectAttemptsExhausted { attempts: 3 })
+        ));
+    }
+}
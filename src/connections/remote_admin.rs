@@ -0,0 +1,1144 @@
+use std::{fmt::Display, time::Duration};
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+
+use super::{
+    ack::SentPacket,
+    config_transaction::ConfigTransaction,
+    stream_api::{delay_secs, state, ConnectedStreamApi},
+    wrappers::{mesh_channel::MeshChannel, NodeId},
+    PacketDestination, PacketRouter,
+};
+
+/// A struct that represents the node that admin messages are sent to, and the channel they
+/// are sent on.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) struct AdminTarget {
+    node_id: Option<NodeId>,
+    channel: MeshChannel,
+}
+
+impl AdminTarget {
+    /// Targets the connected radio. Local admin messages are always sent on channel 0.
+    pub fn local() -> AdminTarget {
+        AdminTarget::default()
+    }
+
+    /// Targets a remote node through the specified admin channel.
+    pub fn remote(node_id: NodeId, channel: MeshChannel) -> AdminTarget {
+        AdminTarget {
+            node_id: Some(node_id),
+            channel,
+        }
+    }
+
+    /// Returns the ID of the targeted remote node, or `None` for the connected radio.
+    pub fn node_id(&self) -> Option<NodeId> {
+        self.node_id
+    }
+
+    /// Returns the channel admin messages are sent on.
+    pub fn channel(&self) -> MeshChannel {
+        self.channel
+    }
+
+    /// Returns whether the connected radio is targeted.
+    pub fn is_local(&self) -> bool {
+        self.node_id.is_none()
+    }
+
+    /// Returns the destination of packets sent to the target.
+    pub fn destination(&self) -> PacketDestination {
+        match self.node_id {
+            Some(node_id) => PacketDestination::Node(node_id),
+            None => PacketDestination::Local,
+        }
+    }
+}
+
+/// A handle used to read, reconfigure, reboot or factory reset a remote node over the mesh.
+///
+/// This struct cannot be created directly, and must be created by calling the `remote_admin`
+/// method on a configured `ConnectedStreamApi` instance. Every request is sent as an `AdminApp`
+/// packet to the remote node on the admin channel of the handle, with `want_ack` and
+/// `want_response` set. Setters and device actions resolve to a `SentPacket` handle, which can
+/// be used to wait for the acknowledgement of the remote node. Getters wait for the response of
+/// the remote node, and ignore responses from any other node.
+///
+/// Remote requests usually take several seconds to cross the mesh, so timeouts should be chosen
+/// accordingly.
+#[derive(Debug)]
+pub struct RemoteAdmin<'a> {
+    stream_api: &'a mut ConnectedStreamApi<state::Configured>,
+    node_id: NodeId,
+    admin_channel: MeshChannel,
+}
+
+impl<'a> RemoteAdmin<'a> {
+    /// Creates a handle for the specified remote node and admin channel.
+    pub(crate) fn new(
+        stream_api: &'a mut ConnectedStreamApi<state::Configured>,
+        node_id: NodeId,
+        admin_channel: MeshChannel,
+    ) -> Self {
+        RemoteAdmin {
+            stream_api,
+            node_id,
+            admin_channel,
+        }
+    }
+
+    /// Returns the ID of the remote node.
+    pub fn node_id(&self) -> NodeId {
+        self.node_id
+    }
+
+    /// Returns the admin channel requests are sent on.
+    pub fn admin_channel(&self) -> MeshChannel {
+        self.admin_channel
+    }
+
+    /// Returns the admin target of the handle.
+    fn target(&self) -> AdminTarget {
+        AdminTarget::remote(self.node_id, self.admin_channel)
+    }
+
+    /// Requests a section of the configuration of the remote node.
+    /// See `ConnectedStreamApi::get_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `config_type` - The section of the configuration to request.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the requested `Config`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let lora_config = remote
+    ///     .get_config(packet_router, protobufs::admin_message::ConfigType::LoraConfig, Duration::from_secs(30))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::RequestNak` if the mesh reports that the
+    /// request could not be delivered, with `Error::ResponseTimeout` if no response is received
+    /// from the remote node before `timeout` elapses, and with `Error::UnexpectedResponse` if the
+    /// response does not contain a configuration.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn get_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        config_type: protobufs::admin_message::ConfigType,
+        timeout: Duration,
+    ) -> Result<protobufs::Config, Error> {
+        self.stream_api
+            .request_admin_response(
+                packet_router,
+                self.target(),
+                protobufs::admin_message::PayloadVariant::GetConfigRequest(config_type as i32),
+                timeout,
+                |response| match response {
+                    protobufs::admin_message::PayloadVariant::GetConfigResponse(config) => {
+                        Some(config)
+                    }
+                    _ => None,
+                },
+            )
+            .await
+    }
+
+    /// Requests a section of the module configuration of the remote node.
+    /// See `ConnectedStreamApi::get_module_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `module_config_type` - The section of the module configuration to request.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the requested `ModuleConfig`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mqtt_config = remote
+    ///     .get_module_config(packet_router, protobufs::admin_message::ModuleConfigType::MqttConfig, Duration::from_secs(30))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::RequestNak` if the mesh reports that the
+    /// request could not be delivered, with `Error::ResponseTimeout` if no response is received
+    /// from the remote node before `timeout` elapses, and with `Error::UnexpectedResponse` if the
+    /// response does not contain a module configuration.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn get_module_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        module_config_type: protobufs::admin_message::ModuleConfigType,
+        timeout: Duration,
+    ) -> Result<protobufs::ModuleConfig, Error> {
+        self.stream_api
+            .request_admin_response(
+                packet_router,
+                self.target(),
+                protobufs::admin_message::PayloadVariant::GetModuleConfigRequest(
+                    module_config_type as i32,
+                ),
+                timeout,
+                |response| match response {
+                    protobufs::admin_message::PayloadVariant::GetModuleConfigResponse(
+                        module_config,
+                    ) => Some(module_config),
+                    _ => None,
+                },
+            )
+            .await
+    }
+
+    /// Requests the configuration of a single message channel of the remote node.
+    /// See `ConnectedStreamApi::get_channel`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `channel` - The index of the channel to request.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the requested `Channel`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let primary_channel = remote
+    ///     .get_channel(packet_router, MeshChannel::new(0)?, Duration::from_secs(30))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::RequestNak` if the mesh reports that the
+    /// request could not be delivered, with `Error::ResponseTimeout` if no response is received
+    /// from the remote node before `timeout` elapses, and with `Error::UnexpectedResponse` if the
+    /// response does not contain a channel.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn get_channel<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        channel: MeshChannel,
+        timeout: Duration,
+    ) -> Result<protobufs::Channel, Error> {
+        // The firmware expects the channel index + 1, as a value of 0 would not be encoded
+        self.stream_api
+            .request_admin_response(
+                packet_router,
+                self.target(),
+                protobufs::admin_message::PayloadVariant::GetChannelRequest(channel.channel() + 1),
+                timeout,
+                |response| match response {
+                    protobufs::admin_message::PayloadVariant::GetChannelResponse(channel) => {
+                        Some(channel)
+                    }
+                    _ => None,
+                },
+            )
+            .await
+    }
+
+    /// Requests the information on the user of the remote node. See `ConnectedStreamApi::get_owner`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `User` of the remote node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let owner = remote.get_owner(packet_router, Duration::from_secs(30)).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::RequestNak` if the mesh reports that the
+    /// request could not be delivered, with `Error::ResponseTimeout` if no response is received
+    /// from the remote node before `timeout` elapses, and with `Error::UnexpectedResponse` if the
+    /// response does not contain a user.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn get_owner<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        timeout: Duration,
+    ) -> Result<protobufs::User, Error> {
+        self.stream_api
+            .request_admin_response(
+                packet_router,
+                self.target(),
+                protobufs::admin_message::PayloadVariant::GetOwnerRequest(true),
+                timeout,
+                |response| match response {
+                    protobufs::admin_message::PayloadVariant::GetOwnerResponse(user) => Some(user),
+                    _ => None,
+                },
+            )
+            .await
+    }
+
+    /// Requests metadata on the firmware and hardware of the remote node.
+    /// See `ConnectedStreamApi::get_metadata`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `DeviceMetadata` of the remote node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let metadata = remote.get_metadata(packet_router, Duration::from_secs(30)).await?;
+    /// println!("Firmware version: {}", metadata.firmware_version);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::RequestNak` if the mesh reports that the
+    /// request could not be delivered, with `Error::ResponseTimeout` if no response is received
+    /// from the remote node before `timeout` elapses, and with `Error::UnexpectedResponse` if the
+    /// response does not contain device metadata.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn get_metadata<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        timeout: Duration,
+    ) -> Result<protobufs::DeviceMetadata, Error> {
+        self.stream_api
+            .request_admin_response(
+                packet_router,
+                self.target(),
+                protobufs::admin_message::PayloadVariant::GetDeviceMetadataRequest(true),
+                timeout,
+                |response| match response {
+                    protobufs::admin_message::PayloadVariant::GetDeviceMetadataResponse(
+                        metadata,
+                    ) => Some(metadata),
+                    _ => None,
+                },
+            )
+            .await
+    }
+
+    /// Updates a section of the configuration of the remote node. The remote node restarts after
+    /// applying the update, unless the update is sent within a configuration transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `config` - An instance of the `Config` struct to update the remote node with.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote
+    ///     .update_config(packet_router, config_update)
+    ///     .await?
+    ///     .wait_for_ack(Duration::from_secs(30))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn update_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        config: protobufs::Config,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::SetConfig(config),
+        )
+        .await
+    }
+
+    /// Updates a section of the module configuration of the remote node. The remote node restarts
+    /// after applying the update, unless the update is sent within a configuration transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `module_config` - An instance of the `ModuleConfig` struct to update the remote node with.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote.update_module_config(packet_router, module_config_update).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn update_module_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        module_config: protobufs::ModuleConfig,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::SetModuleConfig(module_config),
+        )
+        .await
+    }
+
+    /// Updates the configuration of a message channel of the remote node.
+    ///
+    /// **Note:** Changing the admin channel of the remote node, or the key of the channel it is
+    /// shared with, prevents further admin messages from reaching the remote node.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `channel_config` - An instance of the `Channel` struct to update the remote node with.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote.update_channel_config(packet_router, channel_config_update).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn update_channel_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        channel_config: protobufs::Channel,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::SetChannel(channel_config),
+        )
+        .await
+    }
+
+    /// Updates the user information of the remote node.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `user` - An instance of the `User` struct to update the remote node with.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote.update_user(packet_router, new_user).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn update_user<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        user: protobufs::User,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::SetOwner(user),
+        )
+        .await
+    }
+
+    /// Starts a bulk configuration update on the remote node. See
+    /// `ConnectedStreamApi::begin_edit_settings`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `ConfigTransaction` guard targeting the remote node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut transaction = remote.begin_edit_settings(packet_router).await?;
+    /// transaction.update_config(packet_router, config_update).await?;
+    /// transaction
+    ///     .commit(packet_router)
+    ///     .await?
+    ///     .wait_for_ack(Duration::from_secs(30))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn begin_edit_settings<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<ConfigTransaction<'_>, Error> {
+        self.stream_api
+            .begin_edit_settings_on(packet_router, self.target())
+            .await
+    }
+
+    /// Tells the remote node to reboot after the specified delay. See `ConnectedStreamApi::reboot`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `delay` - The amount of time the node waits before rebooting, rounded down to whole seconds.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote.reboot(packet_router, Duration::from_secs(5)).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn reboot<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        delay: Duration,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::RebootSeconds(delay_secs(delay)),
+        )
+        .await
+    }
+
+    /// Tells the remote node to reboot into its OTA firmware after the specified delay.
+    /// See `ConnectedStreamApi::reboot_ota`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `delay` - The amount of time the node waits before rebooting, rounded down to whole seconds.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote.reboot_ota(packet_router, Duration::from_secs(5)).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn reboot_ota<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        delay: Duration,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::RebootOtaSeconds(delay_secs(delay)),
+        )
+        .await
+    }
+
+    /// Cancels a pending reboot of the remote node. See `ConnectedStreamApi::cancel_reboot`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote.cancel_reboot(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn cancel_reboot<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::RebootSeconds(-1),
+        )
+        .await
+    }
+
+    /// Tells the remote node to shut down after the specified delay.
+    /// See `ConnectedStreamApi::shutdown`.
+    ///
+    /// **Note:** A node that has been shut down cannot be restarted over the mesh.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `delay` - The amount of time the node waits before shutting down, rounded down to whole
+    ///     seconds.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote.shutdown(packet_router, Duration::from_secs(5)).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn shutdown<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        delay: Duration,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::ShutdownSeconds(delay_secs(delay)),
+        )
+        .await
+    }
+
+    /// Cancels a pending shutdown of the remote node. See `ConnectedStreamApi::cancel_shutdown`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote.cancel_shutdown(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn cancel_shutdown<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::ShutdownSeconds(-1),
+        )
+        .await
+    }
+
+    /// Resets the remote node to its factory settings, including its node database and keys.
+    /// See `ConnectedStreamApi::factory_reset_device`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote.factory_reset_device(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn factory_reset_device<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::FactoryResetDevice(1),
+        )
+        .await
+    }
+
+    /// Resets the configuration of the remote node to its factory settings, while keeping its
+    /// node database. See `ConnectedStreamApi::factory_reset_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote.factory_reset_config(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn factory_reset_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::FactoryResetConfig(1),
+        )
+        .await
+    }
+
+    /// Clears the node database of the remote node. See `ConnectedStreamApi::reset_nodedb`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// remote.reset_nodedb(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn reset_nodedb<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message(
+            packet_router,
+            protobufs::admin_message::PayloadVariant::NodedbReset(1),
+        )
+        .await
+    }
+
+    /// Sends an admin message to the remote node.
+    async fn send_admin_message<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        payload_variant: protobufs::admin_message::PayloadVariant,
+    ) -> Result<SentPacket, Error> {
+        self.stream_api
+            .send_admin_message_to(packet_router, self.target(), true, payload_variant)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use crate::connections::test_support::{
+        configured_stream_api, next_admin_packet, routing_error_packet, TestRouter, MY_NODE_NUM,
+    };
+    use crate::packet::AckOutcome;
+
+    use super::*;
+
+    const REMOTE_NODE_NUM: u32 = 0x1234abcd;
+
+    fn owner_response(from: u32, request_id: u32, long_name: &str) -> protobufs::MeshPacket {
+        let admin_message = protobufs::AdminMessage {
+            payload_variant: Some(protobufs::admin_message::PayloadVariant::GetOwnerResponse(
+                protobufs::User {
+                    long_name: long_name.to_string(),
+                    ..Default::default()
+                },
+            )),
+        };
+
+        protobufs::MeshPacket {
+            from,
+            to: MY_NODE_NUM,
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                protobufs::Data {
+                    portnum: protobufs::PortNum::AdminApp as i32,
+                    payload: admin_message.encode_to_vec(),
+                    request_id,
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_remote_owner_on_admin_channel() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        let respond = async {
+            let (request, payload_variant) = next_admin_packet(&mock_radio, 0).await;
+
+            assert_eq!(request.to, REMOTE_NODE_NUM);
+            assert_eq!(request.channel, 2);
+            assert!(request.want_ack);
+            assert_eq!(
+                payload_variant,
+                protobufs::admin_message::PayloadVariant::GetOwnerRequest(true)
+            );
+
+            // Responses from other nodes must be ignored
+            mock_radio
+                .inject_mesh_packet(owner_response(REMOTE_NODE_NUM + 1, request.id, "Impostor"))
+                .unwrap();
+            mock_radio
+                .inject_mesh_packet(owner_response(REMOTE_NODE_NUM, request.id, "Solar Router"))
+                .unwrap();
+        };
+
+        let mut remote =
+            stream_api.remote_admin(NodeId::new(REMOTE_NODE_NUM), MeshChannel::new(2).unwrap());
+        let (owner, _) = tokio::join!(
+            remote.get_owner(&mut router, Duration::from_secs(1)),
+            respond
+        );
+
+        assert_eq!(owner.unwrap().long_name, "Solar Router");
+    }
+
+    #[tokio::test]
+    async fn fails_fast_when_request_is_not_delivered() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        let respond = async {
+            let (request, _) = next_admin_packet(&mock_radio, 0).await;
+
+            // The connected radio reports that the remote node is unreachable
+            mock_radio
+                .inject_mesh_packet(routing_error_packet(
+                    MY_NODE_NUM,
+                    request.id,
+                    protobufs::routing::Error::NoRoute,
+                ))
+                .unwrap();
+
+            request.id
+        };
+
+        let mut remote =
+            stream_api.remote_admin(NodeId::new(REMOTE_NODE_NUM), MeshChannel::new(2).unwrap());
+        let (owner, request_id) = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(
+                remote.get_owner(&mut router, Duration::from_secs(60)),
+                respond
+            )
+        })
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            owner,
+            Err(Error::RequestNak {
+                request_id: id,
+                reason: protobufs::routing::Error::NoRoute,
+            }) if id == request_id
+        ));
+    }
+
+    #[tokio::test]
+    async fn sends_remote_setters_with_ack_tracking() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+        let mut remote =
+            stream_api.remote_admin(NodeId::new(REMOTE_NODE_NUM), MeshChannel::new(1).unwrap());
+
+        let mut transaction = remote.begin_edit_settings(&mut router).await.unwrap();
+        transaction
+            .update_user(&mut router, protobufs::User::default())
+            .await
+            .unwrap();
+        let outcome = transaction
+            .commit(&mut router)
+            .await
+            .unwrap()
+            .wait_for_ack(Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        // The mock radio acknowledges packets on behalf of their destination
+        assert_eq!(outcome, AckOutcome::Ack);
+
+        for (index, expected) in [
+            protobufs::admin_message::PayloadVariant::BeginEditSettings(true),
+            protobufs::admin_message::PayloadVariant::SetOwner(protobufs::User::default()),
+            protobufs::admin_message::PayloadVariant::CommitEditSettings(true),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let (packet, payload_variant) = next_admin_packet(&mock_radio, index).await;
+
+            assert_eq!(packet.to, REMOTE_NODE_NUM);
+            assert_eq!(packet.channel, 1);
+            assert!(packet.want_ack);
+            assert!(packet
+                .payload_variant
+                .is_some_and(|payload_variant| matches!(
+                    payload_variant,
+                    protobufs::mesh_packet::PayloadVariant::Decoded(data) if data.want_response
+                )));
+            assert_eq!(payload_variant, expected);
+        }
+
+        // The remote node is not the mock radio, so its state is left unchanged
+        assert_eq!(mock_radio.state().owner(), None);
+    }
+
+    #[tokio::test]
+    async fn sends_remote_device_actions_on_admin_channel() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+        let mut remote =
+            stream_api.remote_admin(NodeId::new(REMOTE_NODE_NUM), MeshChannel::new(1).unwrap());
+
+        remote
+            .reboot(&mut router, Duration::from_secs(5))
+            .await
+            .unwrap();
+        remote
+            .reboot_ota(&mut router, Duration::from_secs(10))
+            .await
+            .unwrap();
+        remote.cancel_reboot(&mut router).await.unwrap();
+        remote.cancel_shutdown(&mut router).await.unwrap();
+
+        for (index, expected) in [
+            protobufs::admin_message::PayloadVariant::RebootSeconds(5),
+            protobufs::admin_message::PayloadVariant::RebootOtaSeconds(10),
+            protobufs::admin_message::PayloadVariant::RebootSeconds(-1),
+            protobufs::admin_message::PayloadVariant::ShutdownSeconds(-1),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let (packet, payload_variant) = next_admin_packet(&mock_radio, index).await;
+
+            assert_eq!(packet.to, REMOTE_NODE_NUM);
+            assert_eq!(packet.channel, 1);
+            assert_eq!(payload_variant, expected);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use log::warn;
+use prost::Message;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::errors_internal::{Error, InternalChannelError};
+use crate::protobufs;
+
+/// Returns the mesh packet and its decoded `Data` payload if the given `FromRadio` packet
+/// was sent on `port_num` in response to the packet with ID `request_id`.
+pub(crate) fn decoded_response(
+    packet: &protobufs::FromRadio,
+    request_id: u32,
+    port_num: protobufs::PortNum,
+) -> Option<(&protobufs::MeshPacket, &protobufs::Data)> {
+    let Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)) = &packet.payload_variant
+    else {
+        return None;
+    };
+
+    let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) = &mesh_packet.payload_variant
+    else {
+        return None;
+    };
+
+    if data.request_id != request_id || data.portnum != port_num as i32 {
+        return None;
+    }
+
+    Some((mesh_packet, data))
+}
+
+/// Returns an `Error::RequestNak` if the given `FromRadio` packet is a `Routing` packet reporting
+/// that the packet with ID `request_id` could not be delivered.
+pub(crate) fn routing_error(packet: &protobufs::FromRadio, request_id: u32) -> Option<Error> {
+    let (_, data) = decoded_response(packet, request_id, protobufs::PortNum::RoutingApp)?;
+    let routing = protobufs::Routing::decode(data.payload.as_slice()).ok()?;
+
+    let Some(protobufs::routing::Variant::ErrorReason(error_reason)) = routing.variant else {
+        return None;
+    };
+
+    match protobufs::routing::Error::try_from(error_reason) {
+        Ok(protobufs::routing::Error::None) => None,
+        Ok(reason) => Some(Error::RequestNak { request_id, reason }),
+        Err(_) => None,
+    }
+}
+
+/// Waits for the first packet from the radio for which `matcher` returns a value.
+///
+/// The `matcher` closure returns `None` for packets unrelated to the request, and `Some`
+/// with either the final value or an error once a related packet has been received.
+/// This function fails with `Error::ResponseTimeout` once `timeout` has elapsed.
+pub(crate) async fn wait_for_response<T, F>(
+    packet_rx: &mut broadcast::Receiver<protobufs::FromRadio>,
+    request_id: u32,
+    timeout: Duration,
+    mut matcher: F,
+) -> Result<T, Error>
+where
+    F: FnMut(&protobufs::FromRadio) -> Option<Result<T, Error>>,
+{
+    let wait_for_match = async {
+        loop {
+            let packet = match packet_rx.recv().await {
+                Ok(packet) => packet,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Missed {skipped} packets while waiting for a response to {request_id}");
+                    continue;
+                }
+                Err(RecvError::Closed) => {
+                    return Err(Error::InternalChannelError(
+                        InternalChannelError::ChannelClosedEarly,
+                    ))
+                }
+            };
+
+            if let Some(result) = matcher(&packet) {
+                return result;
+            }
+        }
+    };
+
+    tokio::time::timeout(timeout, wait_for_match)
+        .await
+        .unwrap_or(Err(Error::ResponseTimeout { request_id }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh_packet_response(request_id: u32, port_num: protobufs::PortNum) -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(
+                protobufs::MeshPacket {
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: port_num as i32,
+                            request_id,
+                            ..Default::default()
+                        },
+                    )),
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+
+    #[test]
+    fn matches_response_on_request_id_and_port() {
+        let packet = mesh_packet_response(5, protobufs::PortNum::AdminApp);
+
+        assert!(decoded_response(&packet, 5, protobufs::PortNum::AdminApp).is_some());
+        assert!(decoded_response(&packet, 6, protobufs::PortNum::AdminApp).is_none());
+        assert!(decoded_response(&packet, 5, protobufs::PortNum::RoutingApp).is_none());
+    }
+
+    #[tokio::test]
+    async fn skips_unrelated_packets() {
+        let (packet_tx, mut packet_rx) = broadcast::channel(16);
+
+        packet_tx
+            .send(mesh_packet_response(4, protobufs::PortNum::AdminApp))
+            .unwrap();
+        packet_tx
+            .send(mesh_packet_response(5, protobufs::PortNum::AdminApp))
+            .unwrap();
+
+        let result = wait_for_response(&mut packet_rx, 5, Duration::from_secs(1), |packet| {
+            decoded_response(packet, 5, protobufs::PortNum::AdminApp)
+                .map(|(_, data)| Ok(data.request_id))
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn times_out_without_response() {
+        let (_packet_tx, mut packet_rx) = broadcast::channel::<protobufs::FromRadio>(16);
+
+        let result: Result<(), Error> =
+            wait_for_response(&mut packet_rx, 5, Duration::from_millis(20), |_| None).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::ResponseTimeout { request_id: 5 })
+        ));
+    }
+}
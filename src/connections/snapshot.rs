@@ -0,0 +1,379 @@
+use log::{trace, warn};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::errors_internal::{Error, InternalChannelError};
+use crate::protobufs;
+
+use super::wrappers::NodeId;
+
+/// A struct that represents the state of a radio, as reported by the radio during the
+/// `WantConfigId` handshake.
+///
+/// This struct is returned by the `ConnectedStreamApi::configure_and_wait` method, and
+/// contains every `MyInfo`, `NodeInfo`, `Channel`, `Config`, `ModuleConfig`, `Metadata`
+/// and `FileInfo` packet sent by the radio before it completed the handshake.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RadioSnapshot {
+    /// The configuration ID that was completed by the radio.
+    pub config_id: u32,
+
+    /// Information about the connected node, including its node number.
+    pub my_info: Option<protobufs::MyNodeInfo>,
+
+    /// The node database of the radio, including the connected node.
+    pub nodes: Vec<protobufs::NodeInfo>,
+
+    /// The message channels of the radio, sorted by channel index.
+    pub channels: Vec<protobufs::Channel>,
+
+    /// The configuration of the radio.
+    pub config: protobufs::LocalConfig,
+
+    /// The module configuration of the radio.
+    pub module_config: protobufs::LocalModuleConfig,
+
+    /// Metadata on the firmware and hardware of the radio.
+    pub metadata: Option<protobufs::DeviceMetadata>,
+
+    /// The files stored on the filesystem of the radio.
+    pub files: Vec<protobufs::FileInfo>,
+}
+
+impl RadioSnapshot {
+    /// Returns the node ID of the connected radio, if the radio reported it.
+    pub fn my_node_id(&self) -> Option<NodeId> {
+        self.my_info.as_ref().map(|info| info.my_node_num.into())
+    }
+
+    /// Returns the node database entry for the given node, if present.
+    pub fn node(&self, node_id: NodeId) -> Option<&protobufs::NodeInfo> {
+        self.nodes.iter().find(|node| node_id == node.num)
+    }
+
+    /// Returns the channel with the given channel index, if present.
+    pub fn channel(&self, index: i32) -> Option<&protobufs::Channel> {
+        self.channels.iter().find(|channel| channel.index == index)
+    }
+
+    /// Merges a `FromRadio` payload sent during the handshake into the snapshot.
+    /// Payloads that do not describe the state of the radio are ignored.
+    pub(crate) fn update(&mut self, payload_variant: protobufs::from_radio::PayloadVariant) {
+        use protobufs::from_radio::PayloadVariant;
+
+        match payload_variant {
+            PayloadVariant::MyInfo(my_info) => self.my_info = Some(my_info),
+            PayloadVariant::NodeInfo(node_info) => {
+                match self.nodes.iter_mut().find(|n| n.num == node_info.num) {
+                    Some(existing) => *existing = node_info,
+                    None => self.nodes.push(node_info),
+                }
+            }
+            PayloadVariant::Channel(channel) => {
+                self.channels.retain(|c| c.index != channel.index);
+                self.channels.push(channel);
+                self.channels.sort_by_key(|c| c.index);
+            }
+            PayloadVariant::Config(config) => apply_config(&mut self.config, config),
+            PayloadVariant::ModuleConfig(module_config) => {
+                apply_module_config(&mut self.module_config, module_config)
+            }
+            PayloadVariant::Metadata(metadata) => self.metadata = Some(metadata),
+            PayloadVariant::FileInfo(file_info) => self.files.push(file_info),
+            _ => trace!("Ignoring packet not describing radio state"),
+        }
+    }
+}
+
+/// Merges a single `Config` variant into the corresponding field of a `LocalConfig`.
+pub(crate) fn apply_config(local_config: &mut protobufs::LocalConfig, config: protobufs::Config) {
+    use protobufs::config::PayloadVariant;
+
+    match config.payload_variant {
+        Some(PayloadVariant::Device(c)) => local_config.device = Some(c),
+        Some(PayloadVariant::Position(c)) => local_config.position = Some(c),
+        Some(PayloadVariant::Power(c)) => local_config.power = Some(c),
+        Some(PayloadVariant::Network(c)) => local_config.network = Some(c),
+        Some(PayloadVariant::Display(c)) => local_config.display = Some(c),
+        Some(PayloadVariant::Lora(c)) => local_config.lora = Some(c),
+        Some(PayloadVariant::Bluetooth(c)) => local_config.bluetooth = Some(c),
+        None => (),
+    }
+}
+
+/// Splits a `LocalConfig` into a `Config` per field that is set, in the order in which the
+/// `set_local_config` method sends them.
+pub(crate) fn split_local_config(local_config: protobufs::LocalConfig) -> Vec<protobufs::Config> {
+    use protobufs::config::PayloadVariant;
+
+    [
+        local_config.bluetooth.map(PayloadVariant::Bluetooth),
+        local_config.device.map(PayloadVariant::Device),
+        local_config.display.map(PayloadVariant::Display),
+        local_config.lora.map(PayloadVariant::Lora),
+        local_config.network.map(PayloadVariant::Network),
+        local_config.position.map(PayloadVariant::Position),
+        local_config.power.map(PayloadVariant::Power),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|payload_variant| protobufs::Config {
+        payload_variant: Some(payload_variant),
+    })
+    .collect()
+}
+
+/// Splits a `LocalModuleConfig` into a `ModuleConfig` per field that is set.
+pub(crate) fn split_local_module_config(
+    local_module_config: protobufs::LocalModuleConfig,
+) -> Vec<protobufs::ModuleConfig> {
+    use protobufs::module_config::PayloadVariant;
+
+    [
+        local_module_config.audio.map(PayloadVariant::Audio),
+        local_module_config
+            .canned_message
+            .map(PayloadVariant::CannedMessage),
+        local_module_config
+            .external_notification
+            .map(PayloadVariant::ExternalNotification),
+        local_module_config.mqtt.map(PayloadVariant::Mqtt),
+        local_module_config
+            .range_test
+            .map(PayloadVariant::RangeTest),
+        local_module_config
+            .remote_hardware
+            .map(PayloadVariant::RemoteHardware),
+        local_module_config.serial.map(PayloadVariant::Serial),
+        local_module_config
+            .store_forward
+            .map(PayloadVariant::StoreForward),
+        local_module_config.telemetry.map(PayloadVariant::Telemetry),
+        local_module_config
+            .neighbor_info
+            .map(PayloadVariant::NeighborInfo),
+        local_module_config
+            .ambient_lighting
+            .map(PayloadVariant::AmbientLighting),
+        local_module_config
+            .detection_sensor
+            .map(PayloadVariant::DetectionSensor),
+        local_module_config
+            .paxcounter
+            .map(PayloadVariant::Paxcounter),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|payload_variant| protobufs::ModuleConfig {
+        payload_variant: Some(payload_variant),
+    })
+    .collect()
+}
+
+/// Merges a single `ModuleConfig` variant into the corresponding field of a `LocalModuleConfig`.
+pub(crate) fn apply_module_config(
+    local_module_config: &mut protobufs::LocalModuleConfig,
+    module_config: protobufs::ModuleConfig,
+) {
+    use protobufs::module_config::PayloadVariant;
+
+    match module_config.payload_variant {
+        Some(PayloadVariant::Mqtt(c)) => local_module_config.mqtt = Some(c),
+        Some(PayloadVariant::Serial(c)) => local_module_config.serial = Some(c),
+        Some(PayloadVariant::ExternalNotification(c)) => {
+            local_module_config.external_notification = Some(c)
+        }
+        Some(PayloadVariant::StoreForward(c)) => local_module_config.store_forward = Some(c),
+        Some(PayloadVariant::RangeTest(c)) => local_module_config.range_test = Some(c),
+        Some(PayloadVariant::Telemetry(c)) => local_module_config.telemetry = Some(c),
+        Some(PayloadVariant::CannedMessage(c)) => local_module_config.canned_message = Some(c),
+        Some(PayloadVariant::Audio(c)) => local_module_config.audio = Some(c),
+        Some(PayloadVariant::RemoteHardware(c)) => local_module_config.remote_hardware = Some(c),
+        Some(PayloadVariant::NeighborInfo(c)) => local_module_config.neighbor_info = Some(c),
+        Some(PayloadVariant::AmbientLighting(c)) => local_module_config.ambient_lighting = Some(c),
+        Some(PayloadVariant::DetectionSensor(c)) => local_module_config.detection_sensor = Some(c),
+        Some(PayloadVariant::Paxcounter(c)) => local_module_config.paxcounter = Some(c),
+        None => (),
+    }
+}
+
+/// Collects the packets sent by the radio into a `RadioSnapshot` until the radio sends a
+/// `ConfigCompleteId` packet. This function does not time out on its own.
+pub(crate) async fn collect_radio_snapshot(
+    packet_rx: &mut broadcast::Receiver<protobufs::FromRadio>,
+    config_id: u32,
+) -> Result<RadioSnapshot, Error> {
+    let mut snapshot = RadioSnapshot {
+        config_id,
+        ..Default::default()
+    };
+
+    loop {
+        let packet = match packet_rx.recv().await {
+            Ok(packet) => packet,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Radio snapshot missed {skipped} packets, snapshot will be incomplete");
+                continue;
+            }
+            Err(RecvError::Closed) => {
+                return Err(Error::InternalChannelError(
+                    InternalChannelError::ChannelClosedEarly,
+                ))
+            }
+        };
+
+        match packet.payload_variant {
+            Some(protobufs::from_radio::PayloadVariant::ConfigCompleteId(received))
+                if received == config_id =>
+            {
+                return Ok(snapshot);
+            }
+            Some(protobufs::from_radio::PayloadVariant::ConfigCompleteId(received)) => {
+                return Err(Error::ConfigIdMismatch {
+                    expected: config_id,
+                    received,
+                });
+            }
+            Some(payload_variant) => snapshot.update(payload_variant),
+            None => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use prost::Message;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::mpsc::error::TryRecvError;
+
+    use crate::api::StreamApi;
+    use crate::protobufs::from_radio::PayloadVariant;
+    use crate::utils::format_data_packet;
+
+    use super::*;
+
+    fn from_radio(payload_variant: PayloadVariant) -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(payload_variant),
+        }
+    }
+
+    fn lora_config(hop_limit: u32) -> protobufs::Config {
+        protobufs::Config {
+            payload_variant: Some(protobufs::config::PayloadVariant::Lora(
+                protobufs::config::LoRaConfig {
+                    hop_limit,
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+
+    #[tokio::test]
+    async fn collects_packets_until_config_complete() {
+        let (packet_tx, mut packet_rx) = broadcast::channel(16);
+
+        let channel = protobufs::Channel {
+            index: 1,
+            ..Default::default()
+        };
+
+        packet_tx
+            .send(from_radio(PayloadVariant::MyInfo(protobufs::MyNodeInfo {
+                my_node_num: 42,
+                ..Default::default()
+            })))
+            .unwrap();
+        packet_tx
+            .send(from_radio(PayloadVariant::Channel(channel.clone())))
+            .unwrap();
+        packet_tx
+            .send(from_radio(PayloadVariant::Config(lora_config(3))))
+            .unwrap();
+        packet_tx
+            .send(from_radio(PayloadVariant::ConfigCompleteId(7)))
+            .unwrap();
+
+        let snapshot = collect_radio_snapshot(&mut packet_rx, 7).await.unwrap();
+
+        assert_eq!(snapshot.my_node_id(), Some(NodeId::new(42)));
+        assert_eq!(snapshot.channel(1), Some(&channel));
+        assert_eq!(snapshot.config.lora.unwrap().hop_limit, 3);
+    }
+
+    #[tokio::test]
+    async fn fails_on_config_id_mismatch() {
+        let (packet_tx, mut packet_rx) = broadcast::channel(16);
+
+        packet_tx
+            .send(from_radio(PayloadVariant::ConfigCompleteId(8)))
+            .unwrap();
+
+        let result = collect_radio_snapshot(&mut packet_rx, 7).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::ConfigIdMismatch {
+                expected: 7,
+                received: 8
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn configure_and_wait_returns_snapshot() {
+        let (client_stream, mut radio_stream) = tokio::io::duplex(1024);
+        let (_decoded_listener, stream_api) = StreamApi::new()
+            .connect(crate::api::StreamHandle::from_stream(client_stream))
+            .await;
+
+        let radio = tokio::spawn(async move {
+            // Wait for the WantConfigId packet before answering
+            let mut buffer = [0u8; 64];
+            let _ = radio_stream.read(&mut buffer).await.unwrap();
+
+            for payload_variant in [
+                PayloadVariant::Config(lora_config(5)),
+                PayloadVariant::ConfigCompleteId(7),
+            ] {
+                let packet =
+                    format_data_packet(from_radio(payload_variant).encode_to_vec().into()).unwrap();
+                radio_stream.write_all(packet.data()).await.unwrap();
+            }
+
+            radio_stream
+        });
+
+        let (_stream_api, snapshot) = stream_api
+            .configure_and_wait(7, Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        assert_eq!(snapshot.config.lora.unwrap().hop_limit, 5);
+        radio.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn configure_and_wait_times_out() {
+        let (client_stream, _radio_stream) = tokio::io::duplex(1024);
+        let (mut decoded_listener, stream_api) = StreamApi::new()
+            .connect(crate::api::StreamHandle::from_stream(client_stream))
+            .await;
+
+        let result = stream_api
+            .configure_and_wait(7, Duration::from_millis(50))
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::ConfigurationTimeout { config_id: 7 })
+        ));
+
+        // The worker threads have been joined, so the decoded packet channel is closed
+        assert!(decoded_listener
+            .try_recv()
+            .is_err_and(|e| e == TryRecvError::Disconnected));
+    }
+}
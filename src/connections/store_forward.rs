@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use prost::Message;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+use crate::utils_internal::current_epoch_secs_u32;
+
+use super::wrappers::NodeId;
+
+use protobufs::store_and_forward::{RequestResponse, Variant};
+
+/// A struct that represents a Store & Forward server discovered from its heartbeats.
+///
+/// # Fields
+///
+/// * `node_id` - The ID of the node running the server.
+/// * `heartbeat_period` - The interval at which the server broadcasts heartbeats.
+/// * `is_secondary` - Whether the server is not the primary Store & Forward server of the mesh.
+/// * `last_heard` - The time the last heartbeat was received, in seconds since the Unix epoch.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StoreForwardServer {
+    pub node_id: NodeId,
+    pub heartbeat_period: Duration,
+    pub is_secondary: bool,
+    pub last_heard: u32,
+}
+
+/// A struct that represents a text message replayed by a Store & Forward server from its history.
+///
+/// # Fields
+///
+/// * `packet` - The original text message, rebuilt as a `TextMessageApp` packet with the sender,
+///     destination, channel and reception time recorded by the server.
+/// * `text` - The text of the message.
+/// * `is_broadcast` - Whether the message was originally sent as a broadcast.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredTextMessage {
+    pub packet: protobufs::MeshPacket,
+    pub text: String,
+    pub is_broadcast: bool,
+}
+
+impl StoredTextMessage {
+    /// Parses a text message replayed by a Store & Forward server.
+    ///
+    /// Servers replay messages as `StoreForwardApp` packets sent from the original sender, with
+    /// the text carried in a `StoreAndForward` message. The returned message carries a copy of
+    /// the replayed packet rebuilt as a normal `TextMessageApp` packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh_packet` - A `MeshPacket` received from the radio.
+    ///
+    /// # Returns
+    ///
+    /// The `StoredTextMessage` carried by the packet, or `None` if the packet is not a replayed
+    /// text message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// if let Some(message) = StoredTextMessage::from_mesh_packet(&mesh_packet) {
+    ///     println!("{} (from store): {}", message.packet.from, message.text);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn from_mesh_packet(mesh_packet: &protobufs::MeshPacket) -> Option<Self> {
+        let message = decode_store_forward(mesh_packet)?.ok()?;
+
+        let is_broadcast = match message.rr() {
+            RequestResponse::RouterTextDirect => false,
+            RequestResponse::RouterTextBroadcast => true,
+            _ => return None,
+        };
+
+        let Some(Variant::Text(text)) = message.variant else {
+            return None;
+        };
+
+        let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) =
+            &mesh_packet.payload_variant
+        else {
+            return None;
+        };
+
+        let mut packet = mesh_packet.clone();
+        packet.payload_variant = Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+            protobufs::Data {
+                portnum: protobufs::PortNum::TextMessageApp as i32,
+                payload: text.clone(),
+                ..data.clone()
+            },
+        ));
+
+        Some(StoredTextMessage {
+            packet,
+            text: String::from_utf8_lossy(&text).into_owned(),
+            is_broadcast,
+        })
+    }
+}
+
+/// A struct that keeps track of the Store & Forward servers in the mesh.
+///
+/// The tracker is updated by passing every `FromRadio` packet received from the radio to the
+/// `handle_packet` method. Servers are discovered from the heartbeats they broadcast, which are
+/// only sent when the heartbeat of the server is enabled in its `StoreForwardConfig`.
+#[derive(Clone, Debug, Default)]
+pub struct StoreForwardServers {
+    servers: HashMap<u32, StoreForwardServer>,
+}
+
+impl StoreForwardServers {
+    /// Creates an empty server tracker.
+    pub fn new() -> StoreForwardServers {
+        StoreForwardServers::default()
+    }
+
+    /// Returns the server running on the given node, if it has been discovered.
+    pub fn server(&self, node_id: NodeId) -> Option<&StoreForwardServer> {
+        self.servers.get(&node_id.id())
+    }
+
+    /// Returns the discovered servers, sorted from the most recently heard server.
+    pub fn servers(&self) -> Vec<&StoreForwardServer> {
+        let mut servers: Vec<&StoreForwardServer> = self.servers.values().collect();
+        servers.sort_by_key(|server| std::cmp::Reverse(server.last_heard));
+        servers
+    }
+
+    /// Returns the most recently heard primary server, falling back to the most recently heard
+    /// secondary server.
+    pub fn preferred(&self) -> Option<&StoreForwardServer> {
+        let servers = self.servers();
+
+        servers
+            .iter()
+            .find(|server| !server.is_secondary)
+            .or(servers.first())
+            .copied()
+    }
+
+    /// Returns the servers that have sent a heartbeat within their last two heartbeat periods
+    /// before the given time, in seconds since the Unix epoch.
+    pub fn active_servers(&self, now: u32) -> Vec<&StoreForwardServer> {
+        self.servers()
+            .into_iter()
+            .filter(|server| {
+                let period = u32::try_from(server.heartbeat_period.as_secs()).unwrap_or(u32::MAX);
+                server.last_heard >= now.saturating_sub(period.saturating_mul(2))
+            })
+            .collect()
+    }
+
+    /// Updates the tracker from a packet received from the radio. Packets that are not Store &
+    /// Forward heartbeats are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - A `FromRadio` packet received from the radio.
+    ///
+    /// # Returns
+    ///
+    /// The `StoreForwardServer` that sent the heartbeat, if the packet is a heartbeat.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut servers = StoreForwardServers::new();
+    ///
+    /// while let Some(packet) = decoded_listener.recv().await {
+    ///     if let Some(server) = servers.handle_packet(&packet) {
+    ///         println!("Found Store & Forward server {}", server.node_id);
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn handle_packet(&mut self, packet: &protobufs::FromRadio) -> Option<StoreForwardServer> {
+        let Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)) =
+            &packet.payload_variant
+        else {
+            return None;
+        };
+
+        let message = decode_store_forward(mesh_packet)?.ok()?;
+
+        if message.rr() != RequestResponse::RouterHeartbeat {
+            return None;
+        }
+
+        let heartbeat = match message.variant {
+            Some(Variant::Heartbeat(heartbeat)) => heartbeat,
+            _ => protobufs::store_and_forward::Heartbeat::default(),
+        };
+
+        let server = StoreForwardServer {
+            node_id: mesh_packet.from.into(),
+            heartbeat_period: Duration::from_secs(heartbeat.period.into()),
+            is_secondary: heartbeat.secondary != 0,
+            last_heard: match mesh_packet.rx_time {
+                0 => current_epoch_secs_u32(),
+                rx_time => rx_time,
+            },
+        };
+
+        self.servers.insert(mesh_packet.from, server);
+        Some(server)
+    }
+}
+
+/// Decodes the `StoreAndForward` message carried by a mesh packet, or returns `None` if the
+/// packet was not sent on the `StoreForwardApp` port.
+pub(crate) fn decode_store_forward(
+    mesh_packet: &protobufs::MeshPacket,
+) -> Option<Result<protobufs::StoreAndForward, Error>> {
+    let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) = &mesh_packet.payload_variant
+    else {
+        return None;
+    };
+
+    if data.portnum != protobufs::PortNum::StoreForwardApp as i32 {
+        return None;
+    }
+
+    Some(protobufs::StoreAndForward::decode(data.payload.as_slice()).map_err(Error::from))
+}
+
+/// Builds a `StoreAndForward` message with the given request or response type.
+pub(crate) fn store_forward_message(
+    rr: RequestResponse,
+    variant: Option<Variant>,
+) -> protobufs::StoreAndForward {
+    protobufs::StoreAndForward {
+        rr: rr as i32,
+        variant,
+    }
+}
+
+/// Converts a history window into the whole number of minutes expected by servers, where `0`
+/// requests the default window of the server.
+pub(crate) fn history_window_minutes(window: Option<Duration>) -> u32 {
+    window
+        .map(|window| window.as_secs().div_ceil(60).max(1))
+        .map(|minutes| u32::try_from(minutes).unwrap_or(u32::MAX))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connections::events::MeshEvent;
+    use crate::connections::test_support::{configured_stream_api, TestRouter, MY_NODE_NUM};
+    use crate::connections::wrappers::mesh_channel::MeshChannel;
+
+    use super::*;
+
+    const SERVER_NODE_NUM: u32 = 7;
+
+    fn store_forward_packet(
+        from: u32,
+        to: u32,
+        message: protobufs::StoreAndForward,
+    ) -> protobufs::MeshPacket {
+        protobufs::MeshPacket {
+            from,
+            to,
+            rx_time: 1_700_000_000,
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                protobufs::Data {
+                    portnum: protobufs::PortNum::StoreForwardApp as i32,
+                    payload: message.encode_to_vec(),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+
+    fn heartbeat(from: u32, secondary: u32) -> protobufs::FromRadio {
+        let message = store_forward_message(
+            RequestResponse::RouterHeartbeat,
+            Some(Variant::Heartbeat(
+                protobufs::store_and_forward::Heartbeat {
+                    period: 900,
+                    secondary,
+                },
+            )),
+        );
+
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(
+                store_forward_packet(from, u32::MAX, message),
+            )),
+        }
+    }
+
+    #[test]
+    fn discovers_servers_from_heartbeats() {
+        let mut servers = StoreForwardServers::new();
+
+        servers.handle_packet(&heartbeat(8, 1));
+        let server = servers
+            .handle_packet(&heartbeat(SERVER_NODE_NUM, 0))
+            .unwrap();
+
+        assert_eq!(server.heartbeat_period, Duration::from_secs(900));
+        assert_eq!(servers.servers().len(), 2);
+        assert_eq!(servers.preferred(), Some(&server));
+        assert_eq!(servers.active_servers(1_700_001_000).len(), 2);
+        assert!(servers.active_servers(1_700_010_000).is_empty());
+    }
+
+    #[test]
+    fn converts_replayed_messages_to_text_events() {
+        let packet = store_forward_packet(
+            9,
+            u32::MAX,
+            store_forward_message(
+                RequestResponse::RouterTextBroadcast,
+                Some(Variant::Text(b"stored hello".to_vec())),
+            ),
+        );
+
+        let message = StoredTextMessage::from_mesh_packet(&packet).unwrap();
+        assert_eq!(message.text, "stored hello");
+        assert_eq!(message.packet.from, 9);
+        assert!(message.is_broadcast);
+
+        let MeshEvent::StoredText(message) = MeshEvent::from(packet) else {
+            panic!("Expected a stored text event");
+        };
+        assert!(matches!(
+            message.packet.payload_variant,
+            Some(protobufs::mesh_packet::PayloadVariant::Decoded(data))
+                if data.portnum == protobufs::PortNum::TextMessageApp as i32
+        ));
+    }
+
+    #[test]
+    fn rounds_history_window_up_to_minutes() {
+        assert_eq!(history_window_minutes(None), 0);
+        assert_eq!(history_window_minutes(Some(Duration::from_secs(1))), 1);
+        assert_eq!(history_window_minutes(Some(Duration::from_secs(3600))), 60);
+        assert_eq!(history_window_minutes(Some(Duration::from_secs(3601))), 61);
+    }
+
+    #[tokio::test]
+    async fn requests_history_from_server() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        let respond = async {
+            let request = loop {
+                let request = mock_radio
+                    .received_mesh_packets()
+                    .into_iter()
+                    .find_map(|packet| decode_store_forward(&packet).map(|m| (packet, m)));
+
+                if let Some(request) = request {
+                    break request;
+                }
+
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            };
+
+            let (packet, message) = request;
+            let message = message.unwrap();
+            assert_eq!(packet.to, SERVER_NODE_NUM);
+            assert_eq!(message.rr(), RequestResponse::ClientHistory);
+            assert!(matches!(
+                message.variant,
+                Some(Variant::History(history)) if history.window == 120
+            ));
+
+            mock_radio
+                .inject_mesh_packet(store_forward_packet(
+                    SERVER_NODE_NUM,
+                    MY_NODE_NUM,
+                    store_forward_message(
+                        RequestResponse::RouterHistory,
+                        Some(Variant::History(protobufs::store_and_forward::History {
+                            history_messages: 3,
+                            window: 7_200_000,
+                            last_request: 12,
+                        })),
+                    ),
+                ))
+                .unwrap();
+        };
+
+        let (history, _) = tokio::join!(
+            stream_api.request_store_forward_history(
+                &mut router,
+                NodeId::new(SERVER_NODE_NUM),
+                Some(Duration::from_secs(2 * 60 * 60)),
+                None,
+                MeshChannel::new(1).unwrap(),
+                Duration::from_secs(1),
+            ),
+            respond
+        );
+
+        let history = history.unwrap();
+        assert_eq!(history.history_messages, 3);
+        assert_eq!(history.last_request, 12);
+    }
+}
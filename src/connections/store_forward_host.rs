@@ -0,0 +1,964 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+#[cfg(feature = "serde")]
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::{debug, warn};
+use prost::Message;
+use tokio_util::sync::CancellationToken;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+use crate::utils_internal::current_epoch_secs_u32;
+
+use super::store_forward::{decode_store_forward, history_window_minutes, store_forward_message};
+use super::stream_api::{state, ConnectedStreamApi};
+use super::subscription::{FromRadioKind, PacketFilter};
+use super::wrappers::mesh_channel::MeshChannel;
+use super::wrappers::NodeId;
+use super::{PacketDestination, PacketRouter};
+
+use protobufs::store_and_forward::{RequestResponse, Variant};
+
+/// The node number that broadcast packets are addressed to.
+const BROADCAST_NODE_NUM: u32 = u32::MAX;
+
+/// A struct that configures a `StoreForwardHost`.
+///
+/// The defaults match the defaults of the Store & Forward module of the firmware.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoreForwardHostOptions {
+    /// The interval at which router heartbeats are broadcast, or `None` to disable heartbeats.
+    /// Clients only discover servers that send heartbeats.
+    pub heartbeat_interval: Option<Duration>,
+
+    /// Whether to announce the server as a secondary server, for meshes that already have a
+    /// primary Store & Forward server.
+    pub is_secondary: bool,
+
+    /// The channel that heartbeats are broadcast on.
+    pub channel: MeshChannel,
+
+    /// The maximum number of messages kept in the history. The oldest messages are dropped
+    /// once the history is full.
+    pub max_records: usize,
+
+    /// The maximum number of messages replayed for a single history request.
+    pub history_return_max: u32,
+
+    /// The maximum age of the messages replayed for a history request, which is also used when
+    /// the client does not request a window.
+    pub history_return_window: Duration,
+
+    /// The delay between two replayed messages, which keeps replays from saturating the mesh.
+    pub replay_interval: Duration,
+
+    /// The JSON file that the history is saved to after every recorded message, or `None` to
+    /// only keep the history in memory.
+    #[cfg(feature = "serde")]
+    pub storage_path: Option<PathBuf>,
+}
+
+impl Default for StoreForwardHostOptions {
+    fn default() -> Self {
+        StoreForwardHostOptions {
+            heartbeat_interval: Some(Duration::from_secs(15 * 60)),
+            is_secondary: false,
+            channel: MeshChannel::default(),
+            max_records: 3000,
+            history_return_max: 25,
+            history_return_window: Duration::from_secs(240 * 60),
+            replay_interval: Duration::from_secs(5),
+            #[cfg(feature = "serde")]
+            storage_path: None,
+        }
+    }
+}
+
+/// A struct that represents a text message recorded by a `StoreForwardHost`.
+///
+/// # Fields
+///
+/// * `index` - The position of the message in the history, starting at 1. Clients send back the
+///     index of the last message they received to skip messages that were already replayed.
+/// * `id` - The ID of the original packet.
+/// * `time` - The time the message was received, in seconds since the Unix epoch.
+/// * `from` - The node number of the sender of the message.
+/// * `to` - The node number the message was sent to, which is `u32::MAX` for broadcasts.
+/// * `channel` - The channel index the message was received on.
+/// * `payload` - The UTF-8 encoded text of the message.
+/// * `reply_id` - The ID of the packet the message replies to, or `0`.
+/// * `emoji` - Whether the message is an emoji reaction, as a non-zero value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct StoreForwardRecord {
+    pub index: u32,
+    pub id: u32,
+    pub time: u32,
+    pub from: u32,
+    pub to: u32,
+    pub channel: u32,
+    pub payload: Vec<u8>,
+    pub reply_id: u32,
+    pub emoji: u32,
+}
+
+impl StoreForwardRecord {
+    /// Returns whether the message was sent as a broadcast.
+    pub fn is_broadcast(&self) -> bool {
+        self.to == BROADCAST_NODE_NUM
+    }
+}
+
+/// The on-disk representation of the history of a `StoreForwardHost`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedHistory {
+    messages_total: u32,
+    records: Vec<StoreForwardRecord>,
+}
+
+#[cfg(feature = "serde")]
+impl PersistedHistory {
+    /// Writes the history to a JSON file, replacing the file if it exists.
+    fn write(&self, path: &Path) -> Result<(), Error> {
+        let json = serde_json::to_vec(self).map_err(|e| Error::PersistenceError {
+            source: Box::new(e),
+            description: "Failed to serialize Store & Forward history".to_string(),
+        })?;
+
+        // Write to a temporary file first, so an interrupted save does not corrupt the history
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, json)
+            .and_then(|_| std::fs::rename(&temp_path, path))
+            .map_err(|e| Error::PersistenceError {
+                source: Box::new(e),
+                description: format!(
+                    "Failed to write Store & Forward history to {}",
+                    path.display()
+                ),
+            })
+    }
+}
+
+/// A history replay in progress for a single client.
+#[derive(Clone, Debug)]
+struct Replay {
+    client: u32,
+    channel: MeshChannel,
+    records: VecDeque<StoreForwardRecord>,
+}
+
+/// A Store & Forward message to be sent by the host.
+#[derive(Clone, Debug)]
+pub(crate) struct OutgoingMessage {
+    pub(crate) destination: PacketDestination,
+    pub(crate) channel: MeshChannel,
+    pub(crate) message: protobufs::StoreAndForward,
+}
+
+/// A struct that runs a Store & Forward server on the host, for radios that cannot run the Store
+/// & Forward module of the firmware.
+///
+/// The server speaks the same protocol as the firmware, so stock clients can discover it from its
+/// heartbeats and request the history from it. The server records the text messages received by
+/// the radio, answers `ClientHistory` requests by replaying the matching messages one at a time,
+/// and answers `ClientStats` and `ClientPing` requests. Only one client is served at a time, and
+/// other clients receive a `RouterBusy` response until the current replay is finished.
+///
+/// **Note:** The firmware assigns the node number of the connected radio to every packet sent by
+/// a client, so replayed messages are sent from the radio running the server rather than from
+/// their original sender.
+///
+/// # Examples
+///
+/// ```
+/// let mut host = StoreForwardHost::open(StoreForwardHostOptions {
+///     storage_path: Some("store_forward.json".into()),
+///     ..Default::default()
+/// })?;
+///
+/// let cancellation_token = CancellationToken::new();
+/// host.run(&mut stream_api, &mut packet_router, cancellation_token.clone())
+///     .await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct StoreForwardHost {
+    options: StoreForwardHostOptions,
+    records: VecDeque<StoreForwardRecord>,
+    messages_total: u32,
+    requests: u32,
+    requests_history: u32,
+    started_at: u32,
+    last_requests: HashMap<u32, u32>,
+    replay: Option<Replay>,
+}
+
+impl StoreForwardHost {
+    /// Creates a server with an empty history.
+    pub fn new(options: StoreForwardHostOptions) -> StoreForwardHost {
+        StoreForwardHost {
+            options,
+            records: VecDeque::new(),
+            messages_total: 0,
+            requests: 0,
+            requests_history: 0,
+            started_at: current_epoch_secs_u32(),
+            last_requests: HashMap::new(),
+            replay: None,
+        }
+    }
+
+    /// Creates a server, restoring its history from the `storage_path` of the options if the
+    /// file exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The `StoreForwardHostOptions` of the server.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `StoreForwardHost`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let host = StoreForwardHost::open(StoreForwardHostOptions {
+    ///     storage_path: Some("store_forward.json".into()),
+    ///     ..Default::default()
+    /// })?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::PersistenceError` if the file exists but cannot be read or does not
+    /// contain a valid history.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    #[cfg(feature = "serde")]
+    pub fn open(options: StoreForwardHostOptions) -> Result<StoreForwardHost, Error> {
+        let Some(path) = options.storage_path.clone().filter(|path| path.exists()) else {
+            return Ok(StoreForwardHost::new(options));
+        };
+
+        let json = std::fs::read(&path).map_err(|e| Error::PersistenceError {
+            source: Box::new(e),
+            description: format!(
+                "Failed to read Store & Forward history from {}",
+                path.display()
+            ),
+        })?;
+
+        let persisted: PersistedHistory =
+            serde_json::from_slice(&json).map_err(|e| Error::PersistenceError {
+                source: Box::new(e),
+                description: format!(
+                    "Failed to parse Store & Forward history from {}",
+                    path.display()
+                ),
+            })?;
+
+        let mut host = StoreForwardHost::new(options);
+        host.messages_total = persisted.messages_total;
+        host.records = persisted.records.into();
+        host.truncate();
+
+        Ok(host)
+    }
+
+    /// Saves the history to a JSON file, replacing the file if it exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to save the history to.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the history was successfully saved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// host.save("store_forward.json")?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::PersistenceError` if the file cannot be written.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.persisted_history().write(path.as_ref())
+    }
+
+    /// Returns the options of the server.
+    pub fn options(&self) -> &StoreForwardHostOptions {
+        &self.options
+    }
+
+    /// Returns the recorded messages, from the oldest to the newest.
+    pub fn records(&self) -> impl Iterator<Item = &StoreForwardRecord> {
+        self.records.iter()
+    }
+
+    /// Returns the statistics of the server, as reported to clients sending `ClientStats`
+    /// requests.
+    pub fn stats(&self) -> protobufs::store_and_forward::Statistics {
+        self.stats_at(current_epoch_secs_u32())
+    }
+
+    /// Runs the server until `cancellation_token` is cancelled.
+    ///
+    /// The server subscribes to the packets received by the radio, and sends its heartbeats,
+    /// responses and replayed messages through `stream_api`.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_api` - The configured `ConnectedStreamApi` of the radio running the server.
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `cancellation_token` - A token that stops the server when cancelled.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the server stopped cleanly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let cancellation_token = CancellationToken::new();
+    /// host.run(&mut stream_api, &mut packet_router, cancellation_token.clone())
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if a packet fails to send, or once the connection to the radio has been closed.
+    /// Failures to save the history are logged and do not stop the server.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn run<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        stream_api: &mut ConnectedStreamApi<state::Configured>,
+        packet_router: &mut R,
+        cancellation_token: CancellationToken,
+    ) -> Result<(), Error> {
+        let own_node_id = packet_router.source_node_id();
+        let mut subscription =
+            stream_api.subscribe(PacketFilter::new().kind(FromRadioKind::Packet));
+
+        let mut heartbeat_timer = self.options.heartbeat_interval.map(tokio::time::interval);
+        let mut replay_timer = tokio::time::interval(self.options.replay_interval);
+        replay_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        debug!("Started Store & Forward server on node {own_node_id}");
+
+        loop {
+            let outgoing = tokio::select! {
+                _ = cancellation_token.cancelled() => break,
+                _ = async {
+                    match heartbeat_timer.as_mut() {
+                        Some(timer) => timer.tick().await,
+                        None => std::future::pending().await,
+                    }
+                } => self.heartbeat(),
+                _ = replay_timer.tick() => self.next_replay(),
+                packet = subscription.recv() => {
+                    let packet = match packet {
+                        Ok(packet) => packet,
+                        Err(Error::SubscriptionLagged { skipped }) => {
+                            warn!("Store & Forward server missed {skipped} packets");
+                            continue;
+                        }
+                        Err(e) => return Err(e),
+                    };
+
+                    let messages_total = self.messages_total;
+                    let outgoing = self.handle_packet(&packet, own_node_id, current_epoch_secs_u32());
+
+                    if self.messages_total != messages_total {
+                        self.persist().await;
+                    }
+
+                    outgoing
+                }
+            };
+
+            if let Some(outgoing) = outgoing {
+                stream_api
+                    .send_mesh_packet(
+                        packet_router,
+                        outgoing.message.encode_to_vec().into(),
+                        protobufs::PortNum::StoreForwardApp,
+                        outgoing.destination,
+                        outgoing.channel,
+                        false,
+                        false,
+                        false,
+                        None,
+                        None,
+                    )
+                    .await?;
+            }
+        }
+
+        debug!("Stopped Store & Forward server on node {own_node_id}");
+
+        Ok(())
+    }
+
+    /// Updates the server from a packet received from the radio, recording text messages and
+    /// answering Store & Forward requests addressed to the server.
+    pub(crate) fn handle_packet(
+        &mut self,
+        packet: &protobufs::FromRadio,
+        own_node_id: NodeId,
+        now: u32,
+    ) -> Option<OutgoingMessage> {
+        let Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)) =
+            &packet.payload_variant
+        else {
+            return None;
+        };
+
+        if own_node_id == mesh_packet.from {
+            return None;
+        }
+
+        if self.record(mesh_packet, now) {
+            return None;
+        }
+
+        let message = match decode_store_forward(mesh_packet)? {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Failed to decode Store & Forward message: {e}");
+                return None;
+            }
+        };
+
+        if own_node_id != mesh_packet.to && mesh_packet.to != BROADCAST_NODE_NUM {
+            return None;
+        }
+
+        let client = mesh_packet.from;
+        let channel = MeshChannel::from(mesh_packet.channel);
+
+        let (rr, variant) = match message.rr() {
+            RequestResponse::ClientHistory => {
+                self.requests += 1;
+                self.requests_history += 1;
+
+                let requested = match message.variant {
+                    Some(Variant::History(history)) => history,
+                    _ => protobufs::store_and_forward::History::default(),
+                };
+
+                self.start_replay(client, channel, requested, now)
+            }
+            RequestResponse::ClientStats => {
+                self.requests += 1;
+                (
+                    RequestResponse::RouterStats,
+                    Some(Variant::Stats(self.stats_at(now))),
+                )
+            }
+            RequestResponse::ClientPing => (RequestResponse::RouterPong, None),
+            RequestResponse::ClientAbort => {
+                if self
+                    .replay
+                    .as_ref()
+                    .is_some_and(|replay| replay.client == client)
+                {
+                    debug!("Store & Forward client {client} aborted its replay");
+                    self.replay = None;
+                }
+
+                return None;
+            }
+            _ => return None,
+        };
+
+        Some(OutgoingMessage {
+            destination: PacketDestination::Node(client.into()),
+            channel,
+            message: store_forward_message(rr, variant),
+        })
+    }
+
+    /// Returns the next heartbeat of the server.
+    pub(crate) fn heartbeat(&self) -> Option<OutgoingMessage> {
+        let period = self.options.heartbeat_interval?;
+
+        let heartbeat = protobufs::store_and_forward::Heartbeat {
+            period: u32::try_from(period.as_secs()).unwrap_or(u32::MAX),
+            secondary: self.options.is_secondary.into(),
+        };
+
+        Some(OutgoingMessage {
+            destination: PacketDestination::Broadcast,
+            channel: self.options.channel,
+            message: store_forward_message(
+                RequestResponse::RouterHeartbeat,
+                Some(Variant::Heartbeat(heartbeat)),
+            ),
+        })
+    }
+
+    /// Returns the next message of the replay in progress, if any.
+    pub(crate) fn next_replay(&mut self) -> Option<OutgoingMessage> {
+        let replay = self.replay.as_mut()?;
+        let record = replay.records.pop_front();
+
+        let outgoing = record.map(|record| {
+            let rr = match record.is_broadcast() {
+                true => RequestResponse::RouterTextBroadcast,
+                false => RequestResponse::RouterTextDirect,
+            };
+
+            OutgoingMessage {
+                destination: PacketDestination::Node(replay.client.into()),
+                channel: replay.channel,
+                message: store_forward_message(rr, Some(Variant::Text(record.payload))),
+            }
+        });
+
+        if replay.records.is_empty() {
+            self.replay = None;
+        }
+
+        outgoing
+    }
+
+    /// Records a text message received by the radio, returning whether the packet was recorded.
+    fn record(&mut self, mesh_packet: &protobufs::MeshPacket, now: u32) -> bool {
+        let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) =
+            &mesh_packet.payload_variant
+        else {
+            return false;
+        };
+
+        if data.portnum != protobufs::PortNum::TextMessageApp as i32 {
+            return false;
+        }
+
+        self.messages_total += 1;
+        self.records.push_back(StoreForwardRecord {
+            index: self.messages_total,
+            id: mesh_packet.id,
+            time: match mesh_packet.rx_time {
+                0 => now,
+                rx_time => rx_time,
+            },
+            from: mesh_packet.from,
+            to: mesh_packet.to,
+            channel: mesh_packet.channel,
+            payload: data.payload.clone(),
+            reply_id: data.reply_id,
+            emoji: data.emoji,
+        });
+        self.truncate();
+
+        true
+    }
+
+    /// Starts replaying the history to a client, returning the response to its request.
+    fn start_replay(
+        &mut self,
+        client: u32,
+        channel: MeshChannel,
+        requested: protobufs::store_and_forward::History,
+        now: u32,
+    ) -> (RequestResponse, Option<Variant>) {
+        if self
+            .replay
+            .as_ref()
+            .is_some_and(|replay| replay.client != client)
+        {
+            return (RequestResponse::RouterBusy, None);
+        }
+
+        let max_window = history_window_minutes(Some(self.options.history_return_window));
+        let window = match requested.window {
+            0 => max_window,
+            window => window.min(max_window),
+        };
+        let since = now.saturating_sub(window.saturating_mul(60));
+
+        let last_request = match requested.last_request {
+            0 => self.last_requests.get(&client).copied().unwrap_or(0),
+            last_request => last_request,
+        };
+
+        let records: VecDeque<StoreForwardRecord> = self
+            .records
+            .iter()
+            .filter(|record| record.index > last_request && record.time >= since)
+            .filter(|record| record.from != client)
+            .filter(|record| record.is_broadcast() || record.to == client)
+            .take(self.options.history_return_max as usize)
+            .cloned()
+            .collect();
+
+        let last_request = records.back().map_or(last_request, |record| record.index);
+        self.last_requests.insert(client, last_request);
+
+        let history = protobufs::store_and_forward::History {
+            history_messages: records.len() as u32,
+            // * The firmware reports the window in milliseconds
+            window: window.saturating_mul(60 * 1000),
+            last_request,
+        };
+
+        self.replay = match records.is_empty() {
+            true => None,
+            false => Some(Replay {
+                client,
+                channel,
+                records,
+            }),
+        };
+
+        (
+            RequestResponse::RouterHistory,
+            Some(Variant::History(history)),
+        )
+    }
+
+    fn stats_at(&self, now: u32) -> protobufs::store_and_forward::Statistics {
+        protobufs::store_and_forward::Statistics {
+            messages_total: self.messages_total,
+            messages_saved: self.records.len() as u32,
+            messages_max: u32::try_from(self.options.max_records).unwrap_or(u32::MAX),
+            up_time: now.saturating_sub(self.started_at),
+            requests: self.requests,
+            requests_history: self.requests_history,
+            heartbeat: self.options.heartbeat_interval.is_some(),
+            return_max: self.options.history_return_max,
+            return_window: history_window_minutes(Some(self.options.history_return_window)),
+        }
+    }
+
+    fn truncate(&mut self) {
+        while self.records.len() > self.options.max_records {
+            self.records.pop_front();
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn persisted_history(&self) -> PersistedHistory {
+        PersistedHistory {
+            messages_total: self.messages_total,
+            records: self.records.iter().cloned().collect(),
+        }
+    }
+
+    /// Saves the history to the `storage_path` of the options, on a blocking thread so that
+    /// writing the file does not stall the runtime.
+    #[cfg(feature = "serde")]
+    async fn persist(&self) {
+        let Some(path) = self.options.storage_path.clone() else {
+            return;
+        };
+
+        let history = self.persisted_history();
+
+        match tokio::task::spawn_blocking(move || history.write(&path)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("{e}"),
+            Err(e) => warn!("Failed to save Store & Forward history: {e}"),
+        }
+    }
+
+    #[cfg(not(feature = "serde"))]
+    async fn persist(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connections::test_support::{configured_stream_api, TestRouter, MY_NODE_NUM};
+
+    use super::*;
+
+    const CLIENT_NODE_NUM: u32 = 7;
+    const NOW: u32 = 1_700_000_000;
+
+    fn decoded_packet(
+        from: u32,
+        to: u32,
+        rx_time: u32,
+        portnum: protobufs::PortNum,
+        payload: Vec<u8>,
+    ) -> protobufs::MeshPacket {
+        protobufs::MeshPacket {
+            from,
+            to,
+            rx_time,
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                protobufs::Data {
+                    portnum: portnum as i32,
+                    payload,
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+
+    fn from_radio(mesh_packet: protobufs::MeshPacket) -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)),
+        }
+    }
+
+    fn text(from: u32, to: u32, rx_time: u32, text: &str) -> protobufs::FromRadio {
+        from_radio(decoded_packet(
+            from,
+            to,
+            rx_time,
+            protobufs::PortNum::TextMessageApp,
+            text.as_bytes().to_vec(),
+        ))
+    }
+
+    fn request(from: u32, rr: RequestResponse, variant: Option<Variant>) -> protobufs::MeshPacket {
+        decoded_packet(
+            from,
+            MY_NODE_NUM,
+            0,
+            protobufs::PortNum::StoreForwardApp,
+            store_forward_message(rr, variant).encode_to_vec(),
+        )
+    }
+
+    fn history_request(from: u32, window: u32) -> protobufs::FromRadio {
+        from_radio(request(
+            from,
+            RequestResponse::ClientHistory,
+            Some(Variant::History(protobufs::store_and_forward::History {
+                window,
+                ..Default::default()
+            })),
+        ))
+    }
+
+    #[test]
+    fn replays_matching_history_to_client() {
+        let mut host = StoreForwardHost::new(StoreForwardHostOptions::default());
+        let own_node_id = NodeId::new(MY_NODE_NUM);
+
+        for packet in [
+            text(1, BROADCAST_NODE_NUM, NOW - 3 * 60 * 60, "too old"),
+            text(1, BROADCAST_NODE_NUM, NOW - 60, "broadcast"),
+            text(1, CLIENT_NODE_NUM, NOW - 30, "direct"),
+            text(1, 99, NOW - 20, "someone else"),
+            text(CLIENT_NODE_NUM, BROADCAST_NODE_NUM, NOW - 10, "own message"),
+        ] {
+            assert!(host.handle_packet(&packet, own_node_id, NOW).is_none());
+        }
+
+        let response = host
+            .handle_packet(&history_request(CLIENT_NODE_NUM, 60), own_node_id, NOW)
+            .unwrap();
+
+        assert!(matches!(
+            response.destination,
+            PacketDestination::Node(node_id) if node_id == CLIENT_NODE_NUM
+        ));
+        assert_eq!(response.message.rr(), RequestResponse::RouterHistory);
+        assert_eq!(
+            response.message.variant,
+            Some(Variant::History(protobufs::store_and_forward::History {
+                history_messages: 2,
+                window: 60 * 60 * 1000,
+                last_request: 3,
+            }))
+        );
+
+        let replayed: Vec<protobufs::StoreAndForward> = std::iter::from_fn(|| host.next_replay())
+            .map(|outgoing| outgoing.message)
+            .collect();
+
+        assert_eq!(
+            replayed,
+            vec![
+                store_forward_message(
+                    RequestResponse::RouterTextBroadcast,
+                    Some(Variant::Text(b"broadcast".to_vec()))
+                ),
+                store_forward_message(
+                    RequestResponse::RouterTextDirect,
+                    Some(Variant::Text(b"direct".to_vec()))
+                ),
+            ]
+        );
+
+        // A second request only returns messages recorded since the last replay
+        let response = host
+            .handle_packet(&history_request(CLIENT_NODE_NUM, 60), own_node_id, NOW)
+            .unwrap();
+
+        assert!(matches!(
+            response.message.variant,
+            Some(Variant::History(protobufs::store_and_forward::History {
+                history_messages: 0,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn answers_busy_and_stats_requests() {
+        let mut host = StoreForwardHost::new(StoreForwardHostOptions {
+            max_records: 2,
+            ..Default::default()
+        });
+        let own_node_id = NodeId::new(MY_NODE_NUM);
+
+        for message in ["one", "two", "three"] {
+            host.handle_packet(&text(1, BROADCAST_NODE_NUM, NOW, message), own_node_id, NOW);
+        }
+
+        host.handle_packet(&history_request(CLIENT_NODE_NUM, 0), own_node_id, NOW)
+            .unwrap();
+
+        let busy = host
+            .handle_packet(&history_request(8, 0), own_node_id, NOW)
+            .unwrap();
+        assert_eq!(busy.message.rr(), RequestResponse::RouterBusy);
+
+        let stats = host
+            .handle_packet(
+                &from_radio(request(8, RequestResponse::ClientStats, None)),
+                own_node_id,
+                NOW,
+            )
+            .unwrap();
+
+        let Some(Variant::Stats(stats)) = stats.message.variant else {
+            panic!("Expected statistics, got {:?}", stats.message);
+        };
+
+        assert_eq!(stats.messages_total, 3);
+        assert_eq!(stats.messages_saved, 2);
+        assert_eq!(stats.messages_max, 2);
+        assert_eq!(stats.requests, 3);
+        assert_eq!(stats.requests_history, 2);
+        assert_eq!(stats.return_window, 240);
+    }
+
+    #[tokio::test]
+    async fn serves_history_over_stream_api() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+
+        let mut host = StoreForwardHost::new(StoreForwardHostOptions {
+            replay_interval: Duration::from_millis(10),
+            ..Default::default()
+        });
+        let mut router = TestRouter;
+        let cancellation_token = CancellationToken::new();
+
+        let client = async {
+            let sent_messages = |rr: RequestResponse| {
+                mock_radio
+                    .received_mesh_packets()
+                    .iter()
+                    .filter_map(|packet| decode_store_forward(packet)?.ok())
+                    .filter(|message| message.rr() == rr)
+                    .count()
+            };
+
+            while sent_messages(RequestResponse::RouterHeartbeat) == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+
+            mock_radio
+                .inject(text(1, BROADCAST_NODE_NUM, 0, "hello"))
+                .unwrap();
+            mock_radio
+                .inject_mesh_packet(request(
+                    CLIENT_NODE_NUM,
+                    RequestResponse::ClientHistory,
+                    None,
+                ))
+                .unwrap();
+
+            while sent_messages(RequestResponse::RouterTextBroadcast) == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+
+            assert_eq!(sent_messages(RequestResponse::RouterHistory), 1);
+            cancellation_token.cancel();
+        };
+
+        let (result, _) = tokio::join!(
+            host.run(&mut stream_api, &mut router, cancellation_token.clone()),
+            client
+        );
+
+        result.unwrap();
+        assert_eq!(host.records().count(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn persists_history_across_restarts() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+
+        let path = std::env::temp_dir().join(format!("store_forward_{}.json", std::process::id()));
+        let options = StoreForwardHostOptions {
+            heartbeat_interval: None,
+            storage_path: Some(path.clone()),
+            ..Default::default()
+        };
+
+        let mut host = StoreForwardHost::open(options.clone()).unwrap();
+        let mut router = TestRouter;
+        let cancellation_token = CancellationToken::new();
+
+        let client = async {
+            mock_radio
+                .inject(text(1, BROADCAST_NODE_NUM, NOW, "hello"))
+                .unwrap();
+
+            while !path.exists() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+
+            cancellation_token.cancel();
+        };
+
+        let (result, _) = tokio::join!(
+            host.run(&mut stream_api, &mut router, cancellation_token.clone()),
+            client
+        );
+        result.unwrap();
+
+        let reopened = StoreForwardHost::open(options).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            reopened.records().collect::<Vec<_>>(),
+            host.records().collect::<Vec<_>>()
+        );
+        assert_eq!(reopened.records().next().unwrap().payload, b"hello");
+        assert_eq!(reopened.stats().messages_total, 1);
+    }
+}
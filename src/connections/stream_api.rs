@@ -1,10 +1,10 @@
-use futures_util::future::join3;
+use futures_util::future::join_all;
 use log::trace;
 use prost::Message;
-use std::{fmt::Display, marker::PhantomData};
+use std::{fmt::Display, future::Future, marker::PhantomData, time::Duration};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
-    sync::mpsc::UnboundedSender,
+    sync::{broadcast, mpsc::UnboundedSender},
     task::JoinHandle,
 };
 use tokio_util::sync::CancellationToken;
@@ -15,8 +15,27 @@ use crate::{
     utils_internal::{current_epoch_secs_u32, generate_rand_id},
 };
 
+#[cfg(feature = "mqtt")]
+use super::mqtt_proxy::{MqttProxy, MqttProxyOptions};
 use super::{
+    ack::SentPacket,
+    channel_url::{
+        added_channels, parse_channel_url, replacement_channels, ChannelUrl, MAX_CHANNEL_COUNT,
+    },
+    config_transaction::ConfigTransaction,
     handlers,
+    reconnect::{
+        spawn_reconnect_supervisor, ConnectionState, ConnectionStateReceiver, ReconnectOptions,
+    },
+    remote_admin::{AdminTarget, RemoteAdmin},
+    response::{decoded_response, routing_error, wait_for_response},
+    snapshot::{
+        collect_radio_snapshot, split_local_config, split_local_module_config, RadioSnapshot,
+    },
+    store_forward::{decode_store_forward, history_window_minutes, store_forward_message},
+    subscription::{PacketFilter, PacketSubscription},
+    telemetry::{TelemetryKind, TelemetryReport},
+    traceroute::{TracerouteResult, MAX_HOP_LIMIT},
     wrappers::{
         encoded_data::{EncodedMeshPacketData, EncodedToRadioPacket, IncomingStreamData},
         mesh_channel::MeshChannel,
@@ -43,6 +62,10 @@ pub mod state {
     pub struct Configured;
 }
 
+/// The number of decoded packets buffered for internal packet listeners (e.g., configuration
+/// tracking) before the slowest listener starts missing packets.
+const PACKET_BROADCAST_CAPACITY: usize = 1024;
+
 // StreamApi definition
 
 /// A struct that provides a high-level API for communicating with a Meshtastic radio.
@@ -70,10 +93,9 @@ pub struct StreamApi;
 #[derive(Debug)]
 pub struct ConnectedStreamApi<State = state::Configured> {
     write_input_tx: UnboundedSender<EncodedToRadioPacketWithHeader>,
+    packet_broadcast_tx: broadcast::Sender<protobufs::FromRadio>,
 
-    read_handle: JoinHandle<Result<(), Error>>,
-    write_handle: JoinHandle<Result<(), Error>>,
-    processing_handle: JoinHandle<Result<(), Error>>,
+    worker_handles: Vec<JoinHandle<Result<(), Error>>>,
     heartbeat_handle: JoinHandle<Result<(), Error>>,
 
     cancellation_token: CancellationToken,
@@ -122,7 +144,8 @@ impl<State> ConnectedStreamApi<State> {
     ///
     /// # Returns
     ///
-    /// A result indicating whether the packet was successfully dispatched to the radio.
+    /// A result resolving to a `SentPacket` handle if the packet was successfully dispatched to the radio.
+    /// This handle contains the ID of the sent packet, and can be used to wait for an acknowledgement.
     ///
     /// # Examples
     ///
@@ -130,7 +153,7 @@ impl<State> ConnectedStreamApi<State> {
     /// // Example 1: Send a text message to a node
     /// let byte_data = "Hello, world!".to_string().into_bytes();
     ///
-    /// self.send_mesh_packet(
+    /// let sent_packet = self.send_mesh_packet(
     ///     packet_router,
     ///     byte_data,
     ///     protobufs::PortNum::TextMessageApp,
@@ -170,14 +193,62 @@ impl<State> ConnectedStreamApi<State> {
         echo_response: bool,
         reply_id: Option<u32>,
         emoji: Option<u32>,
-    ) -> Result<(), Error> {
+    ) -> Result<SentPacket, Error> {
+        // Subscribe before sending the packet to avoid missing early acknowledgements
+        let packet_rx = self.packet_broadcast_tx.subscribe();
         let own_node_id = packet_router.source_node_id();
 
-        let packet_destination: NodeId = match destination {
-            PacketDestination::Local => own_node_id,
-            PacketDestination::Broadcast => u32::MAX.into(),
-            PacketDestination::Node(id) => id,
-        };
+        let packet_id = self
+            .dispatch_mesh_packet(
+                packet_router,
+                packet_data,
+                port_num,
+                destination,
+                channel,
+                want_ack,
+                want_response,
+                echo_response,
+                reply_id,
+                emoji,
+                None,
+            )
+            .await?;
+
+        Ok(SentPacket::new(
+            packet_id,
+            destination.node_id(own_node_id),
+            own_node_id,
+            packet_rx,
+        ))
+    }
+
+    /// An internal helper method that sends a `MeshPacket` to the radio in the same way as the
+    /// `send_mesh_packet` method, and returns the randomly generated ID of the sent packet.
+    /// This ID is used to match responses and acknowledgements to the sent packet.
+    #[allow(clippy::too_many_arguments)]
+    async fn dispatch_mesh_packet<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        packet_data: EncodedMeshPacketData,
+        port_num: protobufs::PortNum,
+        destination: PacketDestination,
+        channel: MeshChannel,
+        want_ack: bool,
+        want_response: bool,
+        echo_response: bool,
+        reply_id: Option<u32>,
+        emoji: Option<u32>,
+        hop_limit: Option<u32>,
+    ) -> Result<u32, Error> {
+        let own_node_id = packet_router.source_node_id();
+
+        let packet_destination: NodeId = destination.node_id(own_node_id);
+
+        let packet_id: u32 = generate_rand_id();
 
         // NOTE(canardleteer): We don't warn on deprecation here, because it
         //                     remains valid for many active nodes, and
@@ -196,17 +267,17 @@ impl<State> ConnectedStreamApi<State> {
                     source: 0,     // TODO change this
                 },
             )),
-            rx_time: 0,   // * not transmitted
-            rx_snr: 0.0,  // * not transmitted
-            hop_limit: 0, // * not transmitted
-            priority: 0,  // * not transmitted
-            rx_rssi: 0,   // * not transmitted
+            rx_time: 0,                        // * not transmitted
+            rx_snr: 0.0,                       // * not transmitted
+            hop_limit: hop_limit.unwrap_or(0), // * 0 uses the hop limit configured on the radio
+            priority: 0,                       // * not transmitted
+            rx_rssi: 0,                        // * not transmitted
             delayed: 0,   // * not transmitted [deprecated since protobufs v2.2.19]
             hop_start: 0, // * set on device
             via_mqtt: false,
             from: own_node_id.id(),
             to: packet_destination.id(),
-            id: generate_rand_id(),
+            id: packet_id,
             want_ack,
             channel: channel.channel(),
         };
@@ -223,7 +294,7 @@ impl<State> ConnectedStreamApi<State> {
         let payload_variant = Some(protobufs::to_radio::PayloadVariant::Packet(mesh_packet));
         self.send_to_radio_packet(payload_variant).await?;
 
-        Ok(())
+        Ok(packet_id)
     }
 
     /// A helper method to send a raw `ToRadio` packet to the radio based on a provided `protobufs::to_radio::PayloadVariant`.
@@ -345,6 +416,42 @@ impl<State> ConnectedStreamApi<State> {
     pub fn write_input_sender(&self) -> UnboundedSender<EncodedToRadioPacketWithHeader> {
         self.write_input_tx.clone()
     }
+
+    /// A method that creates a new subscription to the packets received from the radio.
+    ///
+    /// Every subscription receives its own copy of each packet accepted by its filter, so any
+    /// number of subscriptions can be created alongside the `PacketReceiver` returned by the
+    /// `connect` method. Packets received before the subscription was created are not delivered.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The `PacketFilter` selecting the packets delivered to the subscription.
+    ///
+    /// # Returns
+    ///
+    /// A `PacketSubscription` that receives the packets accepted by the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut text_messages =
+    ///     stream_api.subscribe(PacketFilter::new().port_num(protobufs::PortNum::TextMessageApp));
+    /// let mut node_updates = stream_api.subscribe(PacketFilter::new().kind(FromRadioKind::NodeInfo));
+    ///
+    /// let text_message = text_messages.recv().await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn subscribe(&self, filter: PacketFilter) -> PacketSubscription {
+        PacketSubscription::new(self.packet_broadcast_tx.subscribe(), filter)
+    }
 }
 
 // Public connection management API
@@ -435,6 +542,9 @@ impl StreamApi {
         let (decoded_packet_tx, decoded_packet_rx) =
             tokio::sync::mpsc::unbounded_channel::<protobufs::FromRadio>();
 
+        let (packet_broadcast_tx, _) =
+            broadcast::channel::<protobufs::FromRadio>(PACKET_BROADCAST_CAPACITY);
+
         // Spawn worker threads with kill switch
 
         let (read_stream, write_stream) = tokio::io::split(stream_handle.stream);
@@ -450,6 +560,7 @@ impl StreamApi {
             cancellation_token.clone(),
             read_output_rx,
             decoded_packet_tx,
+            packet_broadcast_tx.clone(),
         );
 
         let heartbeat_handle =
@@ -466,9 +577,119 @@ impl StreamApi {
             decoded_packet_rx,
             ConnectedStreamApi::<state::Connected> {
                 write_input_tx,
-                read_handle,
-                write_handle,
-                processing_handle,
+                packet_broadcast_tx,
+                worker_handles: vec![read_handle, write_handle, processing_handle],
+                heartbeat_handle,
+                cancellation_token,
+                typestate: PhantomData,
+            },
+        )
+    }
+
+    /// A method to connect to a radio through streams built by a user-supplied factory, and to
+    /// automatically reconnect to the radio whenever the stream is lost (e.g., when the radio
+    /// reboots after a configuration change).
+    ///
+    /// This method spawns a supervisor thread that calls `stream_factory` to build a stream, sends
+    /// a `WantConfigId` packet to the radio, and forwards decoded packets to the returned
+    /// `PacketReceiver`. When the stream reaches EOF, fails, or the radio does not complete the
+    /// handshake within `options.config_timeout`, the supervisor waits with exponential backoff
+    /// and rebuilds the stream with the factory. The `PacketReceiver` stays open across reconnects.
+    ///
+    /// Since the supervisor performs the `WantConfigId` handshake itself, this method returns a
+    /// `ConnectedStreamApi` in the `Configured` state. Packets sent while the radio is disconnected
+    /// are queued, and written to the radio once the next stream has been built.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream_factory` - A closure returning a future that builds a new `StreamHandle` to the radio.
+    /// * `config_id` - A randomly generated configuration ID used for the first handshake. Later
+    ///     handshakes use newly generated configuration IDs.
+    /// * `options` - A `ReconnectOptions` struct that configures the reconnect backoff.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `PacketReceiver` used to receive decoded `FromRadio` packets, a `ConnectionStateReceiver`
+    /// used to receive `ConnectionState` events, and the configured `ConnectedStreamApi` instance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stream_api = StreamApi::new();
+    /// let stream_factory = || build_tcp_stream("localhost:4403".to_string());
+    ///
+    /// let (mut decoded_listener, mut state_listener, stream_api) = stream_api
+    ///     .connect_with_reconnect(stream_factory, generate_rand_id(), ReconnectOptions::default())
+    ///     .await;
+    ///
+    /// while let Some(state) = state_listener.recv().await {
+    ///     println!("Connection state: {:?}", state);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None. If `options.max_attempts` is set and the supervisor gives up, the `PacketReceiver` is
+    /// closed and the `disconnect` method returns `Error::ReconnectAttemptsExhausted`.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn connect_with_reconnect<S, F, Fut>(
+        self,
+        stream_factory: F,
+        config_id: u32,
+        options: ReconnectOptions,
+    ) -> (
+        PacketReceiver,
+        ConnectionStateReceiver,
+        ConnectedStreamApi<state::Configured>,
+    )
+    where
+        S: AsyncReadExt + AsyncWriteExt + Send + 'static,
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<StreamHandle<S>, Error>> + Send,
+    {
+        // Create message channels
+
+        let (write_input_tx, write_input_rx) =
+            tokio::sync::mpsc::unbounded_channel::<EncodedToRadioPacketWithHeader>();
+
+        let (decoded_packet_tx, decoded_packet_rx) =
+            tokio::sync::mpsc::unbounded_channel::<protobufs::FromRadio>();
+
+        let (connection_state_tx, connection_state_rx) =
+            tokio::sync::mpsc::unbounded_channel::<ConnectionState>();
+
+        let (packet_broadcast_tx, _) =
+            broadcast::channel::<protobufs::FromRadio>(PACKET_BROADCAST_CAPACITY);
+
+        // Spawn supervisor and heartbeat threads with kill switch
+
+        let cancellation_token = CancellationToken::new();
+
+        let supervisor_handle = spawn_reconnect_supervisor(
+            cancellation_token.clone(),
+            stream_factory,
+            config_id,
+            options,
+            write_input_rx,
+            decoded_packet_tx,
+            packet_broadcast_tx.clone(),
+            connection_state_tx,
+        );
+
+        let heartbeat_handle =
+            handlers::spawn_heartbeat_handler(cancellation_token.clone(), write_input_tx.clone());
+
+        (
+            decoded_packet_rx,
+            connection_state_rx,
+            ConnectedStreamApi::<state::Configured> {
+                write_input_tx,
+                packet_broadcast_tx,
+                worker_handles: vec![supervisor_handle],
                 heartbeat_handle,
                 cancellation_token,
                 typestate: PhantomData,
@@ -488,7 +709,8 @@ impl ConnectedStreamApi<state::Connected> {
     /// module configuration, and channel configuration. The radio will indicate that it
     /// has finished transmission by sending a `ConfigComplete` packet, which will contain
     /// the same configuration identifier that was sent in the `WantConfigId` packet. Tracking
-    /// whether or not configuration completes successfully is not handled by this method.
+    /// whether or not configuration completes successfully is not handled by this method,
+    /// see the `configure_and_wait` method for a variant that does.
     ///
     /// Once a radio connection has been configured, the radio will send all future packets
     /// it receives through the decoded packet channel. This will continue until the
@@ -542,14 +764,85 @@ impl ConnectedStreamApi<state::Connected> {
 
         Ok(ConnectedStreamApi::<state::Configured> {
             write_input_tx: self.write_input_tx,
-            read_handle: self.read_handle,
-            write_handle: self.write_handle,
-            processing_handle: self.processing_handle,
+            packet_broadcast_tx: self.packet_broadcast_tx,
+            worker_handles: self.worker_handles,
             heartbeat_handle: self.heartbeat_handle,
             cancellation_token: self.cancellation_token,
             typestate: PhantomData,
         })
     }
+
+    /// This method triggers the same `WantConfigId` handshake as the `configure` method, and then
+    /// waits for the radio to finish sending its current state. All `MyInfo`, `NodeInfo`, `Channel`,
+    /// `Config`, `ModuleConfig`, `Metadata` and `FileInfo` packets received before the matching
+    /// `ConfigCompleteId` packet are collected into a `RadioSnapshot`.
+    ///
+    /// The collected packets are still forwarded through the decoded packet channel returned by
+    /// the `connect` method.
+    ///
+    /// If the handshake fails, the worker threads of the connection are cancelled and joined.
+    ///
+    /// # Arguments
+    ///
+    /// * `config_id` - A randomly generated configuration ID that will be used to check that the configuration process has completed.
+    /// * `timeout` - The maximum amount of time to wait for the radio to finish sending its configuration.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the configured `ConnectedStreamApi` instance along with
+    /// the `RadioSnapshot` reported by the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stream_api = StreamApi::new();
+    /// let tcp_stream = build_tcp_stream("localhost:4403".to_string()).await?;
+    /// let (decoded_listener, stream_api) = stream_api.connect(tcp_stream).await;
+    ///
+    /// let config_id = generate_rand_id();
+    /// let (stream_api, snapshot) = stream_api
+    ///     .configure_and_wait(config_id, Duration::from_secs(30))
+    ///     .await?;
+    ///
+    /// println!("Connected to node {:?}", snapshot.my_info);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the `WantConfigId` packet fails to send, with `Error::ConfigurationTimeout` if the
+    /// radio does not complete the configuration before `timeout` elapses, and with
+    /// `Error::ConfigIdMismatch` if the radio completes a configuration with a different ID.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn configure_and_wait(
+        self,
+        config_id: u32,
+        timeout: Duration,
+    ) -> Result<(ConnectedStreamApi<state::Configured>, RadioSnapshot), Error> {
+        // Subscribe before sending the request to avoid missing early responses
+        let mut packet_rx = self.packet_broadcast_tx.subscribe();
+        let stream_api = self.configure(config_id).await?;
+
+        let snapshot_result =
+            tokio::time::timeout(timeout, collect_radio_snapshot(&mut packet_rx, config_id))
+                .await
+                .unwrap_or(Err(Error::ConfigurationTimeout { config_id }));
+
+        match snapshot_result {
+            Ok(snapshot) => Ok((stream_api, snapshot)),
+            Err(e) => {
+                // * Join the worker threads before reporting the handshake error
+                if let Err(disconnect_error) = stream_api.disconnect().await {
+                    trace!("Failed to disconnect after handshake error: {disconnect_error}");
+                }
+
+                Err(e)
+            }
+        }
+    }
 }
 
 impl ConnectedStreamApi<state::Configured> {
@@ -597,13 +890,12 @@ impl ConnectedStreamApi<state::Configured> {
 
         // Close worker threads
 
-        let (read_result, write_result, processing_result) =
-            join3(self.read_handle, self.write_handle, self.processing_handle).await;
+        let worker_results = join_all(self.worker_handles).await;
 
         // Note: we only return the first error.
-        read_result??;
-        write_result??;
-        processing_result??;
+        for worker_result in worker_results {
+            worker_result??;
+        }
 
         trace!("Handlers fully disconnected");
 
@@ -628,7 +920,7 @@ impl ConnectedStreamApi<state::Configured> {
     ///
     /// # Returns
     ///
-    /// A result indicating whether the packet was successfully sent to the radio.
+    /// A result resolving to a `SentPacket` handle, which can be used to wait for an acknowledgement.
     ///
     /// # Examples
     ///
@@ -662,7 +954,7 @@ impl ConnectedStreamApi<state::Configured> {
         destination: PacketDestination,
         want_ack: bool,
         channel: MeshChannel,
-    ) -> Result<(), Error> {
+    ) -> Result<SentPacket, Error> {
         let byte_data: EncodedMeshPacketData = text.into_bytes().into();
 
         self.send_mesh_packet(
@@ -677,9 +969,7 @@ impl ConnectedStreamApi<state::Configured> {
             None,
             None,
         )
-        .await?;
-
-        Ok(())
+        .await
     }
 
     /// Sends the specified `Waypoint` over the mesh.
@@ -700,7 +990,7 @@ impl ConnectedStreamApi<state::Configured> {
     ///
     /// # Returns
     ///
-    /// A result indicating whether the packet was successfully sent to the radio.
+    /// A result resolving to a `SentPacket` handle, which can be used to wait for an acknowledgement.
     ///
     /// # Examples
     ///
@@ -735,7 +1025,7 @@ impl ConnectedStreamApi<state::Configured> {
         destination: PacketDestination,
         want_ack: bool,
         channel: MeshChannel,
-    ) -> Result<(), Error> {
+    ) -> Result<SentPacket, Error> {
         let mut waypoint = waypoint;
 
         // Waypoint with ID of zero denotes a new waypoint; check whether to generate its ID on backend
@@ -757,9 +1047,7 @@ impl ConnectedStreamApi<state::Configured> {
             None,
             None,
         )
-        .await?;
-
-        Ok(())
+        .await
     }
 
     /// Sends the specified `Positon` over the mesh.
@@ -773,7 +1061,7 @@ impl ConnectedStreamApi<state::Configured> {
     ///
     /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
     ///     This router is used in the event a packet needs to be echoed.
-    /// * `position` - An instance of the `Position` struct to send.
+    /// * `position` - The position to send, either as a `Position` struct or as a `GeoPosition`.
     /// * `destination` - A `PacketDestination` enum that specifies the destination of the packet.
     /// * `want_ack` - A `bool` that specifies whether or not the radio should wait for acknowledgement
     ///     from other nodes on the mesh.
@@ -781,7 +1069,7 @@ impl ConnectedStreamApi<state::Configured> {
     ///
     /// # Returns
     ///
-    /// A result indicating whether the packet was successfully sent to the radio.
+    /// A result resolving to a `SentPacket` handle, which can be used to wait for an acknowledgement.
     ///
     /// # Examples
     ///
@@ -795,6 +1083,9 @@ impl ConnectedStreamApi<state::Configured> {
     ///
     /// let position = crate::protobufs::Position { ... };
     /// stream_api.send_position(packet_router, position, PacketDestination::Broadcast, true, 0).await?;
+    ///
+    /// let position = GeoPosition::from_degrees(47.3977, 8.5456)?.with_precision(13)?;
+    /// stream_api.send_position(packet_router, position, PacketDestination::Broadcast, true, 0).await?;
     /// ```
     ///
     /// # Errors
@@ -812,12 +1103,12 @@ impl ConnectedStreamApi<state::Configured> {
     >(
         &mut self,
         packet_router: &mut R,
-        position: crate::protobufs::Position,
+        position: impl Into<crate::protobufs::Position>,
         destination: PacketDestination,
         want_ack: bool,
         channel: MeshChannel,
-    ) -> Result<(), Error> {
-        let byte_data: EncodedMeshPacketData = position.encode_to_vec().into();
+    ) -> Result<SentPacket, Error> {
+        let byte_data: EncodedMeshPacketData = position.into().encode_to_vec().into();
 
         self.send_mesh_packet(
             packet_router,
@@ -831,9 +1122,7 @@ impl ConnectedStreamApi<state::Configured> {
             None,
             None,
         )
-        .await?;
-
-        Ok(())
+        .await
     }
 
     /// Updates the configuration of the radio to the specified configuration.
@@ -1135,47 +1424,47 @@ impl ConnectedStreamApi<state::Configured> {
     /// transaction on the radio. This is meant to avoid needing to wait for the radio
     /// to restart multiple times when updating multiple configuration fields.
     ///
-    /// After calling this method, any calls to `update_config`, `update_module_config`, or `update_channel_config`
-    /// will be buffered on the radio until the `commit_config_transaction` method is called. This will
-    /// then trigger a radio restart, and the buffered configuration updates will be applied.
+    /// This method returns a `ConfigTransaction` guard that mutably borrows this instance. Any
+    /// updates sent through the `update_config`, `update_module_config`, `update_channel_config` or
+    /// `update_user` methods of the guard will be buffered on the radio until the guard is consumed
+    /// by its `commit` method. This will then trigger a radio restart, and the buffered
+    /// configuration updates will be applied.
     ///
     /// **Note:** It is not supported to batch configuration, module configuration,
     /// and channel configuration updates together. These must be done in separate transactions.
     /// This is a limitation of the current firmware.
     ///
-    /// **Note:** It is the responsibility of the user of this library to avoid calling
-    /// this method multiple times. This will result in undefined radio behavior.
-    ///
     /// # Arguments
     ///
-    /// None
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
     ///
     /// # Returns
     ///
-    /// A result indicating whether the transaction start packet was successfully sent to the radio.
+    /// A result resolving to a `ConfigTransaction` guard if the transaction start packet was
+    /// successfully sent to the radio.
     ///
     /// # Examples
     ///
     /// ```
-    /// // Example 1: Use with `update_config` calls
-    /// stream_api.start_config_transaction().await?;
-    /// stream_api.update_config(packet_router, config_update_1).await?;
-    /// stream_api.update_config(packet_router, config_update_2).await?;
-    /// stream_api.commit_config_transaction().await?;
+    /// // Example 1: Batch multiple `update_config` calls
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.update_config(packet_router, config_update_1).await?;
+    /// transaction.update_config(packet_router, config_update_2).await?;
+    /// transaction.commit(packet_router).await?;
     ///
     /// // Example 2: Use with `set_local_config` call
-    /// stream_api.start_config_transaction().await?;
-    /// stream_api.set_local_config(packet_router, local_config).await?;
-    /// stream_api.commit_config_transaction().await?;
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.set_local_config(packet_router, local_config).await?;
+    /// transaction.commit(packet_router).await?;
     ///
     /// // Example 3: Updating module and channel configurations sequentially
-    /// stream_api.start_config_transaction().await?;
-    /// stream_api.update_module_config(packet_router, module_config).await?;
-    /// stream_api.commit_config_transaction().await?;
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.update_module_config(packet_router, module_config).await?;
+    /// transaction.commit(packet_router).await?;
     ///
-    /// stream_api.start_config_transaction().await?;
-    /// stream_api.update_channel_config(packet_router, channel_config).await?;
-    /// stream_api.commit_config_transaction().await?;
+    /// let mut transaction = stream_api.begin_edit_settings(packet_router).await?;
+    /// transaction.update_channel_config(packet_router, channel_config).await?;
+    /// transaction.commit(packet_router).await?;
     /// ```
     ///
     /// # Errors
@@ -1186,53 +1475,119 @@ impl ConnectedStreamApi<state::Configured> {
     ///
     /// None
     ///
-    pub async fn start_config_transaction(&mut self) -> Result<(), Error> {
-        let to_radio = protobufs::AdminMessage {
-            payload_variant: Some(protobufs::admin_message::PayloadVariant::BeginEditSettings(
-                true,
-            )),
+    pub async fn begin_edit_settings<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<ConfigTransaction<'_>, Error> {
+        self.begin_edit_settings_on(packet_router, AdminTarget::local())
+            .await
+    }
+
+    /// An internal helper method that starts a configuration transaction on the target node.
+    pub(crate) async fn begin_edit_settings_on<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        target: AdminTarget,
+    ) -> Result<ConfigTransaction<'_>, Error> {
+        self.send_admin_message_to(
+            packet_router,
+            target,
+            false,
+            protobufs::admin_message::PayloadVariant::BeginEditSettings(true),
+        )
+        .await?;
+
+        Ok(ConfigTransaction::new(self, target))
+    }
+
+    /// An internal helper method that sends an `AdminMessage` to the target node, with `want_ack`
+    /// set. Remote nodes only report the outcome of admin messages sent with `want_response` set.
+    pub(crate) async fn send_admin_message_to<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        target: AdminTarget,
+        want_response: bool,
+        payload_variant: protobufs::admin_message::PayloadVariant,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_packet(
+            packet_router,
+            target.destination(),
+            target.channel(),
+            want_response || !target.is_local(),
+            payload_variant,
+        )
+        .await
+    }
+
+    /// An internal helper method that wraps an `AdminMessage` in a `MeshPacket` on the `AdminApp`
+    /// port, and sends it with `want_ack` set.
+    async fn send_admin_packet<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        destination: PacketDestination,
+        channel: MeshChannel,
+        want_response: bool,
+        payload_variant: protobufs::admin_message::PayloadVariant,
+    ) -> Result<SentPacket, Error> {
+        let admin_message = protobufs::AdminMessage {
+            payload_variant: Some(payload_variant),
         };
 
-        let mut packet_buf = vec![];
-        to_radio.encode(&mut packet_buf)?;
-        self.send_raw(packet_buf.into()).await
+        self.send_mesh_packet(
+            packet_router,
+            admin_message.encode_to_vec().into(),
+            protobufs::PortNum::AdminApp,
+            destination,
+            channel,
+            true,
+            want_response,
+            false,
+            None,
+            None,
+        )
+        .await
     }
 
-    /// A method to tell the radio to complete a bulk configuration update.
-    ///
-    /// This method is intended to be used to batch multiple configuration updates into a single
-    /// transaction on the radio. This is meant to avoid needing to wait for the radio
-    /// to restart multiple times when updating multiple configuration fields.
-    ///
-    /// After calling this method, any calls to `update_config`, `update_module_config`, or `update_channel_config`
-    /// will be buffered on the radio until the `commit_config_transaction` method is called. This will
-    /// then trigger a radio restart, and the buffered configuration updates will be applied.
-    ///
-    /// **Note:** The radio will restart when it receives this packet.
+    /// A helper method to update multiple configuration fields at once.
     ///
-    /// **Note:** It is not supported to batch configuration, module configuration,
-    /// and channel configuration updates together. These must be done in separate transactions.
-    /// This is a limitation of the current firmware.
+    /// This method is intended to simplify the process of updating multiple configuration
+    /// fields at once. This method will call the `update_config` method for each configuration
+    /// field that is specified in the `LocalConfig` struct.
     ///
-    /// **Note:** It is the responsibility of the user of this library to avoid calling
-    /// this method multiple times, and to avoid calling this method without first caling the
-    /// `start_config_transaction` method. This will result in undefined radio behavior.
+    /// **Note:** Outside of a configuration transaction, the radio restarts after the first
+    /// update. To apply all updates at once, use the `set_local_config` method of the
+    /// `ConfigTransaction` guard returned by the `begin_edit_settings` method instead.
     ///
     /// # Arguments
     ///
-    /// None
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///     This router is used in the event a packet needs to be echoed.
+    /// * `local_config` - An instance of the `LocalConfig` struct to update the radio with.
     ///
     /// # Returns
     ///
-    /// A result indicating whether the transaction commit packet was successfully sent to the radio.
+    /// A result indicating whether the config was successfully sent to the radio.
     ///
     /// # Examples
     ///
     /// ```
-    /// stream_api.start_config_transaction().await?;
-    /// stream_api.update_config(packet_router, config_update_1).await?;
-    /// stream_api.update_config(packet_router, config_update_2).await?;
-    /// stream_api.commit_config_transaction().await?;
+    /// stream_api.set_local_config(packet_router, local_config).await?;
     /// ```
     ///
     /// # Errors
@@ -1243,322 +1598,1831 @@ impl ConnectedStreamApi<state::Configured> {
     ///
     /// None
     ///
-    pub async fn commit_config_transaction(&mut self) -> Result<(), Error> {
-        let to_radio = protobufs::AdminMessage {
-            payload_variant: Some(
-                protobufs::admin_message::PayloadVariant::CommitEditSettings(true),
-            ),
-        };
+    pub async fn set_local_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        local_config: protobufs::LocalConfig,
+    ) -> Result<(), Error> {
+        for config in split_local_config(local_config) {
+            self.update_config(packet_router, config).await?;
+        }
 
-        let mut packet_buf = vec![];
-        to_radio.encode(&mut packet_buf)?;
-        self.send_raw(packet_buf.into()).await
+        Ok(())
+    }
+
+    /// A helper method to update multiple module configuration fields at once.
+    ///
+    /// This method is intended to simplify the process of updating multiple module configuration
+    /// fields at once. This method will call the `update_module_config` method for each configuration
+    /// field that is specified in the `LocalModuleConfig` struct.
+    ///
+    /// **Note:** Outside of a configuration transaction, the radio restarts after the first
+    /// update. To apply all updates at once, use the `set_local_module_config` method of the
+    /// `ConfigTransaction` guard returned by the `begin_edit_settings` method instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///     This router is used in the event a packet needs to be echoed.
+    /// * `local_module_config` - An instance of the `LocalModuleConfig` struct to update the radio with.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the module config was successfully sent to the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api.set_local_module_config(packet_router, local_module_config).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn set_local_module_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        local_module_config: protobufs::LocalModuleConfig,
+    ) -> Result<(), Error> {
+        for module_config in split_local_module_config(local_module_config) {
+            self.update_module_config(packet_router, module_config)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// A helper method to update the configuration of multiple message channels at once.
+    ///
+    /// This method is intended to simplify the process of updating multiple channel configuration
+    /// fields at once. This method will call the `update_channel_config` method for each configuration
+    /// field that is specified in the list of `Channel` structs.
+    ///
+    /// **Note:** Outside of a configuration transaction, the radio restarts after the first
+    /// update. To apply all updates at once, use the `set_message_channel_config` method of the
+    /// `ConfigTransaction` guard returned by the `begin_edit_settings` method instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///     This router is used in the event a packet needs to be echoed.
+    /// * `channel_config` - A list of updates to make to radio channels.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the channel configuration updates were successfully sent to the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api.set_message_channel_config(packet_router, vec![ ... ]).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn set_message_channel_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        channel_config: Vec<protobufs::Channel>,
+    ) -> Result<(), Error> {
+        for channel in channel_config {
+            self.update_channel_config(packet_router, channel).await?;
+        }
+
+        Ok(())
+    }
+}
+
+// Public admin request API
+
+impl ConnectedStreamApi<state::Configured> {
+    /// Requests a section of the current configuration of the connected radio.
+    ///
+    /// This method sends a `GetConfigRequest` admin message with `want_response` set, and waits
+    /// for the admin response whose `request_id` matches the ID of the sent packet. This allows
+    /// reading the live configuration of the radio without repeating the `configure` handshake.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `config_type` - The section of the configuration to request.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the requested `Config`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let lora_config = stream_api
+    ///     .get_config(packet_router, protobufs::admin_message::ConfigType::LoraConfig, Duration::from_secs(10))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::ResponseTimeout` if no response is received
+    /// before `timeout` elapses, and with `Error::UnexpectedResponse` if the response does not
+    /// contain a configuration.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn get_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        config_type: protobufs::admin_message::ConfigType,
+        timeout: Duration,
+    ) -> Result<protobufs::Config, Error> {
+        self.request_admin_response(
+            packet_router,
+            AdminTarget::local(),
+            protobufs::admin_message::PayloadVariant::GetConfigRequest(config_type as i32),
+            timeout,
+            |response| match response {
+                protobufs::admin_message::PayloadVariant::GetConfigResponse(config) => Some(config),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    /// Requests a section of the current module configuration of the connected radio.
+    ///
+    /// This method sends a `GetModuleConfigRequest` admin message with `want_response` set, and waits
+    /// for the admin response whose `request_id` matches the ID of the sent packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `module_config_type` - The section of the module configuration to request.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the requested `ModuleConfig`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mqtt_config = stream_api
+    ///     .get_module_config(packet_router, protobufs::admin_message::ModuleConfigType::MqttConfig, Duration::from_secs(10))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::ResponseTimeout` if no response is received
+    /// before `timeout` elapses, and with `Error::UnexpectedResponse` if the response does not
+    /// contain a module configuration.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn get_module_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        module_config_type: protobufs::admin_message::ModuleConfigType,
+        timeout: Duration,
+    ) -> Result<protobufs::ModuleConfig, Error> {
+        self.request_admin_response(
+            packet_router,
+            AdminTarget::local(),
+            protobufs::admin_message::PayloadVariant::GetModuleConfigRequest(
+                module_config_type as i32,
+            ),
+            timeout,
+            |response| match response {
+                protobufs::admin_message::PayloadVariant::GetModuleConfigResponse(
+                    module_config,
+                ) => Some(module_config),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    /// Requests the configuration of a single message channel of the connected radio.
+    ///
+    /// This method sends a `GetChannelRequest` admin message with `want_response` set, and waits
+    /// for the admin response whose `request_id` matches the ID of the sent packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `channel` - The index of the channel to request.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the requested `Channel`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let primary_channel = stream_api
+    ///     .get_channel(packet_router, MeshChannel::new(0)?, Duration::from_secs(10))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::ResponseTimeout` if no response is received
+    /// before `timeout` elapses, and with `Error::UnexpectedResponse` if the response does not
+    /// contain a channel.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn get_channel<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        channel: MeshChannel,
+        timeout: Duration,
+    ) -> Result<protobufs::Channel, Error> {
+        // The firmware expects the channel index + 1, as a value of 0 would not be encoded
+        self.request_admin_response(
+            packet_router,
+            AdminTarget::local(),
+            protobufs::admin_message::PayloadVariant::GetChannelRequest(channel.channel() + 1),
+            timeout,
+            |response| match response {
+                protobufs::admin_message::PayloadVariant::GetChannelResponse(channel) => {
+                    Some(channel)
+                }
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    /// Requests the information on the user of the connected radio.
+    ///
+    /// This method sends a `GetOwnerRequest` admin message with `want_response` set, and waits
+    /// for the admin response whose `request_id` matches the ID of the sent packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `User` of the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let owner = stream_api.get_owner(packet_router, Duration::from_secs(10)).await?;
+    /// println!("Radio owned by {}", owner.long_name);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::ResponseTimeout` if no response is received
+    /// before `timeout` elapses, and with `Error::UnexpectedResponse` if the response does not
+    /// contain a user.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn get_owner<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        timeout: Duration,
+    ) -> Result<protobufs::User, Error> {
+        self.request_admin_response(
+            packet_router,
+            AdminTarget::local(),
+            protobufs::admin_message::PayloadVariant::GetOwnerRequest(true),
+            timeout,
+            |response| match response {
+                protobufs::admin_message::PayloadVariant::GetOwnerResponse(user) => Some(user),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    /// Requests metadata on the firmware and hardware of the connected radio.
+    ///
+    /// This method sends a `GetDeviceMetadataRequest` admin message with `want_response` set, and
+    /// waits for the admin response whose `request_id` matches the ID of the sent packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `DeviceMetadata` of the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let metadata = stream_api.get_metadata(packet_router, Duration::from_secs(10)).await?;
+    /// println!("Firmware version: {}", metadata.firmware_version);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::ResponseTimeout` if no response is received
+    /// before `timeout` elapses, and with `Error::UnexpectedResponse` if the response does not
+    /// contain device metadata.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn get_metadata<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        timeout: Duration,
+    ) -> Result<protobufs::DeviceMetadata, Error> {
+        self.request_admin_response(
+            packet_router,
+            AdminTarget::local(),
+            protobufs::admin_message::PayloadVariant::GetDeviceMetadataRequest(true),
+            timeout,
+            |response| match response {
+                protobufs::admin_message::PayloadVariant::GetDeviceMetadataResponse(metadata) => {
+                    Some(metadata)
+                }
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    /// Requests the status of the Wi-Fi, Ethernet, Bluetooth and serial connections of the connected radio.
+    ///
+    /// This method sends a `GetDeviceConnectionStatusRequest` admin message with `want_response` set,
+    /// and waits for the admin response whose `request_id` matches the ID of the sent packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `DeviceConnectionStatus` of the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let status = stream_api.get_connection_status(packet_router, Duration::from_secs(10)).await?;
+    /// println!("Wi-Fi status: {:?}", status.wifi);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::ResponseTimeout` if no response is received
+    /// before `timeout` elapses, and with `Error::UnexpectedResponse` if the response does not
+    /// contain a connection status.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn get_connection_status<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        timeout: Duration,
+    ) -> Result<protobufs::DeviceConnectionStatus, Error> {
+        self.request_admin_response(
+            packet_router,
+            AdminTarget::local(),
+            protobufs::admin_message::PayloadVariant::GetDeviceConnectionStatusRequest(true),
+            timeout,
+            |response| match response {
+                protobufs::admin_message::PayloadVariant::GetDeviceConnectionStatusResponse(
+                    status,
+                ) => Some(status),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    /// An internal helper method that sends an admin request to the target node, and waits for
+    /// the admin response from that node whose `request_id` matches the ID of the sent packet.
+    /// The `extract` closure returns `None` if the response does not contain the expected payload.
+    /// Fails with `Error::RequestNak` as soon as the mesh reports that the request was not delivered.
+    pub(crate) async fn request_admin_response<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+        T,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        target: AdminTarget,
+        request: protobufs::admin_message::PayloadVariant,
+        timeout: Duration,
+        extract: impl Fn(protobufs::admin_message::PayloadVariant) -> Option<T>,
+    ) -> Result<T, Error> {
+        // Subscribe before sending the request to avoid missing early responses
+        let mut packet_rx = self.packet_broadcast_tx.subscribe();
+
+        let admin_packet = protobufs::AdminMessage {
+            payload_variant: Some(request),
+        };
+
+        let request_id = self
+            .dispatch_mesh_packet(
+                packet_router,
+                admin_packet.encode_to_vec().into(),
+                protobufs::PortNum::AdminApp,
+                target.destination(),
+                target.channel(),
+                !target.is_local(),
+                true,
+                false,
+                None,
+                None,
+                None,
+            )
+            .await?;
+
+        wait_for_response(&mut packet_rx, request_id, timeout, |packet| {
+            if let Some(e) = routing_error(packet, request_id) {
+                return Some(Err(e));
+            }
+
+            let (mesh_packet, data) =
+                decoded_response(packet, request_id, protobufs::PortNum::AdminApp)?;
+
+            // Only the target node is allowed to answer a remote request
+            if target
+                .node_id()
+                .is_some_and(|node_id| node_id != mesh_packet.from)
+            {
+                return None;
+            }
+
+            let response = match protobufs::AdminMessage::decode(data.payload.as_slice()) {
+                Ok(response) => response,
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            Some(
+                response
+                    .payload_variant
+                    .and_then(&extract)
+                    .ok_or(Error::UnexpectedResponse { request_id }),
+            )
+        })
+        .await
+    }
+}
+
+// Public device action API
+
+/// Converts a delay into the number of seconds expected by delayed admin actions.
+pub(crate) fn delay_secs(delay: Duration) -> i32 {
+    i32::try_from(delay.as_secs()).unwrap_or(i32::MAX)
+}
+
+impl ConnectedStreamApi<state::Configured> {
+    /// Tells the connected radio to reboot after the specified delay.
+    ///
+    /// The device actions of this section only target the connected radio. Remote nodes are
+    /// rebooted, shut down or reset through the handle returned by the `remote_admin` method,
+    /// such as `RemoteAdmin::reboot`.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `delay` - The amount of time the radio waits before rebooting, rounded down to whole seconds.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle. The `wait_for_reboot` method of the handle can
+    /// be used to wait for the reboot to complete.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api
+    ///     .reboot(packet_router, Duration::from_secs(5))
+    ///     .await?
+    ///     .wait_for_reboot(Duration::from_secs(60))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn reboot<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        delay: Duration,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message_to(
+            packet_router,
+            AdminTarget::local(),
+            false,
+            protobufs::admin_message::PayloadVariant::RebootSeconds(delay_secs(delay)),
+        )
+        .await
+    }
+
+    /// Tells the connected radio to reboot into its OTA firmware after the specified delay.
+    /// This is required before sending new firmware to the radio over Bluetooth.
+    ///
+    /// **Note:** This is only supported by ESP32 devices.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `delay` - The amount of time the radio waits before rebooting, rounded down to whole seconds.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api
+    ///     .reboot_ota(packet_router, Duration::from_secs(5))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn reboot_ota<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        delay: Duration,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message_to(
+            packet_router,
+            AdminTarget::local(),
+            false,
+            protobufs::admin_message::PayloadVariant::RebootOtaSeconds(delay_secs(delay)),
+        )
+        .await
+    }
+
+    /// Cancels a pending reboot of the connected radio.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api.cancel_reboot(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn cancel_reboot<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message_to(
+            packet_router,
+            AdminTarget::local(),
+            false,
+            protobufs::admin_message::PayloadVariant::RebootSeconds(-1),
+        )
+        .await
+    }
+
+    /// Tells the connected radio to shut down after the specified delay.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `delay` - The amount of time the radio waits before shutting down, rounded down to whole seconds.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api
+    ///     .shutdown(packet_router, Duration::from_secs(5))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn shutdown<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        delay: Duration,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message_to(
+            packet_router,
+            AdminTarget::local(),
+            false,
+            protobufs::admin_message::PayloadVariant::ShutdownSeconds(delay_secs(delay)),
+        )
+        .await
+    }
+
+    /// Cancels a pending shutdown of the connected radio.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api.cancel_shutdown(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn cancel_shutdown<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message_to(
+            packet_router,
+            AdminTarget::local(),
+            false,
+            protobufs::admin_message::PayloadVariant::ShutdownSeconds(-1),
+        )
+        .await
+    }
+
+    /// Tells the connected radio to return all device state and configuration to factory
+    /// defaults, and to clear its Bluetooth bonds. The radio will reboot once reset.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle. The `wait_for_reboot` method of the handle can
+    /// be used to wait for the reset to complete.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api
+    ///     .factory_reset_device(packet_router)
+    ///     .await?
+    ///     .wait_for_reboot(Duration::from_secs(60))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn factory_reset_device<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message_to(
+            packet_router,
+            AdminTarget::local(),
+            false,
+            protobufs::admin_message::PayloadVariant::FactoryResetDevice(1),
+        )
+        .await
+    }
+
+    /// Tells the connected radio to return all device state and configuration to factory
+    /// defaults, while preserving its Bluetooth bonds. The radio will reboot once reset.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle. The `wait_for_reboot` method of the handle can
+    /// be used to wait for the reset to complete.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api
+    ///     .factory_reset_config(packet_router)
+    ///     .await?
+    ///     .wait_for_reboot(Duration::from_secs(60))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn factory_reset_config<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message_to(
+            packet_router,
+            AdminTarget::local(),
+            false,
+            protobufs::admin_message::PayloadVariant::FactoryResetConfig(1),
+        )
+        .await
+    }
+
+    /// Tells the connected radio to clear its node database.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api.reset_nodedb(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn reset_nodedb<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message_to(
+            packet_router,
+            AdminTarget::local(),
+            false,
+            protobufs::admin_message::PayloadVariant::NodedbReset(1),
+        )
+        .await
+    }
+
+    /// Tells the connected radio to enter (UF2) DFU mode, in which new firmware can be flashed.
+    ///
+    /// **Note:** This is only supported by NRF52 devices.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api.enter_dfu_mode(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn enter_dfu_mode<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message_to(
+            packet_router,
+            AdminTarget::local(),
+            false,
+            protobufs::admin_message::PayloadVariant::EnterDfuModeRequest(true),
+        )
+        .await
+    }
+
+    /// Tells a simulated radio to exit. This is only supported by the simulator (Portduino)
+    /// firmware build, such as the Meshtastic Docker image.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to a `SentPacket` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api.exit_simulator(packet_router).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the packet fails to send.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn exit_simulator<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+    ) -> Result<SentPacket, Error> {
+        self.send_admin_message_to(
+            packet_router,
+            AdminTarget::local(),
+            false,
+            protobufs::admin_message::PayloadVariant::ExitSimulator(true),
+        )
+        .await
+    }
+}
+
+// Public channel URL API
+
+impl ConnectedStreamApi<state::Configured> {
+    /// Applies the channels and LoRa configuration contained in a channel sharing URL to the
+    /// connected radio, within a single configuration transaction.
+    ///
+    /// URLs of the `https://meshtastic.org/e/#...` form replace all channels of the radio. The first
+    /// channel in the URL becomes the primary channel, the remaining channels become secondary
+    /// channels, all other channel slots are disabled, and the LoRa configuration contained in the
+    /// URL is applied.
+    ///
+    /// URLs of the `https://meshtastic.org/e/?add=true#...` form add their channels to the radio
+    /// as secondary channels. The existing channels are all requested from the radio at once,
+    /// channels that already exist with the same name and key are skipped, and the LoRa
+    /// configuration of the radio is left unchanged.
+    ///
+    /// **Note:** The radio will restart once the transaction is committed.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `url` - The channel sharing URL to apply.
+    /// * `timeout` - The maximum amount of time to wait for the existing channels to be returned
+    ///     by the radio. This is only used for URLs that add channels.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the channels were successfully sent to the radio.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// stream_api
+    ///     .set_channel_url(
+    ///         packet_router,
+    ///         "https://meshtastic.org/e/#CgMSAQE",
+    ///         Duration::from_secs(10),
+    ///     )
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidChannelUrl` or `Error::DecodeError` if the URL cannot be decoded,
+    /// with `Error::ChannelSlotsExhausted` if the radio does not have enough free channel slots for
+    /// the channels being added, and if any of the packets fail to send or any of the existing
+    /// channels cannot be requested.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn set_channel_url<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        url: &str,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let ChannelUrl {
+            channel_set,
+            add_only,
+        } = parse_channel_url(url)?;
+
+        let (channels, lora_config) = if add_only {
+            let existing = self.get_all_channels(packet_router, timeout).await?;
+
+            (added_channels(&existing, &channel_set.settings)?, None)
+        } else {
+            (
+                replacement_channels(&channel_set.settings),
+                channel_set.lora_config,
+            )
+        };
+
+        let mut transaction = self.begin_edit_settings(packet_router).await?;
+
+        for channel in channels {
+            transaction
+                .update_channel_config(packet_router, channel)
+                .await?;
+        }
+
+        if let Some(lora_config) = lora_config {
+            transaction
+                .update_config(
+                    packet_router,
+                    protobufs::Config {
+                        payload_variant: Some(protobufs::config::PayloadVariant::Lora(lora_config)),
+                    },
+                )
+                .await?;
+        }
+
+        transaction.commit(packet_router).await?;
+
+        Ok(())
+    }
+
+    /// An internal helper method that requests every channel slot of the connected radio at once,
+    /// and waits for all of the responses. The channels are returned in slot order.
+    async fn get_all_channels<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        timeout: Duration,
+    ) -> Result<Vec<protobufs::Channel>, Error> {
+        // Subscribe before sending the requests to avoid missing early responses
+        let mut packet_rx = self.packet_broadcast_tx.subscribe();
+
+        let mut request_ids = Vec::with_capacity(MAX_CHANNEL_COUNT);
+        for index in 0..MAX_CHANNEL_COUNT as u32 {
+            // The firmware expects the channel index + 1, as a value of 0 would not be encoded
+            let request = protobufs::AdminMessage {
+                payload_variant: Some(protobufs::admin_message::PayloadVariant::GetChannelRequest(
+                    index + 1,
+                )),
+            };
+
+            let request_id = self
+                .dispatch_mesh_packet(
+                    packet_router,
+                    request.encode_to_vec().into(),
+                    protobufs::PortNum::AdminApp,
+                    PacketDestination::Local,
+                    MeshChannel::new(0)?,
+                    false,
+                    true,
+                    false,
+                    None,
+                    None,
+                    None,
+                )
+                .await?;
+
+            request_ids.push(request_id);
+        }
+
+        let mut channels = vec![None; MAX_CHANNEL_COUNT];
+
+        wait_for_response(&mut packet_rx, request_ids[0], timeout, |packet| {
+            let (slot, data) = request_ids
+                .iter()
+                .enumerate()
+                .find_map(|(slot, request_id)| {
+                    decoded_response(packet, *request_id, protobufs::PortNum::AdminApp)
+                        .map(|(_, data)| (slot, data))
+                })?;
+
+            let response = match protobufs::AdminMessage::decode(data.payload.as_slice()) {
+                Ok(response) => response,
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            match response.payload_variant {
+                Some(protobufs::admin_message::PayloadVariant::GetChannelResponse(channel)) => {
+                    channels[slot] = Some(channel);
+                }
+                _ => {
+                    return Some(Err(Error::UnexpectedResponse {
+                        request_id: request_ids[slot],
+                    }))
+                }
+            }
+
+            // * Keep waiting until every channel slot has been returned
+            if channels.iter().any(Option::is_none) {
+                return None;
+            }
+
+            Some(Ok(channels
+                .iter_mut()
+                .flatten()
+                .map(std::mem::take)
+                .collect()))
+        })
+        .await
+    }
+}
+
+// Public remote administration API
+
+impl ConnectedStreamApi<state::Configured> {
+    /// Returns a handle to administer a remote node over the mesh.
+    ///
+    /// Requests sent through the handle are sent as `AdminApp` packets to the remote node on the
+    /// specified admin channel, with `want_ack` and `want_response` set. Setters and device actions
+    /// return a `SentPacket` handle to track the acknowledgement of the remote node, and getters
+    /// wait for the response of the remote node.
+    ///
+    /// **Note:** The remote node only accepts admin messages received on a channel named `admin`.
+    /// Both radios must share this channel, with the same name and key, and `admin_channel` must be
+    /// the index of that channel on the connected radio. Any node that shares the channel can
+    /// administer the remote node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - The ID of the node to administer.
+    /// * `admin_channel` - The index of the `admin` channel on the connected radio.
+    ///
+    /// # Returns
+    ///
+    /// A `RemoteAdmin` handle that mutably borrows the `ConnectedStreamApi` instance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut remote = stream_api.remote_admin(NodeId::new(0x1234abcd), MeshChannel::new(1)?);
+    ///
+    /// let owner = remote.get_owner(packet_router, Duration::from_secs(30)).await?;
+    /// remote
+    ///     .reboot(packet_router, Duration::from_secs(5))
+    ///     .await?
+    ///     .wait_for_ack(Duration::from_secs(30))
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn remote_admin(&mut self, node_id: NodeId, admin_channel: MeshChannel) -> RemoteAdmin<'_> {
+        RemoteAdmin::new(self, node_id, admin_channel)
     }
+}
 
-    /// A helper method to update multiple configuration fields at once.
+// Public traceroute API
+
+impl ConnectedStreamApi<state::Configured> {
+    /// Traces the route that packets take through the mesh to the specified node.
     ///
-    /// This method is intended to simplify the process of updating multiple configuration
-    /// fields at once. This method will call the `update_config` method for each configuration
-    /// field that is specified in the `LocalConfig` struct. This method is intended
-    /// to be used with the `start_config_transaction` and `commit_config_transaction` methods.
+    /// This method sends an empty `RouteDiscovery` message on the `TracerouteApp` port with
+    /// `want_response` set. Every node relaying the request appends its ID to the route, and the
+    /// destination answers with the recorded route. This method waits for the response whose
+    /// `request_id` matches the ID of the sent packet.
+    ///
+    /// **Note:** The current protobuf definitions only record the route towards the destination,
+    /// so the return route and the per-hop SNR of the result are not reported.
     ///
     /// # Arguments
     ///
     /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
-    ///     This router is used in the event a packet needs to be echoed.
-    /// * `local_config` - An instance of the `LocalConfig` struct to update the radio with.
+    /// * `destination` - The ID of the node to trace the route to.
+    /// * `channel` - The message channel to send the request on, which the destination must share.
+    /// * `hop_limit` - The maximum number of hops the request can be relayed over [0..7], where `0`
+    ///     uses the hop limit configured on the radio.
+    /// * `timeout` - The maximum amount of time to wait for the response.
     ///
     /// # Returns
     ///
-    /// A result indicating whether the config was successfully sent to the radio.
+    /// A result resolving to the `TracerouteResult` of the request.
     ///
     /// # Examples
     ///
     /// ```
-    /// stream_api.start_config_transaction().await?;
-    /// stream_api.set_local_config(packet_router, local_config).await?;
-    /// stream_api.commit_config_transaction().await?;
+    /// let result = stream_api
+    ///     .traceroute(
+    ///         packet_router,
+    ///         NodeId::new(0x1234abcd),
+    ///         MeshChannel::new(0)?,
+    ///         3,
+    ///         Duration::from_secs(60),
+    ///     )
+    ///     .await?;
+    ///
+    /// for hop in result.route_towards {
+    ///     println!("{} (SNR {:?})", hop.node_id, hop.snr);
+    /// }
     /// ```
     ///
     /// # Errors
     ///
-    /// Fails if the packet fails to send.
+    /// Fails with `Error::InvalidHopLimit` if `hop_limit` is greater than 7, if the request fails to
+    /// send, with `Error::RequestNak` if the mesh reports that the destination cannot be reached,
+    /// and with `Error::ResponseTimeout` if no response is received before `timeout` elapses.
     ///
     /// # Panics
     ///
     /// None
     ///
-    pub async fn set_local_config<
+    pub async fn traceroute<
         M,
         E: Display + std::error::Error + Send + Sync + 'static,
         R: PacketRouter<M, E>,
     >(
         &mut self,
         packet_router: &mut R,
-        local_config: protobufs::LocalConfig,
-    ) -> Result<(), Error> {
-        if let Some(c) = local_config.bluetooth {
-            self.update_config(
-                packet_router,
-                protobufs::Config {
-                    payload_variant: Some(protobufs::config::PayloadVariant::Bluetooth(c)),
-                },
-            )
-            .await?;
+        destination: NodeId,
+        channel: MeshChannel,
+        hop_limit: u32,
+        timeout: Duration,
+    ) -> Result<TracerouteResult, Error> {
+        if hop_limit > MAX_HOP_LIMIT {
+            return Err(Error::InvalidHopLimit { hop_limit });
         }
 
-        if let Some(c) = local_config.device {
-            self.update_config(
-                packet_router,
-                protobufs::Config {
-                    payload_variant: Some(protobufs::config::PayloadVariant::Device(c)),
-                },
-            )
-            .await?;
-        }
+        // Subscribe before sending the request to avoid missing early responses
+        let mut packet_rx = self.packet_broadcast_tx.subscribe();
+        let own_node_id = packet_router.source_node_id();
 
-        if let Some(c) = local_config.display {
-            self.update_config(
+        let request_id = self
+            .dispatch_mesh_packet(
                 packet_router,
-                protobufs::Config {
-                    payload_variant: Some(protobufs::config::PayloadVariant::Display(c)),
-                },
+                protobufs::RouteDiscovery::default().encode_to_vec().into(),
+                protobufs::PortNum::TracerouteApp,
+                PacketDestination::Node(destination),
+                channel,
+                false,
+                true,
+                false,
+                None,
+                None,
+                Some(hop_limit),
             )
             .await?;
-        }
 
-        if let Some(c) = local_config.lora {
-            self.update_config(
-                packet_router,
-                protobufs::Config {
-                    payload_variant: Some(protobufs::config::PayloadVariant::Lora(c)),
-                },
+        wait_for_response(&mut packet_rx, request_id, timeout, |packet| {
+            if let Some(e) = routing_error(packet, request_id) {
+                return Some(Err(e));
+            }
+
+            let (mesh_packet, data) =
+                decoded_response(packet, request_id, protobufs::PortNum::TracerouteApp)?;
+
+            if destination != mesh_packet.from {
+                return None;
+            }
+
+            Some(
+                protobufs::RouteDiscovery::decode(data.payload.as_slice())
+                    .map(|route_discovery| {
+                        TracerouteResult::from_route_discovery(
+                            own_node_id,
+                            destination,
+                            &route_discovery,
+                        )
+                    })
+                    .map_err(Error::from),
             )
-            .await?;
-        }
+        })
+        .await
+    }
+}
 
-        if let Some(c) = local_config.network {
-            self.update_config(
-                packet_router,
-                protobufs::Config {
-                    payload_variant: Some(protobufs::config::PayloadVariant::Network(c)),
-                },
-            )
-            .await?;
-        }
+// Public telemetry API
 
-        if let Some(c) = local_config.position {
-            self.update_config(
-                packet_router,
-                protobufs::Config {
-                    payload_variant: Some(protobufs::config::PayloadVariant::Position(c)),
-                },
-            )
-            .await?;
-        }
+impl ConnectedStreamApi<state::Configured> {
+    /// Requests the current metrics of the given kind from a node.
+    ///
+    /// This method sends an empty `Telemetry` message of the requested kind on the `TelemetryApp`
+    /// port with `want_response` set, and waits for the response of the node whose `request_id`
+    /// matches the ID of the sent packet.
+    ///
+    /// **Note:** Nodes only answer requests for kinds of metrics they measure, so requesting
+    /// environment, air quality or power metrics from a node without such a sensor times out.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `node_id` - The ID of the node to request the metrics from.
+    /// * `kind` - The `TelemetryKind` of the requested metrics.
+    /// * `channel` - The message channel to send the request on, which the node must share.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `TelemetryReport` sent by the node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let report = stream_api
+    ///     .request_telemetry(
+    ///         packet_router,
+    ///         NodeId::new(0x1234abcd),
+    ///         TelemetryKind::Device,
+    ///         MeshChannel::new(0)?,
+    ///         Duration::from_secs(60),
+    ///     )
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::ResponseTimeout` if no response is received
+    /// before `timeout` elapses, and with `Error::UnexpectedResponse` if the response does not
+    /// contain metrics of the requested kind.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn request_telemetry<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        node_id: NodeId,
+        kind: TelemetryKind,
+        channel: MeshChannel,
+        timeout: Duration,
+    ) -> Result<TelemetryReport, Error> {
+        // Subscribe before sending the request to avoid missing early responses
+        let mut packet_rx = self.packet_broadcast_tx.subscribe();
 
-        if let Some(c) = local_config.power {
-            self.update_config(
+        let request_id = self
+            .dispatch_mesh_packet(
                 packet_router,
-                protobufs::Config {
-                    payload_variant: Some(protobufs::config::PayloadVariant::Power(c)),
-                },
+                kind.request().encode_to_vec().into(),
+                protobufs::PortNum::TelemetryApp,
+                PacketDestination::Node(node_id),
+                channel,
+                false,
+                true,
+                false,
+                None,
+                None,
+                None,
             )
             .await?;
-        }
 
-        Ok(())
+        wait_for_response(&mut packet_rx, request_id, timeout, |packet| {
+            let (mesh_packet, _) =
+                decoded_response(packet, request_id, protobufs::PortNum::TelemetryApp)?;
+
+            if node_id != mesh_packet.from {
+                return None;
+            }
+
+            Some(match TelemetryReport::from_mesh_packet(mesh_packet) {
+                Ok(Some(report)) if report.metrics.kind() == kind => Ok(report),
+                Ok(_) => Err(Error::UnexpectedResponse { request_id }),
+                Err(e) => Err(e),
+            })
+        })
+        .await
     }
+}
 
-    /// A helper method to update multiple module configuration fields at once.
+// Public store and forward API
+
+impl ConnectedStreamApi<state::Configured> {
+    /// Requests a replay of the messages stored by a Store & Forward server.
     ///
-    /// This method is intended to simplify the process of updating multiple module configuration
-    /// fields at once. This method will call the `update_module_config` method for each configuration
-    /// field that is specified in the `LocalModuleConfig` struct. This method is intended
-    /// to be used with the `start_config_transaction` and `commit_config_transaction` methods.
+    /// This method sends a `ClientHistory` request to the server, and waits for the `RouterHistory`
+    /// response announcing the number of messages that will be replayed. The replayed messages are
+    /// then received as `StoreForwardApp` packets, which are converted into `MeshEvent::StoredText`
+    /// events by the `MeshEvent` conversions and can be parsed with `StoredTextMessage`.
+    ///
+    /// **Note:** Servers ignore history requests received on a channel using the default key.
     ///
     /// # Arguments
     ///
     /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
-    ///     This router is used in the event a packet needs to be echoed.
-    /// * `local_module_config` - An instance of the `LocalModuleConfig` struct to update the radio with.
+    /// * `server` - The ID of the node running the Store & Forward server.
+    /// * `window` - How far back to replay messages from, rounded up to whole minutes. `None` uses
+    ///     the default window of the server.
+    /// * `last_request` - The `last_request` index of a previous history response, to skip the
+    ///     messages that were already replayed.
+    /// * `channel` - The message channel to send the request on, which the server must share.
+    /// * `timeout` - The maximum amount of time to wait for the response.
     ///
     /// # Returns
     ///
-    /// A result indicating whether the module config was successfully sent to the radio.
+    /// A result resolving to the `History` response of the server.
     ///
     /// # Examples
     ///
     /// ```
-    /// stream_api.start_config_transaction().await?;
-    /// stream_api.set_local_module_config(packet_router, local_module_config).await?;
-    /// stream_api.commit_config_transaction().await?;
+    /// let history = stream_api
+    ///     .request_store_forward_history(
+    ///         packet_router,
+    ///         server.node_id,
+    ///         Some(Duration::from_secs(60 * 60)),
+    ///         None,
+    ///         MeshChannel::new(0)?,
+    ///         Duration::from_secs(60),
+    ///     )
+    ///     .await?;
+    ///
+    /// println!("Replaying {} messages", history.history_messages);
     /// ```
     ///
     /// # Errors
     ///
-    /// Fails if the packet fails to send.
+    /// Fails if the request fails to send, with `Error::StoreForwardServerBusy` if the server is
+    /// busy, with `Error::UnexpectedResponse` if the server reports an error, and with
+    /// `Error::ResponseTimeout` if no response is received before `timeout` elapses.
     ///
     /// # Panics
     ///
     /// None
     ///
-    pub async fn set_local_module_config<
+    #[allow(clippy::too_many_arguments)]
+    pub async fn request_store_forward_history<
         M,
         E: Display + std::error::Error + Send + Sync + 'static,
         R: PacketRouter<M, E>,
     >(
         &mut self,
         packet_router: &mut R,
-        local_module_config: protobufs::LocalModuleConfig,
-    ) -> Result<(), Error> {
-        if let Some(c) = local_module_config.audio {
-            self.update_module_config(
-                packet_router,
-                protobufs::ModuleConfig {
-                    payload_variant: Some(protobufs::module_config::PayloadVariant::Audio(c)),
-                },
-            )
-            .await?;
-        }
-
-        if let Some(c) = local_module_config.canned_message {
-            self.update_module_config(
-                packet_router,
-                protobufs::ModuleConfig {
-                    payload_variant: Some(protobufs::module_config::PayloadVariant::CannedMessage(
-                        c,
-                    )),
-                },
-            )
-            .await?;
-        }
-
-        if let Some(c) = local_module_config.external_notification {
-            self.update_module_config(
-                packet_router,
-                protobufs::ModuleConfig {
-                    payload_variant: Some(
-                        protobufs::module_config::PayloadVariant::ExternalNotification(c),
-                    ),
+        server: NodeId,
+        window: Option<Duration>,
+        last_request: Option<u32>,
+        channel: MeshChannel,
+        timeout: Duration,
+    ) -> Result<protobufs::store_and_forward::History, Error> {
+        let request = store_forward_message(
+            protobufs::store_and_forward::RequestResponse::ClientHistory,
+            Some(protobufs::store_and_forward::Variant::History(
+                protobufs::store_and_forward::History {
+                    history_messages: 0,
+                    window: history_window_minutes(window),
+                    last_request: last_request.unwrap_or(0),
                 },
-            )
-            .await?;
-        }
+            )),
+        );
 
-        if let Some(c) = local_module_config.mqtt {
-            self.update_module_config(
-                packet_router,
-                protobufs::ModuleConfig {
-                    payload_variant: Some(protobufs::module_config::PayloadVariant::Mqtt(c)),
-                },
-            )
-            .await?;
-        }
+        self.request_store_forward_response(
+            packet_router,
+            server,
+            request,
+            channel,
+            timeout,
+            |response| match response {
+                protobufs::store_and_forward::Variant::History(history) => Some(history),
+                _ => None,
+            },
+        )
+        .await
+    }
 
-        if let Some(c) = local_module_config.range_test {
-            self.update_module_config(
-                packet_router,
-                protobufs::ModuleConfig {
-                    payload_variant: Some(protobufs::module_config::PayloadVariant::RangeTest(c)),
-                },
-            )
-            .await?;
-        }
+    /// Requests the statistics of a Store & Forward server.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
+    /// * `server` - The ID of the node running the Store & Forward server.
+    /// * `channel` - The message channel to send the request on, which the server must share.
+    /// * `timeout` - The maximum amount of time to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `Statistics` of the server.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stats = stream_api
+    ///     .request_store_forward_stats(
+    ///         packet_router,
+    ///         server.node_id,
+    ///         MeshChannel::new(0)?,
+    ///         Duration::from_secs(60),
+    ///     )
+    ///     .await?;
+    ///
+    /// println!("{} of {} messages stored", stats.messages_saved, stats.messages_max);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails to send, with `Error::StoreForwardServerBusy` if the server is
+    /// busy, with `Error::UnexpectedResponse` if the server reports an error, and with
+    /// `Error::ResponseTimeout` if no response is received before `timeout` elapses.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn request_store_forward_stats<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        server: NodeId,
+        channel: MeshChannel,
+        timeout: Duration,
+    ) -> Result<protobufs::store_and_forward::Statistics, Error> {
+        let request = store_forward_message(
+            protobufs::store_and_forward::RequestResponse::ClientStats,
+            None,
+        );
 
-        if let Some(c) = local_module_config.remote_hardware {
-            self.update_module_config(
-                packet_router,
-                protobufs::ModuleConfig {
-                    payload_variant: Some(
-                        protobufs::module_config::PayloadVariant::RemoteHardware(c),
-                    ),
-                },
-            )
-            .await?;
-        }
+        self.request_store_forward_response(
+            packet_router,
+            server,
+            request,
+            channel,
+            timeout,
+            |response| match response {
+                protobufs::store_and_forward::Variant::Stats(stats) => Some(stats),
+                _ => None,
+            },
+        )
+        .await
+    }
 
-        if let Some(c) = local_module_config.serial {
-            self.update_module_config(
-                packet_router,
-                protobufs::ModuleConfig {
-                    payload_variant: Some(protobufs::module_config::PayloadVariant::Serial(c)),
-                },
-            )
-            .await?;
-        }
+    /// An internal helper method that sends a request to a Store & Forward server, and waits for
+    /// the first response of the server that `extract` accepts. Servers do not set the
+    /// `request_id` of their responses, so responses are matched on the server node instead.
+    async fn request_store_forward_response<
+        M,
+        E: Display + std::error::Error + Send + Sync + 'static,
+        R: PacketRouter<M, E>,
+        T,
+    >(
+        &mut self,
+        packet_router: &mut R,
+        server: NodeId,
+        request: protobufs::StoreAndForward,
+        channel: MeshChannel,
+        timeout: Duration,
+        extract: impl Fn(protobufs::store_and_forward::Variant) -> Option<T>,
+    ) -> Result<T, Error> {
+        use protobufs::store_and_forward::RequestResponse;
 
-        if let Some(c) = local_module_config.store_forward {
-            self.update_module_config(
-                packet_router,
-                protobufs::ModuleConfig {
-                    payload_variant: Some(protobufs::module_config::PayloadVariant::StoreForward(
-                        c,
-                    )),
-                },
-            )
-            .await?;
-        }
+        // Subscribe before sending the request to avoid missing early responses
+        let mut packet_rx = self.packet_broadcast_tx.subscribe();
 
-        if let Some(c) = local_module_config.telemetry {
-            self.update_module_config(
+        let request_id = self
+            .dispatch_mesh_packet(
                 packet_router,
-                protobufs::ModuleConfig {
-                    payload_variant: Some(protobufs::module_config::PayloadVariant::Telemetry(c)),
-                },
+                request.encode_to_vec().into(),
+                protobufs::PortNum::StoreForwardApp,
+                PacketDestination::Node(server),
+                channel,
+                false,
+                false,
+                false,
+                None,
+                None,
+                None,
             )
             .await?;
-        }
 
-        Ok(())
+        wait_for_response(&mut packet_rx, request_id, timeout, |packet| {
+            let Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)) =
+                &packet.payload_variant
+            else {
+                return None;
+            };
+
+            if server != mesh_packet.from {
+                return None;
+            }
+
+            let response = match decode_store_forward(mesh_packet)? {
+                Ok(response) => response,
+                Err(e) => return Some(Err(e)),
+            };
+
+            match response.rr() {
+                RequestResponse::RouterBusy => Some(Err(Error::StoreForwardServerBusy {
+                    node_id: server.id(),
+                })),
+                RequestResponse::RouterError => Some(Err(Error::UnexpectedResponse { request_id })),
+                _ => response.variant.and_then(&extract).map(Ok),
+            }
+        })
+        .await
     }
+}
 
-    /// A helper method to update the configuration of multiple message channels at once.
+// Public MQTT proxy API
+
+#[cfg(feature = "mqtt")]
+impl ConnectedStreamApi<state::Configured> {
+    /// Starts proxying the MQTT connection of the radio through the host.
     ///
-    /// This method is intended to simplify the process of updating multiple channel configuration
-    /// fields at once. This method will call the `update_channel_config` method for each configuration
-    /// field that is specified in the list of `Channel` structs. This method is intended
-    /// to be used with the `start_config_transaction` and `commit_config_transaction` methods.
+    /// The proxy connects to the broker configured in the `MqttConfig` of the snapshot, unless it
+    /// is overridden by the given options. It publishes the `MqttClientProxyMessage` packets sent
+    /// by the radio, subscribes to the channels of the radio that have downlink enabled, and
+    /// forwards the messages received from the broker to the radio. The radio only sends proxy
+    /// messages when `proxy_to_client_enabled` is set in its `MqttConfig`.
     ///
     /// # Arguments
     ///
-    /// * `packet_router` - A generic packet router field that implements the `PacketRouter` trait.
-    ///     This router is used in the event a packet needs to be echoed.
-    /// * `channel_config` - A list of updates to make to radio channels.
+    /// * `snapshot` - The `RadioSnapshot` returned by the `configure_and_wait` method, which holds
+    ///     the MQTT configuration and channels of the radio.
+    /// * `options` - The `MqttProxyOptions` overriding the broker settings of the radio.
     ///
     /// # Returns
     ///
-    /// A result indicating whether the channel configuration updates were successfully sent to the radio.
+    /// A result resolving to the running `MqttProxy`.
     ///
     /// # Examples
     ///
     /// ```
-    /// stream_api.start_config_transaction().await?;
-    /// stream_api.set_message_channel_config(packet_router, vec![ ... ]).await?;
-    /// stream_api.commit_config_transaction().await?;
+    /// let (stream_api, snapshot) = stream_api.configure_and_wait(config_id, timeout).await?;
+    /// let proxy = stream_api.start_mqtt_proxy(&snapshot, MqttProxyOptions::default())?;
+    ///
+    /// println!("Proxying {:?}", proxy.subscriptions());
     /// ```
     ///
     /// # Errors
     ///
-    /// Fails if the packet fails to send.
+    /// Fails with `Error::InvalidMqttBrokerAddress` if the broker address cannot be parsed.
     ///
     /// # Panics
     ///
     /// None
     ///
-    pub async fn set_message_channel_config<
-        M,
-        E: Display + std::error::Error + Send + Sync + 'static,
-        R: PacketRouter<M, E>,
-    >(
-        &mut self,
-        packet_router: &mut R,
-        channel_config: Vec<protobufs::Channel>,
-    ) -> Result<(), Error> {
-        for channel in channel_config {
-            self.update_channel_config(packet_router, channel).await?;
+    pub fn start_mqtt_proxy(
+        &self,
+        snapshot: &RadioSnapshot,
+        options: MqttProxyOptions,
+    ) -> Result<MqttProxy, Error> {
+        MqttProxy::start(self, snapshot, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connections::test_support::{
+        configured_stream_api, next_admin_packet, TestRouter, MY_NODE_NUM,
+    };
+
+    use super::*;
+
+    #[test]
+    fn converts_delays_to_whole_seconds() {
+        assert_eq!(delay_secs(Duration::from_millis(2_999)), 2);
+        assert_eq!(delay_secs(Duration::ZERO), 0);
+        assert_eq!(delay_secs(Duration::from_secs(u64::MAX)), i32::MAX);
+    }
+
+    #[tokio::test]
+    async fn sends_device_actions_to_connected_radio() {
+        use protobufs::admin_message::PayloadVariant;
+
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        stream_api
+            .reboot_ota(&mut router, Duration::from_millis(5_500))
+            .await
+            .unwrap();
+        stream_api.cancel_reboot(&mut router).await.unwrap();
+        stream_api
+            .shutdown(&mut router, Duration::from_secs(30))
+            .await
+            .unwrap();
+        stream_api.cancel_shutdown(&mut router).await.unwrap();
+        stream_api.reset_nodedb(&mut router).await.unwrap();
+        stream_api.enter_dfu_mode(&mut router).await.unwrap();
+        stream_api.exit_simulator(&mut router).await.unwrap();
+
+        for (index, expected) in [
+            PayloadVariant::RebootOtaSeconds(5),
+            PayloadVariant::RebootSeconds(-1),
+            PayloadVariant::ShutdownSeconds(30),
+            PayloadVariant::ShutdownSeconds(-1),
+            PayloadVariant::NodedbReset(1),
+            PayloadVariant::EnterDfuModeRequest(true),
+            PayloadVariant::ExitSimulator(true),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let (packet, payload_variant) = next_admin_packet(&mock_radio, index).await;
+
+            assert_eq!(packet.to, MY_NODE_NUM);
+            assert_eq!(packet.channel, 0);
+            assert!(packet.want_ack);
+            assert_eq!(payload_variant, expected);
         }
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn waits_for_reboot_after_device_actions() {
+        use protobufs::admin_message::PayloadVariant;
+
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        // The mock radio reports a reboot for each of these actions
+        stream_api
+            .reboot(&mut router, Duration::from_secs(5))
+            .await
+            .unwrap()
+            .wait_for_reboot(Duration::from_secs(1))
+            .await
+            .unwrap();
+        stream_api
+            .factory_reset_device(&mut router)
+            .await
+            .unwrap()
+            .wait_for_reboot(Duration::from_secs(1))
+            .await
+            .unwrap();
+        stream_api
+            .factory_reset_config(&mut router)
+            .await
+            .unwrap()
+            .wait_for_reboot(Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        for (index, expected) in [
+            PayloadVariant::RebootSeconds(5),
+            PayloadVariant::FactoryResetDevice(1),
+            PayloadVariant::FactoryResetConfig(1),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            assert_eq!(next_admin_packet(&mock_radio, index).await.1, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_reboot_is_not_confirmed() {
+        let (_mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        let sent_packet = stream_api.cancel_reboot(&mut router).await.unwrap();
+        let request_id = sent_packet.packet_id();
+        let result = sent_packet.wait_for_reboot(Duration::from_millis(50)).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::ResponseTimeout { request_id: id }) if id == request_id
+        ));
     }
 }
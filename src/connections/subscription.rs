@@ -0,0 +1,341 @@
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::errors_internal::{Error, InternalChannelError};
+use crate::protobufs;
+
+use super::events::MeshEvent;
+use super::wrappers::{mesh_channel::MeshChannel, NodeId};
+
+/// An enum that identifies the payload variant of a `FromRadio` packet, used to filter
+/// subscriptions on the kind of packet received from the radio.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FromRadioKind {
+    Packet,
+    MyInfo,
+    NodeInfo,
+    Config,
+    LogRecord,
+    ConfigComplete,
+    Rebooted,
+    ModuleConfig,
+    Channel,
+    QueueStatus,
+    XmodemPacket,
+    Metadata,
+    MqttClientProxyMessage,
+    FileInfo,
+}
+
+impl FromRadioKind {
+    /// Returns the kind of the given `FromRadio` packet, or `None` if the packet has no payload.
+    pub fn of(packet: &protobufs::FromRadio) -> Option<FromRadioKind> {
+        use protobufs::from_radio::PayloadVariant;
+
+        let kind = match packet.payload_variant.as_ref()? {
+            PayloadVariant::Packet(_) => FromRadioKind::Packet,
+            PayloadVariant::MyInfo(_) => FromRadioKind::MyInfo,
+            PayloadVariant::NodeInfo(_) => FromRadioKind::NodeInfo,
+            PayloadVariant::Config(_) => FromRadioKind::Config,
+            PayloadVariant::LogRecord(_) => FromRadioKind::LogRecord,
+            PayloadVariant::ConfigCompleteId(_) => FromRadioKind::ConfigComplete,
+            PayloadVariant::Rebooted(_) => FromRadioKind::Rebooted,
+            PayloadVariant::ModuleConfig(_) => FromRadioKind::ModuleConfig,
+            PayloadVariant::Channel(_) => FromRadioKind::Channel,
+            PayloadVariant::QueueStatus(_) => FromRadioKind::QueueStatus,
+            PayloadVariant::XmodemPacket(_) => FromRadioKind::XmodemPacket,
+            PayloadVariant::Metadata(_) => FromRadioKind::Metadata,
+            PayloadVariant::MqttClientProxyMessage(_) => FromRadioKind::MqttClientProxyMessage,
+            PayloadVariant::FileInfo(_) => FromRadioKind::FileInfo,
+        };
+
+        Some(kind)
+    }
+}
+
+/// A struct that selects the packets delivered to a `PacketSubscription`.
+///
+/// Each criterion accepts a packet if the packet matches any of the values added for that
+/// criterion, and a packet is delivered if it is accepted by every criterion. Criteria without
+/// any values accept every packet. The port, sender and channel criteria only accept mesh
+/// packets, so adding a value to any of them filters out all other `FromRadio` packets.
+///
+/// # Examples
+///
+/// ```
+/// // Text messages and positions sent by node 0x1234abcd on the primary channel
+/// let filter = PacketFilter::new()
+///     .port_num(protobufs::PortNum::TextMessageApp)
+///     .port_num(protobufs::PortNum::PositionApp)
+///     .from_node(NodeId::new(0x1234abcd))
+///     .channel(MeshChannel::new(0)?);
+///
+/// // Node database updates only
+/// let filter = PacketFilter::new().kind(FromRadioKind::NodeInfo);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PacketFilter {
+    kinds: Vec<FromRadioKind>,
+    port_nums: Vec<protobufs::PortNum>,
+    from_nodes: Vec<NodeId>,
+    channels: Vec<MeshChannel>,
+}
+
+impl PacketFilter {
+    /// Creates a filter that accepts every packet.
+    pub fn new() -> PacketFilter {
+        PacketFilter::default()
+    }
+
+    /// Accepts `FromRadio` packets with the given payload variant.
+    pub fn kind(mut self, kind: FromRadioKind) -> PacketFilter {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Accepts decoded mesh packets sent on the given port.
+    pub fn port_num(mut self, port_num: protobufs::PortNum) -> PacketFilter {
+        self.port_nums.push(port_num);
+        self
+    }
+
+    /// Accepts mesh packets sent by the given node.
+    pub fn from_node(mut self, node_id: NodeId) -> PacketFilter {
+        self.from_nodes.push(node_id);
+        self
+    }
+
+    /// Accepts mesh packets received on the given channel index.
+    pub fn channel(mut self, channel: MeshChannel) -> PacketFilter {
+        self.channels.push(channel);
+        self
+    }
+
+    /// Returns whether the given packet is accepted by the filter.
+    pub fn matches(&self, packet: &protobufs::FromRadio) -> bool {
+        if !self.kinds.is_empty()
+            && !FromRadioKind::of(packet).is_some_and(|kind| self.kinds.contains(&kind))
+        {
+            return false;
+        }
+
+        if self.port_nums.is_empty() && self.from_nodes.is_empty() && self.channels.is_empty() {
+            return true;
+        }
+
+        let Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)) =
+            &packet.payload_variant
+        else {
+            return false;
+        };
+
+        let port_matches = self.port_nums.is_empty()
+            || matches!(
+                &mesh_packet.payload_variant,
+                Some(protobufs::mesh_packet::PayloadVariant::Decoded(data))
+                    if self.port_nums.iter().any(|port_num| *port_num as i32 == data.portnum)
+            );
+
+        let from_matches = self.from_nodes.is_empty()
+            || self
+                .from_nodes
+                .iter()
+                .any(|node_id| *node_id == mesh_packet.from);
+
+        let channel_matches = self.channels.is_empty()
+            || self
+                .channels
+                .iter()
+                .any(|channel| channel.channel() == mesh_packet.channel);
+
+        port_matches && from_matches && channel_matches
+    }
+}
+
+/// A receiver of the packets received from the radio that are accepted by a `PacketFilter`.
+///
+/// This struct is created by the `subscribe` method of the `ConnectedStreamApi` struct. Every
+/// subscription receives its own copy of each packet, independently of the `PacketReceiver`
+/// returned by the `connect` method and of other subscriptions.
+///
+/// Packets are buffered for each subscription. If a subscription falls too far behind, the
+/// oldest packets are dropped, and the next call to `recv` reports how many packets were missed.
+#[derive(Debug)]
+pub struct PacketSubscription {
+    packet_rx: broadcast::Receiver<protobufs::FromRadio>,
+    filter: PacketFilter,
+}
+
+impl PacketSubscription {
+    /// Creates a subscription over the given packet broadcast.
+    pub(crate) fn new(
+        packet_rx: broadcast::Receiver<protobufs::FromRadio>,
+        filter: PacketFilter,
+    ) -> Self {
+        PacketSubscription { packet_rx, filter }
+    }
+
+    /// Returns the filter of the subscription.
+    pub fn filter(&self) -> &PacketFilter {
+        &self.filter
+    }
+
+    /// Waits for the next packet accepted by the filter of the subscription.
+    ///
+    /// # Arguments
+    ///
+    /// None
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the next accepted `FromRadio` packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut subscription = stream_api.subscribe(PacketFilter::new());
+    ///
+    /// loop {
+    ///     match subscription.recv().await {
+    ///         Ok(packet) => println!("Received packet: {:?}", packet),
+    ///         Err(Error::SubscriptionLagged { skipped }) => println!("Missed {skipped} packets"),
+    ///         Err(_) => break,
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::SubscriptionLagged` if packets were dropped because the subscription
+    /// fell behind, after which the subscription can still be used. Fails with
+    /// `InternalChannelError::ChannelClosedEarly` once the connection to the radio has been closed.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn recv(&mut self) -> Result<protobufs::FromRadio, Error> {
+        loop {
+            let packet = match self.packet_rx.recv().await {
+                Ok(packet) => packet,
+                Err(RecvError::Lagged(skipped)) => {
+                    return Err(Error::SubscriptionLagged { skipped })
+                }
+                Err(RecvError::Closed) => {
+                    return Err(Error::InternalChannelError(
+                        InternalChannelError::ChannelClosedEarly,
+                    ))
+                }
+            };
+
+            if self.filter.matches(&packet) {
+                return Ok(packet);
+            }
+        }
+    }
+
+    /// Waits for the next packet accepted by the filter of the subscription, and converts it
+    /// into a `MeshEvent`. See `recv`.
+    pub async fn recv_event(&mut self) -> Result<MeshEvent, Error> {
+        self.recv().await.map(MeshEvent::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh_packet(from: u32, channel: u32, port_num: protobufs::PortNum) -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(
+                protobufs::MeshPacket {
+                    from,
+                    channel,
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: port_num as i32,
+                            ..Default::default()
+                        },
+                    )),
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+
+    fn node_info() -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::NodeInfo(
+                protobufs::NodeInfo::default(),
+            )),
+        }
+    }
+
+    #[test]
+    fn filters_on_every_criterion() {
+        let text = mesh_packet(7, 1, protobufs::PortNum::TextMessageApp);
+
+        assert!(PacketFilter::new().matches(&text));
+        assert!(PacketFilter::new().matches(&node_info()));
+
+        let filter = PacketFilter::new()
+            .port_num(protobufs::PortNum::TextMessageApp)
+            .port_num(protobufs::PortNum::PositionApp)
+            .from_node(NodeId::new(7))
+            .channel(MeshChannel::new(1).unwrap());
+
+        assert!(filter.matches(&text));
+        assert!(filter.matches(&mesh_packet(7, 1, protobufs::PortNum::PositionApp)));
+        assert!(!filter.matches(&mesh_packet(7, 1, protobufs::PortNum::TelemetryApp)));
+        assert!(!filter.matches(&mesh_packet(8, 1, protobufs::PortNum::TextMessageApp)));
+        assert!(!filter.matches(&mesh_packet(7, 0, protobufs::PortNum::TextMessageApp)));
+        assert!(!filter.matches(&node_info()));
+
+        let filter = PacketFilter::new().kind(FromRadioKind::NodeInfo);
+        assert!(filter.matches(&node_info()));
+        assert!(!filter.matches(&text));
+    }
+
+    #[tokio::test]
+    async fn delivers_packets_to_independent_subscriptions() {
+        let (packet_tx, _) = broadcast::channel(2);
+
+        let mut all = PacketSubscription::new(packet_tx.subscribe(), PacketFilter::new());
+        let mut texts = PacketSubscription::new(
+            packet_tx.subscribe(),
+            PacketFilter::new().port_num(protobufs::PortNum::TextMessageApp),
+        );
+
+        packet_tx.send(node_info()).unwrap();
+        packet_tx
+            .send(mesh_packet(7, 0, protobufs::PortNum::TextMessageApp))
+            .unwrap();
+
+        assert_eq!(all.recv().await.unwrap(), node_info());
+        assert_eq!(
+            texts.recv().await.unwrap(),
+            mesh_packet(7, 0, protobufs::PortNum::TextMessageApp)
+        );
+
+        // Overflow the buffer of the first subscription
+        for _ in 0..3 {
+            packet_tx.send(node_info()).unwrap();
+        }
+
+        assert!(matches!(
+            all.recv().await,
+            Err(Error::SubscriptionLagged { skipped: 2 })
+        ));
+        assert!(all.recv().await.is_ok());
+
+        let mut late = PacketSubscription::new(packet_tx.subscribe(), PacketFilter::new());
+        drop(packet_tx);
+        assert!(matches!(
+            late.recv().await,
+            Err(Error::InternalChannelError(
+                InternalChannelError::ChannelClosedEarly
+            ))
+        ));
+    }
+}
@@ -0,0 +1,722 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use log::warn;
+use prost::Message;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+use crate::utils_internal::current_epoch_secs_u32;
+
+use super::wrappers::NodeId;
+
+/// An enum that identifies the kind of metrics carried by a `Telemetry` message.
+///
+/// # Variants
+///
+/// * `Device` - Metrics of the device itself, such as its battery level and channel utilization.
+/// * `Environment` - Metrics of an attached environment sensor, such as a weather station.
+/// * `AirQuality` - Metrics of an attached air quality sensor.
+/// * `Power` - Metrics of an attached voltage and current sensor.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TelemetryKind {
+    Device,
+    Environment,
+    AirQuality,
+    Power,
+}
+
+impl TelemetryKind {
+    /// Returns an empty `Telemetry` message of this kind, which is sent to request telemetry of
+    /// this kind from a node.
+    pub(crate) fn request(&self) -> protobufs::Telemetry {
+        use protobufs::telemetry::Variant;
+
+        let variant = match self {
+            TelemetryKind::Device => Variant::DeviceMetrics(Default::default()),
+            TelemetryKind::Environment => Variant::EnvironmentMetrics(Default::default()),
+            TelemetryKind::AirQuality => Variant::AirQualityMetrics(Default::default()),
+            TelemetryKind::Power => Variant::PowerMetrics(Default::default()),
+        };
+
+        protobufs::Telemetry {
+            time: 0,
+            variant: Some(variant),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            TelemetryKind::Device => "device",
+            TelemetryKind::Environment => "environment",
+            TelemetryKind::AirQuality => "air_quality",
+            TelemetryKind::Power => "power",
+        }
+    }
+}
+
+/// An enum that holds the metrics carried by a `Telemetry` message.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TelemetryMetrics {
+    Device(protobufs::DeviceMetrics),
+    Environment(protobufs::EnvironmentMetrics),
+    AirQuality(protobufs::AirQualityMetrics),
+    Power(protobufs::PowerMetrics),
+}
+
+impl TelemetryMetrics {
+    /// Returns the kind of the metrics.
+    pub fn kind(&self) -> TelemetryKind {
+        match self {
+            TelemetryMetrics::Device(_) => TelemetryKind::Device,
+            TelemetryMetrics::Environment(_) => TelemetryKind::Environment,
+            TelemetryMetrics::AirQuality(_) => TelemetryKind::AirQuality,
+            TelemetryMetrics::Power(_) => TelemetryKind::Power,
+        }
+    }
+}
+
+impl From<protobufs::telemetry::Variant> for TelemetryMetrics {
+    fn from(variant: protobufs::telemetry::Variant) -> Self {
+        use protobufs::telemetry::Variant;
+
+        match variant {
+            Variant::DeviceMetrics(metrics) => TelemetryMetrics::Device(metrics),
+            Variant::EnvironmentMetrics(metrics) => TelemetryMetrics::Environment(metrics),
+            Variant::AirQualityMetrics(metrics) => TelemetryMetrics::AirQuality(metrics),
+            Variant::PowerMetrics(metrics) => TelemetryMetrics::Power(metrics),
+        }
+    }
+}
+
+/// A struct that represents the metrics reported by a node in a single `TelemetryApp` packet.
+///
+/// # Fields
+///
+/// * `node_id` - The ID of the node that reported the metrics.
+/// * `time` - The time the metrics were measured, in seconds since the Unix epoch. Falls back to
+///     the time the packet was received if the node did not report it.
+/// * `metrics` - The reported metrics.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TelemetryReport {
+    pub node_id: NodeId,
+    pub time: u32,
+    pub metrics: TelemetryMetrics,
+}
+
+impl TelemetryReport {
+    /// Decodes the telemetry carried by a mesh packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh_packet` - The `MeshPacket` to decode the telemetry of.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the decoded `TelemetryReport`, or to `None` if the packet was not
+    /// sent on the `TelemetryApp` port or does not contain any metrics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// if let Some(report) = TelemetryReport::from_mesh_packet(&mesh_packet)? {
+    ///     if let TelemetryMetrics::Device(metrics) = report.metrics {
+    ///         println!("Battery level of {}: {}%", report.node_id, metrics.battery_level);
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::DecodeError` if the payload is not a valid `Telemetry` message.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn from_mesh_packet(mesh_packet: &protobufs::MeshPacket) -> Result<Option<Self>, Error> {
+        let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) =
+            &mesh_packet.payload_variant
+        else {
+            return Ok(None);
+        };
+
+        if data.portnum != protobufs::PortNum::TelemetryApp as i32 {
+            return Ok(None);
+        }
+
+        let telemetry = protobufs::Telemetry::decode(data.payload.as_slice())?;
+
+        let Some(variant) = telemetry.variant else {
+            return Ok(None);
+        };
+
+        let time = match (telemetry.time, mesh_packet.rx_time) {
+            (0, 0) => current_epoch_secs_u32(),
+            (0, rx_time) => rx_time,
+            (time, _) => time,
+        };
+
+        Ok(Some(TelemetryReport {
+            node_id: mesh_packet.from.into(),
+            time,
+            metrics: variant.into(),
+        }))
+    }
+}
+
+/// A struct that keeps the latest telemetry report of each kind for every node in the mesh.
+///
+/// The store is updated by passing every `FromRadio` packet received from the radio to the
+/// `handle_packet` method, and can be rendered in the Prometheus text exposition format with
+/// the `to_prometheus` method.
+#[derive(Clone, Debug, Default)]
+pub struct TelemetryStore {
+    reports: HashMap<(NodeId, TelemetryKind), TelemetryReport>,
+}
+
+impl TelemetryStore {
+    /// Creates an empty telemetry store.
+    pub fn new() -> TelemetryStore {
+        TelemetryStore::default()
+    }
+
+    /// Returns the latest report of the given kind from the given node, if any.
+    pub fn latest(&self, node_id: NodeId, kind: TelemetryKind) -> Option<&TelemetryReport> {
+        self.reports.get(&(node_id, kind))
+    }
+
+    /// Returns an iterator over the latest reports of every kind from every node, in no
+    /// particular order.
+    pub fn reports(&self) -> impl Iterator<Item = &TelemetryReport> {
+        self.reports.values()
+    }
+
+    /// Updates the store from a packet received from the radio. Packets that do not carry
+    /// telemetry are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - A `FromRadio` packet received from the radio.
+    ///
+    /// # Returns
+    ///
+    /// The `TelemetryReport` carried by the packet, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut telemetry = TelemetryStore::new();
+    ///
+    /// while let Some(packet) = decoded_listener.recv().await {
+    ///     telemetry.handle_packet(&packet);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn handle_packet(&mut self, packet: &protobufs::FromRadio) -> Option<TelemetryReport> {
+        let Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)) =
+            &packet.payload_variant
+        else {
+            return None;
+        };
+
+        let report = match TelemetryReport::from_mesh_packet(mesh_packet) {
+            Ok(report) => report?,
+            Err(e) => {
+                warn!(
+                    "Failed to decode telemetry of node {}: {e}",
+                    mesh_packet.from
+                );
+                return None;
+            }
+        };
+
+        self.insert(report);
+        Some(report)
+    }
+
+    /// Stores a report, unless a more recent report of the same kind from the same node is
+    /// already stored.
+    pub fn insert(&mut self, report: TelemetryReport) {
+        let key = (report.node_id, report.metrics.kind());
+
+        if self
+            .reports
+            .get(&key)
+            .is_some_and(|stored| stored.time > report.time)
+        {
+            return;
+        }
+
+        self.reports.insert(key, report);
+    }
+
+    /// Renders the latest metrics of every node in the Prometheus text exposition format.
+    ///
+    /// Every metric is a gauge prefixed with `meshtastic_` and labelled with the `node` number
+    /// that reported it. The `meshtastic_telemetry_timestamp_seconds` gauge additionally reports
+    /// the time of the latest report of each kind.
+    pub fn to_prometheus(&self) -> String {
+        let mut reports: Vec<&TelemetryReport> = self.reports.values().collect();
+        reports.sort_by_key(|report| (report.metrics.kind(), report.node_id));
+
+        let mut output = String::new();
+
+        write_family(
+            &mut output,
+            "telemetry_timestamp_seconds",
+            "Time of the latest telemetry report, in seconds since the Unix epoch.",
+            reports.iter().map(|report| {
+                (
+                    format!(
+                        "node=\"{}\",kind=\"{}\"",
+                        report.node_id,
+                        report.metrics.kind().label()
+                    ),
+                    report.time as f64,
+                )
+            }),
+        );
+
+        write_gauges(
+            &mut output,
+            &reports,
+            DEVICE_GAUGES,
+            |metrics| match metrics {
+                TelemetryMetrics::Device(metrics) => Some(metrics),
+                _ => None,
+            },
+        );
+        write_gauges(
+            &mut output,
+            &reports,
+            ENVIRONMENT_GAUGES,
+            |metrics| match metrics {
+                TelemetryMetrics::Environment(metrics) => Some(metrics),
+                _ => None,
+            },
+        );
+        write_gauges(
+            &mut output,
+            &reports,
+            AIR_QUALITY_GAUGES,
+            |metrics| match metrics {
+                TelemetryMetrics::AirQuality(metrics) => Some(metrics),
+                _ => None,
+            },
+        );
+        write_gauges(
+            &mut output,
+            &reports,
+            POWER_GAUGES,
+            |metrics| match metrics {
+                TelemetryMetrics::Power(metrics) => Some(metrics),
+                _ => None,
+            },
+        );
+
+        output
+    }
+}
+
+/// The name, help text and value accessor of a Prometheus gauge.
+type Gauge<T> = (&'static str, &'static str, fn(&T) -> f64);
+
+const DEVICE_GAUGES: &[Gauge<protobufs::DeviceMetrics>] = &[
+    (
+        "device_battery_level_percent",
+        "Battery level, above 100 when powered.",
+        |m| m.battery_level as f64,
+    ),
+    ("device_voltage_volts", "Battery voltage.", |m| {
+        m.voltage as f64
+    }),
+    (
+        "device_channel_utilization_percent",
+        "Utilization of the current channel.",
+        |m| m.channel_utilization as f64,
+    ),
+    (
+        "device_air_util_tx_percent",
+        "Airtime used for transmissions.",
+        |m| m.air_util_tx as f64,
+    ),
+    (
+        "device_uptime_seconds",
+        "Time since the device last booted.",
+        |m| m.uptime_seconds as f64,
+    ),
+];
+
+const ENVIRONMENT_GAUGES: &[Gauge<protobufs::EnvironmentMetrics>] = &[
+    ("environment_temperature_celsius", "Temperature.", |m| {
+        m.temperature as f64
+    }),
+    (
+        "environment_relative_humidity_percent",
+        "Relative humidity.",
+        |m| m.relative_humidity as f64,
+    ),
+    (
+        "environment_barometric_pressure_hpa",
+        "Barometric pressure.",
+        |m| m.barometric_pressure as f64,
+    ),
+    (
+        "environment_gas_resistance_megaohms",
+        "Gas resistance.",
+        |m| m.gas_resistance as f64,
+    ),
+    (
+        "environment_voltage_volts",
+        "Voltage of the attached sensor.",
+        |m| m.voltage as f64,
+    ),
+    (
+        "environment_current_milliamperes",
+        "Current of the attached sensor.",
+        |m| m.current as f64,
+    ),
+    ("environment_iaq", "Indoor air quality index.", |m| {
+        m.iaq as f64
+    }),
+    (
+        "environment_distance_millimeters",
+        "Distance measured by a range sensor.",
+        |m| m.distance as f64,
+    ),
+    ("environment_lux", "Ambient light.", |m| m.lux as f64),
+    ("environment_white_lux", "White light.", |m| {
+        m.white_lux as f64
+    }),
+    ("environment_ir_lux", "Infrared light.", |m| m.ir_lux as f64),
+    ("environment_uv_lux", "Ultraviolet light.", |m| {
+        m.uv_lux as f64
+    }),
+    (
+        "environment_wind_direction_degrees",
+        "Wind direction.",
+        |m| m.wind_direction as f64,
+    ),
+    (
+        "environment_wind_speed_meters_per_second",
+        "Wind speed.",
+        |m| m.wind_speed as f64,
+    ),
+    (
+        "environment_wind_gust_meters_per_second",
+        "Wind gust.",
+        |m| m.wind_gust as f64,
+    ),
+    (
+        "environment_wind_lull_meters_per_second",
+        "Wind lull.",
+        |m| m.wind_lull as f64,
+    ),
+    (
+        "environment_weight_kilograms",
+        "Weight measured by a load cell.",
+        |m| m.weight as f64,
+    ),
+];
+
+const AIR_QUALITY_GAUGES: &[Gauge<protobufs::AirQualityMetrics>] = &[
+    (
+        "air_quality_pm10_standard",
+        "Concentration units standard PM1.0.",
+        |m| m.pm10_standard as f64,
+    ),
+    (
+        "air_quality_pm25_standard",
+        "Concentration units standard PM2.5.",
+        |m| m.pm25_standard as f64,
+    ),
+    (
+        "air_quality_pm100_standard",
+        "Concentration units standard PM10.0.",
+        |m| m.pm100_standard as f64,
+    ),
+    (
+        "air_quality_pm10_environmental",
+        "Concentration units environmental PM1.0.",
+        |m| m.pm10_environmental as f64,
+    ),
+    (
+        "air_quality_pm25_environmental",
+        "Concentration units environmental PM2.5.",
+        |m| m.pm25_environmental as f64,
+    ),
+    (
+        "air_quality_pm100_environmental",
+        "Concentration units environmental PM10.0.",
+        |m| m.pm100_environmental as f64,
+    ),
+    (
+        "air_quality_particles_03um",
+        "Particles of 0.3um per 0.1L of air.",
+        |m| m.particles_03um as f64,
+    ),
+    (
+        "air_quality_particles_05um",
+        "Particles of 0.5um per 0.1L of air.",
+        |m| m.particles_05um as f64,
+    ),
+    (
+        "air_quality_particles_10um",
+        "Particles of 1.0um per 0.1L of air.",
+        |m| m.particles_10um as f64,
+    ),
+    (
+        "air_quality_particles_25um",
+        "Particles of 2.5um per 0.1L of air.",
+        |m| m.particles_25um as f64,
+    ),
+    (
+        "air_quality_particles_50um",
+        "Particles of 5.0um per 0.1L of air.",
+        |m| m.particles_50um as f64,
+    ),
+    (
+        "air_quality_particles_100um",
+        "Particles of 10.0um per 0.1L of air.",
+        |m| m.particles_100um as f64,
+    ),
+];
+
+const POWER_GAUGES: &[Gauge<protobufs::PowerMetrics>] = &[
+    ("power_ch1_voltage_volts", "Voltage of channel 1.", |m| {
+        m.ch1_voltage as f64
+    }),
+    (
+        "power_ch1_current_milliamperes",
+        "Current of channel 1.",
+        |m| m.ch1_current as f64,
+    ),
+    ("power_ch2_voltage_volts", "Voltage of channel 2.", |m| {
+        m.ch2_voltage as f64
+    }),
+    (
+        "power_ch2_current_milliamperes",
+        "Current of channel 2.",
+        |m| m.ch2_current as f64,
+    ),
+    ("power_ch3_voltage_volts", "Voltage of channel 3.", |m| {
+        m.ch3_voltage as f64
+    }),
+    (
+        "power_ch3_current_milliamperes",
+        "Current of channel 3.",
+        |m| m.ch3_current as f64,
+    ),
+];
+
+/// Writes a family of gauges for every report containing metrics of type `T`.
+fn write_gauges<T>(
+    output: &mut String,
+    reports: &[&TelemetryReport],
+    gauges: &[Gauge<T>],
+    extract: impl Fn(&TelemetryMetrics) -> Option<&T>,
+) {
+    for (name, help, value) in gauges {
+        let samples = reports.iter().filter_map(|report| {
+            extract(&report.metrics)
+                .map(|metrics| (format!("node=\"{}\"", report.node_id), value(metrics)))
+        });
+
+        write_family(output, name, help, samples);
+    }
+}
+
+/// Writes the header and samples of a gauge, if the gauge has any samples.
+fn write_family(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    samples: impl Iterator<Item = (String, f64)>,
+) {
+    let mut samples = samples.peekable();
+
+    if samples.peek().is_none() {
+        return;
+    }
+
+    let _ = writeln!(output, "# HELP meshtastic_{name} {help}");
+    let _ = writeln!(output, "# TYPE meshtastic_{name} gauge");
+
+    for (labels, value) in samples {
+        let _ = writeln!(output, "meshtastic_{name}{{{labels}}} {value}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::connections::test_support::{configured_stream_api, TestRouter};
+    use crate::connections::wrappers::mesh_channel::MeshChannel;
+
+    use super::*;
+
+    fn telemetry_packet(from: u32, telemetry: protobufs::Telemetry) -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(
+                protobufs::MeshPacket {
+                    from,
+                    rx_time: 1_700_000_000,
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: protobufs::PortNum::TelemetryApp as i32,
+                            payload: telemetry.encode_to_vec(),
+                            ..Default::default()
+                        },
+                    )),
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+
+    fn device_telemetry(time: u32, battery_level: u32) -> protobufs::Telemetry {
+        protobufs::Telemetry {
+            time,
+            variant: Some(protobufs::telemetry::Variant::DeviceMetrics(
+                protobufs::DeviceMetrics {
+                    battery_level,
+                    voltage: 4.25,
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+
+    #[test]
+    fn keeps_latest_report_per_node_and_kind() {
+        let mut store = TelemetryStore::new();
+
+        let report = store
+            .handle_packet(&telemetry_packet(7, device_telemetry(0, 80)))
+            .unwrap();
+        assert_eq!(report.time, 1_700_000_000);
+        assert_eq!(report.metrics.kind(), TelemetryKind::Device);
+
+        store.handle_packet(&telemetry_packet(7, device_telemetry(1_700_000_100, 75)));
+        // Reports measured before the stored report are ignored
+        store.handle_packet(&telemetry_packet(7, device_telemetry(1_600_000_000, 99)));
+
+        let latest = store.latest(NodeId::new(7), TelemetryKind::Device).unwrap();
+        assert!(matches!(
+            latest.metrics,
+            TelemetryMetrics::Device(metrics) if metrics.battery_level == 75
+        ));
+        assert!(store
+            .latest(NodeId::new(7), TelemetryKind::Environment)
+            .is_none());
+    }
+
+    #[test]
+    fn renders_prometheus_exposition_format() {
+        let mut store = TelemetryStore::new();
+        store.handle_packet(&telemetry_packet(7, device_telemetry(1_700_000_100, 75)));
+        store.handle_packet(&telemetry_packet(
+            8,
+            protobufs::Telemetry {
+                time: 1_700_000_200,
+                variant: Some(protobufs::telemetry::Variant::EnvironmentMetrics(
+                    protobufs::EnvironmentMetrics {
+                        temperature: 21.5,
+                        ..Default::default()
+                    },
+                )),
+            },
+        ));
+
+        let output = store.to_prometheus();
+
+        assert!(output.contains("# TYPE meshtastic_device_battery_level_percent gauge\n"));
+        assert!(output.contains("meshtastic_device_battery_level_percent{node=\"7\"} 75\n"));
+        assert!(output.contains("meshtastic_device_voltage_volts{node=\"7\"} 4.25\n"));
+        assert!(output.contains("meshtastic_environment_temperature_celsius{node=\"8\"} 21.5\n"));
+        assert!(output.contains(
+            "meshtastic_telemetry_timestamp_seconds{node=\"8\",kind=\"environment\"} 1700000200\n"
+        ));
+        assert!(!output.contains("power_"));
+    }
+
+    #[tokio::test]
+    async fn requests_telemetry_from_node() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        let respond = async {
+            let request = loop {
+                let request = mock_radio
+                    .received_mesh_packets()
+                    .into_iter()
+                    .find_map(|packet| match &packet.payload_variant {
+                        Some(protobufs::mesh_packet::PayloadVariant::Decoded(data))
+                            if data.portnum == protobufs::PortNum::TelemetryApp as i32 =>
+                        {
+                            Some((packet.id, packet.to, data.clone()))
+                        }
+                        _ => None,
+                    });
+
+                if let Some(request) = request {
+                    break request;
+                }
+
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            };
+
+            let (request_id, to, data) = request;
+            assert_eq!(to, 7);
+            assert!(data.want_response);
+            assert_eq!(
+                protobufs::Telemetry::decode(data.payload.as_slice()).unwrap(),
+                TelemetryKind::Device.request()
+            );
+
+            let protobufs::FromRadio {
+                payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(mut response)),
+                ..
+            } = telemetry_packet(7, device_telemetry(1_700_000_000, 55))
+            else {
+                unreachable!()
+            };
+            if let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) =
+                &mut response.payload_variant
+            {
+                data.request_id = request_id;
+            }
+
+            mock_radio.inject_mesh_packet(response).unwrap();
+        };
+
+        let (report, _) = tokio::join!(
+            stream_api.request_telemetry(
+                &mut router,
+                NodeId::new(7),
+                TelemetryKind::Device,
+                MeshChannel::new(0).unwrap(),
+                Duration::from_secs(1),
+            ),
+            respond
+        );
+
+        let report = report.unwrap();
+        assert_eq!(report.node_id, NodeId::new(7));
+        assert!(matches!(
+            report.metrics,
+            TelemetryMetrics::Device(metrics) if metrics.battery_level == 55
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use prost::Message;
+
+use crate::api::StreamApi;
+use crate::protobufs;
+
+use super::mock_radio::{MockRadio, MockRadioState};
+use super::stream_api::{state, ConnectedStreamApi};
+use super::wrappers::NodeId;
+use super::PacketRouter;
+
+/// The node number of the mock radio used by the unit tests.
+pub(crate) const MY_NODE_NUM: u32 = 42;
+
+/// A packet router that ignores every packet, and reports `MY_NODE_NUM` as its node ID.
+pub(crate) struct TestRouter;
+
+impl PacketRouter<(), std::fmt::Error> for TestRouter {
+    fn handle_packet_from_radio(
+        &mut self,
+        _packet: protobufs::FromRadio,
+    ) -> Result<(), std::fmt::Error> {
+        Ok(())
+    }
+
+    fn handle_mesh_packet(
+        &mut self,
+        _packet: protobufs::MeshPacket,
+    ) -> Result<(), std::fmt::Error> {
+        Ok(())
+    }
+
+    fn source_node_id(&self) -> NodeId {
+        NodeId::new(MY_NODE_NUM)
+    }
+}
+
+/// Returns the state of a mock radio with the node number `MY_NODE_NUM`.
+pub(crate) fn mock_radio_state() -> MockRadioState {
+    MockRadioState {
+        my_info: protobufs::MyNodeInfo {
+            my_node_num: MY_NODE_NUM,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Connects to a mock radio in the state returned by `mock_radio_state`, and completes the
+/// configuration handshake.
+pub(crate) async fn configured_stream_api() -> (MockRadio, ConnectedStreamApi<state::Configured>) {
+    let (mock_radio, stream_handle) = MockRadio::new(mock_radio_state());
+    let (_decoded_listener, stream_api) = StreamApi::new().connect(stream_handle).await;
+    let (stream_api, _) = stream_api
+        .configure_and_wait(7, Duration::from_secs(1))
+        .await
+        .unwrap();
+
+    (mock_radio, stream_api)
+}
+
+/// Waits for the mock radio to receive an admin packet, skipping the first `skip` admin packets,
+/// and returns it with its decoded admin message.
+pub(crate) async fn next_admin_packet(
+    mock_radio: &MockRadio,
+    skip: usize,
+) -> (
+    protobufs::MeshPacket,
+    protobufs::admin_message::PayloadVariant,
+) {
+    loop {
+        let admin_packet = mock_radio
+            .received_mesh_packets()
+            .into_iter()
+            .filter_map(|packet| match &packet.payload_variant {
+                Some(protobufs::mesh_packet::PayloadVariant::Decoded(data))
+                    if data.portnum == protobufs::PortNum::AdminApp as i32 =>
+                {
+                    let admin_message =
+                        protobufs::AdminMessage::decode(data.payload.as_slice()).unwrap();
+                    Some((packet, admin_message.payload_variant.unwrap()))
+                }
+                _ => None,
+            })
+            .nth(skip);
+
+        if let Some(admin_packet) = admin_packet {
+            return admin_packet;
+        }
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
+
+/// Returns a `Routing` packet from `from` reporting that the packet with ID `request_id` could not
+/// be delivered, for the given reason.
+pub(crate) fn routing_error_packet(
+    from: u32,
+    request_id: u32,
+    reason: protobufs::routing::Error,
+) -> protobufs::MeshPacket {
+    let routing = protobufs::Routing {
+        variant: Some(protobufs::routing::Variant::ErrorReason(reason as i32)),
+    };
+
+    protobufs::MeshPacket {
+        from,
+        to: MY_NODE_NUM,
+        payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+            protobufs::Data {
+                portnum: protobufs::PortNum::RoutingApp as i32,
+                payload: routing.encode_to_vec(),
+                request_id,
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,796 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::time::Duration;
+
+use log::warn;
+use prost::Message;
+
+#[cfg(feature = "serde")]
+use crate::errors_internal::Error;
+use crate::protobufs;
+use crate::utils_internal::current_epoch_secs_u32;
+
+use super::traceroute::TracerouteResult;
+use super::wrappers::NodeId;
+
+/// The default amount of time after which a link that has not been reported again is removed
+/// from the graph. Radios broadcast neighbor information every few hours by default.
+pub const DEFAULT_MAX_LINK_AGE: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// An enum that describes how a link between two nodes was observed.
+///
+/// # Variants
+///
+/// * `NeighborInfo` - The receiving node reported the transmitting node as a neighbor.
+/// * `Traceroute` - A traceroute request was relayed from the transmitting node to the
+///     receiving node.
+/// * `Direct` - The connected radio received a packet from the transmitting node without
+///     any relays.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum LinkSource {
+    NeighborInfo,
+    Traceroute,
+    Direct,
+}
+
+/// A struct that represents a radio link over which packets sent by one node are heard by
+/// another node. Links are directed, since the link quality can differ between directions.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct TopologyLink {
+    /// The node number of the transmitting node.
+    pub from: u32,
+
+    /// The node number of the receiving node.
+    pub to: u32,
+
+    /// The signal-to-noise ratio at which the receiving node last heard the transmitting node.
+    pub snr: Option<f32>,
+
+    /// The time the link was last observed, in seconds since the Unix epoch.
+    pub last_seen: u32,
+
+    /// How the link was last observed.
+    pub source: LinkSource,
+}
+
+/// A struct that represents a node of the topology graph.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct TopologyNode {
+    /// The node number of the node.
+    pub num: u32,
+
+    /// The number of hops packets from the node last took to reach the connected radio.
+    pub hops_away: Option<u32>,
+
+    /// The time the node was last observed, in seconds since the Unix epoch.
+    pub last_seen: Option<u32>,
+}
+
+impl TopologyNode {
+    /// Returns the ID of the node.
+    pub fn node_id(&self) -> NodeId {
+        self.num.into()
+    }
+}
+
+/// The serialized representation of a `MeshTopology`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TopologyExport<'a> {
+    my_node_num: Option<u32>,
+    nodes: Vec<&'a TopologyNode>,
+    links: Vec<&'a TopologyLink>,
+}
+
+/// A struct that maintains a live graph of the radio links within the mesh.
+///
+/// The graph is updated by passing every `FromRadio` packet received from the radio to the
+/// `handle_packet` method. Links are added from the neighbors reported in `NeighborinfoApp`
+/// packets, from the routes of `TracerouteApp` responses, and from packets that the connected
+/// radio received directly from their sender. Hop counts are recorded from the `NodeInfo`
+/// packets sent during the configuration handshake and from the metadata of mesh packets.
+///
+/// **Note:** Radios only broadcast neighbor information when the `NeighborInfo` module is
+/// enabled through `module_config::NeighborInfoConfig`.
+///
+/// Links that have not been observed within the maximum link age are removed by the
+/// `expire_stale_links` method. The graph can be exported with the `to_dot`, `to_graphml` and
+/// `to_json` methods, and analyzed with the `weak_links` and `single_points_of_failure` methods.
+#[derive(Clone, Debug)]
+pub struct MeshTopology {
+    my_node_num: Option<u32>,
+    max_link_age: Duration,
+    nodes: HashMap<u32, TopologyNode>,
+    links: HashMap<(u32, u32), TopologyLink>,
+}
+
+impl Default for MeshTopology {
+    fn default() -> Self {
+        MeshTopology::new(DEFAULT_MAX_LINK_AGE)
+    }
+}
+
+impl MeshTopology {
+    /// Creates an empty graph whose links are removed once they have not been observed for
+    /// `max_link_age`.
+    pub fn new(max_link_age: Duration) -> MeshTopology {
+        MeshTopology {
+            my_node_num: None,
+            max_link_age,
+            nodes: HashMap::new(),
+            links: HashMap::new(),
+        }
+    }
+
+    /// Returns the ID of the connected radio, if the radio has reported it.
+    pub fn my_node_id(&self) -> Option<NodeId> {
+        self.my_node_num.map(NodeId::from)
+    }
+
+    /// Returns the node of the graph with the given ID, if the node is known.
+    pub fn node(&self, node_id: NodeId) -> Option<&TopologyNode> {
+        self.nodes.get(&node_id.id())
+    }
+
+    /// Returns an iterator over all nodes of the graph, in no particular order.
+    pub fn nodes(&self) -> impl Iterator<Item = &TopologyNode> {
+        self.nodes.values()
+    }
+
+    /// Returns the link from the transmitting node to the receiving node, if it is known.
+    pub fn link(&self, from: NodeId, to: NodeId) -> Option<&TopologyLink> {
+        self.links.get(&(from.id(), to.id()))
+    }
+
+    /// Returns an iterator over all links of the graph, in no particular order.
+    pub fn links(&self) -> impl Iterator<Item = &TopologyLink> {
+        self.links.values()
+    }
+
+    /// Returns the IDs of the nodes that are heard by the given node.
+    pub fn neighbors(&self, node_id: NodeId) -> Vec<NodeId> {
+        let neighbors: BTreeSet<u32> = self
+            .links
+            .values()
+            .filter(|link| link.to == node_id.id())
+            .map(|link| link.from)
+            .collect();
+
+        neighbors.into_iter().map(NodeId::from).collect()
+    }
+
+    /// Updates the graph from a packet received from the radio. Packets that do not carry
+    /// topology information are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - A `FromRadio` packet received from the radio.
+    ///
+    /// # Returns
+    ///
+    /// Whether the packet changed the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut topology = MeshTopology::default();
+    ///
+    /// while let Some(packet) = decoded_listener.recv().await {
+    ///     topology.handle_packet(&packet);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn handle_packet(&mut self, packet: &protobufs::FromRadio) -> bool {
+        use protobufs::from_radio::PayloadVariant;
+
+        match &packet.payload_variant {
+            Some(PayloadVariant::MyInfo(my_info)) => {
+                self.my_node_num = Some(my_info.my_node_num);
+                true
+            }
+            Some(PayloadVariant::NodeInfo(node_info)) => self.handle_node_info(node_info),
+            Some(PayloadVariant::Packet(mesh_packet)) => {
+                self.handle_mesh_packet(mesh_packet);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Adds the links along the route towards the destination of a traceroute, and along the
+    /// return route if it is known.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - The `TracerouteResult` returned by the `traceroute` method.
+    /// * `seen_at` - The time the traceroute completed, in seconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// None
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let result = stream_api.traceroute(packet_router, node_id, channel, 3, timeout).await?;
+    /// topology.add_traceroute(&result, current_epoch_secs_u32());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn add_traceroute(&mut self, result: &TracerouteResult, seen_at: u32) {
+        let routes = std::iter::once(&result.route_towards).chain(result.route_back.as_ref());
+
+        for route in routes {
+            for hops in route.windows(2) {
+                self.observe_node(hops[0].node_id.id(), seen_at);
+                self.observe_node(hops[1].node_id.id(), seen_at);
+                self.observe_link(
+                    hops[0].node_id.id(),
+                    hops[1].node_id.id(),
+                    hops[1].snr,
+                    seen_at,
+                    LinkSource::Traceroute,
+                );
+            }
+        }
+    }
+
+    /// Removes the links that have not been observed within the maximum link age.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, in seconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// The removed links.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// for link in topology.expire_stale_links(current_epoch_secs_u32()) {
+    ///     println!("Lost link from {} to {}", link.from, link.to);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn expire_stale_links(&mut self, now: u32) -> Vec<TopologyLink> {
+        let max_age = u32::try_from(self.max_link_age.as_secs()).unwrap_or(u32::MAX);
+        let cutoff = now.saturating_sub(max_age);
+
+        let stale: Vec<(u32, u32)> = self
+            .links
+            .iter()
+            .filter(|(_, link)| link.last_seen < cutoff)
+            .map(|(key, _)| *key)
+            .collect();
+
+        stale
+            .into_iter()
+            .filter_map(|key| self.links.remove(&key))
+            .collect()
+    }
+
+    /// Returns the links whose SNR is below the given threshold in dB, sorted from the weakest
+    /// link. Links with an unknown SNR are not included.
+    pub fn weak_links(&self, snr_threshold: f32) -> Vec<&TopologyLink> {
+        let mut links: Vec<&TopologyLink> = self
+            .links
+            .values()
+            .filter(|link| link.snr.is_some_and(|snr| snr < snr_threshold))
+            .collect();
+
+        links.sort_by(|a, b| {
+            a.snr
+                .partial_cmp(&b.snr)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        links
+    }
+
+    /// Returns the nodes whose failure would split the mesh into disconnected parts, sorted by
+    /// node ID. Links are treated as bidirectional for this analysis.
+    pub fn single_points_of_failure(&self) -> Vec<NodeId> {
+        let mut adjacency: HashMap<u32, BTreeSet<u32>> = HashMap::new();
+
+        for link in self.links.values().filter(|link| link.from != link.to) {
+            adjacency.entry(link.from).or_default().insert(link.to);
+            adjacency.entry(link.to).or_default().insert(link.from);
+        }
+
+        let mut search = ArticulationSearch {
+            adjacency: &adjacency,
+            discovery: HashMap::new(),
+            low: HashMap::new(),
+            points: BTreeSet::new(),
+        };
+
+        let mut roots: Vec<u32> = adjacency.keys().copied().collect();
+        roots.sort_unstable();
+
+        for root in roots {
+            if !search.discovery.contains_key(&root) {
+                search.visit(root, None);
+            }
+        }
+
+        search.points.into_iter().map(NodeId::from).collect()
+    }
+
+    /// Exports the graph in the Graphviz DOT format. Edges are labelled with their SNR.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph mesh {\n");
+
+        for node in self.sorted_nodes() {
+            let _ = match node.hops_away {
+                Some(hops_away) => writeln!(
+                    dot,
+                    "  \"{}\" [label=\"{}\\n{} hops\"];",
+                    node.num, node.num, hops_away
+                ),
+                None => writeln!(dot, "  \"{}\";", node.num),
+            };
+        }
+
+        for link in self.sorted_links() {
+            let _ = match link.snr {
+                Some(snr) => writeln!(
+                    dot,
+                    "  \"{}\" -> \"{}\" [label=\"{:.2} dB\"];",
+                    link.from, link.to, snr
+                ),
+                None => writeln!(dot, "  \"{}\" -> \"{}\";", link.from, link.to),
+            };
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Exports the graph in the GraphML format. Nodes carry their hop count, and edges carry
+    /// their SNR and the time they were last observed.
+    pub fn to_graphml(&self) -> String {
+        let mut graphml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"hops_away\" for=\"node\" attr.name=\"hops_away\" attr.type=\"int\"/>\n",
+            "  <key id=\"snr\" for=\"edge\" attr.name=\"snr\" attr.type=\"float\"/>\n",
+            "  <key id=\"last_seen\" for=\"edge\" attr.name=\"last_seen\" attr.type=\"long\"/>\n",
+            "  <key id=\"source\" for=\"edge\" attr.name=\"source\" attr.type=\"string\"/>\n",
+            "  <graph id=\"mesh\" edgedefault=\"directed\">\n",
+        ));
+
+        for node in self.sorted_nodes() {
+            let _ = match node.hops_away {
+                Some(hops_away) => writeln!(
+                    graphml,
+                    "    <node id=\"{}\"><data key=\"hops_away\">{}</data></node>",
+                    node.num, hops_away
+                ),
+                None => writeln!(graphml, "    <node id=\"{}\"/>", node.num),
+            };
+        }
+
+        for link in self.sorted_links() {
+            let _ = write!(
+                graphml,
+                "    <edge source=\"{}\" target=\"{}\">",
+                link.from, link.to
+            );
+            if let Some(snr) = link.snr {
+                let _ = write!(graphml, "<data key=\"snr\">{snr}</data>");
+            }
+            let _ = writeln!(
+                graphml,
+                "<data key=\"last_seen\">{}</data><data key=\"source\">{:?}</data></edge>",
+                link.last_seen, link.source
+            );
+        }
+
+        graphml.push_str("  </graph>\n</graphml>\n");
+        graphml
+    }
+
+    /// Exports the nodes and links of the graph as JSON.
+    ///
+    /// # Arguments
+    ///
+    /// None
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the JSON representation of the graph.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// std::fs::write("topology.json", topology.to_json()?)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::PersistenceError` if the graph cannot be serialized.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, Error> {
+        let export = TopologyExport {
+            my_node_num: self.my_node_num,
+            nodes: self.sorted_nodes(),
+            links: self.sorted_links(),
+        };
+
+        serde_json::to_string_pretty(&export).map_err(|e| Error::PersistenceError {
+            source: Box::new(e),
+            description: "Failed to serialize mesh topology".to_string(),
+        })
+    }
+
+    /// Records the hop count of a node reported during the configuration handshake, and the
+    /// link to the connected radio if the node is a direct neighbor.
+    fn handle_node_info(&mut self, node_info: &protobufs::NodeInfo) -> bool {
+        // Nodes that have never been heard carry no hop count
+        if node_info.last_heard == 0 {
+            return false;
+        }
+
+        let node = self.observe_node(node_info.num, node_info.last_heard);
+        node.hops_away = Some(node_info.hops_away);
+
+        if let Some(my_node_num) = self.my_node_num.filter(|_| node_info.hops_away == 0) {
+            if node_info.num != my_node_num && !node_info.via_mqtt {
+                self.observe_link(
+                    node_info.num,
+                    my_node_num,
+                    Some(node_info.snr),
+                    node_info.last_heard,
+                    LinkSource::Direct,
+                );
+            }
+        }
+
+        true
+    }
+
+    /// Records the hop count of the sender of a mesh packet, and the links reported by the
+    /// payload of the packet.
+    fn handle_mesh_packet(&mut self, packet: &protobufs::MeshPacket) {
+        let seen_at = match packet.rx_time {
+            0 => current_epoch_secs_u32(),
+            rx_time => rx_time,
+        };
+
+        let hops_away = match packet.hop_start {
+            // Firmware older than 2.3 does not set `hop_start`
+            0 => None,
+            hop_start => Some(hop_start.saturating_sub(packet.hop_limit)),
+        };
+
+        let node = self.observe_node(packet.from, seen_at);
+        if hops_away.is_some() {
+            node.hops_away = hops_away;
+        }
+
+        // Packets originating from the connected radio itself carry no reception metadata
+        let is_received = packet.rx_rssi != 0 || packet.rx_snr != 0.0;
+
+        if let Some(my_node_num) = self.my_node_num {
+            if hops_away == Some(0) && is_received && !packet.via_mqtt && packet.from != my_node_num
+            {
+                self.observe_link(
+                    packet.from,
+                    my_node_num,
+                    Some(packet.rx_snr),
+                    seen_at,
+                    LinkSource::Direct,
+                );
+            }
+        }
+
+        let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) = &packet.payload_variant
+        else {
+            return;
+        };
+
+        match protobufs::PortNum::try_from(data.portnum) {
+            Ok(protobufs::PortNum::NeighborinfoApp) => {
+                match protobufs::NeighborInfo::decode(data.payload.as_slice()) {
+                    Ok(neighbor_info) => self.handle_neighbor_info(&neighbor_info, seen_at),
+                    Err(e) => warn!("Failed to decode neighbors of node {}: {e}", packet.from),
+                }
+            }
+            // Only responses carry the complete route towards the destination
+            Ok(protobufs::PortNum::TracerouteApp) if data.request_id != 0 => {
+                match protobufs::RouteDiscovery::decode(data.payload.as_slice()) {
+                    Ok(route_discovery) => {
+                        let result = TracerouteResult::from_route_discovery(
+                            packet.to.into(),
+                            packet.from.into(),
+                            &route_discovery,
+                        );
+                        self.add_traceroute(&result, seen_at);
+                    }
+                    Err(e) => warn!("Failed to decode route from node {}: {e}", packet.from),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Adds a link from every neighbor reported by a node to that node.
+    fn handle_neighbor_info(&mut self, neighbor_info: &protobufs::NeighborInfo, seen_at: u32) {
+        self.observe_node(neighbor_info.node_id, seen_at);
+
+        for neighbor in &neighbor_info.neighbors {
+            self.observe_node(neighbor.node_id, seen_at);
+            self.observe_link(
+                neighbor.node_id,
+                neighbor_info.node_id,
+                Some(neighbor.snr),
+                seen_at,
+                LinkSource::NeighborInfo,
+            );
+        }
+    }
+
+    /// Returns the node with the given number, creating it if needed, and records that it was
+    /// observed at the given time.
+    fn observe_node(&mut self, num: u32, seen_at: u32) -> &mut TopologyNode {
+        let node = self.nodes.entry(num).or_insert_with(|| TopologyNode {
+            num,
+            ..Default::default()
+        });
+
+        node.last_seen = node.last_seen.max(Some(seen_at));
+        node
+    }
+
+    /// Adds or refreshes a link. The SNR of the link is kept if the new observation does not
+    /// report one.
+    fn observe_link(
+        &mut self,
+        from: u32,
+        to: u32,
+        snr: Option<f32>,
+        seen_at: u32,
+        source: LinkSource,
+    ) {
+        let link = self
+            .links
+            .entry((from, to))
+            .or_insert_with(|| TopologyLink {
+                from,
+                to,
+                snr,
+                last_seen: seen_at,
+                source,
+            });
+
+        if seen_at >= link.last_seen {
+            link.snr = snr.or(link.snr);
+            link.last_seen = seen_at;
+            link.source = source;
+        }
+    }
+
+    fn sorted_nodes(&self) -> Vec<&TopologyNode> {
+        let mut nodes: Vec<&TopologyNode> = self.nodes.values().collect();
+        nodes.sort_by_key(|node| node.num);
+        nodes
+    }
+
+    fn sorted_links(&self) -> Vec<&TopologyLink> {
+        let mut links: Vec<&TopologyLink> = self.links.values().collect();
+        links.sort_by_key(|link| (link.from, link.to));
+        links
+    }
+}
+
+/// The state of a depth-first search for the articulation points of an undirected graph.
+struct ArticulationSearch<'a> {
+    adjacency: &'a HashMap<u32, BTreeSet<u32>>,
+    discovery: HashMap<u32, usize>,
+    low: HashMap<u32, usize>,
+    points: BTreeSet<u32>,
+}
+
+impl ArticulationSearch<'_> {
+    fn visit(&mut self, node: u32, parent: Option<u32>) {
+        let order = self.discovery.len();
+        self.discovery.insert(node, order);
+        self.low.insert(node, order);
+
+        let mut children = 0;
+
+        for &neighbor in &self.adjacency[&node] {
+            if Some(neighbor) == parent {
+                continue;
+            }
+
+            if let Some(&neighbor_order) = self.discovery.get(&neighbor) {
+                let low = self.low[&node].min(neighbor_order);
+                self.low.insert(node, low);
+                continue;
+            }
+
+            children += 1;
+            self.visit(neighbor, Some(node));
+
+            let low = self.low[&node].min(self.low[&neighbor]);
+            self.low.insert(node, low);
+
+            if parent.is_some() && self.low[&neighbor] >= order {
+                self.points.insert(node);
+            }
+        }
+
+        if parent.is_none() && children > 1 {
+            self.points.insert(node);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MY_NODE_NUM: u32 = 1;
+
+    fn neighbor_info_packet(
+        node_id: u32,
+        neighbors: &[(u32, f32)],
+        rx_time: u32,
+    ) -> protobufs::FromRadio {
+        let neighbor_info = protobufs::NeighborInfo {
+            node_id,
+            neighbors: neighbors
+                .iter()
+                .map(|&(node_id, snr)| protobufs::Neighbor {
+                    node_id,
+                    snr,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(
+                protobufs::MeshPacket {
+                    from: node_id,
+                    to: u32::MAX,
+                    rx_time,
+                    hop_start: 3,
+                    hop_limit: 2,
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: protobufs::PortNum::NeighborinfoApp as i32,
+                            payload: neighbor_info.encode_to_vec(),
+                            ..Default::default()
+                        },
+                    )),
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+
+    fn my_info() -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::MyInfo(
+                protobufs::MyNodeInfo {
+                    my_node_num: MY_NODE_NUM,
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+
+    #[test]
+    fn builds_links_from_neighbor_info() {
+        let mut topology = MeshTopology::default();
+        topology.handle_packet(&my_info());
+        topology.handle_packet(&neighbor_info_packet(2, &[(1, 7.5), (3, -12.0)], 1_000));
+
+        let link = topology.link(NodeId::new(3), NodeId::new(2)).unwrap();
+        assert_eq!(link.snr, Some(-12.0));
+        assert_eq!(link.source, LinkSource::NeighborInfo);
+        assert_eq!(
+            topology.neighbors(NodeId::new(2)),
+            vec![NodeId::new(1), NodeId::new(3)]
+        );
+        assert_eq!(topology.node(NodeId::new(2)).unwrap().hops_away, Some(1));
+
+        // Node 2 is the only path between the connected radio and node 3
+        assert_eq!(topology.single_points_of_failure(), vec![NodeId::new(2)]);
+        assert_eq!(topology.weak_links(0.0), vec![link]);
+    }
+
+    #[test]
+    fn adds_traceroute_links_and_expires_stale_links() {
+        let mut topology = MeshTopology::new(Duration::from_secs(100));
+        topology.handle_packet(&neighbor_info_packet(2, &[(1, 7.5)], 1_000));
+
+        let result = TracerouteResult::from_route_discovery(
+            NodeId::new(1),
+            NodeId::new(4),
+            &protobufs::RouteDiscovery { route: vec![2, 3] },
+        );
+        topology.add_traceroute(&result, 1_050);
+
+        // The traceroute does not report an SNR, so the reported SNR is kept
+        let link = topology.link(NodeId::new(1), NodeId::new(2)).unwrap();
+        assert_eq!(link.snr, Some(7.5));
+        assert_eq!(link.source, LinkSource::Traceroute);
+        assert_eq!(topology.links().count(), 3);
+
+        let expired = topology.expire_stale_links(1_120);
+        assert!(expired.is_empty());
+
+        let expired = topology.expire_stale_links(1_200);
+        assert_eq!(expired.len(), 3);
+        assert_eq!(topology.links().count(), 0);
+    }
+
+    #[test]
+    fn exports_graph() {
+        let mut topology = MeshTopology::default();
+        topology.handle_packet(&neighbor_info_packet(2, &[(3, -4.5)], 1_000));
+
+        let dot = topology.to_dot();
+        assert!(dot.starts_with("digraph mesh {\n"));
+        assert!(dot.contains("  \"3\" -> \"2\" [label=\"-4.50 dB\"];\n"));
+
+        let graphml = topology.to_graphml();
+        assert!(graphml.contains("<node id=\"2\"><data key=\"hops_away\">1</data></node>"));
+        assert!(graphml.contains("<edge source=\"3\" target=\"2\"><data key=\"snr\">-4.5</data>"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn exports_graph_as_json() {
+        let mut topology = MeshTopology::default();
+        topology.handle_packet(&neighbor_info_packet(2, &[(3, -4.5)], 1_000));
+
+        let json: serde_json::Value = serde_json::from_str(&topology.to_json().unwrap()).unwrap();
+
+        assert_eq!(json["links"][0]["from"], 3);
+        assert_eq!(json["links"][0]["to"], 2);
+        assert_eq!(json["links"][0]["source"], "neighborInfo");
+        assert_eq!(json["nodes"].as_array().unwrap().len(), 2);
+    }
+}
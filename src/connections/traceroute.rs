@@ -0,0 +1,284 @@
+use crate::protobufs;
+
+use super::wrappers::NodeId;
+
+/// The maximum number of hops a packet can be relayed over within the mesh.
+pub const MAX_HOP_LIMIT: u32 = 7;
+
+/// A struct that represents a single node along a traced route.
+///
+/// # Fields
+///
+/// * `node_id` - The ID of the node.
+/// * `snr` - The signal-to-noise ratio in dB at which this node received the packet from the
+///     previous node of the route, or `None` if it was not reported. The current protobuf
+///     definitions do not report per-hop SNR, so this field is currently always `None`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RouteHop {
+    pub node_id: NodeId,
+    pub snr: Option<f32>,
+}
+
+impl RouteHop {
+    fn new(node_id: NodeId) -> Self {
+        RouteHop { node_id, snr: None }
+    }
+}
+
+/// A struct that represents the result of a traceroute request.
+///
+/// Both routes start at the node that sent the packet and end at the node that received it,
+/// so the forward route starts at the connected radio and ends at the destination, and the
+/// return route starts at the destination and ends at the connected radio.
+///
+/// # Fields
+///
+/// * `destination` - The ID of the node that the route was traced to.
+/// * `route_towards` - The nodes the request passed through on its way to the destination.
+/// * `route_back` - The nodes the response passed through on its way back, or `None` if the
+///     return route was not reported. The current protobuf definitions only record the route
+///     towards the destination, so this field is currently always `None`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TracerouteResult {
+    pub destination: NodeId,
+    pub route_towards: Vec<RouteHop>,
+    pub route_back: Option<Vec<RouteHop>>,
+}
+
+impl TracerouteResult {
+    /// Builds the result of a traceroute from the `RouteDiscovery` payload of a response.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The ID of the node that sent the traceroute request.
+    /// * `destination` - The ID of the node that answered the traceroute request.
+    /// * `route_discovery` - The `RouteDiscovery` payload of the response.
+    ///
+    /// # Returns
+    ///
+    /// The `TracerouteResult` of the traceroute, including both endpoints in each route.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let result = TracerouteResult::from_route_discovery(
+    ///     NodeId::new(1),
+    ///     NodeId::new(3),
+    ///     &protobufs::RouteDiscovery { route: vec![2] },
+    /// );
+    ///
+    /// assert_eq!(result.hops_towards(), 2);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn from_route_discovery(
+        origin: NodeId,
+        destination: NodeId,
+        route_discovery: &protobufs::RouteDiscovery,
+    ) -> Self {
+        let route_towards = std::iter::once(origin)
+            .chain(route_discovery.route.iter().copied().map(NodeId::from))
+            .chain(std::iter::once(destination))
+            .map(RouteHop::new)
+            .collect();
+
+        TracerouteResult {
+            destination,
+            route_towards,
+            route_back: None,
+        }
+    }
+
+    /// Returns the number of hops the request was relayed over to reach the destination.
+    pub fn hops_towards(&self) -> usize {
+        self.route_towards.len().saturating_sub(1)
+    }
+
+    /// Returns the number of hops the response was relayed over, if the return route is known.
+    pub fn hops_back(&self) -> Option<usize> {
+        self.route_back
+            .as_ref()
+            .map(|route| route.len().saturating_sub(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use prost::Message;
+
+    use crate::connections::mock_radio::MockRadio;
+    use crate::connections::test_support::{
+        configured_stream_api, routing_error_packet, TestRouter, MY_NODE_NUM,
+    };
+    use crate::connections::wrappers::mesh_channel::MeshChannel;
+    use crate::errors_internal::Error;
+
+    use super::*;
+
+    const DESTINATION_NODE_NUM: u32 = 0xdeadbeef;
+
+    /// Waits for the mock radio to receive a traceroute request.
+    async fn next_traceroute_request(mock_radio: &MockRadio) -> protobufs::MeshPacket {
+        loop {
+            let request = mock_radio
+                .received_mesh_packets()
+                .into_iter()
+                .find(|packet| {
+                    matches!(
+                        &packet.payload_variant,
+                        Some(protobufs::mesh_packet::PayloadVariant::Decoded(data))
+                            if data.portnum == protobufs::PortNum::TracerouteApp as i32
+                    )
+                });
+
+            if let Some(request) = request {
+                return request;
+            }
+
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    fn traceroute_response(from: u32, request_id: u32, route: Vec<u32>) -> protobufs::MeshPacket {
+        protobufs::MeshPacket {
+            from,
+            to: MY_NODE_NUM,
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                protobufs::Data {
+                    portnum: protobufs::PortNum::TracerouteApp as i32,
+                    payload: protobufs::RouteDiscovery { route }.encode_to_vec(),
+                    request_id,
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn builds_route_with_both_endpoints() {
+        let result = TracerouteResult::from_route_discovery(
+            NodeId::new(1),
+            NodeId::new(4),
+            &protobufs::RouteDiscovery { route: vec![2, 3] },
+        );
+
+        let node_ids: Vec<u32> = result
+            .route_towards
+            .iter()
+            .map(|hop| hop.node_id.id())
+            .collect();
+
+        assert_eq!(node_ids, vec![1, 2, 3, 4]);
+        assert_eq!(result.hops_towards(), 3);
+        assert_eq!(result.hops_back(), None);
+    }
+
+    #[tokio::test]
+    async fn traces_route_to_destination() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        let respond = async {
+            let request = next_traceroute_request(&mock_radio).await;
+
+            assert_eq!(request.to, DESTINATION_NODE_NUM);
+            assert_eq!(request.channel, 1);
+            assert_eq!(request.hop_limit, 5);
+
+            mock_radio
+                .inject_mesh_packet(traceroute_response(
+                    DESTINATION_NODE_NUM,
+                    request.id,
+                    vec![7, 8],
+                ))
+                .unwrap();
+        };
+
+        let (result, _) = tokio::join!(
+            stream_api.traceroute(
+                &mut router,
+                NodeId::new(DESTINATION_NODE_NUM),
+                MeshChannel::new(1).unwrap(),
+                5,
+                Duration::from_secs(1),
+            ),
+            respond
+        );
+
+        let result = result.unwrap();
+        assert_eq!(result.destination, NodeId::new(DESTINATION_NODE_NUM));
+        assert_eq!(result.hops_towards(), 3);
+        assert_eq!(result.route_towards[1].node_id, NodeId::new(7));
+    }
+
+    #[tokio::test]
+    async fn fails_when_destination_is_unreachable() {
+        let (mock_radio, mut stream_api) = configured_stream_api().await;
+        let mut router = TestRouter;
+
+        let respond = async {
+            let request = next_traceroute_request(&mock_radio).await;
+
+            mock_radio
+                .inject_mesh_packet(routing_error_packet(
+                    MY_NODE_NUM,
+                    request.id,
+                    protobufs::routing::Error::NoRoute,
+                ))
+                .unwrap();
+        };
+
+        let (result, _) = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(
+                stream_api.traceroute(
+                    &mut router,
+                    NodeId::new(DESTINATION_NODE_NUM),
+                    MeshChannel::new(0).unwrap(),
+                    3,
+                    Duration::from_secs(60),
+                ),
+                respond
+            )
+        })
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::RequestNak {
+                reason: protobufs::routing::Error::NoRoute,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_hop_limit() {
+        let (_mock_radio, mut stream_api) = configured_stream_api().await;
+
+        let result = stream_api
+            .traceroute(
+                &mut TestRouter,
+                NodeId::new(DESTINATION_NODE_NUM),
+                MeshChannel::new(0).unwrap(),
+                MAX_HOP_LIMIT + 1,
+                Duration::from_secs(1),
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidHopLimit { hop_limit: 8 })
+        ));
+    }
+}
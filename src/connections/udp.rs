@@ -0,0 +1,651 @@
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+
+use log::{debug, trace, warn};
+use prost::Message;
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::{net::UdpSocket, sync::mpsc::UnboundedSender, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+
+use crate::errors_internal::{Error, InternalStreamError};
+use crate::packet::PacketReceiver;
+use crate::protobufs;
+use crate::utils_internal::{current_epoch_secs_u32, generate_rand_id};
+
+use super::crypto::{channel_hash, encrypt_packet, ChannelKey, ChannelKeyring};
+use super::wrappers::NodeId;
+use super::PacketDestination;
+
+/// The multicast group and port that the firmware exchanges mesh packets on.
+pub const DEFAULT_UDP_MULTICAST_ADDRESS: SocketAddrV4 =
+    SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 69), 4403);
+
+/// The size of the receive buffer, which fits any encoded `MeshPacket`.
+const MAX_DATAGRAM_LENGTH: usize = 1500;
+
+/// The number of recently received packets remembered to drop duplicates.
+const RECENT_PACKET_COUNT: usize = 256;
+
+/// A struct that configures the multicast group joined by a `UdpTransport`.
+///
+/// # Fields
+///
+/// * `multicast_address` - The multicast group and port that packets are exchanged on.
+/// * `interface` - The address of the interface to join the group on. The unspecified address
+///     lets the operating system pick the interface.
+/// * `ttl` - The multicast time to live of sent packets, which limits them to the local network.
+/// * `hop_limit` - The hop limit of the packets sent through the `send_data` method.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UdpTransportOptions {
+    pub multicast_address: SocketAddrV4,
+    pub interface: Ipv4Addr,
+    pub ttl: u32,
+    pub hop_limit: u32,
+}
+
+impl Default for UdpTransportOptions {
+    fn default() -> Self {
+        UdpTransportOptions {
+            multicast_address: DEFAULT_UDP_MULTICAST_ADDRESS,
+            interface: Ipv4Addr::UNSPECIFIED,
+            ttl: 1,
+            hop_limit: 3,
+        }
+    }
+}
+
+/// A struct that exchanges mesh packets with the nodes of the local network over UDP multicast,
+/// which allows a host to participate in the mesh without a radio.
+///
+/// Newer firmware versions and `meshtasticd` send every packet they transmit as an encoded
+/// `MeshPacket` to a multicast group. The transport joins the group, decrypts the received
+/// packets with the keys of its `ChannelKeyring`, and delivers them through a `PacketReceiver`
+/// as `FromRadio` packets, just like the packets received over a serial or TCP connection.
+/// Packets that cannot be decrypted are delivered with their `Encrypted` payload, and packets
+/// that are received more than once are only delivered the first time.
+///
+/// The transport stops receiving when the `shutdown` method is called or when the struct is
+/// dropped, at which point the `PacketReceiver` is closed.
+///
+/// # Examples
+///
+/// ```
+/// let mut keyring = ChannelKeyring::new();
+/// keyring.add("LongFast", ChannelKey::from_psk(&[1])?);
+///
+/// let node_id = NodeId::new(generate_rand_id());
+/// let (packet_rx, transport) =
+///     UdpTransport::bind(node_id, keyring, UdpTransportOptions::default()).await?;
+///
+/// let mut events = MeshEventStream::new(packet_rx);
+/// while let Some(event) = events.recv().await {
+///     println!("{event:?}");
+/// }
+/// ```
+#[derive(Debug)]
+pub struct UdpTransport {
+    socket: Arc<UdpSocket>,
+    target: SocketAddr,
+    node_id: NodeId,
+    hop_limit: u32,
+    cancellation_token: CancellationToken,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl UdpTransport {
+    /// Joins the multicast group of the mesh.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - The node ID of the host, which is used as the sender of outgoing packets.
+    ///     Received packets sent from this node ID are dropped.
+    /// * `keyring` - The `ChannelKeyring` holding the keys of the channels to decrypt.
+    /// * `options` - The `UdpTransportOptions` selecting the multicast group.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `PacketReceiver` of the received packets and the transport.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let (packet_rx, transport) =
+    ///     UdpTransport::bind(node_id, keyring, UdpTransportOptions::default()).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::StreamBuildError` if the socket cannot be bound or the multicast group
+    /// cannot be joined.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn bind(
+        node_id: NodeId,
+        keyring: ChannelKeyring,
+        options: UdpTransportOptions,
+    ) -> Result<(PacketReceiver, UdpTransport), Error> {
+        let build_error = |description: &str| {
+            let description = description.to_string();
+            move |e: std::io::Error| Error::StreamBuildError {
+                source: Box::new(e),
+                description,
+            }
+        };
+
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+            .map_err(build_error("Failed to create UDP socket"))?;
+
+        // * Other clients and meshtasticd may listen on the same port of the host
+        socket
+            .set_reuse_address(true)
+            .map_err(build_error("Failed to share UDP port"))?;
+
+        let bind_address =
+            SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, options.multicast_address.port());
+        socket
+            .bind(&SocketAddr::V4(bind_address).into())
+            .map_err(build_error("Failed to bind UDP socket"))?;
+
+        socket
+            .join_multicast_v4(options.multicast_address.ip(), &options.interface)
+            .map_err(build_error("Failed to join multicast group"))?;
+        socket
+            .set_multicast_ttl_v4(options.ttl)
+            .map_err(build_error("Failed to set multicast TTL"))?;
+
+        if !options.interface.is_unspecified() {
+            socket
+                .set_multicast_if_v4(&options.interface)
+                .map_err(build_error("Failed to set multicast interface"))?;
+        }
+
+        UdpTransport::from_socket(socket.into(), node_id, keyring, options)
+    }
+
+    /// Uses an existing socket to exchange packets with the address of the given options, which
+    /// does not need to be a multicast group. This allows the transport to exchange packets with
+    /// a single peer, or to run over sockets configured by the caller.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket` - The bound socket to receive packets on.
+    /// * `node_id` - The node ID of the host, which is used as the sender of outgoing packets.
+    /// * `keyring` - The `ChannelKeyring` holding the keys of the channels to decrypt.
+    /// * `options` - The `UdpTransportOptions` whose `multicast_address` packets are sent to.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the `PacketReceiver` of the received packets and the transport.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let socket = std::net::UdpSocket::bind("127.0.0.1:4403")?;
+    /// let options = UdpTransportOptions {
+    ///     multicast_address: "127.0.0.1:4404".parse()?,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let (packet_rx, transport) = UdpTransport::from_socket(socket, node_id, keyring, options)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::StreamBuildError` if the socket cannot be registered with the runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    ///
+    pub fn from_socket(
+        socket: std::net::UdpSocket,
+        node_id: NodeId,
+        keyring: ChannelKeyring,
+        options: UdpTransportOptions,
+    ) -> Result<(PacketReceiver, UdpTransport), Error> {
+        let socket = socket
+            .set_nonblocking(true)
+            .and_then(|_| UdpSocket::from_std(socket))
+            .map_err(|e| Error::StreamBuildError {
+                source: Box::new(e),
+                description: "Failed to register UDP socket".to_string(),
+            })?;
+        let socket = Arc::new(socket);
+
+        let (packet_tx, packet_rx) = tokio::sync::mpsc::unbounded_channel();
+        let cancellation_token = CancellationToken::new();
+
+        let handle = tokio::spawn(receive_packets(
+            socket.clone(),
+            node_id,
+            keyring,
+            packet_tx,
+            cancellation_token.clone(),
+        ));
+
+        let transport = UdpTransport {
+            socket,
+            target: SocketAddr::V4(options.multicast_address),
+            node_id,
+            hop_limit: options.hop_limit,
+            cancellation_token,
+            handle: Some(handle),
+        };
+
+        Ok((packet_rx, transport))
+    }
+
+    /// Returns the node ID that outgoing packets are sent from.
+    pub fn node_id(&self) -> NodeId {
+        self.node_id
+    }
+
+    /// Returns the local address of the socket of the transport.
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.socket
+            .local_addr()
+            .map_err(|e| Error::StreamBuildError {
+                source: Box::new(e),
+                description: "Failed to read address of UDP socket".to_string(),
+            })
+    }
+
+    /// Sends a mesh packet to the group as is. Packets with a `Decoded` payload are sent in plain
+    /// text, so packets on encrypted channels should be encrypted with `encrypt_packet` first.
+    ///
+    /// # Arguments
+    ///
+    /// * `mesh_packet` - The `MeshPacket` to send.
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the packet was sent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mesh_packet = encrypt_packet(&mesh_packet, &key)?;
+    /// transport.send_mesh_packet(&mesh_packet).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InternalStreamError` if the packet cannot be written to the socket.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn send_mesh_packet(&self, mesh_packet: &protobufs::MeshPacket) -> Result<(), Error> {
+        self.socket
+            .send_to(&mesh_packet.encode_to_vec(), self.target)
+            .await
+            .map_err(|e| InternalStreamError::StreamWriteError {
+                source: Box::new(e),
+            })?;
+
+        Ok(())
+    }
+
+    /// Builds a mesh packet from the host, encrypts it with the key of a channel, and sends it
+    /// to the group.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The `Data` payload of the packet.
+    /// * `destination` - A `PacketDestination` enum that specifies the destination of the packet.
+    ///     The `Local` destination addresses the host itself.
+    /// * `channel` - The name of the channel to send the packet on, such as `LongFast`.
+    /// * `key` - The `ChannelKey` of the channel.
+    /// * `want_ack` - Whether the destination should acknowledge the packet.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the ID of the sent packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let data = protobufs::Data {
+    ///     portnum: protobufs::PortNum::TextMessageApp as i32,
+    ///     payload: b"Hello from the LAN".to_vec(),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let key = ChannelKey::from_psk(&[1])?;
+    /// transport.send_data(data, PacketDestination::Broadcast, "LongFast", &key, false).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InternalStreamError` if the packet cannot be written to the socket.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn send_data(
+        &self,
+        data: protobufs::Data,
+        destination: PacketDestination,
+        channel: &str,
+        key: &ChannelKey,
+        want_ack: bool,
+    ) -> Result<u32, Error> {
+        let mesh_packet = protobufs::MeshPacket {
+            id: generate_rand_id(),
+            from: self.node_id.id(),
+            to: destination.node_id(self.node_id).id(),
+            channel: channel_hash(channel, key).into(),
+            hop_limit: self.hop_limit,
+            hop_start: self.hop_limit,
+            want_ack,
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)),
+            ..Default::default()
+        };
+
+        let mesh_packet = encrypt_packet(&mesh_packet, key)?;
+        self.send_mesh_packet(&mesh_packet).await?;
+
+        Ok(mesh_packet.id)
+    }
+
+    /// Stops receiving packets and waits for the background task to exit.
+    ///
+    /// # Arguments
+    ///
+    /// None
+    ///
+    /// # Returns
+    ///
+    /// A result indicating whether the background task exited cleanly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// transport.shutdown().await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::JoinError` if the background task panicked.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub async fn shutdown(mut self) -> Result<(), Error> {
+        self.cancellation_token.cancel();
+
+        match self.handle.take() {
+            Some(handle) => Ok(handle.await?),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for UdpTransport {
+    fn drop(&mut self) {
+        self.cancellation_token.cancel();
+    }
+}
+
+/// Receives packets until the transport is cancelled or the `PacketReceiver` is dropped.
+async fn receive_packets(
+    socket: Arc<UdpSocket>,
+    node_id: NodeId,
+    keyring: ChannelKeyring,
+    packet_tx: UnboundedSender<protobufs::FromRadio>,
+    cancellation_token: CancellationToken,
+) {
+    let mut buffer = [0u8; MAX_DATAGRAM_LENGTH];
+    let mut recent_packets: VecDeque<(u32, u32)> = VecDeque::with_capacity(RECENT_PACKET_COUNT);
+
+    loop {
+        let received = tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            received = socket.recv_from(&mut buffer) => received,
+        };
+
+        let (length, peer) = match received {
+            Ok(received) => received,
+            Err(e) => {
+                warn!("Failed to receive UDP packet: {e}");
+                continue;
+            }
+        };
+
+        let Some(mesh_packet) = decode_datagram(&buffer[..length], node_id, &keyring) else {
+            trace!("Ignoring UDP datagram of {length} bytes from {peer}");
+            continue;
+        };
+
+        // * Packets are received once from every node that rebroadcasts them
+        let key = (mesh_packet.from, mesh_packet.id);
+        if recent_packets.contains(&key) {
+            continue;
+        }
+
+        if recent_packets.len() == RECENT_PACKET_COUNT {
+            recent_packets.pop_front();
+        }
+        recent_packets.push_back(key);
+
+        let packet = protobufs::FromRadio {
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)),
+            ..Default::default()
+        };
+
+        if packet_tx.send(packet).is_err() {
+            debug!("Stopping UDP transport after packet receiver was dropped");
+            break;
+        }
+    }
+}
+
+/// Decodes a received datagram, returning `None` for datagrams that are not mesh packets and for
+/// packets sent by the host itself.
+pub(crate) fn decode_datagram(
+    datagram: &[u8],
+    node_id: NodeId,
+    keyring: &ChannelKeyring,
+) -> Option<protobufs::MeshPacket> {
+    let mut mesh_packet = protobufs::MeshPacket::decode(datagram).ok()?;
+
+    if mesh_packet.from == 0 || node_id == mesh_packet.from {
+        return None;
+    }
+
+    if let Some(protobufs::mesh_packet::PayloadVariant::Encrypted(_)) = mesh_packet.payload_variant
+    {
+        if let Ok(decrypted) = keyring.decrypt(&mesh_packet) {
+            mesh_packet = decrypted;
+        }
+    }
+
+    if mesh_packet.rx_time == 0 {
+        mesh_packet.rx_time = current_epoch_secs_u32();
+    }
+
+    Some(mesh_packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::connections::events::{MeshEvent, MeshEventStream};
+    use crate::connections::payload::DecodedPayload;
+
+    use super::*;
+
+    const MY_NODE_NUM: u32 = 42;
+    const PEER_NODE_NUM: u32 = 43;
+
+    fn loopback_pair() -> (
+        (PacketReceiver, UdpTransport),
+        (PacketReceiver, UdpTransport),
+    ) {
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let peer_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let address = |socket: &std::net::UdpSocket| match socket.local_addr().unwrap() {
+            SocketAddr::V4(address) => address,
+            SocketAddr::V6(_) => unreachable!(),
+        };
+        let options = |address| UdpTransportOptions {
+            multicast_address: address,
+            ..Default::default()
+        };
+
+        let mut keyring = ChannelKeyring::new();
+        keyring.add("LongFast", ChannelKey::from_psk(&[1]).unwrap());
+
+        let peer_options = options(address(&socket));
+        let transport = UdpTransport::from_socket(
+            socket,
+            NodeId::new(MY_NODE_NUM),
+            keyring.clone(),
+            options(address(&peer_socket)),
+        )
+        .unwrap();
+        let peer = UdpTransport::from_socket(
+            peer_socket,
+            NodeId::new(PEER_NODE_NUM),
+            keyring,
+            peer_options,
+        )
+        .unwrap();
+
+        (transport, peer)
+    }
+
+    fn text(payload: &str) -> protobufs::Data {
+        protobufs::Data {
+            portnum: protobufs::PortNum::TextMessageApp as i32,
+            payload: payload.as_bytes().to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn exchanges_encrypted_packets_over_loopback() {
+        let ((_packet_rx, transport), (peer_rx, peer)) = loopback_pair();
+        let key = ChannelKey::from_psk(&[1]).unwrap();
+
+        let packet_id = transport
+            .send_data(
+                text("hello"),
+                PacketDestination::Broadcast,
+                "LongFast",
+                &key,
+                false,
+            )
+            .await
+            .unwrap();
+
+        let mut events = MeshEventStream::new(peer_rx);
+        let event = tokio::time::timeout(Duration::from_secs(1), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        match event {
+            MeshEvent::Packet {
+                packet,
+                payload: DecodedPayload::Text(text),
+            } => {
+                assert_eq!(packet.id, packet_id);
+                assert_eq!(packet.from, MY_NODE_NUM);
+                assert_eq!(packet.channel, u32::from(channel_hash("LongFast", &key)));
+                assert_eq!(text, "hello");
+            }
+            event => panic!("Expected a text message, received {event:?}"),
+        }
+
+        peer.shutdown().await.unwrap();
+        assert!(events.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn drops_duplicate_and_undecryptable_packets() {
+        let ((_packet_rx, transport), (mut peer_rx, _peer)) = loopback_pair();
+        let unknown_key = ChannelKey::from_psk(&[0xaa; 16]).unwrap();
+
+        let mesh_packet = protobufs::MeshPacket {
+            id: 7,
+            from: MY_NODE_NUM,
+            to: u32::MAX,
+            channel: 1,
+            payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(text("hi"))),
+            ..Default::default()
+        };
+        let encrypted = encrypt_packet(&mesh_packet, &unknown_key).unwrap();
+
+        transport.send_mesh_packet(&encrypted).await.unwrap();
+        transport.send_mesh_packet(&encrypted).await.unwrap();
+        transport
+            .send_mesh_packet(&protobufs::MeshPacket {
+                id: 8,
+                ..mesh_packet
+            })
+            .await
+            .unwrap();
+
+        let mut received = vec![];
+        for _ in 0..2 {
+            let packet = tokio::time::timeout(Duration::from_secs(1), peer_rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+
+            match packet.payload_variant {
+                Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)) => {
+                    received.push(mesh_packet)
+                }
+                payload => panic!("Expected a mesh packet, received {payload:?}"),
+            }
+        }
+
+        assert_eq!(received[0].id, 7);
+        assert_eq!(received[0].payload_variant, encrypted.payload_variant);
+        assert_eq!(received[1].id, 8);
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), peer_rx.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn ignores_own_and_invalid_datagrams() {
+        let keyring = ChannelKeyring::new();
+        let own_packet = protobufs::MeshPacket {
+            id: 1,
+            from: MY_NODE_NUM,
+            ..Default::default()
+        };
+
+        assert!(decode_datagram(
+            &own_packet.encode_to_vec(),
+            NodeId::new(MY_NODE_NUM),
+            &keyring
+        )
+        .is_none());
+        assert!(decode_datagram(&[0xff, 0xff, 0xff], NodeId::new(MY_NODE_NUM), &keyring).is_none());
+
+        let peer_packet = decode_datagram(
+            &own_packet.encode_to_vec(),
+            NodeId::new(PEER_NODE_NUM),
+            &keyring,
+        )
+        .unwrap();
+        assert_eq!(peer_packet.from, MY_NODE_NUM);
+        assert_ne!(peer_packet.rx_time, 0);
+    }
+}
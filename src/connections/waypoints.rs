@@ -0,0 +1,809 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use log::warn;
+use prost::Message;
+
+use crate::errors_internal::Error;
+use crate::protobufs;
+use crate::utils_internal::generate_rand_id;
+
+use super::wrappers::NodeId;
+use super::xml_document::{escape, XmlElement};
+
+/// The namespace of the GPX extension elements that carry the Meshtastic fields of a waypoint.
+pub(crate) const GPX_EXTENSIONS_NAMESPACE: &str = "urn:meshtastic:gpx";
+
+/// An enum that represents a change to the waypoints tracked by a `WaypointManager`.
+///
+/// # Variants
+///
+/// * `Added` - A waypoint that was not yet known has been added.
+/// * `Updated` - A known waypoint has been replaced by a newer version.
+/// * `Deleted` - A known waypoint has been deleted by its owner or has expired.
+/// * `Rejected` - A node that is not allowed to edit a locked waypoint attempted to change it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WaypointChange {
+    Added(protobufs::Waypoint),
+    Updated(protobufs::Waypoint),
+    Deleted(protobufs::Waypoint),
+    Rejected {
+        waypoint: protobufs::Waypoint,
+        from: NodeId,
+    },
+}
+
+/// A struct that keeps track of the waypoints shared in the mesh.
+///
+/// Waypoints are tracked by their ID, and are updated by passing every `FromRadio` packet received
+/// from the radio to the `handle_packet` method. A waypoint is deleted by sending it again with an
+/// `expire` time in the past, and a waypoint with a non-zero `locked_to` field can only be changed
+/// or deleted by the node it is locked to.
+///
+/// Waypoints can be exchanged with mapping tools through GPX and KML documents. The Meshtastic
+/// fields that these formats do not support are stored in GPX extensions and KML extended data,
+/// so that exported documents can be imported again without losing information.
+#[derive(Clone, Debug, Default)]
+pub struct WaypointManager {
+    waypoints: BTreeMap<u32, protobufs::Waypoint>,
+}
+
+impl WaypointManager {
+    /// Creates an empty waypoint manager.
+    pub fn new() -> WaypointManager {
+        WaypointManager::default()
+    }
+
+    /// Returns the waypoint with the given ID, if it is known.
+    pub fn waypoint(&self, waypoint_id: u32) -> Option<&protobufs::Waypoint> {
+        self.waypoints.get(&waypoint_id)
+    }
+
+    /// Returns the known waypoints, sorted by ID.
+    pub fn waypoints(&self) -> impl Iterator<Item = &protobufs::Waypoint> {
+        self.waypoints.values()
+    }
+
+    /// Returns whether the given node is allowed to change or delete the waypoint with the given
+    /// ID. Unknown waypoints can be created by any node.
+    pub fn can_edit(&self, waypoint_id: u32, node_id: NodeId) -> bool {
+        self.waypoints
+            .get(&waypoint_id)
+            .is_none_or(|waypoint| waypoint.locked_to == 0 || node_id == waypoint.locked_to)
+    }
+
+    /// Updates the manager from a packet received from the radio. Packets that are not sent on
+    /// the `WaypointApp` port are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - A `FromRadio` packet received from the radio.
+    /// * `now` - The current time in seconds since the Unix epoch, used to detect deletions.
+    ///
+    /// # Returns
+    ///
+    /// The `WaypointChange` caused by the packet, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut waypoints = WaypointManager::new();
+    ///
+    /// while let Some(packet) = decoded_listener.recv().await {
+    ///     if let Some(WaypointChange::Added(waypoint)) =
+    ///         waypoints.handle_packet(&packet, current_epoch_secs_u32())
+    ///     {
+    ///         println!("New waypoint: {}", waypoint.name);
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn handle_packet(
+        &mut self,
+        packet: &protobufs::FromRadio,
+        now: u32,
+    ) -> Option<WaypointChange> {
+        let Some(protobufs::from_radio::PayloadVariant::Packet(mesh_packet)) =
+            &packet.payload_variant
+        else {
+            return None;
+        };
+
+        let Some(protobufs::mesh_packet::PayloadVariant::Decoded(data)) =
+            &mesh_packet.payload_variant
+        else {
+            return None;
+        };
+
+        if data.portnum != protobufs::PortNum::WaypointApp as i32 {
+            return None;
+        }
+
+        match protobufs::Waypoint::decode(data.payload.as_slice()) {
+            Ok(waypoint) => self.apply(mesh_packet.from.into(), waypoint, now),
+            Err(e) => {
+                warn!("Failed to decode Waypoint: {e}");
+                None
+            }
+        }
+    }
+
+    /// Applies a waypoint sent by a node, adding, replacing or deleting the waypoint with the same
+    /// ID. Waypoints with an ID of `0` are invalid and are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The ID of the node that sent the waypoint.
+    /// * `waypoint` - The waypoint sent by the node.
+    /// * `now` - The current time in seconds since the Unix epoch, used to detect deletions.
+    ///
+    /// # Returns
+    ///
+    /// The `WaypointChange` caused by the waypoint, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let change = waypoints.apply(NodeId::new(7), waypoint, current_epoch_secs_u32());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn apply(
+        &mut self,
+        from: NodeId,
+        waypoint: protobufs::Waypoint,
+        now: u32,
+    ) -> Option<WaypointChange> {
+        if waypoint.id == 0 {
+            return None;
+        }
+
+        if !self.can_edit(waypoint.id, from) {
+            return Some(WaypointChange::Rejected { waypoint, from });
+        }
+
+        if is_expired(&waypoint, now) {
+            return self
+                .waypoints
+                .remove(&waypoint.id)
+                .map(|_| WaypointChange::Deleted(waypoint));
+        }
+
+        match self.waypoints.insert(waypoint.id, waypoint.clone()) {
+            Some(_) => Some(WaypointChange::Updated(waypoint)),
+            None => Some(WaypointChange::Added(waypoint)),
+        }
+    }
+
+    /// Adds or replaces a waypoint on behalf of the given node, and returns the waypoint to send
+    /// over the mesh through the `send_waypoint` method.
+    ///
+    /// # Arguments
+    ///
+    /// * `waypoint` - The new version of the waypoint. A random ID is assigned to waypoints with an
+    ///     ID of `0`.
+    /// * `editor` - The ID of the node making the change, usually the connected radio.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the stored waypoint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let waypoint = waypoints.update(waypoint, my_node_id)?;
+    /// stream_api
+    ///     .send_waypoint(packet_router, waypoint, PacketDestination::Broadcast, true, channel)
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::WaypointLocked` if the waypoint is locked to another node.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn update(
+        &mut self,
+        waypoint: protobufs::Waypoint,
+        editor: NodeId,
+    ) -> Result<protobufs::Waypoint, Error> {
+        let mut waypoint = waypoint;
+
+        if waypoint.id == 0 {
+            waypoint.id = generate_rand_id();
+        }
+
+        self.check_editable(waypoint.id, editor)?;
+        self.waypoints.insert(waypoint.id, waypoint.clone());
+
+        Ok(waypoint)
+    }
+
+    /// Deletes a waypoint on behalf of the given node, and returns the deletion to send over the
+    /// mesh through the `send_waypoint` method. Deletions are sent as the waypoint with an `expire`
+    /// time of `now`.
+    ///
+    /// # Arguments
+    ///
+    /// * `waypoint_id` - The ID of the waypoint to delete.
+    /// * `editor` - The ID of the node making the change, usually the connected radio.
+    /// * `now` - The current time in seconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the expired waypoint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let deletion = waypoints.delete(waypoint_id, my_node_id, current_epoch_secs_u32())?;
+    /// stream_api
+    ///     .send_waypoint(packet_router, deletion, PacketDestination::Broadcast, true, channel)
+    ///     .await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::WaypointNotFound` if the waypoint is not known, and with
+    /// `Error::WaypointLocked` if the waypoint is locked to another node.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn delete(
+        &mut self,
+        waypoint_id: u32,
+        editor: NodeId,
+        now: u32,
+    ) -> Result<protobufs::Waypoint, Error> {
+        self.check_editable(waypoint_id, editor)?;
+
+        let mut waypoint = self
+            .waypoints
+            .remove(&waypoint_id)
+            .ok_or(Error::WaypointNotFound { waypoint_id })?;
+
+        // * An expire time of 0 means that the waypoint never expires
+        waypoint.expire = now.max(1);
+
+        Ok(waypoint)
+    }
+
+    /// Removes the waypoints whose `expire` time has passed.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time in seconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// The waypoints that were removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// for waypoint in waypoints.expire(current_epoch_secs_u32()) {
+    ///     println!("Waypoint {} expired", waypoint.name);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// None
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn expire(&mut self, now: u32) -> Vec<protobufs::Waypoint> {
+        let expired: Vec<u32> = self
+            .waypoints
+            .values()
+            .filter(|waypoint| is_expired(waypoint, now))
+            .map(|waypoint| waypoint.id)
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|waypoint_id| self.waypoints.remove(&waypoint_id))
+            .collect()
+    }
+
+    /// Renders the known waypoints as a GPX 1.1 document.
+    pub fn to_gpx(&self) -> String {
+        let mut gpx = String::new();
+
+        let _ = writeln!(gpx, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(
+            gpx,
+            r#"<gpx version="1.1" creator="{}" xmlns="http://www.topografix.com/GPX/1/1" xmlns:meshtastic="{GPX_EXTENSIONS_NAMESPACE}">"#,
+            env!("CARGO_PKG_NAME")
+        );
+
+        for waypoint in self.waypoints.values() {
+            let _ = writeln!(
+                gpx,
+                r#"  <wpt lat="{:.7}" lon="{:.7}">"#,
+                degrees(waypoint.latitude_i),
+                degrees(waypoint.longitude_i)
+            );
+            let _ = writeln!(gpx, "    <name>{}</name>", escape(&waypoint.name));
+
+            if !waypoint.description.is_empty() {
+                let _ = writeln!(gpx, "    <desc>{}</desc>", escape(&waypoint.description));
+            }
+
+            if let Some(icon) = char::from_u32(waypoint.icon).filter(|_| waypoint.icon != 0) {
+                let _ = writeln!(gpx, "    <sym>{}</sym>", escape(&icon.to_string()));
+            }
+
+            let _ = writeln!(gpx, "    <extensions>");
+            for (name, value) in extension_fields(waypoint) {
+                let _ = writeln!(gpx, "      <meshtastic:{name}>{value}</meshtastic:{name}>");
+            }
+            let _ = writeln!(gpx, "    </extensions>");
+            let _ = writeln!(gpx, "  </wpt>");
+        }
+
+        let _ = writeln!(gpx, "</gpx>");
+        gpx
+    }
+
+    /// Renders the known waypoints as a KML 2.2 document.
+    pub fn to_kml(&self) -> String {
+        let mut kml = String::new();
+
+        let _ = writeln!(kml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        let _ = writeln!(kml, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#);
+        let _ = writeln!(kml, "  <Document>");
+
+        for waypoint in self.waypoints.values() {
+            let _ = writeln!(kml, "    <Placemark>");
+            let _ = writeln!(kml, "      <name>{}</name>", escape(&waypoint.name));
+
+            if !waypoint.description.is_empty() {
+                let _ = writeln!(
+                    kml,
+                    "      <description>{}</description>",
+                    escape(&waypoint.description)
+                );
+            }
+
+            let _ = writeln!(kml, "      <ExtendedData>");
+            for (name, value) in extension_fields(waypoint) {
+                let _ = writeln!(
+                    kml,
+                    r#"        <Data name="{name}"><value>{value}</value></Data>"#
+                );
+            }
+            let _ = writeln!(kml, "      </ExtendedData>");
+            let _ = writeln!(
+                kml,
+                "      <Point><coordinates>{:.7},{:.7}</coordinates></Point>",
+                degrees(waypoint.longitude_i),
+                degrees(waypoint.latitude_i)
+            );
+            let _ = writeln!(kml, "    </Placemark>");
+        }
+
+        let _ = writeln!(kml, "  </Document>");
+        let _ = writeln!(kml, "</kml>");
+        kml
+    }
+
+    /// Imports the waypoints of a GPX document, replacing known waypoints with the same ID.
+    /// Waypoints locked to a node other than `editor` are skipped.
+    ///
+    /// Waypoints without a Meshtastic ID, such as waypoints created by other mapping tools, are
+    /// assigned a random ID. The `sym` of a waypoint is used as its icon if it is a single
+    /// character, such as an emoji.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The GPX document to import.
+    /// * `editor` - The ID of the node making the change, usually the connected radio.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the imported waypoints, which can be shared over the mesh through
+    /// the `send_waypoint` method. Skipped waypoints are not included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let gpx = std::fs::read_to_string("waypoints.gpx")?;
+    /// let imported = waypoints.import_gpx(&gpx, my_node_id)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidGeoDocument` if the document is not valid XML or contains
+    /// invalid coordinates.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn import_gpx(
+        &mut self,
+        document: &str,
+        editor: NodeId,
+    ) -> Result<Vec<protobufs::Waypoint>, Error> {
+        let root = XmlElement::parse(document)?;
+
+        let waypoints = root
+            .descendants("wpt")
+            .into_iter()
+            .map(|wpt| {
+                let extensions = wpt.child("extensions");
+                let extension = |name: &str| extensions.and_then(|e| e.child_text(name));
+
+                Ok(waypoint_from_fields(
+                    parse_coordinate(wpt.attribute("lat"), 90.0)?,
+                    parse_coordinate(wpt.attribute("lon"), 180.0)?,
+                    wpt.child_text("name"),
+                    wpt.child_text("desc").or(wpt.child_text("cmt")),
+                    wpt.child_text("sym"),
+                    extension,
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(self.insert_all(waypoints, editor))
+    }
+
+    /// Imports the point placemarks of a KML document, replacing known waypoints with the same ID.
+    /// Waypoints locked to a node other than `editor` are skipped.
+    ///
+    /// Placemarks without a Meshtastic ID, such as placemarks created by other mapping tools, are
+    /// assigned a random ID. Placemarks that are not points are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The KML document to import.
+    /// * `editor` - The ID of the node making the change, usually the connected radio.
+    ///
+    /// # Returns
+    ///
+    /// A result resolving to the imported waypoints, which can be shared over the mesh through
+    /// the `send_waypoint` method. Skipped waypoints are not included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let kml = std::fs::read_to_string("waypoints.kml")?;
+    /// let imported = waypoints.import_kml(&kml, my_node_id)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidGeoDocument` if the document is not valid XML or contains
+    /// invalid coordinates.
+    ///
+    /// # Panics
+    ///
+    /// None
+    ///
+    pub fn import_kml(
+        &mut self,
+        document: &str,
+        editor: NodeId,
+    ) -> Result<Vec<protobufs::Waypoint>, Error> {
+        let root = XmlElement::parse(document)?;
+
+        let waypoints = root
+            .descendants("Placemark")
+            .into_iter()
+            .filter_map(|placemark| {
+                let coordinates = placemark.child("Point")?.child_text("coordinates")?;
+                Some((placemark, coordinates))
+            })
+            .map(|(placemark, coordinates)| {
+                // * KML coordinates are ordered as longitude, latitude and optional altitude
+                let mut coordinates = coordinates.split(',').map(str::trim);
+                let longitude = parse_coordinate(coordinates.next(), 180.0)?;
+                let latitude = parse_coordinate(coordinates.next(), 90.0)?;
+
+                let extended_data = placemark.child("ExtendedData");
+                let extension = |name: &str| {
+                    extended_data?
+                        .children
+                        .iter()
+                        .find(|data| data.attribute("name") == Some(name))?
+                        .child_text("value")
+                };
+
+                Ok(waypoint_from_fields(
+                    latitude,
+                    longitude,
+                    placemark.child_text("name"),
+                    placemark.child_text("description"),
+                    None,
+                    extension,
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(self.insert_all(waypoints, editor))
+    }
+
+    fn check_editable(&self, waypoint_id: u32, editor: NodeId) -> Result<(), Error> {
+        match self.waypoints.get(&waypoint_id) {
+            Some(waypoint) if !self.can_edit(waypoint_id, editor) => Err(Error::WaypointLocked {
+                waypoint_id,
+                locked_to: waypoint.locked_to,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn insert_all(
+        &mut self,
+        waypoints: Vec<protobufs::Waypoint>,
+        editor: NodeId,
+    ) -> Vec<protobufs::Waypoint> {
+        let mut imported = Vec::with_capacity(waypoints.len());
+
+        for waypoint in waypoints {
+            if let Err(e) = self.check_editable(waypoint.id, editor) {
+                warn!("Skipping imported waypoint: {e}");
+                continue;
+            }
+
+            self.waypoints.insert(waypoint.id, waypoint.clone());
+            imported.push(waypoint);
+        }
+
+        imported
+    }
+}
+
+/// Returns whether a waypoint has expired. An `expire` time of `0` means that the waypoint never
+/// expires.
+fn is_expired(waypoint: &protobufs::Waypoint, now: u32) -> bool {
+    waypoint.expire != 0 && waypoint.expire <= now
+}
+
+/// Converts a coordinate in units of 1e-7 degrees into degrees.
+pub(crate) fn degrees(coordinate_i: i32) -> f64 {
+    f64::from(coordinate_i) * 1e-7
+}
+
+/// Parses a coordinate in degrees into units of 1e-7 degrees, checking that it lies within
+/// `[-limit, limit]`.
+pub(crate) fn parse_coordinate(coordinate: Option<&str>, limit: f64) -> Result<i32, Error> {
+    let coordinate = coordinate.unwrap_or_default();
+
+    match coordinate.trim().parse::<f64>() {
+        Ok(degrees) if degrees.abs() <= limit => Ok((degrees * 1e7).round() as i32),
+        _ => Err(Error::InvalidGeoDocument {
+            description: format!("Invalid coordinate \"{coordinate}\""),
+        }),
+    }
+}
+
+/// Returns the Meshtastic fields of a waypoint that are stored in GPX extensions and KML
+/// extended data.
+fn extension_fields(waypoint: &protobufs::Waypoint) -> Vec<(&'static str, u32)> {
+    [
+        ("id", waypoint.id),
+        ("expire", waypoint.expire),
+        ("lockedTo", waypoint.locked_to),
+        ("icon", waypoint.icon),
+    ]
+    .into_iter()
+    .filter(|(name, value)| *name == "id" || *value != 0)
+    .collect()
+}
+
+/// Builds an imported waypoint from the fields of a GPX or KML document.
+fn waypoint_from_fields<'a>(
+    latitude_i: i32,
+    longitude_i: i32,
+    name: Option<&str>,
+    description: Option<&str>,
+    symbol: Option<&str>,
+    extension: impl Fn(&str) -> Option<&'a str>,
+) -> protobufs::Waypoint {
+    let extension_value = |name: &str| extension(name).and_then(|value| value.parse::<u32>().ok());
+
+    let mut symbol_chars = symbol.unwrap_or_default().chars();
+    let symbol_icon = match (symbol_chars.next(), symbol_chars.next()) {
+        (Some(icon), None) => u32::from(icon),
+        _ => 0,
+    };
+
+    protobufs::Waypoint {
+        id: extension_value("id")
+            .filter(|id| *id != 0)
+            .unwrap_or_else(generate_rand_id),
+        latitude_i,
+        longitude_i,
+        expire: extension_value("expire").unwrap_or(0),
+        locked_to: extension_value("lockedTo").unwrap_or(0),
+        name: name.unwrap_or_default().to_string(),
+        description: description.unwrap_or_default().to_string(),
+        icon: extension_value("icon").unwrap_or(symbol_icon),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u32 = 1_700_000_000;
+
+    fn waypoint(id: u32, locked_to: u32) -> protobufs::Waypoint {
+        protobufs::Waypoint {
+            id,
+            latitude_i: 473_977_420,
+            longitude_i: 85_455_940,
+            expire: 0,
+            locked_to,
+            name: format!("Camp <{id}>"),
+            description: "Tents & fire".to_string(),
+            icon: u32::from('⛺'),
+        }
+    }
+
+    fn waypoint_packet(from: u32, waypoint: &protobufs::Waypoint) -> protobufs::FromRadio {
+        protobufs::FromRadio {
+            id: 0,
+            payload_variant: Some(protobufs::from_radio::PayloadVariant::Packet(
+                protobufs::MeshPacket {
+                    from,
+                    payload_variant: Some(protobufs::mesh_packet::PayloadVariant::Decoded(
+                        protobufs::Data {
+                            portnum: protobufs::PortNum::WaypointApp as i32,
+                            payload: waypoint.encode_to_vec(),
+                            ..Default::default()
+                        },
+                    )),
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+
+    #[test]
+    fn tracks_updates_locks_and_deletions() {
+        let mut waypoints = WaypointManager::new();
+
+        let added = waypoints.handle_packet(&waypoint_packet(1, &waypoint(10, 1)), NOW);
+        assert!(matches!(added, Some(WaypointChange::Added(_))));
+
+        let mut moved = waypoint(10, 1);
+        moved.latitude_i += 1000;
+        let updated = waypoints.handle_packet(&waypoint_packet(1, &moved), NOW);
+        assert!(matches!(updated, Some(WaypointChange::Updated(_))));
+
+        // Only the owner of a locked waypoint can change it
+        let rejected = waypoints.handle_packet(&waypoint_packet(2, &waypoint(10, 0)), NOW);
+        assert!(matches!(
+            rejected,
+            Some(WaypointChange::Rejected { from, .. }) if from == 2
+        ));
+        assert!(matches!(
+            waypoints.delete(10, NodeId::new(2), NOW),
+            Err(Error::WaypointLocked {
+                waypoint_id: 10,
+                locked_to: 1
+            })
+        ));
+        assert_eq!(waypoints.waypoint(10), Some(&moved));
+
+        let deletion = waypoints.delete(10, NodeId::new(1), NOW).unwrap();
+        assert_eq!(deletion.expire, NOW);
+        assert!(waypoints.waypoint(10).is_none());
+
+        // Deletions received from the mesh remove the waypoint
+        waypoints.handle_packet(&waypoint_packet(3, &waypoint(11, 0)), NOW);
+        let mut expired = waypoint(11, 0);
+        expired.expire = NOW;
+        let deleted = waypoints.handle_packet(&waypoint_packet(3, &expired), NOW);
+        assert!(matches!(deleted, Some(WaypointChange::Deleted(_))));
+
+        let mut expiring = waypoint(12, 0);
+        expiring.expire = NOW + 60;
+        waypoints.update(expiring, NodeId::new(42)).unwrap();
+        assert!(waypoints.expire(NOW).is_empty());
+        assert_eq!(waypoints.expire(NOW + 60).len(), 1);
+        assert_eq!(waypoints.waypoints().count(), 0);
+    }
+
+    #[test]
+    fn round_trips_waypoints_through_gpx_and_kml() {
+        let mut waypoints = WaypointManager::new();
+        waypoints.update(waypoint(10, 1), NodeId::new(1)).unwrap();
+        waypoints.update(waypoint(11, 0), NodeId::new(1)).unwrap();
+
+        let gpx = waypoints.to_gpx();
+        assert!(gpx.contains(r#"<wpt lat="47.3977420" lon="8.5455940">"#));
+        assert!(gpx.contains("<name>Camp &lt;10&gt;</name>"));
+
+        let kml = waypoints.to_kml();
+        assert!(kml.contains("<coordinates>8.5455940,47.3977420</coordinates>"));
+
+        let expected: Vec<protobufs::Waypoint> = waypoints.waypoints().cloned().collect();
+        assert_eq!(
+            WaypointManager::new()
+                .import_gpx(&gpx, NodeId::new(1))
+                .unwrap(),
+            expected
+        );
+        assert_eq!(
+            WaypointManager::new()
+                .import_kml(&kml, NodeId::new(1))
+                .unwrap(),
+            expected
+        );
+
+        // Waypoints locked to another node are not overwritten by imports
+        let mut locked = WaypointManager::new();
+        let mut original = waypoint(10, 1);
+        original.name = "Original".to_string();
+        locked.update(original.clone(), NodeId::new(1)).unwrap();
+
+        assert_eq!(
+            locked.import_gpx(&gpx, NodeId::new(2)).unwrap(),
+            vec![waypoint(11, 0)]
+        );
+        assert_eq!(
+            locked.import_kml(&kml, NodeId::new(2)).unwrap(),
+            vec![waypoint(11, 0)]
+        );
+        assert_eq!(locked.waypoint(10), Some(&original));
+    }
+
+    #[test]
+    fn imports_documents_from_other_tools() {
+        let gpx = r#"<?xml version="1.0"?>
+            <gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+              <wpt lat="-33.8568" lon="151.2153"><name>Opera</name><sym>Flag, Blue</sym></wpt>
+            </gpx>"#;
+
+        let mut waypoints = WaypointManager::new();
+        let imported = waypoints.import_gpx(gpx, NodeId::new(42)).unwrap();
+
+        assert_eq!(imported.len(), 1);
+        assert_ne!(imported[0].id, 0);
+        assert_eq!(imported[0].name, "Opera");
+        assert_eq!(imported[0].latitude_i, -338_568_000);
+        assert_eq!(imported[0].icon, 0);
+
+        let kml = r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>
+              <Placemark><name>Line</name><LineString><coordinates>1,2 3,4</coordinates></LineString></Placemark>
+              <Placemark><name>Peak</name><Point><coordinates> 7.6586,45.9763,4478 </coordinates></Point></Placemark>
+            </Document></kml>"#;
+
+        let imported = waypoints.import_kml(kml, NodeId::new(42)).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].longitude_i, 76_586_000);
+        assert_eq!(waypoints.waypoints().count(), 2);
+
+        let invalid = r#"<gpx><wpt lat="91" lon="0"/></gpx>"#;
+        assert!(matches!(
+            waypoints.import_gpx(invalid, NodeId::new(42)),
+            Err(Error::InvalidGeoDocument { .. })
+        ));
+    }
+}
@@ -32,6 +32,22 @@ impl NodeId {
     pub fn id(&self) -> u32 {
         self.0
     }
+
+    /// Returns the user ID of the node, as displayed by the Meshtastic apps, such as `!deadbeef`.
+    pub fn to_user_id(&self) -> String {
+        format!("!{:08x}", self.0)
+    }
+
+    /// Parses a user ID, such as `!deadbeef`, into a `NodeId`.
+    pub fn from_user_id(user_id: &str) -> Option<NodeId> {
+        let hex = user_id.strip_prefix('!')?;
+
+        if hex.len() != 8 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        u32::from_str_radix(hex, 16).ok().map(NodeId)
+    }
 }
 
 impl From<u32> for NodeId {
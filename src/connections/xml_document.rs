@@ -0,0 +1,97 @@
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::errors_internal::Error;
+
+/// A minimal in-memory XML element, used to import the GPX and KML documents produced by mapping
+/// tools. Namespaces are ignored, and elements and attributes are matched on their local names.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct XmlElement {
+    pub(crate) name: String,
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<XmlElement>,
+    pub(crate) text: String,
+}
+
+impl XmlElement {
+    /// Parses a document, returning its root element.
+    pub(crate) fn parse(document: &str) -> Result<XmlElement, Error> {
+        let mut stack: Vec<XmlElement> = vec![];
+
+        for event in EventReader::from_str(document) {
+            let event = event.map_err(|e| Error::InvalidGeoDocument {
+                description: e.to_string(),
+            })?;
+
+            match event {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => stack.push(XmlElement {
+                    name: name.local_name,
+                    attributes: attributes
+                        .into_iter()
+                        .map(|attribute| (attribute.name.local_name, attribute.value))
+                        .collect(),
+                    ..Default::default()
+                }),
+                XmlEvent::EndElement { .. } => {
+                    let element = stack.pop().ok_or_else(|| Error::InvalidGeoDocument {
+                        description: "Unbalanced closing tag".to_string(),
+                    })?;
+
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&text);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Err(Error::InvalidGeoDocument {
+            description: "Document has no root element".to_string(),
+        })
+    }
+
+    /// Returns the value of the attribute with the given local name.
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the first direct child with the given local name.
+    pub(crate) fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Returns the trimmed text of the first direct child with the given local name.
+    pub(crate) fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+
+    /// Returns every element with the given local name below this element, in document order.
+    pub(crate) fn descendants<'a>(&'a self, name: &'a str) -> Vec<&'a XmlElement> {
+        let mut found = vec![];
+
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            }
+
+            found.extend(child.descendants(name));
+        }
+
+        found
+    }
+}
+
+/// Escapes text for use in XML character data and attribute values.
+pub(crate) fn escape(text: &str) -> String {
+    xml::escape::escape_str_attribute(text).into_owned()
+}
@@ -3,6 +3,7 @@ use thiserror::Error;
 use crate::connections::wrappers::encoded_data::{
     EncodedToRadioPacket, EncodedToRadioPacketWithHeader, IncomingStreamData,
 };
+use crate::protobufs;
 
 /// This enum defines the possible errors that can occur within the public API of the library.
 #[derive(Error, Debug)]
@@ -15,6 +16,10 @@ pub enum Error {
     #[error(transparent)]
     EncodeError(#[from] prost::EncodeError),
 
+    /// An error indicating that the library failed to decode a protocol buffer message.
+    #[error(transparent)]
+    DecodeError(#[from] prost::DecodeError),
+
     /// An error indicating that the library failed to join a spawned worker task.
     #[error(transparent)]
     JoinError(#[from] tokio::task::JoinError),
@@ -43,6 +48,114 @@ pub enum Error {
         packet: EncodedToRadioPacketWithHeader,
     },
 
+    /// An error indicating that the radio did not complete the configuration handshake before the timeout elapsed.
+    #[error("Timed out waiting for the radio to complete configuration {config_id}")]
+    ConfigurationTimeout { config_id: u32 },
+
+    /// An error indicating that the radio completed a configuration handshake with an unexpected configuration ID.
+    #[error("Expected the radio to complete configuration {expected}, but it completed configuration {received}")]
+    ConfigIdMismatch { expected: u32, received: u32 },
+
+    /// An error indicating that no response to a request was received before the timeout elapsed.
+    #[error("Timed out waiting for a response to packet {request_id}")]
+    ResponseTimeout { request_id: u32 },
+
+    /// An error indicating that a response to a request did not contain the expected payload.
+    #[error("Received an unexpected response to packet {request_id}")]
+    UnexpectedResponse { request_id: u32 },
+
+    /// An error indicating that the mesh reported that a request could not be delivered, for the
+    /// specified reason (e.g., `NoRoute` or `MaxRetransmit`).
+    #[error("Packet {request_id} could not be delivered: {reason:?}")]
+    RequestNak {
+        request_id: u32,
+        reason: protobufs::routing::Error,
+    },
+
+    /// An error indicating that the reconnect supervisor gave up after failing to reconnect to the radio.
+    #[error("Failed to reconnect to the radio after {attempts} attempts")]
+    ReconnectAttemptsExhausted { attempts: u32 },
+
+    /// An error indicating that a channel sharing URL could not be parsed.
+    #[error("Invalid channel URL {url}: {description}")]
+    InvalidChannelUrl { url: String, description: String },
+
+    /// An error indicating that the radio has no free channel slots for the channels being added.
+    #[error("Cannot add {required} channels, the radio only has {available} free channel slots")]
+    ChannelSlotsExhausted { required: usize, available: usize },
+
+    /// An error indicating that the user has entered a hop limit outside of the range of valid hop limits [0..7].
+    #[error("Invalid hop limit {hop_limit} entered. Valid hop limits are in the range [0..7]")]
+    InvalidHopLimit { hop_limit: u32 },
+
+    /// An error indicating that a Store & Forward server is too busy to handle a request.
+    #[error("Store & Forward server {node_id} is busy")]
+    StoreForwardServerBusy { node_id: u32 },
+
+    /// An error indicating that a waypoint is not known to the waypoint manager.
+    #[error("Waypoint {waypoint_id} not found")]
+    WaypointNotFound { waypoint_id: u32 },
+
+    /// An error indicating that a waypoint is locked to another node, which is the only node
+    /// allowed to change or delete it.
+    #[error("Waypoint {waypoint_id} is locked to node {locked_to}")]
+    WaypointLocked { waypoint_id: u32, locked_to: u32 },
+
+    /// An error indicating that coordinates in degrees are outside of the valid range.
+    #[error("Invalid coordinates ({latitude}, {longitude}). Latitudes must be in the range [-90..90] and longitudes in the range [-180..180]")]
+    InvalidCoordinates { latitude: f64, longitude: f64 },
+
+    /// An error indicating that the user has entered a position precision outside of the range of valid precisions [1..32].
+    #[error("Invalid position precision of {precision_bits} bits entered. Valid precisions are in the range [1..32]")]
+    InvalidPrecisionBits { precision_bits: u32 },
+
+    /// An error indicating that a GPX or KML document could not be parsed.
+    #[error("Invalid GPX or KML document: {description}")]
+    InvalidGeoDocument { description: String },
+
+    /// An error indicating that a channel PSK is longer than the 32 bytes of an AES-256 key.
+    #[error("Invalid channel key of {length} bytes. Channel keys are at most 32 bytes long")]
+    InvalidChannelKey { length: usize },
+
+    /// An error indicating that an encrypted packet could not be decrypted with the available
+    /// channel keys.
+    #[error("Failed to decrypt packet {packet_id}")]
+    PacketDecryptionFailed { packet_id: u32 },
+
+    /// An error indicating that an MQTT topic is not a Meshtastic topic.
+    #[error("Invalid MQTT topic \"{topic}\": {description}")]
+    InvalidMqttTopic { topic: String, description: String },
+
+    /// An error indicating that the payload of an MQTT message is not a valid service envelope.
+    #[error("Invalid MQTT message: {description}")]
+    InvalidMqttMessage { description: String },
+
+    /// An error indicating that the address of an MQTT broker could not be parsed.
+    #[error(
+        "Invalid MQTT broker address \"{address}\". Addresses must be in the form host[:port]"
+    )]
+    InvalidMqttBrokerAddress { address: String },
+
+    /// An error indicating that a packet subscription fell behind and missed packets. The
+    /// subscription remains usable after this error.
+    #[error("Packet subscription lagged behind and skipped {skipped} packets")]
+    SubscriptionLagged { skipped: u64 },
+
+    /// An error indicating that the library failed to save or load persisted state, such as a node database.
+    #[error("{description} with error {source:?}")]
+    PersistenceError {
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        description: String,
+    },
+
+    /// An error indicating that the library failed to listen for incoming connections, such as
+    /// scrapes of the Prometheus exporter.
+    #[error("{description} with error {source:?}")]
+    ListenerBindError {
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        description: String,
+    },
+
     /// An error indicating that the library failed when performing an operation on an internal data stream.
     #[error(transparent)]
     InternalStreamError(#[from] InternalStreamError),
@@ -25,12 +25,102 @@ pub use prost::Message;
 /// instance of the `ConnectedStreamApi` struct. This resulting instance will then have access
 /// to the full set of API sender methods.
 ///
+/// The `configure_and_wait` method performs the same request, but waits for the radio to finish
+/// sending its configuration and returns it as a `RadioSnapshot` alongside the configured instance.
+/// Once configured, the live configuration of the radio can be read back through the `get_config`,
+/// `get_module_config`, `get_channel`, `get_owner`, `get_metadata` and `get_connection_status` methods.
+///
+/// Channel sharing URLs, as generated by the Meshtastic apps, can be applied to the radio through
+/// the `set_channel_url` method.
+///
+/// Device actions such as `reboot`, `shutdown`, `factory_reset_device`, `factory_reset_config`,
+/// `reset_nodedb` and `enter_dfu_mode` can be sent to the connected radio or to a remote node.
+///
+/// To update multiple configuration fields without restarting the radio after each of them, the
+/// user can call the `begin_edit_settings` method, which returns a `ConfigTransaction` guard. Updates
+/// sent through the guard are applied by the radio once the guard is consumed by its `commit` method.
+///
+/// Other nodes in the mesh can be administered over the air through the `RemoteAdmin` handle returned
+/// by the `remote_admin` method. The handle sends its requests to the remote node on a given admin
+/// channel, and exposes the same getters, setters, configuration transactions and device actions.
+///
+/// Instead of `connect`, the `connect_with_reconnect` method can be used to build streams from a
+/// user-supplied factory and to perform the handshake automatically. If the stream is lost, for
+/// example when the radio reboots, the stream is rebuilt with exponential backoff as configured by
+/// `ReconnectOptions`, while the same `PacketReceiver` stays open. Changes in the connection are
+/// reported as `ConnectionState` events.
+///
+/// The `NodeDb` struct aggregates the node information, positions, device metrics and reception
+/// metadata of the packets received from the radio into a `NodeRecord` per node. Changes to the
+/// database are reported as `NodeDbEvent` events, and the database can be persisted to a JSON file.
+///
+/// The `MeshTopology` struct maintains a graph of the radio links between nodes, built from neighbor
+/// information reports, traceroute results and directly received packets. Stale links are aged out,
+/// weak links and single points of failure can be listed, and the graph can be exported as DOT,
+/// GraphML or JSON.
+///
+/// The `TelemetryStore` struct keeps the latest device, environment, air quality and power metrics of every
+/// node, as decoded into `TelemetryReport`s, and renders them in the Prometheus text exposition format. Metrics
+/// can be requested from a node through the `request_telemetry` method. With the `prometheus` feature, the
+/// `PrometheusExporter` struct serves the metrics of a shared store over HTTP for scraping.
+///
+/// The `StoreForwardServers` struct tracks the Store & Forward servers announced by heartbeats in the mesh.
+/// Stored messages can be replayed from a server through the `request_store_forward_history` method, and
+/// the statistics of a server can be read through the `request_store_forward_stats` method.
+///
+/// For radios that cannot run the Store & Forward module of the firmware, the `StoreForwardHost` struct runs a
+/// compatible server on the host. It records the text messages received by the radio as `StoreForwardRecord`s,
+/// broadcasts heartbeats, and replays the history to clients at the rate configured by `StoreForwardHostOptions`.
+///
+/// The `WaypointManager` struct tracks the waypoints shared in the mesh by their ID, and reports changes to them
+/// as `WaypointChange`s. It enforces the `locked_to` ownership of waypoints when they are updated or deleted, ages
+/// out expired waypoints, and imports and exports waypoints as GPX and KML documents for use in mapping tools.
+///
+/// The `PositionTracks` struct records the positions reported by every node as `GeoPosition`s, and exports them
+/// as GPX tracks. The `NodeDb` struct reports the distance and bearing between the last known positions of nodes.
+///
+/// With the `udp` feature, the `UdpTransport` struct exchanges mesh packets without a radio, with the nodes of
+/// the local network over the UDP multicast group used by newer firmware versions and `meshtasticd`. Received
+/// packets are decrypted with the keys of a `ChannelKeyring`, and are delivered through a `PacketReceiver` like
+/// the packets of a radio connection.
+///
 /// To disconnect from the radio, the user can call the `disconnect` method at any time.
 pub mod api {
+    pub use crate::connections::config_transaction::ConfigTransaction;
+    pub use crate::connections::node_db::NodeDb;
+    pub use crate::connections::node_db::NodeDbEvent;
+    pub use crate::connections::node_db::NodeDbEventReceiver;
+    pub use crate::connections::node_db::NodeRecord;
+    pub use crate::connections::position::PositionTracks;
+    #[cfg(feature = "prometheus")]
+    pub use crate::connections::prometheus::PrometheusExporter;
+    #[cfg(feature = "prometheus")]
+    pub use crate::connections::prometheus::SharedTelemetryStore;
+    pub use crate::connections::reconnect::ConnectionState;
+    pub use crate::connections::reconnect::ConnectionStateReceiver;
+    pub use crate::connections::reconnect::ReconnectOptions;
+    pub use crate::connections::remote_admin::RemoteAdmin;
+    pub use crate::connections::snapshot::RadioSnapshot;
+    pub use crate::connections::store_forward::StoreForwardServer;
+    pub use crate::connections::store_forward::StoreForwardServers;
+    pub use crate::connections::store_forward_host::StoreForwardHost;
+    pub use crate::connections::store_forward_host::StoreForwardHostOptions;
+    pub use crate::connections::store_forward_host::StoreForwardRecord;
     pub use crate::connections::stream_api::state;
     pub use crate::connections::stream_api::ConnectedStreamApi;
     pub use crate::connections::stream_api::StreamApi;
     pub use crate::connections::stream_api::StreamHandle;
+    pub use crate::connections::telemetry::TelemetryStore;
+    pub use crate::connections::topology::LinkSource;
+    pub use crate::connections::topology::MeshTopology;
+    pub use crate::connections::topology::TopologyLink;
+    pub use crate::connections::topology::TopologyNode;
+    #[cfg(feature = "udp")]
+    pub use crate::connections::udp::UdpTransport;
+    #[cfg(feature = "udp")]
+    pub use crate::connections::udp::UdpTransportOptions;
+    pub use crate::connections::waypoints::WaypointChange;
+    pub use crate::connections::waypoints::WaypointManager;
 }
 
 /// This module contains the global `Error` type of the library. This enum implements
@@ -60,8 +150,41 @@ pub mod errors {
 ///
 /// The `PacketReceiver` type defines the type of the tokio channel that is used to receive decoded packets from the radio.
 /// This is intended to simplify the complexity of the underlying channel type.
+///
+/// The `SentPacket` struct is returned by the "send" methods of the `ConnectedStreamApi` struct. It contains the ID
+/// of the sent packet, and can be used to wait for the `AckOutcome` of the packet, which is either an acknowledgement
+/// from the destination node, an implicit acknowledgement from a rebroadcast, or a negative acknowledgement with
+/// the reason the packet could not be delivered.
+///
+/// The `DecodedPayload` enum represents the payload of a mesh packet, decoded based on the `PortNum` the packet
+/// was sent on. The `MeshEventStream` struct wraps a `PacketReceiver`, and converts every received packet into a
+/// `MeshEvent`, which allows packet handlers to match on a single flat enum.
+///
+/// The `PacketSubscription` struct is returned by the `subscribe` method of the `ConnectedStreamApi` struct, and
+/// receives its own copy of every packet accepted by a `PacketFilter`. Filters can select packets by `PortNum`,
+/// sender `NodeId`, channel index and `FromRadioKind`.
+///
+/// The `TracerouteResult` struct is returned by the `traceroute` method of the `ConnectedStreamApi` struct, and
+/// lists the `RouteHop`s that a traceroute request passed through on its way to the destination.
+///
+/// The `StoredTextMessage` struct represents a text message replayed by a Store & Forward server, and is
+/// reported as a `MeshEvent::StoredText` event with the original sender and receive time of the message.
 pub mod packet {
+    pub use crate::connections::ack::AckOutcome;
+    pub use crate::connections::ack::SentPacket;
+    pub use crate::connections::events::MeshEvent;
+    pub use crate::connections::events::MeshEventStream;
     pub use crate::connections::handlers::CLIENT_HEARTBEAT_INTERVAL;
+    pub use crate::connections::payload::DecodedPayload;
+    pub use crate::connections::store_forward::StoredTextMessage;
+    pub use crate::connections::subscription::FromRadioKind;
+    pub use crate::connections::subscription::PacketFilter;
+    pub use crate::connections::subscription::PacketSubscription;
+    pub use crate::connections::telemetry::TelemetryKind;
+    pub use crate::connections::telemetry::TelemetryMetrics;
+    pub use crate::connections::telemetry::TelemetryReport;
+    pub use crate::connections::traceroute::RouteHop;
+    pub use crate::connections::traceroute::TracerouteResult;
     pub use crate::connections::PacketDestination;
     pub use crate::connections::PacketRouter;
 
@@ -69,9 +192,58 @@ pub mod packet {
     pub type PacketReceiver = tokio::sync::mpsc::UnboundedReceiver<crate::protobufs::FromRadio>;
 }
 
+/// This module contains the channel encryption used by the firmware, which allows packets received
+/// with an `Encrypted` payload, such as packets forwarded over MQTT, to be decoded by the library.
+///
+/// The `ChannelKey` enum represents the AES key of a channel, and is derived from the `psk` field of
+/// its `ChannelSettings`, including the 1-byte shorthands for the `DEFAULT_PSK` of the firmware.
+/// The `channel_hash` function computes the hash sent in the `channel` field of encrypted packets, and
+/// the `decrypt_packet` and `encrypt_packet` functions convert between `Encrypted` and `Decoded`
+/// payloads using AES-CTR with the nonce built by the `packet_nonce` function.
+///
+/// The `ChannelKeyring` struct holds the keys of multiple channels, and decrypts packets with the key
+/// of the channel matching their channel hash.
+pub mod crypto {
+    pub use crate::connections::crypto::channel_hash;
+    pub use crate::connections::crypto::decrypt_packet;
+    pub use crate::connections::crypto::encrypt_packet;
+    pub use crate::connections::crypto::packet_nonce;
+    pub use crate::connections::crypto::ChannelKey;
+    pub use crate::connections::crypto::ChannelKeyring;
+    pub use crate::connections::crypto::DEFAULT_PSK;
+}
+
+/// This module contains the codec used by gateways to exchange mesh packets over MQTT.
+///
+/// The `MqttTopic` struct parses and builds the topics that gateways publish on, such as
+/// `msh/US/2/e/LongFast/!deadbeef` for packets of a channel and `msh/US/2/map/` for map reports.
+/// The `MqttEnvelope` struct decodes and encodes the `ServiceEnvelope` payloads published on these
+/// topics, and decrypts the packets they carry with a `ChannelKey` or a `ChannelKeyring`.
+///
+/// With the `mqtt` feature, the `MqttProxy` struct connects a radio that has `proxy_to_client_enabled`
+/// set in its `MqttConfig` to its broker through the connection of the host. The proxy is started by the
+/// `ConnectedStreamApi::start_mqtt_proxy` method, and its broker settings can be overridden through
+/// `MqttProxyOptions`.
+pub mod mqtt {
+    pub use crate::connections::mqtt::MqttEnvelope;
+    pub use crate::connections::mqtt::MqttTopic;
+    pub use crate::connections::mqtt::MqttTopicKind;
+    pub use crate::connections::mqtt::DEFAULT_MQTT_ROOT_TOPIC;
+    #[cfg(feature = "mqtt")]
+    pub use crate::connections::mqtt_proxy::MqttProxy;
+    #[cfg(feature = "mqtt")]
+    pub use crate::connections::mqtt_proxy::MqttProxyOptions;
+    #[cfg(feature = "mqtt")]
+    pub use crate::connections::mqtt_proxy::DEFAULT_MQTT_ADDRESS;
+}
+
 /// This module contains structs and enums that are generated from the protocol buffer (protobuf)
 /// definitions of the `meshtastic/protobufs` Git submodule. These structs and enums
 /// are not edited directly, but are instead generated at build time.
+///
+/// The generated `ChannelSet` struct additionally exposes the `from_url` and `to_url` methods,
+/// which decode and encode the channel sharing URLs used by the Meshtastic apps. These URLs can
+/// be applied to a radio through the `set_channel_url` method of the `ConnectedStreamApi` struct.
 pub mod protobufs {
     #![allow(non_snake_case)]
     include!("generated/meshtastic.rs");
@@ -102,11 +274,26 @@ pub mod ts {
 /// constant is used to define the default baud rate of incoming serial connections created by the
 /// `build_serial_stream` method.
 ///
+/// The `CHANNEL_URL_PREFIX` and `ADD_CHANNEL_URL_PREFIX` constants are the prefixes of the channel
+/// sharing URLs that replace and add to the channels of a radio, respectively.
+///
 /// Additionally, this module exposes helper methods that are used internally to format data packets.
 /// These methods are intended for use by more advanced users.
 ///
 /// The `stream` module contains helper methods that are used to build connection stream instances.
 pub mod utils {
+    pub use crate::connections::channel_url::ADD_CHANNEL_URL_PREFIX;
+    pub use crate::connections::channel_url::CHANNEL_URL_PREFIX;
+    pub use crate::connections::position::DEFAULT_MAX_TRACK_POINTS;
+    pub use crate::connections::position::FULL_PRECISION_BITS;
+    #[cfg(feature = "prometheus")]
+    pub use crate::connections::prometheus::PROMETHEUS_METRICS_PATH;
+    pub use crate::connections::topology::DEFAULT_MAX_LINK_AGE;
+    pub use crate::connections::traceroute::MAX_HOP_LIMIT;
+    #[cfg(feature = "udp")]
+    pub use crate::connections::udp::DEFAULT_UDP_MULTICAST_ADDRESS;
+    #[cfg(feature = "bluetooth-le")]
+    pub use crate::utils_internal::DEFAULT_BLE_SCAN_DURATION;
     pub use crate::utils_internal::DEFAULT_DTR_PIN_STATE;
     pub use crate::utils_internal::DEFAULT_RTS_PIN_STATE;
     pub use crate::utils_internal::DEFAULT_SERIAL_BAUD;
@@ -125,10 +312,36 @@ pub mod utils {
     /// simplify the process of initializing a connection stream. The vast majority of users will
     /// only need to use these two methods to connect to a radio. The `available_serial_ports` method
     /// can also be used to list all available serial ports on the host machine.
+    ///
+    /// When the `bluetooth-le` feature is enabled, this module also exposes the `build_ble_stream`
+    /// method. BLE radios exchange whole packets rather than bytes, so this method bridges the radio
+    /// characteristics to an in-memory stream. The `BlePeripheral` trait and the
+    /// `build_ble_stream_from_peripheral` method allow this bridge to be driven by a mock peripheral.
+    ///
+    /// When the `http` feature is enabled, this module also exposes the `build_http_stream` method,
+    /// which connects to the HTTP API of radios on Wi-Fi or Ethernet. The radio is polled for packets
+    /// at the interval configured by `HttpStreamOptions`, over HTTP or HTTPS.
     pub mod stream {
         pub use crate::utils_internal::available_serial_ports;
         pub use crate::utils_internal::build_serial_stream;
         pub use crate::utils_internal::build_tcp_stream;
+
+        #[cfg(feature = "bluetooth-le")]
+        pub use crate::connections::ble_stream::build_ble_stream_from_peripheral;
+        #[cfg(feature = "bluetooth-le")]
+        pub use crate::connections::ble_stream::BlePeripheral;
+        #[cfg(feature = "bluetooth-le")]
+        pub use crate::connections::ble_stream::BlePeripheralEvent;
+        #[cfg(feature = "http")]
+        pub use crate::connections::http::build_http_stream;
+        #[cfg(feature = "http")]
+        pub use crate::connections::http::HttpStreamOptions;
+        #[cfg(feature = "http")]
+        pub use crate::connections::http::HTTP_FROM_RADIO_PATH;
+        #[cfg(feature = "http")]
+        pub use crate::connections::http::HTTP_TO_RADIO_PATH;
+        #[cfg(feature = "bluetooth-le")]
+        pub use crate::utils_internal::build_ble_stream;
     }
 }
 
@@ -144,6 +357,10 @@ pub mod utils {
 /// of the mesh. This struct is used to provide additional type safety when specifying
 /// mesh channels, as it will only allow channels with indices between 0 and 7, inclusive.
 ///
+/// The `GeoPosition` struct represents the coordinates of a `Position` struct without loss of
+/// precision. It computes the distance and bearing between positions, and truncates positions to
+/// a reduced precision before they are shared. It can be passed directly to `send_position`.
+///
 /// The `EncodedMeshPacketData` struct is a wrapper around a `Vec<u8>` value that represents
 /// the payload data of a mesh packet (e.g., a text message).
 ///
@@ -158,6 +375,8 @@ pub mod utils {
 pub mod types {
     pub use crate::connections::wrappers::NodeId;
 
+    pub use crate::connections::position::GeoPosition;
+
     pub use crate::connections::wrappers::mesh_channel::MeshChannel;
 
     pub use crate::connections::wrappers::encoded_data::EncodedMeshPacketData;
@@ -165,3 +384,23 @@ pub mod types {
     pub use crate::connections::wrappers::encoded_data::EncodedToRadioPacketWithHeader;
     pub use crate::connections::wrappers::encoded_data::IncomingStreamData;
 }
+
+/// This module contains utilities for testing code built on the `StreamApi` without a physical
+/// device. This module is only compiled if the `testing` feature is enabled.
+///
+/// The `MockRadio` struct is an in-memory radio that is connected to the `StreamApi` through
+/// a `StreamHandle` wrapping a `tokio::io::duplex` stream. The mock radio answers the
+/// `WantConfigId` handshake with the node database, channels and configuration held in its
+/// `MockRadioState`, acknowledges mesh packets, applies and answers admin messages, and allows
+/// tests to inject arbitrary `FromRadio` packets.
+///
+/// When the `http` feature is also enabled, the `HttpStubServer` struct exposes a stream, such as
+/// the stream of a `MockRadio`, through a local server implementing the HTTP API of the firmware,
+/// which allows code built on `build_http_stream` to be tested without a radio.
+#[cfg(feature = "testing")]
+pub mod testing {
+    #[cfg(feature = "http")]
+    pub use crate::connections::http::HttpStubServer;
+    pub use crate::connections::mock_radio::MockRadio;
+    pub use crate::connections::mock_radio::MockRadioState;
+}
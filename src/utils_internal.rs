@@ -194,6 +194,58 @@ pub async fn build_tcp_stream(
     Ok(StreamHandle::from_stream(stream))
 }
 
+/// The default amount of time spent scanning for a radio within the `build_ble_stream` method.
+#[cfg(feature = "bluetooth-le")]
+pub const DEFAULT_BLE_SCAN_DURATION: Duration = Duration::from_secs(5);
+
+/// A helper method that uses the `btleplug` crate to build a BLE stream
+/// that is compatible with the `StreamApi` API.
+///
+/// BLE radios exchange whole `ToRadio` and `FromRadio` packets through the `TORADIO`,
+/// `FROMRADIO` and `FROMNUM` characteristics rather than through a byte stream. This
+/// method connects to the radio and spawns a bridge task that exposes these characteristics
+/// as an in-memory stream, which is then passed into the `StreamApi::connect` method.
+///
+/// # Arguments
+///
+/// * `name_or_mac` - The advertised name (e.g., `Meshtastic_1234`) or MAC address (e.g., `AA:BB:CC:DD:EE:FF`) of the radio.
+/// * `scan_duration` - The maximum amount of time to spend scanning for the radio. Defaults to 5 seconds if not passed.
+///
+/// # Returns
+///
+/// Returns a result that resolves to a `StreamHandle` wrapping a `tokio::io::DuplexStream`
+/// instance, or an error if the radio could not be found or connected to.
+///
+/// # Examples
+///
+/// ```
+/// let ble_stream = utils::stream::build_ble_stream("Meshtastic_1234".to_string(), None).await?;
+/// let (decoded_listener, stream_api) = stream_api.connect(ble_stream).await;
+/// ```
+///
+/// # Errors
+///
+/// Will return an error if no radio matching `name_or_mac` is found, or if the connection
+/// to the radio or the discovery of its characteristics fails.
+///
+/// # Panics
+///
+/// None
+///
+#[cfg(feature = "bluetooth-le")]
+pub async fn build_ble_stream(
+    name_or_mac: String,
+    scan_duration: Option<Duration>,
+) -> Result<StreamHandle<tokio::io::DuplexStream>, Error> {
+    let handler = crate::connections::ble_handler::BleHandler::new(
+        name_or_mac,
+        scan_duration.unwrap_or(DEFAULT_BLE_SCAN_DURATION),
+    )
+    .await?;
+
+    Ok(crate::connections::ble_stream::build_ble_stream_from_peripheral(handler))
+}
+
 /// A helper method to generate random numbers using the `rand` crate.
 ///
 /// This method is intended to be used to generate random id values. This method
@@ -343,6 +395,43 @@ pub fn strip_data_packet_header(
     Ok(stripped_data.into())
 }
 
+/// The magic bytes that prefix every framed packet sent over serial and TCP connections.
+#[cfg(any(test, feature = "bluetooth-le", feature = "http", feature = "testing"))]
+pub(crate) const FRAME_MAGIC: [u8; 2] = [0x94, 0xc3];
+
+/// The size of the header that prefixes every framed packet sent over serial and TCP connections.
+#[cfg(any(test, feature = "bluetooth-le", feature = "http", feature = "testing"))]
+pub(crate) const FRAME_HEADER_SIZE: usize = 4;
+
+/// Removes the first complete framed packet from the buffer and returns its payload
+/// without the packet header. Bytes preceding a valid header are discarded.
+#[cfg(any(test, feature = "bluetooth-le", feature = "http", feature = "testing"))]
+pub(crate) fn take_next_frame(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    let Some(start) = buffer.windows(2).position(|w| w == FRAME_MAGIC) else {
+        // Keep a trailing magic byte, as the rest of the header may not have arrived yet
+        let keep_from = buffer.len().saturating_sub(1);
+        let keep_last = buffer.last() == Some(&FRAME_MAGIC[0]);
+        buffer.drain(..if keep_last { keep_from } else { buffer.len() });
+        return None;
+    };
+
+    buffer.drain(..start);
+
+    if buffer.len() < FRAME_HEADER_SIZE {
+        return None;
+    }
+
+    let packet_size = u16::from_be_bytes([buffer[2], buffer[3]]) as usize;
+
+    if buffer.len() < FRAME_HEADER_SIZE + packet_size {
+        return None;
+    }
+
+    let frame: Vec<u8> = buffer.drain(..FRAME_HEADER_SIZE + packet_size).collect();
+
+    Some(frame[FRAME_HEADER_SIZE..].to_vec())
+}
+
 /// A helper function that returns the number of seconds since the unix epoch.
 ///
 /// # Arguments
@@ -381,6 +470,36 @@ pub fn current_epoch_secs_u32() -> u32 {
         .expect("Could not convert u128 to u32")
 }
 
+/// A helper function that formats a time in seconds since the Unix epoch as an RFC 3339 UTC
+/// timestamp, such as `2023-11-14T22:13:20Z`.
+pub(crate) fn format_rfc3339(epoch_secs: u32) -> String {
+    let days = epoch_secs / 86_400;
+    let secs_of_day = epoch_secs % 86_400;
+
+    // Converts days since the epoch into a civil date, following Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u32::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,4 +541,24 @@ mod tests {
 
         assert!(serial_data.is_err());
     }
+
+    #[test]
+    fn take_frame_strips_header() {
+        let mut buffer = vec![0x00, 0x94, 0xc3, 0x00, 0x02, 0xaa, 0xbb, 0x94];
+
+        assert_eq!(take_next_frame(&mut buffer), Some(vec![0xaa, 0xbb]));
+        assert_eq!(take_next_frame(&mut buffer), None);
+        assert_eq!(buffer, vec![0x94]);
+    }
+
+    #[test]
+    fn take_frame_waits_for_complete_packet() {
+        let mut buffer = vec![0x94, 0xc3, 0x00, 0x03, 0xaa];
+
+        assert_eq!(take_next_frame(&mut buffer), None);
+
+        buffer.extend_from_slice(&[0xbb, 0xcc]);
+        assert_eq!(take_next_frame(&mut buffer), Some(vec![0xaa, 0xbb, 0xcc]));
+        assert!(buffer.is_empty());
+    }
 }